@@ -0,0 +1,104 @@
+//! Benchmarks of the deterministic collections against `std::collections`.
+//!
+//! ```bash
+//! cargo bench -p dst --bench collections
+//! ```
+//!
+//! Every map is filled with and then queried for the same keys, drawn from
+//! three distributions: sequential integers, strings, and integers that
+//! differ only in the top bit of each byte, on which FNV-1a's low bits
+//! collapse.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use dst::{DetIndexMap, DeterministicBuildHasher, SeededBuildHasher};
+use indexmap::IndexMap;
+
+const KEYS: u64 = 10_000;
+
+fn sequential() -> Vec<u64> {
+    (0..KEYS).collect()
+}
+
+fn strings() -> Vec<String> {
+    (0..KEYS)
+        .map(|i| format!("node-{}/key-{}", i % 7, i))
+        .collect()
+}
+
+/// Keys that differ only in the top bit of each byte. The low 7 bits of an
+/// FNV-1a hash depend only on the low 7 bits of each byte, so every key
+/// lands in the same 1/128th of the table.
+fn high_bits() -> Vec<u128> {
+    (0..KEYS)
+        .map(|i| {
+            (0..16).fold(0, |key, byte| {
+                key | (u128::from((i >> byte) & 1) << (byte * 8 + 7))
+            })
+        })
+        .collect()
+}
+
+/// Fills a map with `keys`, then looks every key up.
+fn insert_and_get<K, S>(keys: &[K], hasher: S) -> usize
+where
+    K: Hash + Eq + Clone,
+    S: BuildHasher,
+{
+    let mut map = HashMap::with_hasher(hasher);
+    for (i, key) in keys.iter().enumerate() {
+        map.insert(key.clone(), i);
+    }
+    keys.iter().filter_map(|key| map.get(key)).count()
+}
+
+fn index_insert_and_get<K: Hash + Eq + Clone>(keys: &[K]) -> usize {
+    let mut map = DetIndexMap::with_hasher(DeterministicBuildHasher);
+    for (i, key) in keys.iter().enumerate() {
+        map.insert(key.clone(), i);
+    }
+    keys.iter().filter_map(|key| map.get(key)).count()
+}
+
+fn bench_distribution<K: Hash + Eq + Clone>(c: &mut Criterion, name: &str, keys: &[K]) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(keys.len() as u64));
+    group.bench_with_input(BenchmarkId::new("std HashMap", KEYS), keys, |b, keys| {
+        b.iter(|| insert_and_get(black_box(keys), RandomState::new()))
+    });
+    group.bench_with_input(BenchmarkId::new("DetHashMap", KEYS), keys, |b, keys| {
+        b.iter(|| insert_and_get(black_box(keys), DeterministicBuildHasher))
+    });
+    group.bench_with_input(BenchmarkId::new("SeededHashMap", KEYS), keys, |b, keys| {
+        b.iter(|| insert_and_get(black_box(keys), SeededBuildHasher::new(42)))
+    });
+    group.bench_with_input(BenchmarkId::new("DetIndexMap", KEYS), keys, |b, keys| {
+        b.iter(|| index_insert_and_get(black_box(keys)))
+    });
+    group.bench_with_input(
+        BenchmarkId::new("IndexMap (RandomState)", KEYS),
+        keys,
+        |b, keys| {
+            b.iter(|| {
+                let mut map = IndexMap::new();
+                for (i, key) in keys.iter().enumerate() {
+                    map.insert(key.clone(), i);
+                }
+                keys.iter().filter_map(|key| map.get(key)).count()
+            })
+        },
+    );
+    group.finish();
+}
+
+fn collections(c: &mut Criterion) {
+    bench_distribution(c, "sequential", &sequential());
+    bench_distribution(c, "strings", &strings());
+    bench_distribution(c, "high_bits", &high_bits());
+}
+
+criterion_group!(benches, collections);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+//! Runs declarative cluster scenarios.
+//!
+//! ```bash
+//! cargo run -p dst-kv --features simulation --bin dst-run -- \
+//!     crates/dst-kv/scenarios/*.toml --count 10
+//! ```
+//!
+//! See [`dst_kv::scenarios`] for the factories and invariants scenario
+//! files can name, and [`dst::runner::Runner::main`] for the options.
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    dst_kv::scenarios::runner().main()
+}
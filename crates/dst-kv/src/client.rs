@@ -0,0 +1,156 @@
+//! The client: finds the leader and retries until a command commits.
+
+use dst::io::net::UdpSocket;
+use dst::io::time::{self, Duration, Instant};
+
+use crate::message::{Command, Envelope, Message, Request, Response};
+use crate::{Error, Result};
+
+/// Pause before retrying after a server said it is not the leader.
+const REDIRECT_BACKOFF: Duration = Duration::from_millis(20);
+
+/// A connection to the cluster.
+///
+/// Each command gets a new sequence number and is retried with that number
+/// until it commits, so servers apply it at most once. One client must only
+/// run one command at a time, and each client needs a unique name.
+#[derive(Debug)]
+pub struct Client {
+    name: String,
+    nodes: Vec<String>,
+    port: u16,
+    socket: UdpSocket,
+    seq: u64,
+    target: usize,
+    attempt_timeout: Duration,
+    timeout: Duration,
+}
+
+impl Client {
+    /// Binds a socket for client `name` talking to the servers `nodes` on
+    /// `port`.
+    ///
+    /// Attempts time out after 300ms and commands after 5s.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nodes` is empty.
+    pub async fn connect<I, S>(name: impl Into<String>, nodes: I, port: u16) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let nodes: Vec<String> = nodes.into_iter().map(Into::into).collect();
+        assert!(!nodes.is_empty(), "a client needs at least one server");
+        Ok(Client {
+            name: name.into(),
+            nodes,
+            port,
+            socket: UdpSocket::bind(("0.0.0.0", 0)).await?,
+            seq: 0,
+            target: 0,
+            attempt_timeout: Duration::from_millis(300),
+            timeout: Duration::from_secs(5),
+        })
+    }
+
+    /// Sets how long to wait for one server before trying the next.
+    pub fn attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a command may take before it fails with
+    /// [`Error::Timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reads `key`.
+    pub async fn get(&mut self, key: impl Into<String>) -> Result<Option<String>> {
+        match self.execute(Command::Get { key: key.into() }).await? {
+            Response::Value(value) => Ok(value),
+            response => Err(Error::UnexpectedResponse(response)),
+        }
+    }
+
+    /// Sets `key` to `value`.
+    pub async fn put(&mut self, key: impl Into<String>, value: impl Into<String>) -> Result<()> {
+        let command = Command::Put {
+            key: key.into(),
+            value: value.into(),
+        };
+        self.expect_ok(command).await
+    }
+
+    /// Removes `key`.
+    pub async fn delete(&mut self, key: impl Into<String>) -> Result<()> {
+        self.expect_ok(Command::Delete { key: key.into() }).await
+    }
+
+    /// Runs `command` on the leader and returns its response.
+    ///
+    /// On [`Error::Timeout`] the command may or may not have taken effect.
+    pub async fn execute(&mut self, command: Command) -> Result<Response> {
+        self.seq += 1;
+        let request = Envelope {
+            from: self.name.clone(),
+            message: Message::Request(Request {
+                client: self.name.clone(),
+                seq: self.seq,
+                command,
+            }),
+        }
+        .encode();
+
+        let started = Instant::now();
+        let mut buf = vec![0u8; 64 * 1024];
+        while started.elapsed() < self.timeout {
+            let node = self.nodes[self.target].as_str();
+            // An unreachable server is handled like a silent one.
+            let _ = self.socket.send_to(&request, (node, self.port)).await;
+
+            let deadline = Instant::now() + self.attempt_timeout;
+            let response = loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let received = time::timeout(remaining, self.socket.recv_from(&mut buf)).await;
+                let Ok(received) = received else {
+                    break None;
+                };
+                let (len, _) = received?;
+                match Envelope::decode(&buf[..len]).map(|e| e.message) {
+                    Some(Message::Reply { seq, response }) if seq == self.seq => {
+                        break Some(response)
+                    }
+                    // Late replies to abandoned commands.
+                    _ => continue,
+                }
+            };
+
+            match response {
+                Some(Response::NotLeader { leader }) => {
+                    self.redirect(leader.as_deref());
+                    time::sleep(REDIRECT_BACKOFF).await;
+                }
+                Some(response) => return Ok(response),
+                None => self.redirect(None),
+            }
+        }
+        Err(Error::Timeout(self.timeout))
+    }
+
+    async fn expect_ok(&mut self, command: Command) -> Result<()> {
+        match self.execute(command).await? {
+            Response::Ok => Ok(()),
+            response => Err(Error::UnexpectedResponse(response)),
+        }
+    }
+
+    /// Targets `leader` if it is a known server, otherwise the next one.
+    fn redirect(&mut self, leader: Option<&str>) {
+        self.target = leader
+            .and_then(|leader| self.nodes.iter().position(|node| node == leader))
+            .unwrap_or((self.target + 1) % self.nodes.len());
+    }
+}
@@ -0,0 +1,93 @@
+//! A small Raft-style replicated key-value store, tested with `dst`.
+//!
+//! This crate is the reference for testing a consensus system with the
+//! `dst` framework. Servers elect a leader, replicate a log of client
+//! commands over UDP, and persist their term, vote and log before replying.
+//! All I/O goes through the [`dst::io`] facade, so the same code runs on
+//! tokio in production and on turmoil hosts under the `simulation` feature.
+//!
+//! - [`Server`] runs one node: leader election with randomized timeouts,
+//!   log replication, and commit once a majority has an entry.
+//! - [`Client`] finds the leader, retries on timeouts and redirects, and
+//!   numbers each command so a retry is applied at most once.
+//! - Reads go through the log, so every completed operation is
+//!   linearizable.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//! use dst::{Entropy, SimFs};
+//! use dst_kv::{Client, Config, Server};
+//!
+//! const NODES: [&str; 3] = ["kv-a", "kv-b", "kv-c"];
+//!
+//! let mut sim = dst::turmoil::Builder::new().rng_seed(1).build();
+//! let fs = SimFs::new(1);
+//! let entropy = Entropy::new(1);
+//!
+//! for node in NODES {
+//!     let (fs, entropy) = (fs.clone(), entropy);
+//!     sim.host(node, move || {
+//!         let server = Server::new(Config::new(node, NODES, 7000), fs.host(node), entropy.host(node));
+//!         async move { Ok(server.run().await?) }
+//!     });
+//! }
+//! sim.client("client", async {
+//!     let mut client = Client::connect("client", NODES, 7000).await?;
+//!     client.put("greeting", "hello").await?;
+//!     assert_eq!(client.get("greeting").await?.as_deref(), Some("hello"));
+//!     Ok(())
+//! });
+//! sim.run().unwrap();
+//! ```
+//!
+//! The integration tests in `tests/dst-kv/` run the cluster through
+//! partitions, crashes and slow links, check election safety after every
+//! step and check the recorded client history for linearizability. The
+//! same checks back the [`scenarios`] runner, which runs the declarative
+//! scenario files in `crates/dst-kv/scenarios/` through the `dst-run`
+//! binary.
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+mod client;
+mod message;
+mod raft;
+#[cfg(feature = "simulation")]
+pub mod scenarios;
+mod server;
+
+use std::io;
+use std::time::Duration;
+
+use thiserror::Error;
+
+pub use client::Client;
+pub use message::{Command, Response};
+pub use raft::{Role, Status};
+pub use server::{Config, Server};
+
+/// Errors from servers and clients.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A socket or the state file failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// The state file could not be encoded or decoded.
+    #[error("corrupt Raft state: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// No leader committed the command in time. It may still take effect.
+    #[error("no reply within {0:?}")]
+    Timeout(Duration),
+
+    /// The server answered with a response of the wrong kind.
+    #[error("unexpected response: {0:?}")]
+    UnexpectedResponse(Response),
+}
+
+/// Result alias for `dst-kv` operations.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
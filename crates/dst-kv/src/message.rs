@@ -0,0 +1,106 @@
+//! Wire format shared by servers and clients.
+//!
+//! Every datagram is one JSON-encoded [`Envelope`].
+
+use serde::{Deserialize, Serialize};
+
+/// An operation on the store.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Command {
+    /// Reads a key.
+    Get {
+        /// The key to read.
+        key: String,
+    },
+    /// Sets a key.
+    Put {
+        /// The key to set.
+        key: String,
+        /// Its new value.
+        value: String,
+    },
+    /// Removes a key.
+    Delete {
+        /// The key to remove.
+        key: String,
+    },
+}
+
+/// A server's answer to a [`Command`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Response {
+    /// The value a `Get` read, or `None` if the key was absent.
+    Value(Option<String>),
+    /// A `Put` or `Delete` was committed.
+    Ok,
+    /// The server is not the leader; retry at `leader` if it is known.
+    NotLeader {
+        /// The leader the server last heard from.
+        leader: Option<String>,
+    },
+}
+
+/// A client command, numbered so retries are applied at most once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Request {
+    pub(crate) client: String,
+    pub(crate) seq: u64,
+    pub(crate) command: Command,
+}
+
+/// One replicated log entry. Leaders append an entry without a request when
+/// elected, so entries from earlier terms can commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Entry {
+    pub(crate) term: u64,
+    pub(crate) request: Option<Request>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Message {
+    RequestVote {
+        term: u64,
+        last_log_index: u64,
+        last_log_term: u64,
+    },
+    Vote {
+        term: u64,
+        granted: bool,
+    },
+    AppendEntries {
+        term: u64,
+        prev_log_index: u64,
+        prev_log_term: u64,
+        entries: Vec<Entry>,
+        leader_commit: u64,
+    },
+    /// `match_index` is the follower's last matching index on success, or a
+    /// hint for where the leader should retry on failure.
+    AppendResponse {
+        term: u64,
+        success: bool,
+        match_index: u64,
+    },
+    Request(Request),
+    Reply {
+        seq: u64,
+        response: Response,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Envelope {
+    /// Node or client name of the sender.
+    pub(crate) from: String,
+    pub(crate) message: Message,
+}
+
+impl Envelope {
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("messages always serialize")
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Option<Envelope> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
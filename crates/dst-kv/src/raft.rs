@@ -0,0 +1,562 @@
+//! The Raft state machine, free of I/O.
+//!
+//! [`Raft`] consumes messages and clock ticks and produces messages to send.
+//! The server persists [`HardState`] whenever [`Raft::take_dirty`] says it
+//! changed, before sending anything, as Raft requires.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::message::{Command, Entry, Message, Request, Response};
+use crate::server::Config;
+
+/// Upper bound on entries sent in one `AppendEntries`, keeping datagrams
+/// small.
+const MAX_ENTRIES_PER_APPEND: usize = 64;
+
+/// State that must survive a crash.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct HardState {
+    pub(crate) term: u64,
+    pub(crate) voted_for: Option<String>,
+    pub(crate) log: Vec<Entry>,
+}
+
+/// A node's role in its current term.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// Replicates the leader's log.
+    Follower,
+    /// Asking peers for votes.
+    Candidate,
+    /// Accepts client commands and replicates them.
+    Leader,
+}
+
+/// A snapshot of one node's view of the cluster, for monitoring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    /// The node's name.
+    pub id: String,
+    /// Its current term.
+    pub term: u64,
+    /// Its role in that term.
+    pub role: Role,
+    /// The leader it last heard from in that term.
+    pub leader: Option<String>,
+    /// Index of the last entry known to be committed.
+    pub commit_index: u64,
+    /// Index of the last entry in its log.
+    pub last_log_index: u64,
+}
+
+/// Where an outgoing message goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Target {
+    Peer(String),
+    Client(SocketAddr),
+}
+
+pub(crate) type Outbox = Vec<(Target, Message)>;
+
+/// A client waiting for the entry at some log index to apply.
+#[derive(Debug)]
+struct Waiter {
+    addr: SocketAddr,
+    client: String,
+    seq: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct Raft {
+    id: String,
+    peers: Vec<String>,
+    hard: HardState,
+    dirty: bool,
+    role: Role,
+    leader: Option<String>,
+    commit_index: u64,
+    last_applied: u64,
+    election_timeout: (Duration, Duration),
+    election_deadline: Duration,
+    heartbeat_interval: Duration,
+    next_heartbeat: Duration,
+    votes: BTreeSet<String>,
+    next_index: BTreeMap<String, u64>,
+    match_index: BTreeMap<String, u64>,
+    rng: StdRng,
+    store: BTreeMap<String, String>,
+    /// Last applied sequence number and response per client.
+    sessions: BTreeMap<String, (u64, Response)>,
+    waiters: BTreeMap<u64, Waiter>,
+}
+
+impl Raft {
+    pub(crate) fn new(config: &Config, hard: HardState, rng: StdRng, now: Duration) -> Self {
+        let mut raft = Raft {
+            id: config.id.clone(),
+            peers: config.peers.clone(),
+            hard,
+            dirty: false,
+            role: Role::Follower,
+            leader: None,
+            commit_index: 0,
+            last_applied: 0,
+            election_timeout: config.election_timeout,
+            election_deadline: now,
+            heartbeat_interval: config.heartbeat_interval,
+            next_heartbeat: now,
+            votes: BTreeSet::new(),
+            next_index: BTreeMap::new(),
+            match_index: BTreeMap::new(),
+            rng,
+            store: BTreeMap::new(),
+            sessions: BTreeMap::new(),
+            waiters: BTreeMap::new(),
+        };
+        raft.reset_election_deadline(now);
+        raft
+    }
+
+    pub(crate) fn status(&self) -> Status {
+        Status {
+            id: self.id.clone(),
+            term: self.hard.term,
+            role: self.role,
+            leader: self.leader.clone(),
+            commit_index: self.commit_index,
+            last_log_index: self.last_index(),
+        }
+    }
+
+    pub(crate) fn hard_state(&self) -> &HardState {
+        &self.hard
+    }
+
+    /// Returns whether the hard state changed since the last call.
+    pub(crate) fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Advances timers: leaders send heartbeats, everyone else starts an
+    /// election once the leader has been silent too long.
+    pub(crate) fn tick(&mut self, now: Duration, out: &mut Outbox) {
+        if self.role == Role::Leader {
+            if now >= self.next_heartbeat {
+                self.broadcast_append(out);
+                self.next_heartbeat = now + self.heartbeat_interval;
+            }
+        } else if now >= self.election_deadline {
+            self.start_election(now, out);
+        }
+    }
+
+    pub(crate) fn receive(
+        &mut self,
+        now: Duration,
+        from: String,
+        addr: SocketAddr,
+        message: Message,
+        out: &mut Outbox,
+    ) {
+        match message {
+            Message::RequestVote {
+                term,
+                last_log_index,
+                last_log_term,
+            } => self.handle_request_vote(now, from, term, last_log_index, last_log_term, out),
+            Message::Vote { term, granted } => self.handle_vote(now, from, term, granted, out),
+            Message::AppendEntries {
+                term,
+                prev_log_index,
+                prev_log_term,
+                entries,
+                leader_commit,
+            } => {
+                let reply = self.handle_append(
+                    now,
+                    &from,
+                    term,
+                    prev_log_index,
+                    prev_log_term,
+                    entries,
+                    leader_commit,
+                    out,
+                );
+                out.push((Target::Peer(from), reply));
+            }
+            Message::AppendResponse {
+                term,
+                success,
+                match_index,
+            } => self.handle_append_response(from, term, success, match_index, out),
+            Message::Request(request) => self.handle_request(addr, request, out),
+            Message::Reply { .. } => {}
+        }
+    }
+
+    fn last_index(&self) -> u64 {
+        self.hard.log.len() as u64
+    }
+
+    fn term_at(&self, index: u64) -> u64 {
+        match index {
+            0 => 0,
+            i => self.hard.log.get(i as usize - 1).map_or(0, |e| e.term),
+        }
+    }
+
+    fn quorum(&self) -> usize {
+        let nodes = self.peers.len() + 1;
+        nodes / 2 + 1
+    }
+
+    fn reset_election_deadline(&mut self, now: Duration) {
+        let (min, max) = self.election_timeout;
+        self.election_deadline = now + self.rng.random_range(min..max);
+    }
+
+    /// Adopts a newer term seen in any message, stepping down.
+    fn observe_term(&mut self, term: u64) {
+        if term > self.hard.term {
+            self.hard.term = term;
+            self.hard.voted_for = None;
+            self.dirty = true;
+            self.become_follower(None);
+        }
+    }
+
+    fn become_follower(&mut self, leader: Option<String>) {
+        self.role = Role::Follower;
+        self.leader = leader;
+        self.votes.clear();
+        // Uncommitted entries may be overwritten; clients retry on timeout.
+        self.waiters.clear();
+    }
+
+    fn start_election(&mut self, now: Duration, out: &mut Outbox) {
+        self.hard.term += 1;
+        self.hard.voted_for = Some(self.id.clone());
+        self.dirty = true;
+        self.role = Role::Candidate;
+        self.leader = None;
+        self.votes = BTreeSet::from([self.id.clone()]);
+        self.reset_election_deadline(now);
+
+        if self.votes.len() >= self.quorum() {
+            self.become_leader(now, out);
+            return;
+        }
+        let message = Message::RequestVote {
+            term: self.hard.term,
+            last_log_index: self.last_index(),
+            last_log_term: self.term_at(self.last_index()),
+        };
+        for peer in &self.peers {
+            out.push((Target::Peer(peer.clone()), message.clone()));
+        }
+    }
+
+    fn become_leader(&mut self, now: Duration, out: &mut Outbox) {
+        self.role = Role::Leader;
+        self.leader = Some(self.id.clone());
+        let next = self.last_index() + 1;
+        self.next_index = self.peers.iter().map(|p| (p.clone(), next)).collect();
+        self.match_index = self.peers.iter().map(|p| (p.clone(), 0)).collect();
+
+        // Entries from earlier terms only commit once an entry of the
+        // current term does.
+        self.hard.log.push(Entry {
+            term: self.hard.term,
+            request: None,
+        });
+        self.dirty = true;
+        self.advance_commit(out);
+        self.broadcast_append(out);
+        self.next_heartbeat = now + self.heartbeat_interval;
+    }
+
+    fn handle_request_vote(
+        &mut self,
+        now: Duration,
+        candidate: String,
+        term: u64,
+        last_log_index: u64,
+        last_log_term: u64,
+        out: &mut Outbox,
+    ) {
+        self.observe_term(term);
+
+        let my_last_term = self.term_at(self.last_index());
+        let up_to_date = last_log_term > my_last_term
+            || (last_log_term == my_last_term && last_log_index >= self.last_index());
+        let free = self
+            .hard
+            .voted_for
+            .as_ref()
+            .is_none_or(|voted| *voted == candidate);
+        let granted = term == self.hard.term && free && up_to_date;
+
+        if granted {
+            self.hard.voted_for = Some(candidate.clone());
+            self.dirty = true;
+            self.reset_election_deadline(now);
+        }
+        out.push((
+            Target::Peer(candidate),
+            Message::Vote {
+                term: self.hard.term,
+                granted,
+            },
+        ));
+    }
+
+    fn handle_vote(
+        &mut self,
+        now: Duration,
+        voter: String,
+        term: u64,
+        granted: bool,
+        out: &mut Outbox,
+    ) {
+        self.observe_term(term);
+        if self.role != Role::Candidate || term != self.hard.term || !granted {
+            return;
+        }
+        self.votes.insert(voter);
+        if self.votes.len() >= self.quorum() {
+            self.become_leader(now, out);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn handle_append(
+        &mut self,
+        now: Duration,
+        leader: &str,
+        term: u64,
+        prev_log_index: u64,
+        prev_log_term: u64,
+        entries: Vec<Entry>,
+        leader_commit: u64,
+        out: &mut Outbox,
+    ) -> Message {
+        let reject = |term, match_index| Message::AppendResponse {
+            term,
+            success: false,
+            match_index,
+        };
+        if term < self.hard.term {
+            return reject(self.hard.term, 0);
+        }
+        self.observe_term(term);
+        if self.role != Role::Follower || self.leader.as_deref() != Some(leader) {
+            self.become_follower(Some(leader.to_string()));
+        }
+        self.reset_election_deadline(now);
+
+        if prev_log_index > self.last_index() {
+            return reject(term, self.last_index());
+        }
+        if self.term_at(prev_log_index) != prev_log_term {
+            return reject(term, prev_log_index - 1);
+        }
+
+        let sent = entries.len() as u64;
+        for (offset, entry) in entries.into_iter().enumerate() {
+            let index = prev_log_index + 1 + offset as u64;
+            if index <= self.last_index() {
+                if self.term_at(index) == entry.term {
+                    continue;
+                }
+                self.hard.log.truncate(index as usize - 1);
+            }
+            self.hard.log.push(entry);
+            self.dirty = true;
+        }
+
+        // Only the prefix the leader sent is known to match; a longer local
+        // log may still hold stale entries past it.
+        let match_index = prev_log_index + sent;
+        if leader_commit > self.commit_index {
+            self.commit_index = leader_commit.min(match_index);
+            self.apply(out);
+        }
+        Message::AppendResponse {
+            term,
+            success: true,
+            match_index,
+        }
+    }
+
+    fn handle_append_response(
+        &mut self,
+        peer: String,
+        term: u64,
+        success: bool,
+        match_index: u64,
+        out: &mut Outbox,
+    ) {
+        self.observe_term(term);
+        if self.role != Role::Leader || term != self.hard.term {
+            return;
+        }
+
+        if success {
+            let matched = self.match_index.entry(peer.clone()).or_insert(0);
+            *matched = (*matched).max(match_index);
+            let matched = *matched;
+            let next = self.next_index.entry(peer.clone()).or_insert(1);
+            *next = (*next).max(matched + 1);
+            let behind = *next <= self.last_index();
+            self.advance_commit(out);
+            if behind {
+                self.send_append(&peer, out);
+            }
+        } else {
+            let next = self.next_index.entry(peer.clone()).or_insert(1);
+            *next = (*next - 1).min(match_index + 1).max(1);
+            self.send_append(&peer, out);
+        }
+    }
+
+    fn handle_request(&mut self, addr: SocketAddr, request: Request, out: &mut Outbox) {
+        let reply = |seq, response| (Target::Client(addr), Message::Reply { seq, response });
+
+        if self.role != Role::Leader {
+            let response = Response::NotLeader {
+                leader: self.leader.clone(),
+            };
+            out.push(reply(request.seq, response));
+            return;
+        }
+        if let Some((applied, response)) = self.sessions.get(&request.client) {
+            if request.seq == *applied {
+                out.push(reply(request.seq, response.clone()));
+            }
+            if request.seq <= *applied {
+                return;
+            }
+        }
+        // A retry of a command that is already in the log waits for it.
+        if let Some(waiter) = self
+            .waiters
+            .values_mut()
+            .find(|w| w.client == request.client && w.seq == request.seq)
+        {
+            waiter.addr = addr;
+            return;
+        }
+
+        self.waiters.insert(
+            self.last_index() + 1,
+            Waiter {
+                addr,
+                client: request.client.clone(),
+                seq: request.seq,
+            },
+        );
+        self.hard.log.push(Entry {
+            term: self.hard.term,
+            request: Some(request),
+        });
+        self.dirty = true;
+        self.advance_commit(out);
+        self.broadcast_append(out);
+    }
+
+    fn broadcast_append(&self, out: &mut Outbox) {
+        for peer in &self.peers {
+            self.send_append(peer, out);
+        }
+    }
+
+    fn send_append(&self, peer: &str, out: &mut Outbox) {
+        let next = self.next_index.get(peer).copied().unwrap_or(1);
+        let prev_log_index = next - 1;
+        let end = self
+            .hard
+            .log
+            .len()
+            .min(prev_log_index as usize + MAX_ENTRIES_PER_APPEND);
+        out.push((
+            Target::Peer(peer.to_string()),
+            Message::AppendEntries {
+                term: self.hard.term,
+                prev_log_index,
+                prev_log_term: self.term_at(prev_log_index),
+                entries: self.hard.log[prev_log_index as usize..end].to_vec(),
+                leader_commit: self.commit_index,
+            },
+        ));
+    }
+
+    /// Commits the newest current-term entry a quorum has replicated.
+    fn advance_commit(&mut self, out: &mut Outbox) {
+        for index in (self.commit_index + 1..=self.last_index()).rev() {
+            if self.term_at(index) != self.hard.term {
+                break;
+            }
+            let replicas = 1 + self.match_index.values().filter(|m| **m >= index).count();
+            if replicas >= self.quorum() {
+                self.commit_index = index;
+                self.apply(out);
+                return;
+            }
+        }
+    }
+
+    fn apply(&mut self, out: &mut Outbox) {
+        while self.last_applied < self.commit_index {
+            self.last_applied += 1;
+            let index = self.last_applied;
+            let Some(request) = self.hard.log[index as usize - 1].request.clone() else {
+                continue;
+            };
+            let response = self.apply_request(&request);
+            match (self.waiters.remove(&index), response) {
+                (Some(waiter), Some(response))
+                    if waiter.client == request.client && waiter.seq == request.seq =>
+                {
+                    let reply = Message::Reply {
+                        seq: request.seq,
+                        response,
+                    };
+                    out.push((Target::Client(waiter.addr), reply));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Applies a committed request at most once per client sequence number.
+    fn apply_request(&mut self, request: &Request) -> Option<Response> {
+        if let Some((applied, response)) = self.sessions.get(&request.client) {
+            if request.seq == *applied {
+                return Some(response.clone());
+            }
+            if request.seq < *applied {
+                return None;
+            }
+        }
+        let response = match &request.command {
+            Command::Get { key } => Response::Value(self.store.get(key).cloned()),
+            Command::Put { key, value } => {
+                self.store.insert(key.clone(), value.clone());
+                Response::Ok
+            }
+            Command::Delete { key } => {
+                self.store.remove(key);
+                Response::Ok
+            }
+        };
+        self.sessions
+            .insert(request.client.clone(), (request.seq, response.clone()));
+        Some(response)
+    }
+}
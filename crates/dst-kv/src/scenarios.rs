@@ -0,0 +1,144 @@
+//! Factories and invariants for declarative cluster scenarios.
+//!
+//! [`runner`] registers everything the scenario files in
+//! `crates/dst-kv/scenarios/` refer to; the `dst-run` binary runs them:
+//!
+//! ```bash
+//! cargo run -p dst-kv --features simulation --bin dst-run -- \
+//!     crates/dst-kv/scenarios/*.toml --count 10
+//! ```
+//!
+//! | Name | Kind | Args |
+//! |------|------|------|
+//! | `kv-server` | factory | `port` (7000); every scenario host is a peer |
+//! | `kv-client` | factory | `port` (7000), `ops` (40), `keys` (`["x", "y"]`) |
+//! | `one leader per term` | invariant, every step | |
+//! | `linearizable` | check, after the run | |
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use dst::linearizability::{HistoryRecorder, KvOp, KvRet, KvStore};
+use dst::runner::{HostContext, Runner};
+use rand::Rng;
+
+use crate::{Client, Command, Config, Error, Response, Role, Server};
+
+const DEFAULT_PORT: u16 = 7000;
+const DEFAULT_OPS: usize = 40;
+const DEFAULT_KEYS: [&str; 2] = ["x", "y"];
+
+/// Recorded history of one key.
+pub type Recorder = HistoryRecorder<KvOp<String, String>, KvRet<String>>;
+
+/// What servers and clients publish for the invariants.
+#[derive(Debug, Default)]
+pub struct ClusterState {
+    /// Nodes that became leader, by term.
+    pub leaders: BTreeMap<u64, BTreeSet<String>>,
+    /// Client operations, by key. Linearizability is compositional, so
+    /// each key is checked on its own.
+    pub histories: BTreeMap<String, Recorder>,
+}
+
+/// A runner with the `kv-server` and `kv-client` factories, the
+/// `one leader per term` invariant and the `linearizable` check.
+pub fn runner() -> Runner<ClusterState> {
+    Runner::new()
+        .factory("kv-server", server)
+        .factory("kv-client", client)
+        .invariant("one leader per term", |state: &ClusterState| {
+            match state.leaders.iter().find(|(_, ids)| ids.len() > 1) {
+                Some((term, ids)) => Err(format!("term {} has leaders {:?}", term, ids)),
+                None => Ok(()),
+            }
+        })
+        .check("linearizable", |state: &ClusterState| {
+            for (key, recorder) in &state.histories {
+                recorder
+                    .history()
+                    .check(KvStore::new())
+                    .map_err(|violation| format!("key {}: {}", key, violation))?;
+            }
+            Ok(())
+        })
+}
+
+fn server(
+    ctx: &HostContext<ClusterState>,
+) -> impl std::future::Future<Output = dst::turmoil::Result> {
+    let port = ctx.arg("port");
+    let config = port.map(|port: Option<u16>| {
+        Config::new(ctx.name.as_str(), &ctx.hosts, port.unwrap_or(DEFAULT_PORT))
+    });
+    let (fs, entropy, state) = (ctx.fs.clone(), ctx.entropy.clone(), ctx.state.clone());
+    async move {
+        let server = Server::new(config?, fs, entropy).observe(move |status| {
+            if status.role == Role::Leader {
+                let mut state = state.lock().unwrap();
+                state
+                    .leaders
+                    .entry(status.term)
+                    .or_default()
+                    .insert(status.id.clone());
+            }
+        });
+        Ok(server.run().await?)
+    }
+}
+
+/// Issues random gets, puts and deletes, recording each key's history.
+fn client(
+    ctx: &HostContext<ClusterState>,
+) -> impl std::future::Future<Output = dst::turmoil::Result> {
+    let args = (ctx.arg("port"), ctx.arg("ops"), ctx.arg("keys"));
+    let (name, nodes, entropy, state) = (
+        ctx.name.clone(),
+        ctx.hosts.clone(),
+        ctx.entropy.clone(),
+        ctx.state.clone(),
+    );
+    async move {
+        let port: u16 = args.0?.unwrap_or(DEFAULT_PORT);
+        let ops: usize = args.1?.unwrap_or(DEFAULT_OPS);
+        let keys: Vec<String> = args
+            .2?
+            .unwrap_or_else(|| DEFAULT_KEYS.map(String::from).to_vec());
+        let histories: Vec<Recorder> = {
+            let mut state = state.lock().unwrap();
+            keys.iter()
+                .map(|key| state.histories.entry(key.clone()).or_default().clone())
+                .collect()
+        };
+
+        let mut rng = entropy.rng("workload");
+        let mut client = Client::connect(name.as_str(), nodes, port).await?;
+        for i in 0..ops {
+            let k = rng.random_range(0..keys.len());
+            let (key, history) = (keys[k].clone(), &histories[k]);
+            let (command, op) = match rng.random_range(0..3) {
+                0 => (Command::Get { key: key.clone() }, KvOp::Get(key)),
+                1 => {
+                    let value = format!("{}-{}", name, i);
+                    let command = Command::Put {
+                        key: key.clone(),
+                        value: value.clone(),
+                    };
+                    (command, KvOp::Put(key, value))
+                }
+                _ => (Command::Delete { key: key.clone() }, KvOp::Delete(key)),
+            };
+
+            let id = history.invoke(name.as_str(), op);
+            match client.execute(command).await {
+                Ok(Response::Value(value)) => history.complete(id, KvRet::Value(value)),
+                Ok(_) => history.complete(id, KvRet::Ok),
+                // Indeterminate: the command may still commit later.
+                Err(Error::Timeout(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+            tokio::time::sleep(Duration::from_millis(rng.random_range(0..300))).await;
+        }
+        Ok(())
+    }
+}
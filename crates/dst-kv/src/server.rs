@@ -0,0 +1,193 @@
+//! The server: a [`Raft`] node driven by a UDP socket and a clock.
+
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use dst::io::fs::Fs;
+use dst::io::net::UdpSocket;
+use dst::io::random::Random;
+use dst::io::time::{self, Duration, Instant};
+
+use crate::message::Envelope;
+use crate::raft::{HardState, Raft, Status, Target};
+use crate::Result;
+
+/// How often the server advances the Raft timers.
+const TICK: Duration = Duration::from_millis(10);
+
+/// Largest datagram the server accepts.
+const MAX_DATAGRAM: usize = 64 * 1024;
+
+const STATE_FILE: &str = "raft-state.json";
+const STATE_TMP: &str = "raft-state.json.tmp";
+
+type Observer = Box<dyn Fn(&Status) + Send + Sync>;
+
+/// Cluster membership and timing for one server.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub(crate) id: String,
+    pub(crate) peers: Vec<String>,
+    pub(crate) port: u16,
+    pub(crate) election_timeout: (Duration, Duration),
+    pub(crate) heartbeat_interval: Duration,
+    pub(crate) data_dir: PathBuf,
+}
+
+impl Config {
+    /// Configures the server `id` in a cluster of `nodes`, all listening on
+    /// `port`. `nodes` may include `id`.
+    ///
+    /// Election timeouts default to 150–300ms and heartbeats to 50ms.
+    pub fn new<I, S>(id: impl Into<String>, nodes: I, port: u16) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let id = id.into();
+        let peers = nodes
+            .into_iter()
+            .map(Into::into)
+            .filter(|node| *node != id)
+            .collect();
+        Config {
+            id,
+            peers,
+            port,
+            election_timeout: (Duration::from_millis(150), Duration::from_millis(300)),
+            heartbeat_interval: Duration::from_millis(50),
+            data_dir: PathBuf::new(),
+        }
+    }
+
+    /// Sets the range election timeouts are drawn from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is not less than `max`.
+    pub fn election_timeout(mut self, min: Duration, max: Duration) -> Self {
+        assert!(min < max, "election timeout range is empty");
+        self.election_timeout = (min, max);
+        self
+    }
+
+    /// Sets how often the leader sends heartbeats. Keep it well below the
+    /// election timeout.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Sets the directory the Raft state file lives in. Defaults to the
+    /// current directory.
+    pub fn data_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = dir.into();
+        self
+    }
+}
+
+/// A replicated key-value server.
+///
+/// The term, vote and log are written to disk and synced before the server
+/// answers any message, so a crash never makes it forget a vote or an
+/// acknowledged entry.
+pub struct Server {
+    config: Config,
+    fs: Fs,
+    random: Random,
+    observer: Option<Observer>,
+}
+
+impl std::fmt::Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("config", &self.config)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+
+impl Server {
+    /// Creates a server that stores its state in `fs` and draws election
+    /// timeouts from `random`.
+    pub fn new(config: Config, fs: Fs, random: Random) -> Self {
+        Server {
+            config,
+            fs,
+            random,
+            observer: None,
+        }
+    }
+
+    /// Calls `observer` with the node's status after every message and
+    /// tick, e.g. to check election safety from a test.
+    pub fn observe(mut self, observer: impl Fn(&Status) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Serves until an I/O error occurs.
+    pub async fn run(self) -> Result<()> {
+        let Server {
+            config,
+            fs,
+            random,
+            observer,
+        } = self;
+        let state_file = config.data_dir.join(STATE_FILE);
+        let state_tmp = config.data_dir.join(STATE_TMP);
+
+        let hard = match fs.read(&state_file).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => HardState::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let socket = UdpSocket::bind(("0.0.0.0", config.port)).await?;
+
+        let start = Instant::now();
+        let mut raft = Raft::new(&config, hard, random.rng("election"), Duration::ZERO);
+        let mut ticker = time::interval(TICK);
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        let mut out = Vec::new();
+
+        loop {
+            tokio::select! {
+                received = socket.recv_from(&mut buf) => {
+                    let (len, addr) = received?;
+                    if let Some(envelope) = Envelope::decode(&buf[..len]) {
+                        raft.receive(start.elapsed(), envelope.from, addr, envelope.message, &mut out);
+                    }
+                }
+                _ = ticker.tick() => raft.tick(start.elapsed(), &mut out),
+            }
+
+            if raft.take_dirty() {
+                let bytes = serde_json::to_vec(raft.hard_state())?;
+                let mut file = fs.create(&state_tmp).await?;
+                file.write_all(&bytes).await?;
+                file.sync_all().await?;
+                fs.rename(&state_tmp, &state_file).await?;
+            }
+
+            for (target, message) in out.drain(..) {
+                let bytes = Envelope {
+                    from: config.id.clone(),
+                    message,
+                }
+                .encode();
+                // Datagrams to unreachable peers are lost like any other;
+                // Raft retries on its own.
+                let _ = match target {
+                    Target::Peer(peer) => {
+                        socket.send_to(&bytes, (peer.as_str(), config.port)).await
+                    }
+                    Target::Client(addr) => socket.send_to(&bytes, addr).await,
+                };
+            }
+
+            if let Some(observer) = &observer {
+                observer(&raft.status());
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Scans Rust sources for nondeterminism.
+//!
+//! ```bash
+//! dst-lint crates/my-service/src
+//! ```
+//!
+//! Prints one `file:line:column: rule: message` line per finding. Exits with
+//! 0 when nothing is found, 1 when something is and 2 on errors.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut paths: Vec<PathBuf> = std::env::args_os().skip(1).map(PathBuf::from).collect();
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    let mut found = 0;
+    for path in &paths {
+        match dst_lint::lint_path(path) {
+            Ok(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    println!("{}", diagnostic);
+                }
+                found += diagnostics.len();
+            }
+            Err(error) => {
+                eprintln!("dst-lint: {}", error);
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    if found == 0 {
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("dst-lint: {} determinism issue(s)", found);
+        ExitCode::from(1)
+    }
+}
@@ -0,0 +1,218 @@
+//! Static checks for nondeterminism in crates tested with `dst`.
+//!
+//! `dst-lint` parses Rust sources with `syn` and reports code that makes a
+//! simulation behave differently between runs with the same seed:
+//!
+//! | Rule | Flags |
+//! |------|-------|
+//! | `time` | `std::time::Instant`, `std::time::SystemTime` |
+//! | `hash_map` | `std::collections::HashMap`/`HashSet` with the default hasher |
+//! | `rand` | `rand::random`, `rand::thread_rng`, `rand::rng` |
+//! | `thread_spawn` | `std::thread::spawn`, `std::thread::scope`, `std::thread::Builder` |
+//! | `unordered_iter` | iterating a default-hasher `HashMap`/`HashSet` |
+//! | `net` | `tokio::net`, `std::net::TcpListener`/`TcpStream`/`UdpSocket` |
+//!
+//! Paths are resolved through each file's `use` items, so `Instant::now()`
+//! is only flagged when `Instant` was imported from `std::time`. Macro
+//! arguments are checked when they parse as expressions.
+//!
+//! Put `#[dst::allow(rule, ...)]` (or `#[dst::allow(all)]`) on an item to
+//! exempt it.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::path::Path;
+//! use dst_lint::{lint_source, Rule};
+//!
+//! let source = "fn now() -> std::time::Instant { std::time::Instant::now() }";
+//! let diagnostics = lint_source(Path::new("src/clock.rs"), source).unwrap();
+//!
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].rule, Rule::Time);
+//! assert_eq!(diagnostics[0].to_string(), "src/clock.rs:1:13: time: std::time::Instant reads the host clock; use dst::io::time");
+//! ```
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+mod scan;
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// A determinism rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rule {
+    /// Wall-clock and host-monotonic time.
+    Time,
+    /// Hash collections seeded per process.
+    HashMap,
+    /// Randomness not derived from the simulation seed.
+    Rand,
+    /// OS threads, which the simulation cannot schedule.
+    ThreadSpawn,
+    /// Iteration whose order depends on a per-process hash seed.
+    UnorderedIter,
+    /// Sockets that bypass the `dst::io::net` facade.
+    Net,
+}
+
+impl Rule {
+    /// Every rule, in reporting order.
+    pub const ALL: [Rule; 6] = [
+        Rule::Time,
+        Rule::HashMap,
+        Rule::Rand,
+        Rule::ThreadSpawn,
+        Rule::UnorderedIter,
+        Rule::Net,
+    ];
+
+    /// The name used in diagnostics and `#[dst::allow(...)]`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Time => "time",
+            Rule::HashMap => "hash_map",
+            Rule::Rand => "rand",
+            Rule::ThreadSpawn => "thread_spawn",
+            Rule::UnorderedIter => "unordered_iter",
+            Rule::Net => "net",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rule::ALL
+            .into_iter()
+            .find(|rule| rule.name() == s)
+            .ok_or_else(|| format!("unknown dst-lint rule `{}`", s))
+    }
+}
+
+/// One finding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// File the finding is in.
+    pub path: PathBuf,
+    /// One-based line.
+    pub line: usize,
+    /// One-based column.
+    pub column: usize,
+    /// The rule that fired.
+    pub rule: Rule,
+    /// What was found and what to use instead.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            self.rule,
+            self.message
+        )
+    }
+}
+
+/// Errors that stop a scan.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A file or directory could not be read.
+    #[error("{}: {source}", path.display())]
+    Io {
+        /// The path being read.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// A file is not valid Rust, or has a malformed `#[dst::allow]`.
+    #[error("{}:{line}: {message}", path.display())]
+    Parse {
+        /// The file being parsed.
+        path: PathBuf,
+        /// One-based line of the error.
+        line: usize,
+        /// What went wrong.
+        message: String,
+    },
+}
+
+/// Result alias for `dst-lint` operations.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Lints one file's source. `path` is only used in diagnostics.
+pub fn lint_source(path: &Path, source: &str) -> Result<Vec<Diagnostic>> {
+    let file = syn::parse_file(source).map_err(|e| Error::Parse {
+        path: path.to_path_buf(),
+        line: e.span().start().line,
+        message: e.to_string(),
+    })?;
+    scan::scan(path, &file)
+}
+
+/// Lints a `.rs` file, or every `.rs` file under a directory.
+///
+/// Directories are walked in sorted order, skipping `target` and hidden
+/// directories, so output is stable between runs.
+pub fn lint_path(path: &Path) -> Result<Vec<Diagnostic>> {
+    let mut files = Vec::new();
+    collect_rust_files(path, &mut files)?;
+
+    let mut diagnostics = Vec::new();
+    for file in files {
+        let source = fs::read_to_string(&file).map_err(|source| Error::Io {
+            path: file.clone(),
+            source,
+        })?;
+        diagnostics.extend(lint_source(&file, &source)?);
+    }
+    Ok(diagnostics)
+}
+
+fn collect_rust_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let io_error = |source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    if path.is_file() {
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path.to_path_buf());
+        }
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)
+        .map_err(io_error)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_error)?;
+    entries.sort();
+
+    for entry in entries {
+        let name = entry.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if entry.is_dir() && (name == "target" || name.starts_with('.')) {
+            continue;
+        }
+        collect_rust_files(&entry, files)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,713 @@
+//! The syntax-tree walk behind [`lint_source`](crate::lint_source).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use proc_macro2::Span;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{
+    Arm, Attribute, Block, Expr, ExprClosure, ExprField, ExprForLoop, ExprMacro, ExprMethodCall,
+    ExprPath, FnArg, GenericArgument, Ident, ImplItem, ImplItemFn, Item, ItemFn, ItemImpl,
+    ItemStruct, Local, Member, Pat, PatIdent, PathArguments, StmtMacro, Token, TraitItem,
+    TraitItemFn, Type, TypePath, UseTree,
+};
+
+use crate::{Diagnostic, Error, Result, Rule};
+
+/// Paths flagged wherever they appear, with the rule and message.
+const FORBIDDEN: &[(&str, Rule, &str)] = &[
+    (
+        "std::time::Instant",
+        Rule::Time,
+        "std::time::Instant reads the host clock; use dst::io::time",
+    ),
+    (
+        "std::time::SystemTime",
+        Rule::Time,
+        "std::time::SystemTime reads the wall clock; derive timestamps from simulated time",
+    ),
+    (
+        "rand::random",
+        Rule::Rand,
+        "rand::random is seeded from the OS; use dst::Entropy",
+    ),
+    (
+        "rand::thread_rng",
+        Rule::Rand,
+        "rand::thread_rng is seeded from the OS; use dst::Entropy",
+    ),
+    (
+        "rand::rng",
+        Rule::Rand,
+        "rand::rng is seeded from the OS; use dst::Entropy",
+    ),
+    (
+        "std::thread::spawn",
+        Rule::ThreadSpawn,
+        "OS threads run outside the simulation scheduler; spawn a task instead",
+    ),
+    (
+        "std::thread::scope",
+        Rule::ThreadSpawn,
+        "OS threads run outside the simulation scheduler; spawn a task instead",
+    ),
+    (
+        "std::thread::Builder",
+        Rule::ThreadSpawn,
+        "OS threads run outside the simulation scheduler; spawn a task instead",
+    ),
+    (
+        "tokio::net",
+        Rule::Net,
+        "tokio::net sockets bypass the simulated network; use dst::io::net",
+    ),
+    (
+        "std::net::TcpListener",
+        Rule::Net,
+        "std::net sockets bypass the simulated network; use dst::io::net",
+    ),
+    (
+        "std::net::TcpStream",
+        Rule::Net,
+        "std::net sockets bypass the simulated network; use dst::io::net",
+    ),
+    (
+        "std::net::UdpSocket",
+        Rule::Net,
+        "std::net sockets bypass the simulated network; use dst::io::net",
+    ),
+];
+
+/// Hash collections and how many type parameters they take with an
+/// explicit hasher.
+const HASH_COLLECTIONS: &[(&str, usize)] = &[
+    ("std::collections::HashMap", 3),
+    ("std::collections::hash_map::HashMap", 3),
+    ("std::collections::HashSet", 2),
+    ("std::collections::hash_set::HashSet", 2),
+];
+
+/// Constructors that only exist for the default hasher.
+const DEFAULT_HASHER_CONSTRUCTORS: &[&str] = &["new", "with_capacity", "from"];
+
+/// Constructors that build a default-hasher collection unless a type
+/// annotation says otherwise.
+const INFERRED_CONSTRUCTORS: &[&str] = &["default", "from_iter"];
+
+/// Methods that expose iteration order.
+const ITERATION_METHODS: &[&str] = &[
+    "iter",
+    "iter_mut",
+    "keys",
+    "values",
+    "values_mut",
+    "into_iter",
+    "into_keys",
+    "into_values",
+    "drain",
+];
+
+/// Attribute paths accepted as the escape hatch.
+const ALLOW_ATTRIBUTES: &[[&str; 2]] = &[["dst", "allow"], ["dst_macros", "allow"]];
+
+pub(crate) fn scan(path: &Path, file: &syn::File) -> Result<Vec<Diagnostic>> {
+    let mut imports = Imports::default();
+    imports.visit_file(file);
+
+    let mut fields = StructFields {
+        imports: &imports,
+        by_struct: BTreeMap::new(),
+    };
+    fields.visit_file(file);
+    let fields = fields.by_struct;
+
+    let mut scanner = Scanner {
+        path,
+        imports: &imports,
+        fields: &fields,
+        scopes: Vec::new(),
+        self_types: Vec::new(),
+        allowed: Vec::new(),
+        reported: BTreeMap::new(),
+        error: None,
+    };
+    scanner.visit_file(file);
+    if let Some(error) = scanner.error {
+        return Err(error);
+    }
+    Ok(scanner.reported.into_values().collect())
+}
+
+/// Local names brought in by `use` items anywhere in the file.
+///
+/// Scoping is ignored: a name imported in one module is assumed to mean the
+/// same thing everywhere in the file.
+#[derive(Default)]
+struct Imports {
+    names: BTreeMap<String, Vec<String>>,
+    globs: Vec<Vec<String>>,
+}
+
+impl Imports {
+    fn add(&mut self, prefix: &mut Vec<String>, tree: &UseTree) {
+        match tree {
+            UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                self.add(prefix, &path.tree);
+                prefix.pop();
+            }
+            UseTree::Name(name) if name.ident == "self" => {
+                if let Some(last) = prefix.last() {
+                    self.names.insert(last.clone(), prefix.clone());
+                }
+            }
+            UseTree::Name(name) => {
+                let mut full = prefix.clone();
+                full.push(name.ident.to_string());
+                self.names.insert(name.ident.to_string(), full);
+            }
+            UseTree::Rename(rename) => {
+                let mut full = prefix.clone();
+                full.push(rename.ident.to_string());
+                self.names.insert(rename.rename.to_string(), full);
+            }
+            UseTree::Glob(_) => self.globs.push(prefix.clone()),
+            UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.add(prefix, tree);
+                }
+            }
+        }
+    }
+
+    /// Candidate absolute paths for `path`: the import-resolved path, plus
+    /// one per glob import when the first segment is not imported by name.
+    fn resolve(&self, path: &syn::Path) -> Vec<Vec<String>> {
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        let Some(first) = segments.first() else {
+            return Vec::new();
+        };
+        if path.leading_colon.is_some() {
+            return vec![segments];
+        }
+        if let Some(full) = self.names.get(first) {
+            let mut resolved = full.clone();
+            resolved.extend_from_slice(&segments[1..]);
+            return vec![resolved];
+        }
+
+        let mut candidates = vec![segments.clone()];
+        for glob in &self.globs {
+            let mut resolved = glob.clone();
+            resolved.extend_from_slice(&segments);
+            candidates.push(resolved);
+        }
+        candidates
+    }
+}
+
+impl<'ast> Visit<'ast> for Imports {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        self.add(&mut Vec::new(), &item.tree);
+    }
+}
+
+/// Returns `true` if `path` (as segments) is `prefix` or starts with it.
+fn has_prefix(path: &[String], prefix: &str) -> bool {
+    let prefix: Vec<&str> = prefix.split("::").collect();
+    path.len() >= prefix.len() && path.iter().zip(&prefix).all(|(a, b)| a == b)
+}
+
+/// Returns the hash collection `ty` names if it uses the default hasher.
+fn default_hasher_collection(imports: &Imports, ty: &TypePath) -> Option<&'static str> {
+    if ty.qself.is_some() {
+        return None;
+    }
+    let last = ty.path.segments.last()?;
+    let type_params = match &last.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter(|arg| matches!(arg, GenericArgument::Type(_)))
+            .count(),
+        _ => 0,
+    };
+    imports.resolve(&ty.path).into_iter().find_map(|resolved| {
+        HASH_COLLECTIONS
+            .iter()
+            .find(|(name, with_hasher)| resolved.join("::") == *name && type_params < *with_hasher)
+            .map(|(name, _)| *name)
+    })
+}
+
+/// Returns `true` if `expr`, bound without a type annotation, builds a
+/// default-hasher collection: `HashMap::new()`, `HashMap::default()` or
+/// `iter.collect::<HashMap<_, _>>()`.
+fn builds_default_hasher_collection(imports: &Imports, expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(call) => {
+            let Expr::Path(func) = &*call.func else {
+                return false;
+            };
+            imports.resolve(&func.path).iter().any(|resolved| {
+                default_hasher_constructor(resolved).is_some()
+                    || resolved.split_last().is_some_and(|(method, collection)| {
+                        INFERRED_CONSTRUCTORS.contains(&method.as_str())
+                            && HASH_COLLECTIONS
+                                .iter()
+                                .any(|(name, _)| collection.join("::") == *name)
+                    })
+            })
+        }
+        Expr::MethodCall(call) if call.method == "collect" => {
+            call.turbofish.as_ref().is_some_and(|turbofish| {
+                turbofish.args.iter().any(|arg| match arg {
+                    GenericArgument::Type(ty) => collection_type(imports, ty).is_some(),
+                    _ => false,
+                })
+            })
+        }
+        Expr::Paren(paren) => builds_default_hasher_collection(imports, &paren.expr),
+        _ => false,
+    }
+}
+
+fn default_hasher_constructor(resolved: &[String]) -> Option<&'static str> {
+    let (method, collection) = resolved.split_last()?;
+    let collection = collection.join("::");
+    if !DEFAULT_HASHER_CONSTRUCTORS.contains(&method.as_str()) {
+        return None;
+    }
+    HASH_COLLECTIONS
+        .iter()
+        .find(|(name, _)| collection == *name)
+        .map(|(name, _)| *name)
+}
+
+/// Returns the default-hasher collection named by `ty`, looking through
+/// references.
+fn collection_type(imports: &Imports, ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::Path(path) => default_hasher_collection(imports, path),
+        Type::Reference(reference) => collection_type(imports, &reference.elem),
+        Type::Paren(paren) => collection_type(imports, &paren.elem),
+        _ => None,
+    }
+}
+
+/// Whether each named field of each struct holds a default-hasher
+/// collection, by struct name.
+type FieldMap = BTreeMap<String, BTreeMap<String, bool>>;
+
+/// Collects the named fields of every struct in the file.
+struct StructFields<'a> {
+    imports: &'a Imports,
+    by_struct: FieldMap,
+}
+
+impl<'ast> Visit<'ast> for StructFields<'_> {
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        let fields = item
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let unordered = collection_type(self.imports, &field.ty).is_some();
+                Some((field.ident.as_ref()?.to_string(), unordered))
+            })
+            .collect();
+        self.by_struct.insert(item.ident.to_string(), fields);
+        visit::visit_item_struct(self, item);
+    }
+}
+
+/// Collects the names a pattern binds.
+#[derive(Default)]
+struct Bindings(Vec<String>);
+
+impl<'ast> Visit<'ast> for Bindings {
+    fn visit_pat_ident(&mut self, pat: &'ast PatIdent) {
+        self.0.push(pat.ident.to_string());
+        visit::visit_pat_ident(self, pat);
+    }
+}
+
+struct Scanner<'a> {
+    path: &'a Path,
+    imports: &'a Imports,
+    fields: &'a FieldMap,
+    /// Variables in scope, innermost block last, and whether each holds a
+    /// default-hasher collection. Shadowing replaces the outer binding.
+    scopes: Vec<BTreeMap<String, bool>>,
+    /// The `Self` type of each enclosing `impl`, if it is a plain path.
+    self_types: Vec<Option<String>>,
+    /// Rules allowed by enclosing items; `None` allows everything.
+    allowed: Vec<Option<BTreeSet<Rule>>>,
+    /// One diagnostic per `(line, rule)`, in source order.
+    reported: BTreeMap<(usize, Rule), Diagnostic>,
+    error: Option<Error>,
+}
+
+impl Scanner<'_> {
+    fn report(&mut self, span: Span, rule: Rule, message: impl Into<String>) {
+        let allowed = self.allowed.iter().any(|rules| match rules {
+            None => true,
+            Some(rules) => rules.contains(&rule),
+        });
+        if allowed {
+            return;
+        }
+        let start = span.start();
+        let key = (start.line, rule);
+        self.reported.entry(key).or_insert_with(|| Diagnostic {
+            path: self.path.to_path_buf(),
+            line: start.line,
+            column: start.column + 1,
+            rule,
+            message: message.into(),
+        });
+    }
+
+    /// Runs `f` with the rules allowed by `attrs` in effect.
+    fn with_attrs(&mut self, attrs: &[Attribute], f: impl FnOnce(&mut Self)) {
+        let mut pushed = 0;
+        for attr in attrs {
+            let segments: Vec<String> = attr
+                .path()
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect();
+            if !ALLOW_ATTRIBUTES.iter().any(|allow| segments == *allow) {
+                continue;
+            }
+            match self.parse_allow(attr) {
+                Ok(rules) => {
+                    self.allowed.push(rules);
+                    pushed += 1;
+                }
+                Err(error) => {
+                    self.error.get_or_insert(error);
+                }
+            }
+        }
+        f(self);
+        self.allowed.truncate(self.allowed.len() - pushed);
+    }
+
+    fn parse_allow(&self, attr: &Attribute) -> Result<Option<BTreeSet<Rule>>> {
+        let parse_error = |span: Span, message: String| Error::Parse {
+            path: self.path.to_path_buf(),
+            line: span.start().line,
+            message,
+        };
+        let names = attr
+            .parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)
+            .map_err(|e| parse_error(e.span(), format!("malformed #[dst::allow]: {}", e)))?;
+
+        let mut rules = BTreeSet::new();
+        for name in names {
+            if name == "all" {
+                return Ok(None);
+            }
+            let rule = name
+                .to_string()
+                .parse()
+                .map_err(|message| parse_error(name.span(), message))?;
+            rules.insert(rule);
+        }
+        Ok(Some(rules))
+    }
+
+    fn check_path(&mut self, path: &syn::Path, span: Span) {
+        for resolved in self.imports.resolve(path) {
+            for (forbidden, rule, message) in FORBIDDEN {
+                if has_prefix(&resolved, forbidden) {
+                    self.report(span, *rule, *message);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Runs `f` in a new variable scope.
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(BTreeMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    /// Binds every name in `pat`; a plain name gets `unordered`, names
+    /// inside destructuring patterns never do.
+    fn bind(&mut self, pat: &Pat, unordered: bool) {
+        match pat {
+            Pat::Type(typed) => self.bind(&typed.pat, unordered),
+            Pat::Ident(ident) if ident.subpat.is_none() => self.bind_name(&ident.ident, unordered),
+            _ => {
+                let mut bindings = Bindings::default();
+                bindings.visit_pat(pat);
+                for name in bindings.0 {
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.insert(name, false);
+                    }
+                }
+            }
+        }
+    }
+
+    fn bind_name(&mut self, name: &Ident, unordered: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), unordered);
+        }
+    }
+
+    /// Binds `pat`, deciding from its type annotation if it has one.
+    fn bind_typed(&mut self, pat: &Pat) {
+        let unordered = match pat {
+            Pat::Type(typed) => collection_type(self.imports, &typed.ty).is_some(),
+            _ => false,
+        };
+        self.bind(pat, unordered);
+    }
+
+    fn is_unordered_local(&self, name: &Ident) -> bool {
+        let name = name.to_string();
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name).copied())
+            .unwrap_or(false)
+    }
+
+    /// Returns `true` if `field` holds a default-hasher collection: on
+    /// `self`, per the enclosing `impl`'s struct; otherwise only if every
+    /// struct in the file with that field name holds one there.
+    fn is_unordered_field(&self, base: &Expr, field: &Ident) -> bool {
+        let field = field.to_string();
+        let is_self = matches!(base, Expr::Path(p) if p.path.is_ident("self"));
+        if let (true, Some(Some(ty))) = (is_self, self.self_types.last()) {
+            if let Some(fields) = self.fields.get(ty) {
+                return fields.get(&field).copied().unwrap_or(false);
+            }
+        }
+        let mut declared = self
+            .fields
+            .values()
+            .filter_map(|fields| fields.get(&field))
+            .peekable();
+        declared.peek().is_some() && declared.all(|unordered| *unordered)
+    }
+
+    /// Returns `true` if `expr` names a tracked default-hasher collection.
+    fn is_unordered(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Path(ExprPath { path, .. }) => path
+                .get_ident()
+                .is_some_and(|ident| self.is_unordered_local(ident)),
+            Expr::Field(ExprField {
+                base,
+                member: Member::Named(name),
+                ..
+            }) => self.is_unordered_field(base, name),
+            Expr::Reference(reference) => self.is_unordered(&reference.expr),
+            Expr::Paren(paren) => self.is_unordered(&paren.expr),
+            _ => false,
+        }
+    }
+
+    fn check_macro(&mut self, mac: &syn::Macro) {
+        // Most macros take comma-separated expressions (`vec!`, `assert!`,
+        // `println!`); anything else is skipped.
+        if let Ok(args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Scanner<'_> {
+    fn visit_item(&mut self, item: &'ast Item) {
+        let attrs: &[Attribute] = match item {
+            Item::Const(i) => &i.attrs,
+            Item::Enum(i) => &i.attrs,
+            Item::Fn(i) => &i.attrs,
+            Item::Impl(i) => &i.attrs,
+            Item::Mod(i) => &i.attrs,
+            Item::Static(i) => &i.attrs,
+            Item::Struct(i) => &i.attrs,
+            Item::Trait(i) => &i.attrs,
+            Item::Type(i) => &i.attrs,
+            Item::Union(i) => &i.attrs,
+            _ => &[],
+        };
+        self.with_attrs(attrs, |this| visit::visit_item(this, item));
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        let self_type = match &*item.self_ty {
+            Type::Path(ty) if ty.qself.is_none() => {
+                ty.path.segments.last().map(|s| s.ident.to_string())
+            }
+            _ => None,
+        };
+        self.self_types.push(self_type);
+        visit::visit_item_impl(self, item);
+        self.self_types.pop();
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        self.scoped(|this| visit::visit_item_fn(this, item));
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        self.scoped(|this| visit::visit_impl_item_fn(this, item));
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        self.scoped(|this| visit::visit_trait_item_fn(this, item));
+    }
+
+    fn visit_fn_arg(&mut self, arg: &'ast FnArg) {
+        if let FnArg::Typed(typed) = arg {
+            let unordered = collection_type(self.imports, &typed.ty).is_some();
+            self.bind(&typed.pat, unordered);
+        }
+        visit::visit_fn_arg(self, arg);
+    }
+
+    fn visit_expr_closure(&mut self, closure: &'ast ExprClosure) {
+        self.scoped(|this| {
+            for input in &closure.inputs {
+                this.bind_typed(input);
+            }
+            visit::visit_expr_closure(this, closure);
+        });
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.scoped(|this| visit::visit_block(this, block));
+    }
+
+    fn visit_local(&mut self, local: &'ast Local) {
+        // The initializer runs before the new name is in scope.
+        visit::visit_local(self, local);
+        let unordered = match &local.pat {
+            Pat::Type(typed) => collection_type(self.imports, &typed.ty).is_some(),
+            _ => local
+                .init
+                .as_ref()
+                .is_some_and(|init| builds_default_hasher_collection(self.imports, &init.expr)),
+        };
+        self.bind(&local.pat, unordered);
+    }
+
+    fn visit_arm(&mut self, arm: &'ast Arm) {
+        self.scoped(|this| {
+            this.bind(&arm.pat, false);
+            visit::visit_arm(this, arm);
+        });
+    }
+
+    fn visit_impl_item(&mut self, item: &'ast ImplItem) {
+        let attrs: &[Attribute] = match item {
+            ImplItem::Const(i) => &i.attrs,
+            ImplItem::Fn(i) => &i.attrs,
+            ImplItem::Type(i) => &i.attrs,
+            _ => &[],
+        };
+        self.with_attrs(attrs, |this| visit::visit_impl_item(this, item));
+    }
+
+    fn visit_trait_item(&mut self, item: &'ast TraitItem) {
+        let attrs: &[Attribute] = match item {
+            TraitItem::Const(i) => &i.attrs,
+            TraitItem::Fn(i) => &i.attrs,
+            TraitItem::Type(i) => &i.attrs,
+            _ => &[],
+        };
+        self.with_attrs(attrs, |this| visit::visit_trait_item(this, item));
+    }
+
+    fn visit_item_use(&mut self, _: &'ast syn::ItemUse) {
+        // Imports are reported where the imported name is used.
+    }
+
+    fn visit_expr_path(&mut self, expr: &'ast ExprPath) {
+        let span = expr.span();
+        self.check_path(&expr.path, span);
+        for resolved in self.imports.resolve(&expr.path) {
+            if let Some(collection) = default_hasher_constructor(&resolved) {
+                self.report(
+                    span,
+                    Rule::HashMap,
+                    format!(
+                        "{} uses the per-process random hasher; use dst::DetHashMap or a BTreeMap",
+                        collection
+                    ),
+                );
+                break;
+            }
+        }
+        visit::visit_expr_path(self, expr);
+    }
+
+    fn visit_type_path(&mut self, ty: &'ast TypePath) {
+        let span = ty.span();
+        self.check_path(&ty.path, span);
+        if let Some(collection) = default_hasher_collection(self.imports, ty) {
+            self.report(
+                span,
+                Rule::HashMap,
+                format!(
+                    "{} uses the per-process random hasher; use dst::DetHashMap or a BTreeMap",
+                    collection
+                ),
+            );
+        }
+        visit::visit_type_path(self, ty);
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast ExprForLoop) {
+        if self.is_unordered(&expr.expr) {
+            self.report(
+                expr.expr.span(),
+                Rule::UnorderedIter,
+                "iteration order of a default-hasher collection differs between runs",
+            );
+        }
+        self.visit_expr(&expr.expr);
+        self.scoped(|this| {
+            this.bind(&expr.pat, false);
+            this.visit_pat(&expr.pat);
+            this.visit_block(&expr.body);
+        });
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        if ITERATION_METHODS.contains(&call.method.to_string().as_str())
+            && self.is_unordered(&call.receiver)
+        {
+            self.report(
+                call.method.span(),
+                Rule::UnorderedIter,
+                format!(
+                    "`{}` on a default-hasher collection yields a different order each run",
+                    call.method
+                ),
+            );
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_macro(&mut self, expr: &'ast ExprMacro) {
+        self.check_macro(&expr.mac);
+        visit::visit_expr_macro(self, expr);
+    }
+
+    fn visit_stmt_macro(&mut self, stmt: &'ast StmtMacro) {
+        self.check_macro(&stmt.mac);
+        visit::visit_stmt_macro(self, stmt);
+    }
+}
@@ -0,0 +1,26 @@
+//! Attribute macros re-exported by the `dst` crate.
+//!
+//! Use them through `dst` (`#[dst::allow(...)]`) rather than depending on
+//! this crate directly.
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+use proc_macro::TokenStream;
+
+/// Exempts an item from `dst-lint` rules.
+///
+/// The attribute leaves the item unchanged; `dst-lint` reads it from the
+/// source. List the rules to silence, or `all`:
+///
+/// ```ignore
+/// #[dst::allow(thread_spawn)]
+/// fn spawn_metrics_exporter() { std::thread::spawn(export_forever); }
+/// ```
+///
+/// Attribute macros cannot sit on statements or expressions on stable Rust,
+/// so put it on the enclosing `fn`, `impl`, inline `mod` or other item.
+#[proc_macro_attribute]
+pub fn allow(_rules: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
@@ -0,0 +1,111 @@
+//! Runs a TCP chaos proxy on localhost between a client and a server.
+//!
+//! ```bash
+//! dst-proxy --listen 127.0.0.1:15432 --upstream 127.0.0.1:5432 --scenario faults.json
+//! ```
+//!
+//! The optional scenario (as written by `save_scenario`) is replayed from
+//! startup. Each line on stdin is applied as one JSON fault, for example
+//! `{"kind":"latency","node":"db","delay_ms":200}`. The proxy runs until
+//! interrupted.
+//!
+//! Exits with 2 on usage or I/O errors.
+
+use std::net::SocketAddr;
+use std::process::ExitCode;
+
+use dst::{load_scenario, ChaosProxy, Fault};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+const USAGE: &str = "usage: dst-proxy --listen <addr> --upstream <addr> [--scenario <faults.json>]";
+
+struct Args {
+    listen: SocketAddr,
+    upstream: SocketAddr,
+    scenario: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let scenario = match args.scenario.as_deref().map(load_scenario).transpose() {
+        Ok(scenario) => scenario.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("{}: {}", args.scenario.unwrap_or_default(), e);
+            return ExitCode::from(2);
+        }
+    };
+    let proxy = match ChaosProxy::start(args.listen, args.upstream).await {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            eprintln!("{}: {}", args.listen, e);
+            return ExitCode::from(2);
+        }
+    };
+    println!("proxying {} -> {}", proxy.local_addr(), proxy.upstream());
+
+    let replay = async {
+        if let Err(e) = proxy.run_scenario(&scenario).await {
+            eprintln!("scenario: {}", e);
+        }
+        // Keep serving after the last fault.
+        std::future::pending::<()>().await
+    };
+    let control = async {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let applied = serde_json::from_str::<Fault>(&line)
+                .map_err(dst::Error::from)
+                .and_then(|fault| proxy.apply(&fault));
+            match applied {
+                Ok(()) => println!("applied {}", line.trim()),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        // Stdin closed: keep serving.
+        std::future::pending::<()>().await
+    };
+    tokio::select! {
+        _ = replay => {}
+        _ = control => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    ExitCode::SUCCESS
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut listen = None;
+    let mut upstream = None;
+    let mut scenario = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--listen" | "--upstream" => {
+                let value = value()?;
+                let addr = value
+                    .parse()
+                    .map_err(|_| format!("invalid {} address: {}", arg, value))?;
+                if arg == "--listen" {
+                    listen = Some(addr);
+                } else {
+                    upstream = Some(addr);
+                }
+            }
+            "--scenario" => scenario = Some(value()?.clone()),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    Ok(Args {
+        listen: listen.ok_or("--listen is required")?,
+        upstream: upstream.ok_or("--upstream is required")?,
+        scenario,
+    })
+}
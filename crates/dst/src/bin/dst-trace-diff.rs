@@ -0,0 +1,81 @@
+//! Reports the first divergent event between two JSON Lines traces.
+//!
+//! ```bash
+//! dst-trace-diff run1.jsonl run2.jsonl --context 5
+//! ```
+//!
+//! Exits with 0 when the traces match, 1 when they diverge and 2 on usage or
+//! I/O errors.
+
+use std::fmt;
+use std::process::ExitCode;
+
+use dst::Trace;
+use serde::Deserialize;
+
+const USAGE: &str = "usage: dst-trace-diff <left.jsonl> <right.jsonl> [--context N]";
+
+/// Events shown before and after the divergence when `--context` is absent.
+const DEFAULT_CONTEXT: usize = 3;
+
+/// An arbitrary event payload, shown as compact JSON in reports.
+#[derive(PartialEq, Deserialize)]
+#[serde(transparent)]
+struct Json(serde_json::Value);
+
+impl fmt::Debug for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (left, right, context) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let load = |path: &str| Trace::<Json>::read_jsonl(path).map_err(|e| format!("{}: {}", path, e));
+    let (left_trace, right_trace) = match (load(left), load(right)) {
+        (Ok(l), Ok(r)) => (l, r),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    match left_trace.diff(&right_trace, context) {
+        None => {
+            println!("traces match ({} events)", left_trace.events().len());
+            ExitCode::SUCCESS
+        }
+        Some(divergence) => {
+            print!("{}", divergence);
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(&str, &str, usize), String> {
+    let mut paths = Vec::new();
+    let mut context = DEFAULT_CONTEXT;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--context" {
+            let value = iter.next().ok_or("--context needs a value")?;
+            context = value
+                .parse()
+                .map_err(|_| format!("invalid --context value: {}", value))?;
+        } else {
+            paths.push(arg.as_str());
+        }
+    }
+    match paths.as_slice() {
+        [left, right] => Ok((left, right, context)),
+        _ => Err("expected exactly two trace files".to_string()),
+    }
+}
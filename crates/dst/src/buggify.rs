@@ -0,0 +1,429 @@
+//! FoundationDB-style `buggify!` fault injection inside application code.
+//!
+//! Network faults reach only the network. Buggify sites let code misbehave
+//! at the points its authors know are interesting: fail a request early,
+//! stall before a reply, read into a smaller buffer than asked for.
+//!
+//! | Macro | Production | `simulation`, when the site fires |
+//! |-------|------------|-----------------------------------|
+//! | [`buggify!()`](crate::buggify!) | `false` | `true` |
+//! | [`buggify!(percent)`](crate::buggify!) | `false` | `true`, firing with `percent` instead of the default chance |
+//! | [`buggify_sleep!(max)`](crate::buggify_sleep!) | evaluates `max` only | sleeps a random duration up to `max` |
+//! | [`buggify_shrink!(len)`](crate::buggify_shrink!) | `len` | a random length in `1..=len` |
+//!
+//! Without the `simulation` feature the macros reduce to the values in the
+//! table at compile time, so production builds pay nothing.
+//!
+//! In simulation, sites do nothing until a [`Buggify`] is enabled on the
+//! thread running the simulation. Then, as in FoundationDB, each site is
+//! either active or inactive for the whole run, and an active site fires on
+//! each pass with a seeded chance. Both draws derive from the seed and the
+//! site's source location alone, so adding a site leaves every other site's
+//! decisions unchanged. Each firing is counted and, with
+//! [`Buggify::with_trace`], recorded in the run's trace under
+//! [`BUGGIFY_HOST`].
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io;
+//! use std::time::Duration;
+//!
+//! async fn handle(request: &[u8]) -> io::Result<usize> {
+//!     if dst::buggify!() {
+//!         return Err(io::Error::other("buggified"));
+//!     }
+//!     dst::buggify_sleep!(Duration::from_millis(50));
+//!     Ok(dst::buggify_shrink!(request.len()))
+//! }
+//!
+//! let buggify = dst::Buggify::new(7).activation_percent(100).fire_percent(100);
+//! let _enabled = buggify.enable();
+//!
+//! let mut sim = dst::turmoil::Builder::new().build();
+//! sim.client("client", async {
+//!     assert!(handle(b"ping").await.is_err());
+//!     Ok(())
+//! });
+//! sim.run().unwrap();
+//! assert_eq!(buggify.fired().len(), 1);
+//! ```
+
+use std::fmt;
+
+#[cfg(feature = "simulation")]
+pub use self::imp::*;
+
+/// Source location of a buggify site.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Site {
+    /// File containing the macro call.
+    pub file: &'static str,
+    /// Line of the macro call.
+    pub line: u32,
+    /// Column of the macro call.
+    pub column: u32,
+}
+
+impl Site {
+    /// Creates a site. The macros pass `file!()`, `line!()` and `column!()`.
+    pub const fn new(file: &'static str, line: u32, column: u32) -> Self {
+        Site { file, line, column }
+    }
+}
+
+impl fmt::Display for Site {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// The production expansion of [`buggify!`](crate::buggify!). A call rather
+/// than a literal `false`, so clippy does not flag the caller's conditions
+/// as constant.
+#[cfg(not(feature = "simulation"))]
+#[doc(hidden)]
+pub const fn off(_site: Site, _percent: Option<u8>) -> bool {
+    false
+}
+
+/// Returns `true` when this site should misbehave.
+///
+/// `buggify!()` fires with the enabled [`Buggify`]'s default chance;
+/// `buggify!(percent)` overrides it for this site. Always `false` without
+/// the `simulation` feature or when no `Buggify` is enabled.
+#[cfg(feature = "simulation")]
+#[macro_export]
+macro_rules! buggify {
+    () => {
+        $crate::buggify::fire(
+            $crate::buggify::Site::new(file!(), line!(), column!()),
+            None,
+        )
+    };
+    ($percent:expr) => {
+        $crate::buggify::fire(
+            $crate::buggify::Site::new(file!(), line!(), column!()),
+            Some($percent),
+        )
+    };
+}
+
+/// Returns `true` when this site should misbehave.
+///
+/// `buggify!()` fires with the enabled [`Buggify`]'s default chance;
+/// `buggify!(percent)` overrides it for this site. Always `false` without
+/// the `simulation` feature or when no `Buggify` is enabled.
+#[cfg(not(feature = "simulation"))]
+#[macro_export]
+macro_rules! buggify {
+    () => {
+        $crate::buggify::off(
+            $crate::buggify::Site::new(file!(), line!(), column!()),
+            None,
+        )
+    };
+    ($percent:expr) => {
+        $crate::buggify::off(
+            $crate::buggify::Site::new(file!(), line!(), column!()),
+            Some($percent),
+        )
+    };
+}
+
+/// Sleeps for a random duration up to `max` when this site fires.
+///
+/// Awaits, so it may only be used inside `async` code. Without the
+/// `simulation` feature it only evaluates `max`, so the argument is still
+/// type-checked and its bindings count as used.
+#[cfg(feature = "simulation")]
+#[macro_export]
+macro_rules! buggify_sleep {
+    ($max:expr) => {
+        if let Some(delay) = $crate::buggify::delay(
+            $crate::buggify::Site::new(file!(), line!(), column!()),
+            $max,
+        ) {
+            $crate::io::time::sleep(delay).await;
+        }
+    };
+}
+
+/// Sleeps for a random duration up to `max` when this site fires.
+///
+/// Awaits, so it may only be used inside `async` code. Without the
+/// `simulation` feature it only evaluates `max`, so the argument is still
+/// type-checked and its bindings count as used.
+#[cfg(not(feature = "simulation"))]
+#[macro_export]
+macro_rules! buggify_sleep {
+    ($max:expr) => {{
+        let _ = $max;
+    }};
+}
+
+/// Evaluates to a random length in `1..=len` when this site fires, and to
+/// `len` otherwise.
+///
+/// Use it for buffer and batch sizes to exercise short reads and partial
+/// batches. A `len` of zero is returned unchanged.
+#[cfg(feature = "simulation")]
+#[macro_export]
+macro_rules! buggify_shrink {
+    ($len:expr) => {
+        $crate::buggify::shrink(
+            $crate::buggify::Site::new(file!(), line!(), column!()),
+            $len,
+        )
+    };
+}
+
+/// Evaluates to a random length in `1..=len` when this site fires, and to
+/// `len` otherwise.
+///
+/// Use it for buffer and batch sizes to exercise short reads and partial
+/// batches. A `len` of zero is returned unchanged.
+#[cfg(not(feature = "simulation"))]
+#[macro_export]
+macro_rules! buggify_shrink {
+    ($len:expr) => {
+        $len
+    };
+}
+
+#[cfg(feature = "simulation")]
+mod imp {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::marker::PhantomData;
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::time::Duration;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use serde::{Deserialize, Serialize};
+
+    use super::Site;
+    use crate::entropy::Entropy;
+    use crate::trace::TraceRecorder;
+
+    /// Host name buggify firings are recorded under in a trace.
+    pub const BUGGIFY_HOST: &str = "buggify";
+
+    /// Entropy host name sites derive their decisions from.
+    const ENTROPY_HOST: &str = "buggify";
+
+    type FireSink = Box<dyn Fn(Duration, Site) + Send>;
+
+    thread_local! {
+        static ENABLED: RefCell<Option<Buggify>> = const { RefCell::new(None) };
+    }
+
+    /// A buggify site fired. Recorded by [`Buggify::with_trace`].
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct BuggifyEvent {
+        /// The site, as `file:line:column`.
+        pub site: String,
+    }
+
+    /// Seeded activation and firing decisions for buggify sites.
+    ///
+    /// Cheap to clone; every clone shares the same decisions and counters.
+    #[derive(Clone)]
+    pub struct Buggify {
+        inner: Arc<Mutex<Inner>>,
+    }
+
+    struct Inner {
+        entropy: Entropy,
+        activation_percent: u8,
+        fire_percent: u8,
+        sites: BTreeMap<Site, SiteState>,
+        trace: Option<FireSink>,
+    }
+
+    struct SiteState {
+        active: bool,
+        rng: StdRng,
+        fired: u64,
+    }
+
+    impl fmt::Debug for Buggify {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let inner = self.lock();
+            f.debug_struct("Buggify")
+                .field("seed", &inner.entropy.seed())
+                .field("activation_percent", &inner.activation_percent)
+                .field("fire_percent", &inner.fire_percent)
+                .field("trace", &inner.trace.is_some())
+                .finish()
+        }
+    }
+
+    impl Buggify {
+        /// Creates decisions derived from `seed`.
+        ///
+        /// A quarter of the sites are active, and an active site fires on a
+        /// quarter of its passes, as in FoundationDB.
+        pub fn new(seed: u64) -> Self {
+            Buggify {
+                inner: Arc::new(Mutex::new(Inner {
+                    entropy: Entropy::new(seed),
+                    activation_percent: 25,
+                    fire_percent: 25,
+                    sites: BTreeMap::new(),
+                    trace: None,
+                })),
+            }
+        }
+
+        /// Sets the chance, in percent, that a site is active for the run.
+        pub fn activation_percent(self, percent: u8) -> Self {
+            self.lock().activation_percent = percent.min(100);
+            self
+        }
+
+        /// Sets the chance, in percent, that an active site fires on a pass.
+        /// `buggify!(percent)` overrides it per site.
+        pub fn fire_percent(self, percent: u8) -> Self {
+            self.lock().fire_percent = percent.min(100);
+            self
+        }
+
+        /// Records every firing into `recorder`, under the host name
+        /// [`BUGGIFY_HOST`] and at the current simulated time.
+        pub fn with_trace<E>(self, recorder: TraceRecorder<E>) -> Self
+        where
+            E: From<BuggifyEvent> + Send + 'static,
+        {
+            self.lock().trace = Some(Box::new(move |at, site| {
+                let event = BuggifyEvent {
+                    site: site.to_string(),
+                };
+                recorder.record_at(at, BUGGIFY_HOST, E::from(event));
+            }));
+            self
+        }
+
+        /// Makes the macros on this thread use these decisions until the
+        /// guard is dropped.
+        ///
+        /// Turmoil runs every host on the thread that steps the simulation,
+        /// so enable it there. Parallel seed sweeps enable one per seed.
+        pub fn enable(&self) -> BuggifyGuard {
+            let previous = ENABLED.with(|enabled| enabled.replace(Some(self.clone())));
+            BuggifyGuard {
+                previous,
+                _not_send: PhantomData,
+            }
+        }
+
+        /// How often each site that fired at least once has fired.
+        pub fn fired(&self) -> BTreeMap<Site, u64> {
+            self.lock()
+                .sites
+                .iter()
+                .filter(|(_, state)| state.fired > 0)
+                .map(|(site, state)| (*site, state.fired))
+                .collect()
+        }
+
+        /// Sites that were reached, and whether each is active in this run.
+        pub fn sites(&self) -> BTreeMap<Site, bool> {
+            self.lock()
+                .sites
+                .iter()
+                .map(|(site, state)| (*site, state.active))
+                .collect()
+        }
+
+        fn roll(&self, site: Site, percent: Option<u8>) -> Option<StdRng> {
+            let mut inner = self.lock();
+            let Inner {
+                entropy,
+                activation_percent,
+                fire_percent,
+                sites,
+                trace,
+            } = &mut *inner;
+            let state = sites.entry(site).or_insert_with(|| {
+                let name = site.to_string();
+                let mut activation = entropy.rng(ENTROPY_HOST, &format!("activate {}", name));
+                SiteState {
+                    active: activation.random_range(0..100) < *activation_percent,
+                    rng: entropy.rng(ENTROPY_HOST, &format!("fire {}", name)),
+                    fired: 0,
+                }
+            });
+            if !state.active {
+                return None;
+            }
+            let percent = percent.unwrap_or(*fire_percent).min(100);
+            if state.rng.random_range(0..100) >= percent {
+                return None;
+            }
+
+            state.fired += 1;
+            if let Some(trace) = trace {
+                trace(turmoil::sim_elapsed().unwrap_or_default(), site);
+            }
+            // Seeded from the site's stream, so what the firing does is as
+            // reproducible as whether it fires.
+            Some(StdRng::seed_from_u64(state.rng.random()))
+        }
+
+        fn lock(&self) -> MutexGuard<'_, Inner> {
+            self.inner
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        }
+    }
+
+    /// Keeps a [`Buggify`] enabled on the current thread. Dropping it
+    /// restores whatever was enabled before.
+    #[derive(Debug)]
+    #[must_use = "buggify is disabled again when the guard is dropped"]
+    pub struct BuggifyGuard {
+        previous: Option<Buggify>,
+        _not_send: PhantomData<*const ()>,
+    }
+
+    impl Drop for BuggifyGuard {
+        fn drop(&mut self) {
+            let previous = self.previous.take();
+            ENABLED.with(|enabled| *enabled.borrow_mut() = previous);
+        }
+    }
+
+    /// Decides whether `site` fires. Called by [`buggify!`](crate::buggify!).
+    #[doc(hidden)]
+    pub fn fire(site: Site, percent: Option<u8>) -> bool {
+        roll(site, percent).is_some()
+    }
+
+    /// Called by [`buggify_sleep!`](crate::buggify_sleep!).
+    #[doc(hidden)]
+    pub fn delay(site: Site, max: Duration) -> Option<Duration> {
+        let mut rng = roll(site, None)?;
+        let micros = u64::try_from(max.as_micros()).unwrap_or(u64::MAX);
+        Some(Duration::from_micros(rng.random_range(0..=micros)))
+    }
+
+    /// Called by [`buggify_shrink!`](crate::buggify_shrink!).
+    #[doc(hidden)]
+    pub fn shrink(site: Site, len: usize) -> usize {
+        if len == 0 {
+            return len;
+        }
+        match roll(site, None) {
+            Some(mut rng) => rng.random_range(1..=len),
+            None => len,
+        }
+    }
+
+    fn roll(site: Site, percent: Option<u8>) -> Option<StdRng> {
+        let buggify = ENABLED.with(|enabled| enabled.borrow().clone())?;
+        buggify.roll(site, percent)
+    }
+}
@@ -0,0 +1,251 @@
+//! Per-host clocks with offsets, drift and jumps.
+//!
+//! Tokio's paused clock, and turmoil's, is perfect and shared by every host.
+//! Real machines disagree: each wall clock sits some offset from true time,
+//! runs a few parts per million fast or slow, and is stepped when NTP
+//! corrects it. Lease and heartbeat logic that is correct on a perfect clock
+//! can fail on these.
+//!
+//! [`SimClocks`] holds that state for every host and [`HostClock`] reads one
+//! host's clocks. Hosts get their `HostClock` as
+//! [`io::time::Clock`](crate::io::time::Clock), and
+//! [`FaultDriver`](crate::FaultDriver) changes the state when it applies
+//! [`Fault::ClockOffset`](crate::Fault::ClockOffset),
+//! [`Fault::ClockDrift`](crate::Fault::ClockDrift) and
+//! [`Fault::ClockJump`](crate::Fault::ClockJump).
+//!
+//! Each host has two clocks, as an OS does:
+//!
+//! | Clock | Offset | Drift | Jumps |
+//! |-------|--------|-------|-------|
+//! | wall ([`HostClock::wall_time`]) | yes | yes | yes |
+//! | monotonic ([`HostClock::elapsed`], [`HostClock::sleep`]) | no | yes | no |
+//!
+//! Wall-clock times are durations since the Unix epoch rather than
+//! `SystemTime`s, which `clippy.toml` and `dst-lint` reject. Hosts without
+//! clock faults read true simulated time, starting at [`DEFAULT_EPOCH`].
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use tokio::time::error::Elapsed;
+
+/// Wall-clock time at the start of a simulation, since the Unix epoch,
+/// unless [`SimClocks::with_epoch`] sets another: 2026-01-01T00:00:00Z.
+pub const DEFAULT_EPOCH: Duration = Duration::from_secs(1_767_225_600);
+
+const NANOS_PER_MS: i128 = 1_000_000;
+const PPM: i128 = 1_000_000;
+
+/// Shared per-host clock state.
+///
+/// Cheap to clone; every clone refers to the same state.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use dst::SimClocks;
+///
+/// let clocks = SimClocks::new().with_epoch(Duration::ZERO);
+/// // 100ppm fast: after 1000s the clock is 100ms ahead.
+/// clocks.set_drift("a", 100, Duration::ZERO);
+/// let at = Duration::from_secs(1000);
+/// assert_eq!(clocks.monotonic("a", at), at + Duration::from_millis(100));
+///
+/// // An NTP step moves the wall clock back, but not the monotonic clock.
+/// clocks.jump("a", -100);
+/// assert_eq!(clocks.wall_time("a", at), at);
+/// assert_eq!(clocks.monotonic("a", at), at + Duration::from_millis(100));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimClocks {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    epoch: Duration,
+    hosts: BTreeMap<String, ClockState>,
+}
+
+/// One host's clocks as piecewise-linear functions of true time.
+#[derive(Debug, Clone, Copy, Default)]
+struct ClockState {
+    drift_ppm: i32,
+    /// True time of the last drift change.
+    anchor: Duration,
+    /// Nanoseconds the clock had gained, or lost, by `anchor`.
+    drift_at_anchor: i128,
+    /// Nanoseconds the wall clock reads ahead of the monotonic clock.
+    offset: i128,
+}
+
+impl ClockState {
+    /// Nanoseconds gained by true time `now`.
+    fn drift(&self, now: Duration) -> i128 {
+        let since = now.saturating_sub(self.anchor).as_nanos() as i128;
+        self.drift_at_anchor + since * i128::from(self.drift_ppm) / PPM
+    }
+
+    fn monotonic(&self, now: Duration) -> i128 {
+        now.as_nanos() as i128 + self.drift(now)
+    }
+
+    fn wall(&self, now: Duration) -> i128 {
+        self.monotonic(now) + self.offset
+    }
+}
+
+impl SimClocks {
+    /// Creates clocks that all read true time, starting at
+    /// [`DEFAULT_EPOCH`].
+    pub fn new() -> Self {
+        SimClocks {
+            inner: Arc::new(Mutex::new(Inner {
+                epoch: DEFAULT_EPOCH,
+                hosts: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Sets the wall-clock time at the start of the simulation, since the
+    /// Unix epoch.
+    pub fn with_epoch(self, epoch: Duration) -> Self {
+        self.lock().epoch = epoch;
+        self
+    }
+
+    /// The clocks of `host`.
+    pub fn host(&self, host: impl Into<String>) -> HostClock {
+        HostClock {
+            host: host.into(),
+            clocks: self.clone(),
+        }
+    }
+
+    /// Sets `host`'s wall clock to read `offset_ms` ahead of true time at
+    /// `now`, or behind when negative. Replaces the error from earlier
+    /// offsets, jumps and drift; the monotonic clock is unaffected.
+    pub fn set_offset(&self, host: &str, offset_ms: i64, now: Duration) {
+        let mut inner = self.lock();
+        let state = inner.hosts.entry(host.to_string()).or_default();
+        state.offset = i128::from(offset_ms) * NANOS_PER_MS - state.drift(now);
+    }
+
+    /// Makes `host`'s clocks run `ppm` parts per million fast from `now`, or
+    /// slow when negative. Zero stops further drift; time already gained or
+    /// lost stays.
+    ///
+    /// Values at or below -1,000,000 would stop the clock and are clamped.
+    pub fn set_drift(&self, host: &str, ppm: i32, now: Duration) {
+        let mut inner = self.lock();
+        let state = inner.hosts.entry(host.to_string()).or_default();
+        state.drift_at_anchor = state.drift(now);
+        state.anchor = now;
+        state.drift_ppm = ppm.max(-999_999);
+    }
+
+    /// Steps `host`'s wall clock by `delta_ms`, as an NTP correction does.
+    /// The monotonic clock is unaffected.
+    pub fn jump(&self, host: &str, delta_ms: i64) {
+        let mut inner = self.lock();
+        let state = inner.hosts.entry(host.to_string()).or_default();
+        state.offset += i128::from(delta_ms) * NANOS_PER_MS;
+    }
+
+    /// What `host`'s wall clock reads at true simulated time `now`, since
+    /// the Unix epoch. Clocks set before the epoch read zero.
+    pub fn wall_time(&self, host: &str, now: Duration) -> Duration {
+        let inner = self.lock();
+        let nanos = inner.epoch.as_nanos() as i128 + inner.state(host).wall(now);
+        duration_from_nanos(nanos)
+    }
+
+    /// What `host`'s monotonic clock reads at true simulated time `now`,
+    /// measured from the start of the simulation.
+    pub fn monotonic(&self, host: &str, now: Duration) -> Duration {
+        let nanos = self.lock().state(host).monotonic(now);
+        duration_from_nanos(nanos)
+    }
+
+    /// True time that passes while `host`'s monotonic clock advances by
+    /// `local`, at its current drift rate.
+    pub fn true_duration(&self, host: &str, local: Duration) -> Duration {
+        let rate = PPM + i128::from(self.lock().state(host).drift_ppm);
+        // Round up so a sleep never ends before the local clock reaches it.
+        let nanos = (local.as_nanos() as i128 * PPM + rate - 1) / rate;
+        duration_from_nanos(nanos)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // A panic while holding the lock cannot leave a clock half-updated.
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for SimClocks {
+    fn default() -> Self {
+        SimClocks::new()
+    }
+}
+
+impl Inner {
+    fn state(&self, host: &str) -> ClockState {
+        self.hosts.get(host).copied().unwrap_or_default()
+    }
+}
+
+fn duration_from_nanos(nanos: i128) -> Duration {
+    let nanos = u128::try_from(nanos).unwrap_or(0);
+    Duration::new(
+        u64::try_from(nanos / 1_000_000_000).unwrap_or(u64::MAX),
+        (nanos % 1_000_000_000) as u32,
+    )
+}
+
+/// One host's view of time under [`SimClocks`].
+///
+/// Must be used from inside a turmoil host or client, whose simulated time
+/// it reads.
+#[derive(Debug, Clone)]
+pub struct HostClock {
+    host: String,
+    clocks: SimClocks,
+}
+
+impl HostClock {
+    /// The host's wall-clock time since the Unix epoch, for timestamps and
+    /// lease expiry times exchanged with other hosts.
+    pub fn wall_time(&self) -> Duration {
+        self.clocks.wall_time(&self.host, turmoil::elapsed())
+    }
+
+    /// Time on the host's monotonic clock since the simulation started, for
+    /// measuring intervals locally.
+    pub fn elapsed(&self) -> Duration {
+        self.clocks.monotonic(&self.host, turmoil::elapsed())
+    }
+
+    /// Sleeps until the host's monotonic clock has advanced by `duration`:
+    /// longer in true time on a slow clock, shorter on a fast one.
+    ///
+    /// The drift rate is read once, when the sleep starts.
+    pub async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(self.clocks.true_duration(&self.host, duration)).await;
+    }
+
+    /// Runs `future` until the host's monotonic clock has advanced by
+    /// `duration`.
+    pub async fn timeout<F: Future>(
+        &self,
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        tokio::time::timeout(self.clocks.true_duration(&self.host, duration), future).await
+    }
+}
@@ -0,0 +1,96 @@
+//! Hash collections with reproducible iteration order.
+//!
+//! | Type | Order | Hasher |
+//! |------|-------|--------|
+//! | [`DetHashMap`], [`DetHashSet`] | Fixed by the keys | FNV-1a, fixed |
+//! | [`DetIndexMap`], [`DetIndexSet`] | Insertion order | FNV-1a, fixed |
+//! | [`SeededHashMap`], [`SeededHashSet`] | Fixed by the keys and seed | FNV-1a keyed from the seed |
+//!
+//! The fixed FNV-1a hasher is the cheapest, but every run sees the same
+//! collisions, and FNV-1a collides badly on keys that differ only in a few
+//! high bits. The seeded hasher costs a finalizer per hash and moves the
+//! collisions with the seed. The insertion-ordered types also make the order
+//! independent of the hasher, at the cost of a second allocation. Run
+//! `cargo bench -p dst --bench collections` for numbers against
+//! `std::collections::HashMap`.
+
+use std::collections::{HashMap, HashSet};
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::hash::{DeterministicBuildHasher, SeededBuildHasher};
+
+/// Type alias for a `HashMap` with deterministic iteration order.
+pub type DetHashMap<K, V> = HashMap<K, V, DeterministicBuildHasher>;
+
+/// Type alias for a `HashSet` with deterministic iteration order.
+pub type DetHashSet<T> = HashSet<T, DeterministicBuildHasher>;
+
+/// A map that iterates in insertion order, hashed with FNV-1a.
+pub type DetIndexMap<K, V> = IndexMap<K, V, DeterministicBuildHasher>;
+
+/// A set that iterates in insertion order, hashed with FNV-1a.
+pub type DetIndexSet<T> = IndexSet<T, DeterministicBuildHasher>;
+
+/// A `HashMap` whose order is reproducible for one seed and varies across
+/// seeds.
+pub type SeededHashMap<K, V> = HashMap<K, V, SeededBuildHasher>;
+
+/// A `HashSet` whose order is reproducible for one seed and varies across
+/// seeds.
+pub type SeededHashSet<T> = HashSet<T, SeededBuildHasher>;
+
+/// Creates a new, empty [`DetHashMap`].
+///
+/// # Examples
+///
+/// ```
+/// let mut heartbeats = dst::det_hash_map();
+/// heartbeats.insert("node-a".to_string(), 1u64);
+/// assert_eq!(heartbeats.get("node-a"), Some(&1));
+/// ```
+pub fn det_hash_map<K, V>() -> DetHashMap<K, V> {
+    HashMap::with_hasher(DeterministicBuildHasher)
+}
+
+/// Creates a new, empty [`DetHashSet`].
+pub fn det_hash_set<T>() -> DetHashSet<T> {
+    HashSet::with_hasher(DeterministicBuildHasher)
+}
+
+/// Creates a new, empty [`DetIndexMap`].
+///
+/// # Examples
+///
+/// ```
+/// let mut joined = dst::collections::det_index_map();
+/// joined.insert("node-c", 3);
+/// joined.insert("node-a", 1);
+/// assert_eq!(joined.keys().collect::<Vec<_>>(), [&"node-c", &"node-a"]);
+/// ```
+pub fn det_index_map<K, V>() -> DetIndexMap<K, V> {
+    IndexMap::with_hasher(DeterministicBuildHasher)
+}
+
+/// Creates a new, empty [`DetIndexSet`].
+pub fn det_index_set<T>() -> DetIndexSet<T> {
+    IndexSet::with_hasher(DeterministicBuildHasher)
+}
+
+/// Creates a new, empty [`SeededHashMap`] keyed with `seed`.
+///
+/// # Examples
+///
+/// ```
+/// let mut peers = dst::collections::seeded_hash_map(42);
+/// peers.insert("node-b", 2);
+/// assert_eq!(peers.get("node-b"), Some(&2));
+/// ```
+pub fn seeded_hash_map<K, V>(seed: u64) -> SeededHashMap<K, V> {
+    HashMap::with_hasher(SeededBuildHasher::new(seed))
+}
+
+/// Creates a new, empty [`SeededHashSet`] keyed with `seed`.
+pub fn seeded_hash_set<T>(seed: u64) -> SeededHashSet<T> {
+    HashSet::with_hasher(SeededBuildHasher::new(seed))
+}
@@ -0,0 +1,423 @@
+//! Phi-accrual failure detection over the [`io`](crate::io) facade.
+//!
+//! A fixed heartbeat timeout is either too short for a congested network or
+//! too long for a dead peer. The phi-accrual detector (Hayashibara et al.)
+//! instead learns the distribution of each peer's heartbeat inter-arrival
+//! times and reports suspicion as a level, phi: the peer is suspected once
+//! `phi = -log10(P(a heartbeat arrives later than now))` exceeds a
+//! threshold. A threshold of 8 means about one wrong suspicion in 10^8
+//! heartbeats, if intervals were normally distributed.
+//!
+//! [`PhiAccrual`] is the arithmetic on its own, driven by explicit
+//! timestamps. [`FailureDetector`] runs it on a host: it sends heartbeats
+//! to its peers over UDP, feeds the ones it receives into a `PhiAccrual`,
+//! and reports every [`MembershipEvent`] to an observer. It uses
+//! [`io::net`](crate::io::net) and [`io::time::Clock`](crate::io::time::Clock),
+//! so the same code runs in production and under turmoil, where partition,
+//! latency and clock faults act on it.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::io::net::UdpSocket;
+use crate::io::time::Clock;
+
+/// Largest heartbeat datagram the detector accepts.
+const MAX_DATAGRAM: usize = 1024;
+
+type Observer = Box<dyn Fn(&MembershipEvent) + Send + Sync>;
+
+/// Per-peer phi-accrual suspicion levels.
+///
+/// Time is whatever the caller measures it from, as long as it is
+/// monotonic; [`FailureDetector`] uses its host's monotonic clock.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use dst::PhiAccrual;
+///
+/// let ms = Duration::from_millis;
+/// let mut detector = PhiAccrual::new();
+/// for beat in 0..20 {
+///     detector.heartbeat("node-b", ms(100 * beat));
+/// }
+/// // Right on schedule: no suspicion.
+/// assert!(detector.is_available("node-b", ms(2_000)));
+/// // A second of silence from a peer that beats every 100ms.
+/// assert!(!detector.is_available("node-b", ms(3_000)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PhiAccrual {
+    threshold: f64,
+    window: usize,
+    min_std_deviation: Duration,
+    acceptable_pause: Duration,
+    first_heartbeat_estimate: Duration,
+    peers: BTreeMap<String, Arrivals>,
+}
+
+/// One peer's recent heartbeat inter-arrival times, in milliseconds.
+#[derive(Debug, Clone)]
+struct Arrivals {
+    last: Duration,
+    intervals: VecDeque<f64>,
+    sum: f64,
+    sum_of_squares: f64,
+}
+
+impl Arrivals {
+    fn push(&mut self, interval: f64, window: usize) {
+        if self.intervals.len() >= window {
+            if let Some(oldest) = self.intervals.pop_front() {
+                self.sum -= oldest;
+                self.sum_of_squares -= oldest * oldest;
+            }
+        }
+        self.intervals.push_back(interval);
+        self.sum += interval;
+        self.sum_of_squares += interval * interval;
+    }
+
+    fn mean(&self) -> f64 {
+        self.sum / self.intervals.len() as f64
+    }
+
+    fn std_deviation(&self) -> f64 {
+        let mean = self.mean();
+        (self.sum_of_squares / self.intervals.len() as f64 - mean * mean)
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+impl PhiAccrual {
+    /// Creates a detector with a threshold of 8, a window of 100 intervals,
+    /// a minimum standard deviation of 50ms, no acceptable pause, and a
+    /// first estimate of 100ms between heartbeats.
+    pub fn new() -> Self {
+        PhiAccrual {
+            threshold: 8.0,
+            window: 100,
+            min_std_deviation: Duration::from_millis(50),
+            acceptable_pause: Duration::ZERO,
+            first_heartbeat_estimate: Duration::from_millis(100),
+            peers: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the phi above which a peer is suspected. Lower values detect
+    /// failures sooner and suspect healthy peers more often.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is not positive.
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        assert!(threshold > 0.0, "phi threshold must be positive");
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets how many recent inter-arrival times the estimate uses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn window(mut self, window: usize) -> Self {
+        assert!(window > 0, "phi window must not be empty");
+        self.window = window;
+        self
+    }
+
+    /// Sets a floor on the estimated standard deviation, so perfectly
+    /// regular heartbeats do not make the smallest delay look fatal.
+    pub fn min_std_deviation(mut self, min: Duration) -> Self {
+        self.min_std_deviation = min;
+        self
+    }
+
+    /// Sets a pause, on top of the mean interval, that does not raise
+    /// suspicion: for example the longest latency change or GC pause the
+    /// system should ride out.
+    pub fn acceptable_pause(mut self, pause: Duration) -> Self {
+        self.acceptable_pause = pause;
+        self
+    }
+
+    /// Sets the interval assumed before a peer's real intervals are known.
+    /// Usually the heartbeat interval.
+    pub fn first_heartbeat_estimate(mut self, estimate: Duration) -> Self {
+        self.first_heartbeat_estimate = estimate;
+        self
+    }
+
+    /// The phi above which a peer is suspected.
+    pub fn suspicion_threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Records a heartbeat from `peer` at `now`.
+    ///
+    /// The first heartbeat only starts the clock. An interval long enough
+    /// to have raised suspicion is not added to the history, so one
+    /// partition does not make the detector slow to notice the next.
+    pub fn heartbeat(&mut self, peer: &str, now: Duration) {
+        let suspected = !self.is_available(peer, now);
+        let window = self.window;
+        match self.peers.get_mut(peer) {
+            Some(arrivals) => {
+                let interval = now.saturating_sub(arrivals.last);
+                if !suspected {
+                    arrivals.push(millis(interval), window);
+                }
+                arrivals.last = arrivals.last.max(now);
+            }
+            None => {
+                // Seed the history with the estimate, give or take a
+                // quarter, until real intervals replace it.
+                let estimate = millis(self.first_heartbeat_estimate);
+                let mut arrivals = Arrivals {
+                    last: now,
+                    intervals: VecDeque::new(),
+                    sum: 0.0,
+                    sum_of_squares: 0.0,
+                };
+                arrivals.push(estimate * 0.75, window);
+                arrivals.push(estimate * 1.25, window);
+                self.peers.insert(peer.to_string(), arrivals);
+            }
+        }
+    }
+
+    /// Suspicion level of `peer` at `now`. Zero for peers never heard from.
+    pub fn phi(&self, peer: &str, now: Duration) -> f64 {
+        let Some(arrivals) = self.peers.get(peer) else {
+            return 0.0;
+        };
+        let since = millis(now.saturating_sub(arrivals.last));
+        let mean = arrivals.mean() + millis(self.acceptable_pause);
+        let std_deviation = arrivals.std_deviation().max(millis(self.min_std_deviation));
+        phi(since, mean, std_deviation)
+    }
+
+    /// Whether `peer`'s phi at `now` is at or below the threshold.
+    pub fn is_available(&self, peer: &str, now: Duration) -> bool {
+        self.phi(peer, now) <= self.threshold
+    }
+
+    /// Forgets `peer`, as if never heard from.
+    pub fn remove(&mut self, peer: &str) {
+        self.peers.remove(peer);
+    }
+}
+
+impl Default for PhiAccrual {
+    fn default() -> Self {
+        PhiAccrual::new()
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000.0
+}
+
+/// `-log10` of the chance that a heartbeat arrives more than `since` after
+/// the last one, using the logistic approximation of the normal CDF.
+fn phi(since: f64, mean: f64, std_deviation: f64) -> f64 {
+    let y = (since - mean) / std_deviation;
+    let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+    let phi = if since > mean {
+        -(e / (1.0 + e)).log10()
+    } else {
+        -(1.0 - 1.0 / (1.0 + e)).log10()
+    };
+    // The tail underflows to infinity long after any sensible threshold.
+    phi.min(f64::MAX)
+}
+
+/// A change in whether a [`FailureDetector`] considers a peer alive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MembershipEvent {
+    /// The peer's phi rose above the threshold.
+    Suspected {
+        /// The suspected peer.
+        peer: String,
+        /// Its phi when the detector noticed.
+        phi: f64,
+    },
+    /// A heartbeat from a suspected peer brought its phi back down.
+    Recovered {
+        /// The peer that is alive again.
+        peer: String,
+    },
+}
+
+impl MembershipEvent {
+    /// The peer the event is about.
+    pub fn peer(&self) -> &str {
+        match self {
+            MembershipEvent::Suspected { peer, .. } | MembershipEvent::Recovered { peer } => peer,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Heartbeat {
+    from: String,
+}
+
+/// Heartbeats a set of peers over UDP and reports which ones it suspects.
+///
+/// Every peer listens on the same port. Peers count as heard from when the
+/// detector starts, so one that never answers is suspected after the usual
+/// delay. Suspicion is re-evaluated when a heartbeat arrives and once per
+/// heartbeat interval, so events lag phi crossing the threshold by at most
+/// one interval.
+pub struct FailureDetector {
+    id: String,
+    peers: Vec<String>,
+    port: u16,
+    clock: Clock,
+    heartbeat_interval: Duration,
+    phi: Option<PhiAccrual>,
+    observer: Option<Observer>,
+}
+
+impl fmt::Debug for FailureDetector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FailureDetector")
+            .field("id", &self.id)
+            .field("peers", &self.peers)
+            .field("port", &self.port)
+            .field("heartbeat_interval", &self.heartbeat_interval)
+            .field("phi", &self.phi)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+
+impl FailureDetector {
+    /// Creates the detector for `id` in a cluster of `nodes`, all listening
+    /// on `port`, timing heartbeats with `clock`. `nodes` may include `id`.
+    ///
+    /// Heartbeats default to every 100ms.
+    pub fn new<I, S>(id: impl Into<String>, nodes: I, port: u16, clock: Clock) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let id = id.into();
+        let peers = nodes
+            .into_iter()
+            .map(Into::into)
+            .filter(|node| *node != id)
+            .collect();
+        FailureDetector {
+            id,
+            peers,
+            port,
+            clock,
+            heartbeat_interval: Duration::from_millis(100),
+            phi: None,
+            observer: None,
+        }
+    }
+
+    /// Sets how often heartbeats are sent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "heartbeat interval must be non-zero");
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Sets the phi-accrual parameters, such as the threshold. Defaults to
+    /// [`PhiAccrual::new`] with the heartbeat interval as first estimate.
+    pub fn phi_accrual(mut self, phi: PhiAccrual) -> Self {
+        self.phi = Some(phi);
+        self
+    }
+
+    /// Calls `observer` with every membership change, e.g. to record
+    /// detection latency from a test.
+    pub fn observe(mut self, observer: impl Fn(&MembershipEvent) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Heartbeats and watches the peers until an I/O error occurs.
+    pub async fn run(self) -> Result<()> {
+        let FailureDetector {
+            id,
+            peers,
+            port,
+            clock,
+            heartbeat_interval,
+            phi,
+            observer,
+        } = self;
+        let mut phi =
+            phi.unwrap_or_else(|| PhiAccrual::new().first_heartbeat_estimate(heartbeat_interval));
+        let socket = UdpSocket::bind(("0.0.0.0", port)).await?;
+        let heartbeat = serde_json::to_vec(&Heartbeat { from: id })?;
+
+        for peer in &peers {
+            phi.heartbeat(peer, clock.elapsed());
+        }
+        let mut suspected = BTreeSet::new();
+        let mut next_beat = clock.elapsed();
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+
+        loop {
+            let until_beat = next_beat.saturating_sub(clock.elapsed());
+            tokio::select! {
+                received = socket.recv_from(&mut buf) => {
+                    let (len, _) = received?;
+                    if let Ok(beat) = serde_json::from_slice::<Heartbeat>(&buf[..len]) {
+                        if peers.contains(&beat.from) {
+                            phi.heartbeat(&beat.from, clock.elapsed());
+                        }
+                    }
+                }
+                _ = clock.sleep(until_beat) => {
+                    for peer in &peers {
+                        // Heartbeats to unreachable peers are lost; their
+                        // silence is what the peers detect.
+                        let _ = socket.send_to(&heartbeat, (peer.as_str(), port)).await;
+                    }
+                    next_beat += heartbeat_interval;
+                }
+            }
+
+            let now = clock.elapsed();
+            for peer in &peers {
+                let level = phi.phi(peer, now);
+                let event = match (level > phi.suspicion_threshold(), suspected.contains(peer)) {
+                    (true, false) => {
+                        suspected.insert(peer.clone());
+                        MembershipEvent::Suspected {
+                            peer: peer.clone(),
+                            phi: level,
+                        }
+                    }
+                    (false, true) => {
+                        suspected.remove(peer);
+                        MembershipEvent::Recovered { peer: peer.clone() }
+                    }
+                    _ => continue,
+                };
+                if let Some(observer) = &observer {
+                    observer(&event);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Determinism checks for arbitrary simulations.
+//!
+//! [`DeterminismCheck`] runs the same simulation several times with one seed,
+//! recording each run into a fresh [`TraceRecorder`], and fails with the
+//! first divergent event if any run's trace differs from the first.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! let trace = dst::assert_deterministic(42, |seed, recorder| {
+//!     let mut sim = dst::turmoil::Builder::new().rng_seed(seed).build();
+//!     let client = recorder.host("client");
+//!     sim.client("client", async move {
+//!         tokio::time::sleep(Duration::from_millis(5)).await;
+//!         client.record("woke");
+//!         Ok(())
+//!     });
+//!     sim.run().unwrap();
+//! });
+//! assert_eq!(trace.events().len(), 1);
+//! ```
+
+use std::fmt;
+
+use crate::trace::{Divergence, Trace, TraceRecorder};
+
+/// Runs a simulation repeatedly with one seed and compares the traces.
+#[derive(Debug, Clone)]
+pub struct DeterminismCheck {
+    seed: u64,
+    runs: usize,
+    context: usize,
+}
+
+impl DeterminismCheck {
+    /// Creates a check that runs twice with `seed` and shows five events of
+    /// context on failure.
+    pub fn new(seed: u64) -> Self {
+        DeterminismCheck {
+            seed,
+            runs: 2,
+            context: 5,
+        }
+    }
+
+    /// Sets how many times the simulation runs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `runs` is less than two; one run has nothing to compare.
+    pub fn runs(mut self, runs: usize) -> Self {
+        assert!(runs >= 2, "a determinism check needs at least two runs");
+        self.runs = runs;
+        self
+    }
+
+    /// Sets how many events around the divergence a failure shows.
+    pub fn context(mut self, context: usize) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Runs `run` once per configured run and compares every trace against
+    /// the first.
+    ///
+    /// `run` receives the seed and an empty recorder; it must build the
+    /// simulation from the seed, record events into the recorder and run
+    /// the simulation to completion. Returns the first run's trace.
+    pub fn check<E, F>(&self, mut run: F) -> Result<Trace<E>, DeterminismFailure<E>>
+    where
+        E: Clone + PartialEq,
+        F: FnMut(u64, &TraceRecorder<E>),
+    {
+        let expected = record(self.seed, &mut run);
+        for index in 1..self.runs {
+            let actual = record(self.seed, &mut run);
+            if expected.diff(&actual, self.context).is_some() {
+                return Err(DeterminismFailure {
+                    seed: self.seed,
+                    run: index,
+                    context: self.context,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(expected)
+    }
+
+    /// Like [`DeterminismCheck::check`], but panics with the divergence
+    /// report on failure.
+    pub fn assert<E, F>(&self, run: F) -> Trace<E>
+    where
+        E: Clone + PartialEq + fmt::Debug,
+        F: FnMut(u64, &TraceRecorder<E>),
+    {
+        self.check(run)
+            .unwrap_or_else(|failure| panic!("{}", failure))
+    }
+}
+
+/// Runs `run` twice with `seed` and panics if the traces differ.
+///
+/// Shorthand for `DeterminismCheck::new(seed).assert(run)`.
+pub fn assert_deterministic<E, F>(seed: u64, run: F) -> Trace<E>
+where
+    E: Clone + PartialEq + fmt::Debug,
+    F: FnMut(u64, &TraceRecorder<E>),
+{
+    DeterminismCheck::new(seed).assert(run)
+}
+
+fn record<E, F>(seed: u64, run: &mut F) -> Trace<E>
+where
+    E: Clone,
+    F: FnMut(u64, &TraceRecorder<E>),
+{
+    let recorder = TraceRecorder::new();
+    run(seed, &recorder);
+    recorder.trace()
+}
+
+/// Two runs with the same seed recorded different traces.
+#[derive(Debug, Clone)]
+pub struct DeterminismFailure<E> {
+    /// The seed both runs used.
+    pub seed: u64,
+    /// Index of the run that diverged from run 0.
+    pub run: usize,
+    /// Trace of run 0.
+    pub expected: Trace<E>,
+    /// Trace of the diverging run.
+    pub actual: Trace<E>,
+    context: usize,
+}
+
+impl<E: PartialEq> DeterminismFailure<E> {
+    /// The first event where the diverging run differs from run 0.
+    pub fn divergence(&self) -> Divergence<'_, E> {
+        self.expected
+            .diff(&self.actual, self.context)
+            .expect("a determinism failure always has a divergence")
+    }
+}
+
+impl<E: PartialEq + fmt::Debug> fmt::Display for DeterminismFailure<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "seed {} is not deterministic: run {} differs from run 0 (left = run 0, right = run {})",
+            self.seed, self.run, self.run
+        )?;
+        write!(f, "{}", self.divergence())
+    }
+}
+
+impl<E: PartialEq + fmt::Debug> std::error::Error for DeterminismFailure<E> {}
@@ -0,0 +1,309 @@
+//! Applies fault scenarios to a live turmoil simulation.
+//!
+//! [`FaultDriver`] steps a [`turmoil::Sim`] and fires each scheduled
+//! [`Fault`] once the simulated clock reaches its time, recording every
+//! applied fault so a run can be inspected afterwards.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+use turmoil::Sim;
+
+use crate::clock::SimClocks;
+use crate::fault::Fault;
+use crate::fs::SimFs;
+use crate::network::NetworkFaults;
+use crate::trace::TraceRecorder;
+
+/// Host name the driver records applied faults under in a trace.
+pub const FAULT_DRIVER_HOST: &str = "fault-driver";
+
+type FaultSink = Box<dyn Fn(Duration, &Fault) + Send>;
+
+/// A fault that the driver has applied to the simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedFault {
+    /// The time the fault was scheduled for.
+    pub scheduled_at: Duration,
+    /// Simulated time when the fault was actually applied. Faults are applied
+    /// between steps, so this is the first step boundary at or after
+    /// `scheduled_at`.
+    pub applied_at: Duration,
+    /// The fault itself.
+    pub fault: Fault,
+}
+
+/// Steps a [`Sim`] and fires a fault schedule at its simulated times.
+///
+/// Every node named in the schedule must already be registered with the
+/// simulation (via `sim.host` or `sim.client`) before the driver first runs.
+/// [`Fault::Crash`] and [`Fault::Restart`] may only target hosts, not
+/// clients.
+///
+/// [`Fault::Duplicate`] and [`Fault::Bandwidth`] are recorded in the
+/// driver's [`NetworkFaults`]; only hosts that receive through a
+/// [`FaultyUdpSocket`](crate::FaultyUdpSocket) built from
+/// [`FaultDriver::network`] observe them. Likewise, clock faults change
+/// [`FaultDriver::clocks`], which hosts read through their
+/// [`HostClock`](crate::HostClock).
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use dst::{Fault, FaultDriver};
+///
+/// let mut sim = dst::turmoil::Builder::new().build();
+/// sim.host("a", || async { std::future::pending().await });
+/// sim.host("b", || async { std::future::pending().await });
+///
+/// let schedule = vec![
+///     (Duration::from_secs(1), Fault::Partition { node_a: "a".into(), node_b: "b".into() }),
+///     (Duration::from_secs(2), Fault::Repair { node_a: "a".into(), node_b: "b".into() }),
+/// ];
+/// let mut driver = FaultDriver::new(["a", "b"], schedule);
+/// driver.run(&mut sim).unwrap();
+///
+/// assert_eq!(driver.applied().len(), 2);
+/// ```
+pub struct FaultDriver {
+    nodes: Vec<String>,
+    network: NetworkFaults,
+    clocks: SimClocks,
+    pending: VecDeque<(Duration, Fault)>,
+    applied: Vec<AppliedFault>,
+    fs: Option<SimFs>,
+    trace: Option<FaultSink>,
+}
+
+impl fmt::Debug for FaultDriver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaultDriver")
+            .field("nodes", &self.nodes)
+            .field("network", &self.network)
+            .field("clocks", &self.clocks)
+            .field("pending", &self.pending)
+            .field("applied", &self.applied)
+            .field("fs", &self.fs)
+            .field("trace", &self.trace.is_some())
+            .finish()
+    }
+}
+
+impl FaultDriver {
+    /// Creates a driver for the cluster `nodes` with the given schedule.
+    ///
+    /// `nodes` is used to expand node-wide faults such as
+    /// [`Fault::Latency`] into per-link settings. The schedule is sorted by
+    /// time; faults scheduled for the same instant keep their order.
+    pub fn new<I, S>(nodes: I, mut schedule: Vec<(Duration, Fault)>) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        schedule.sort_by_key(|(at, _)| *at);
+        FaultDriver {
+            nodes: nodes.into_iter().map(Into::into).collect(),
+            network: NetworkFaults::default(),
+            clocks: SimClocks::new(),
+            pending: schedule.into(),
+            applied: Vec::new(),
+            fs: None,
+            trace: None,
+        }
+    }
+
+    /// Seeds the random decisions made by [`FaultDriver::network`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.network = NetworkFaults::new(seed);
+        self
+    }
+
+    /// Applies clock faults to `clocks` instead of a fresh [`SimClocks`],
+    /// e.g. one with its own epoch.
+    pub fn with_clocks(mut self, clocks: SimClocks) -> Self {
+        self.clocks = clocks;
+        self
+    }
+
+    /// Discards unsynced data in `fs` whenever a host crashes or restarts.
+    pub fn with_fs(mut self, fs: SimFs) -> Self {
+        self.fs = Some(fs);
+        self
+    }
+
+    /// Records every applied fault into `recorder`, under the host name
+    /// [`FAULT_DRIVER_HOST`] and at the simulated time it was applied.
+    ///
+    /// Application events recorded into the same recorder then interleave
+    /// with the faults that caused them.
+    pub fn with_trace<E>(mut self, recorder: TraceRecorder<E>) -> Self
+    where
+        E: From<Fault> + Send + 'static,
+    {
+        self.trace = Some(Box::new(move |at, fault| {
+            recorder.record_at(at, FAULT_DRIVER_HOST, E::from(fault.clone()));
+        }));
+        self
+    }
+
+    /// Fault state for faults turmoil cannot inject itself.
+    ///
+    /// Clone it into host closures before the simulation runs.
+    pub fn network(&self) -> &NetworkFaults {
+        &self.network
+    }
+
+    /// Per-host clocks skewed by clock faults.
+    ///
+    /// Hand each host its [`SimClocks::host`] before the simulation runs.
+    pub fn clocks(&self) -> &SimClocks {
+        &self.clocks
+    }
+
+    /// Applies every pending fault scheduled at or before `sim.elapsed()`.
+    ///
+    /// Returns the number of faults applied.
+    pub fn apply_due(&mut self, sim: &mut Sim<'_>) -> usize {
+        let now = sim.elapsed();
+        let mut count = 0;
+
+        while self.pending.front().is_some_and(|(at, _)| *at <= now) {
+            let Some((scheduled_at, fault)) = self.pending.pop_front() else {
+                break;
+            };
+            self.apply(sim, &fault);
+            if let Some(trace) = &self.trace {
+                trace(now, &fault);
+            }
+            self.applied.push(AppliedFault {
+                scheduled_at,
+                applied_at: now,
+                fault,
+            });
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Applies due faults, then steps the simulation once.
+    ///
+    /// Returns whether all clients have completed, as [`Sim::step`] does.
+    pub fn step(&mut self, sim: &mut Sim<'_>) -> turmoil::Result<bool> {
+        self.apply_due(sim);
+        sim.step()
+    }
+
+    /// Steps the simulation until every fault has fired and all clients have
+    /// completed.
+    ///
+    /// Unlike [`Sim::run`], this keeps stepping a simulation without clients
+    /// until the schedule is exhausted.
+    pub fn run(&mut self, sim: &mut Sim<'_>) -> turmoil::Result {
+        loop {
+            let is_finished = self.step(sim)?;
+            if is_finished && self.pending.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Steps the simulation until `sim.elapsed()` reaches `until`, applying
+    /// faults along the way.
+    ///
+    /// Returns whether all clients had completed at the last step, as
+    /// [`Sim::step`] does. Stepping continues past client completion so that
+    /// host-only simulations run for the full duration.
+    pub fn run_until(&mut self, sim: &mut Sim<'_>, until: Duration) -> turmoil::Result<bool> {
+        let mut is_finished = false;
+        while sim.elapsed() < until {
+            is_finished = self.step(sim)?;
+        }
+        Ok(is_finished)
+    }
+
+    /// Faults applied so far, in the order they fired.
+    pub fn applied(&self) -> &[AppliedFault] {
+        &self.applied
+    }
+
+    /// Faults that have not fired yet.
+    pub fn pending(&self) -> impl Iterator<Item = &(Duration, Fault)> {
+        self.pending.iter()
+    }
+
+    fn apply(&self, sim: &mut Sim<'_>, fault: &Fault) {
+        match fault {
+            Fault::Partition { node_a, node_b } => sim.partition(node_a.as_str(), node_b.as_str()),
+            Fault::Repair { node_a, node_b } => sim.repair(node_a.as_str(), node_b.as_str()),
+            Fault::Latency { node, delay_ms } => {
+                let delay = Duration::from_millis(*delay_ms);
+                for other in self.nodes.iter().filter(|other| *other != node) {
+                    sim.set_link_latency(node.as_str(), other.as_str(), delay);
+                }
+            }
+            Fault::Crash { node } => {
+                sim.crash(node.as_str());
+                self.crash_disk(node);
+            }
+            Fault::Restart { node } => {
+                sim.bounce(node.as_str());
+                self.crash_disk(node);
+            }
+            Fault::MessageLoss {
+                node_a,
+                node_b,
+                percent,
+            } => {
+                let rate = f64::from((*percent).min(100)) / 100.0;
+                sim.set_link_fail_rate(node_a.as_str(), node_b.as_str(), rate);
+            }
+            Fault::Duplicate {
+                node_a,
+                node_b,
+                percent,
+            } => self.network.set_duplicate(node_a, node_b, *percent),
+            Fault::Reorder {
+                node_a,
+                node_b,
+                max_delay_ms,
+            } => {
+                // Reset the floor first so the new ceiling can never sit
+                // below an earlier fixed latency on this link.
+                sim.set_link_latency(node_a.as_str(), node_b.as_str(), Duration::ZERO);
+                sim.set_link_max_message_latency(
+                    node_a.as_str(),
+                    node_b.as_str(),
+                    Duration::from_millis(*max_delay_ms),
+                );
+            }
+            Fault::LinkLatency {
+                node_a,
+                node_b,
+                delay_ms,
+            } => sim.set_link_latency(
+                node_a.as_str(),
+                node_b.as_str(),
+                Duration::from_millis(*delay_ms),
+            ),
+            Fault::Bandwidth {
+                node_a,
+                node_b,
+                bytes_per_sec,
+            } => self.network.set_bandwidth(node_a, node_b, *bytes_per_sec),
+            Fault::ClockOffset { node, offset_ms } => {
+                self.clocks.set_offset(node, *offset_ms, sim.elapsed())
+            }
+            Fault::ClockDrift { node, ppm } => self.clocks.set_drift(node, *ppm, sim.elapsed()),
+            Fault::ClockJump { node, delta_ms } => self.clocks.jump(node, *delta_ms),
+        }
+    }
+
+    fn crash_disk(&self, node: &str) {
+        if let Some(fs) = &self.fs {
+            fs.crash(node);
+        }
+    }
+}
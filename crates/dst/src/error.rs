@@ -0,0 +1,33 @@
+//! Error types for the `dst` crate.
+
+use thiserror::Error;
+
+use crate::fault::Fault;
+
+/// Errors returned by fallible `dst` operations.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Reading or writing a file failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A persisted artifact could not be encoded or decoded.
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// A scenario file could not be decoded.
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    /// A YAML scenario file could not be decoded.
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml_ng::Error),
+
+    /// A fault was applied to something that cannot inject it, such as a
+    /// datagram fault to a TCP proxy.
+    #[error("unsupported fault: {0:?}")]
+    UnsupportedFault(Fault),
+}
+
+/// Result alias for `dst` operations.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,459 @@
+//! Fault model and seeded fault scenario generation.
+//!
+//! A fault scenario is a `Vec<(Duration, Fault)>`: each entry fires once the
+//! simulation has run for at least the given duration. Scenarios are pure
+//! data, so they can be generated, printed, shrunk and replayed without a
+//! running simulation.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// A timed fault scenario: each fault fires once the simulation has run for
+/// at least its duration.
+pub type Scenario = Vec<(Duration, Fault)>;
+
+/// A fault that can be injected into a simulated cluster.
+///
+/// Serializes as a flat object tagged by `kind`, for example
+/// `{"kind":"partition","node_a":"a","node_b":"b"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Fault {
+    /// Drop all messages between `node_a` and `node_b`.
+    Partition {
+        /// One side of the partitioned link.
+        node_a: String,
+        /// The other side of the partitioned link.
+        node_b: String,
+    },
+    /// Heal a partition between `node_a` and `node_b`.
+    Repair {
+        /// One side of the repaired link.
+        node_a: String,
+        /// The other side of the repaired link.
+        node_b: String,
+    },
+    /// Delay every message to and from `node` by `delay_ms`.
+    Latency {
+        /// The node whose links are slowed down.
+        node: String,
+        /// Fixed one-way latency in milliseconds.
+        delay_ms: u64,
+    },
+    /// Stop all software running on `node`, as if the machine lost power.
+    Crash {
+        /// The node to crash.
+        node: String,
+    },
+    /// Restart the software on `node` from scratch, whether or not it crashed.
+    Restart {
+        /// The node to restart.
+        node: String,
+    },
+    /// Lose messages sent between `node_a` and `node_b`. Zero clears the fault.
+    MessageLoss {
+        /// One side of the lossy link.
+        node_a: String,
+        /// The other side of the lossy link.
+        node_b: String,
+        /// Chance, in percent, that any single message is lost.
+        percent: u8,
+    },
+    /// Deliver datagrams between `node_a` and `node_b` twice. Zero clears the
+    /// fault.
+    Duplicate {
+        /// One side of the duplicating link.
+        node_a: String,
+        /// The other side of the duplicating link.
+        node_b: String,
+        /// Chance, in percent, that a received datagram is delivered again.
+        percent: u8,
+    },
+    /// Add per-message jitter to the link between `node_a` and `node_b` so
+    /// that messages overtake each other. Replaces any fixed latency on the
+    /// link with a range from zero to `max_delay_ms`.
+    Reorder {
+        /// One side of the reordering link.
+        node_a: String,
+        /// The other side of the reordering link.
+        node_b: String,
+        /// Upper bound of the per-message latency in milliseconds.
+        max_delay_ms: u64,
+    },
+    /// Set the latency of the link between `node_a` and `node_b` alone.
+    /// [`Topology`](crate::Topology) schedules emit one per sample.
+    LinkLatency {
+        /// One side of the link.
+        node_a: String,
+        /// The other side of the link.
+        node_b: String,
+        /// Fixed one-way latency in milliseconds.
+        delay_ms: u64,
+    },
+    /// Cap the throughput of the link between `node_a` and `node_b`. Zero
+    /// clears the fault.
+    Bandwidth {
+        /// One side of the capped link.
+        node_a: String,
+        /// The other side of the capped link.
+        node_b: String,
+        /// Bytes per second the link carries.
+        bytes_per_sec: u64,
+    },
+    /// Set `node`'s wall clock to read `offset_ms` ahead of true time, or
+    /// behind when negative. Its monotonic clock is unaffected.
+    ClockOffset {
+        /// The node whose clock is skewed.
+        node: String,
+        /// Offset from true time in milliseconds.
+        offset_ms: i64,
+    },
+    /// Make `node`'s clocks run `ppm` parts per million fast, or slow when
+    /// negative. Zero stops further drift.
+    ClockDrift {
+        /// The node whose clock drifts.
+        node: String,
+        /// Drift rate in parts per million.
+        ppm: i32,
+    },
+    /// Step `node`'s wall clock by `delta_ms`, as an NTP correction does.
+    /// Its monotonic clock is unaffected.
+    ClockJump {
+        /// The node whose clock is stepped.
+        node: String,
+        /// Size of the step in milliseconds; negative steps go back.
+        delta_ms: i64,
+    },
+}
+
+impl Fault {
+    /// The nodes this fault touches.
+    pub fn nodes(&self) -> Vec<&str> {
+        match self {
+            Fault::Partition { node_a, node_b }
+            | Fault::Repair { node_a, node_b }
+            | Fault::MessageLoss { node_a, node_b, .. }
+            | Fault::Duplicate { node_a, node_b, .. }
+            | Fault::Reorder { node_a, node_b, .. }
+            | Fault::LinkLatency { node_a, node_b, .. }
+            | Fault::Bandwidth { node_a, node_b, .. } => vec![node_a, node_b],
+            Fault::Latency { node, .. }
+            | Fault::Crash { node }
+            | Fault::Restart { node }
+            | Fault::ClockOffset { node, .. }
+            | Fault::ClockDrift { node, .. }
+            | Fault::ClockJump { node, .. } => vec![node],
+        }
+    }
+}
+
+/// One entry of a persisted scenario.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TimedFault {
+    at_ms: u64,
+    #[serde(flatten)]
+    fault: Fault,
+}
+
+impl TimedFault {
+    /// Converts a scenario to its persisted form, truncating to milliseconds.
+    pub(crate) fn from_scenario(scenario: &[(Duration, Fault)]) -> Vec<TimedFault> {
+        scenario
+            .iter()
+            .map(|(at, fault)| TimedFault {
+                at_ms: u64::try_from(at.as_millis()).unwrap_or(u64::MAX),
+                fault: fault.clone(),
+            })
+            .collect()
+    }
+
+    /// Converts persisted entries back into a scenario.
+    pub(crate) fn into_scenario(entries: Vec<TimedFault>) -> Scenario {
+        entries
+            .into_iter()
+            .map(|entry| (Duration::from_millis(entry.at_ms), entry.fault))
+            .collect()
+    }
+
+    /// Deserializes a scenario embedded in another document, for use with
+    /// `#[serde(deserialize_with = ..)]`.
+    #[cfg(feature = "simulation")]
+    pub(crate) fn deserialize_scenario<'de, D>(deserializer: D) -> Result<Scenario, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Vec::<TimedFault>::deserialize(deserializer).map(TimedFault::into_scenario)
+    }
+}
+
+/// Serializes a scenario as a JSON array of `{"at_ms": .., "kind": .., ..}`
+/// objects.
+///
+/// Times are stored in whole milliseconds; sub-millisecond precision is
+/// truncated.
+pub fn scenario_to_json(scenario: &[(Duration, Fault)]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&TimedFault::from_scenario(
+        scenario,
+    ))?)
+}
+
+/// Parses a scenario written by [`scenario_to_json`].
+pub fn scenario_from_json(json: &str) -> Result<Vec<(Duration, Fault)>> {
+    Ok(TimedFault::into_scenario(serde_json::from_str(json)?))
+}
+
+/// Writes a scenario to `path` so it can be replayed with [`load_scenario`].
+pub fn save_scenario(path: impl AsRef<Path>, scenario: &[(Duration, Fault)]) -> Result<()> {
+    fs::write(path, scenario_to_json(scenario)?)?;
+    Ok(())
+}
+
+/// Reads a scenario written by [`save_scenario`].
+pub fn load_scenario(path: impl AsRef<Path>) -> Result<Vec<(Duration, Fault)>> {
+    scenario_from_json(&fs::read_to_string(path)?)
+}
+
+/// Relative weights used by [`FaultScenarioGenerator`] to pick fault kinds.
+///
+/// A weight of zero disables that kind. The default weighs every kind
+/// equally.
+///
+/// # Examples
+///
+/// ```
+/// use dst::FaultWeights;
+///
+/// // Network faults only: nodes never crash.
+/// let weights = FaultWeights { crash: 0, restart: 0, ..FaultWeights::default() };
+/// assert_eq!(weights.total(), 6);
+/// ```
+///
+/// When deserialized, missing weights keep their default of 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FaultWeights {
+    /// Weight of [`Fault::Partition`].
+    pub partition: u32,
+    /// Weight of [`Fault::Repair`].
+    pub repair: u32,
+    /// Weight of [`Fault::Latency`].
+    pub latency: u32,
+    /// Weight of [`Fault::Crash`].
+    pub crash: u32,
+    /// Weight of [`Fault::Restart`].
+    pub restart: u32,
+    /// Weight of [`Fault::MessageLoss`].
+    pub message_loss: u32,
+    /// Weight of [`Fault::Duplicate`].
+    pub duplicate: u32,
+    /// Weight of [`Fault::Reorder`].
+    pub reorder: u32,
+}
+
+impl FaultWeights {
+    /// Only the original network faults: partition, repair and latency.
+    pub const NETWORK: FaultWeights = FaultWeights {
+        partition: 1,
+        repair: 1,
+        latency: 1,
+        crash: 0,
+        restart: 0,
+        message_loss: 0,
+        duplicate: 0,
+        reorder: 0,
+    };
+
+    /// Sum of all weights.
+    pub fn total(&self) -> u32 {
+        self.as_array().iter().sum()
+    }
+
+    fn as_array(&self) -> [u32; 8] {
+        [
+            self.partition,
+            self.repair,
+            self.latency,
+            self.crash,
+            self.restart,
+            self.message_loss,
+            self.duplicate,
+            self.reorder,
+        ]
+    }
+}
+
+impl Default for FaultWeights {
+    fn default() -> Self {
+        FaultWeights {
+            partition: 1,
+            repair: 1,
+            latency: 1,
+            crash: 1,
+            restart: 1,
+            message_loss: 1,
+            duplicate: 1,
+            reorder: 1,
+        }
+    }
+}
+
+/// Generates reproducible fault scenarios from a seed.
+///
+/// The same seed and node list always yield the same scenario.
+///
+/// # Examples
+///
+/// ```
+/// use dst::FaultScenarioGenerator;
+///
+/// let nodes = vec!["alpha".to_string(), "beta".to_string()];
+/// let a = FaultScenarioGenerator::new(7, nodes.clone()).generate_scenario(5);
+/// let b = FaultScenarioGenerator::new(7, nodes).generate_scenario(5);
+/// assert_eq!(a, b);
+/// ```
+#[derive(Debug)]
+pub struct FaultScenarioGenerator {
+    rng: StdRng,
+    nodes: Vec<String>,
+    weights: FaultWeights,
+}
+
+impl FaultScenarioGenerator {
+    /// Creates a generator for `nodes` seeded with `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nodes` is empty.
+    pub fn new(seed: u64, nodes: Vec<String>) -> Self {
+        assert!(!nodes.is_empty(), "fault generator needs at least one node");
+        FaultScenarioGenerator {
+            rng: StdRng::seed_from_u64(seed),
+            nodes,
+            weights: FaultWeights::default(),
+        }
+    }
+
+    /// Replaces the weights used to pick fault kinds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every weight is zero.
+    pub fn with_weights(mut self, weights: FaultWeights) -> Self {
+        assert!(
+            weights.total() > 0,
+            "at least one fault weight must be non-zero"
+        );
+        self.weights = weights;
+        self
+    }
+
+    /// Generates `num_faults` faults, starting 5 seconds in and spaced 2 to
+    /// 10 seconds apart.
+    pub fn generate_scenario(&mut self, num_faults: usize) -> Vec<(Duration, Fault)> {
+        let mut scenario = Vec::with_capacity(num_faults);
+        let mut time = Duration::from_secs(5); // Start faults after 5 seconds
+
+        for _ in 0..num_faults {
+            let fault = self.generate_fault();
+            scenario.push((time, fault));
+            time += Duration::from_secs(self.rng.random_range(2..10));
+        }
+
+        scenario
+    }
+
+    /// Generates a single random fault, picking its kind by weight.
+    ///
+    /// With a single node there are no links, so only node-level faults
+    /// (latency, crash, restart) are generated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is a single node and every node-level weight is zero.
+    pub fn generate_fault(&mut self) -> Fault {
+        let mut weights = self.weights;
+        if self.nodes.len() < 2 {
+            weights.partition = 0;
+            weights.repair = 0;
+            weights.message_loss = 0;
+            weights.duplicate = 0;
+            weights.reorder = 0;
+            assert!(
+                weights.total() > 0,
+                "a single node has no links; give latency, crash or restart a weight"
+            );
+        }
+        let mut roll = self.rng.random_range(0..weights.total());
+        let mut kind = 0;
+        for (index, weight) in weights.as_array().into_iter().enumerate() {
+            if roll < weight {
+                kind = index;
+                break;
+            }
+            roll -= weight;
+        }
+
+        match kind {
+            0 => {
+                let (node_a, node_b) = self.pick_link();
+                Fault::Partition { node_a, node_b }
+            }
+            1 => {
+                let (node_a, node_b) = self.pick_link();
+                Fault::Repair { node_a, node_b }
+            }
+            2 => Fault::Latency {
+                node: self.pick_node(),
+                delay_ms: self.rng.random_range(100..1000),
+            },
+            3 => Fault::Crash {
+                node: self.pick_node(),
+            },
+            4 => Fault::Restart {
+                node: self.pick_node(),
+            },
+            5 => {
+                let (node_a, node_b) = self.pick_link();
+                Fault::MessageLoss {
+                    node_a,
+                    node_b,
+                    percent: self.rng.random_range(1..=50),
+                }
+            }
+            6 => {
+                let (node_a, node_b) = self.pick_link();
+                Fault::Duplicate {
+                    node_a,
+                    node_b,
+                    percent: self.rng.random_range(1..=50),
+                }
+            }
+            _ => {
+                let (node_a, node_b) = self.pick_link();
+                Fault::Reorder {
+                    node_a,
+                    node_b,
+                    max_delay_ms: self.rng.random_range(10..500),
+                }
+            }
+        }
+    }
+
+    fn pick_node(&mut self) -> String {
+        let i = self.rng.random_range(0..self.nodes.len());
+        self.nodes[i].clone()
+    }
+
+    fn pick_link(&mut self) -> (String, String) {
+        let i = self.rng.random_range(0..self.nodes.len());
+        let j = (i + 1) % self.nodes.len();
+        (self.nodes[i].clone(), self.nodes[j].clone())
+    }
+}
@@ -0,0 +1,541 @@
+//! A simulated filesystem with crash-consistency faults.
+//!
+//! [`SimFs`] keeps every host's files in memory and tracks which bytes have
+//! reached "disk" through `sync_all`/`sync_data`. [`SimFs::crash`] throws
+//! away everything that was not synced, so tests can exercise recovery
+//! paths after [`Fault::Crash`](crate::Fault::Crash). [`DiskFaults`] adds
+//! lying fsyncs, torn writes, `ENOSPC` and `EIO`.
+//!
+//! The API mirrors the subset of `tokio::fs` that services typically use.
+//! Paths form a flat namespace per host: directories need not exist, and
+//! creating, renaming or removing a file is durable immediately. A file
+//! whose contents were never synced disappears on crash. A removed or
+//! renamed-over file stops counting against
+//! [`DiskFaults::capacity_bytes`] once its last [`SimFile`] is dropped, or at
+//! the next crash.
+//!
+//! # Examples
+//!
+//! ```
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let fs = dst::SimFs::new(7);
+//! let disk = fs.host("db");
+//!
+//! let mut wal = disk.create("wal.log").await.unwrap();
+//! wal.write_all(b"committed").await.unwrap();
+//! wal.sync_all().await.unwrap();
+//! wal.write_all(b" + pending").await.unwrap();
+//!
+//! fs.crash("db");
+//! assert_eq!(disk.read("wal.log").await.unwrap(), b"committed");
+//! # });
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Linux `ENOSPC`, reported when a write would exceed the host's capacity.
+const ENOSPC: i32 = 28;
+
+/// Linux `EIO`, reported for injected I/O errors.
+const EIO: i32 = 5;
+
+/// Disk faults for one host. The default injects nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskFaults {
+    /// Total bytes the host's files may hold; writes past it fail with
+    /// `ENOSPC`. `None` means unlimited.
+    pub capacity_bytes: Option<u64>,
+    /// Chance, in percent, that a read, write or sync fails with `EIO`.
+    pub eio_percent: u8,
+    /// Chance, in percent, that a sync reports success without persisting
+    /// anything.
+    pub fsync_loss_percent: u8,
+    /// Chance, in percent, that a crash keeps part of each file's unsynced
+    /// writes, cutting the last kept write at an arbitrary byte.
+    pub torn_write_percent: u8,
+}
+
+/// Shared in-memory disks for every simulated host.
+///
+/// Cheap to clone; every clone refers to the same disks. Hand each host its
+/// [`HostFs`] and keep a clone outside the simulation to inject crashes.
+#[derive(Debug, Clone)]
+pub struct SimFs {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    rng: StdRng,
+    hosts: BTreeMap<String, HostDisk>,
+}
+
+#[derive(Debug, Default)]
+struct HostDisk {
+    faults: DiskFaults,
+    paths: BTreeMap<PathBuf, u64>,
+    inodes: BTreeMap<u64, Inode>,
+    next_inode: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inode {
+    /// Contents as reads see them.
+    data: Vec<u8>,
+    /// Contents as of the last effective sync, or `None` if never synced.
+    durable: Option<Vec<u8>>,
+    /// Changes since the last effective sync, oldest first.
+    unsynced: Vec<Change>,
+    /// Paths naming this inode.
+    links: usize,
+    /// Open [`SimFile`]s on this inode.
+    handles: usize,
+}
+
+#[derive(Debug)]
+enum Change {
+    Write { offset: usize, bytes: Vec<u8> },
+    SetLen(usize),
+}
+
+impl Change {
+    fn apply(&self, data: &mut Vec<u8>) {
+        match self {
+            Change::Write { offset, bytes } => write_at(data, *offset, bytes),
+            Change::SetLen(len) => data.resize(*len, 0),
+        }
+    }
+}
+
+fn write_at(data: &mut Vec<u8>, offset: usize, bytes: &[u8]) {
+    let end = offset + bytes.len();
+    if data.len() < end {
+        data.resize(end, 0);
+    }
+    data[offset..end].copy_from_slice(bytes);
+}
+
+impl SimFs {
+    /// Creates empty disks whose random fault decisions derive from `seed`.
+    pub fn new(seed: u64) -> Self {
+        SimFs {
+            inner: Arc::new(Mutex::new(Inner {
+                rng: StdRng::seed_from_u64(seed),
+                hosts: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Returns the filesystem of `host`.
+    pub fn host(&self, host: impl Into<String>) -> HostFs {
+        HostFs {
+            fs: self.clone(),
+            host: host.into(),
+        }
+    }
+
+    /// Replaces the disk faults of `host`.
+    pub fn set_faults(&self, host: &str, faults: DiskFaults) {
+        self.lock().disk(host).faults = faults;
+    }
+
+    /// Current disk faults of `host`.
+    pub fn faults(&self, host: &str) -> DiskFaults {
+        self.lock().disk(host).faults
+    }
+
+    /// Simulates a power loss on `host`: every unsynced change is discarded,
+    /// or partly kept when a torn write is injected.
+    ///
+    /// Call it alongside `sim.crash`; a [`FaultDriver`](crate::FaultDriver)
+    /// built with [`with_fs`](crate::FaultDriver::with_fs) does so for
+    /// [`Fault::Crash`](crate::Fault::Crash) and
+    /// [`Fault::Restart`](crate::Fault::Restart).
+    pub fn crash(&self, host: &str) {
+        let mut inner = self.lock();
+        let Inner { rng, hosts } = &mut *inner;
+        let Some(disk) = hosts.get_mut(host) else {
+            return;
+        };
+        let torn_percent = disk.faults.torn_write_percent;
+
+        for inode in disk.inodes.values_mut() {
+            let mut data = inode.durable.clone().unwrap_or_default();
+            if !inode.unsynced.is_empty() && roll(rng, torn_percent) {
+                let kept = rng.random_range(0..inode.unsynced.len());
+                for change in &inode.unsynced[..kept] {
+                    change.apply(&mut data);
+                }
+                match &inode.unsynced[kept] {
+                    Change::Write { offset, bytes } => {
+                        let cut = rng.random_range(0..=bytes.len());
+                        write_at(&mut data, *offset, &bytes[..cut]);
+                    }
+                    change => change.apply(&mut data),
+                }
+                inode.durable = Some(data.clone());
+            }
+            inode.data = data;
+            inode.unsynced.clear();
+        }
+
+        // Files that never reached disk vanish along with their names, and
+        // the crash closes every handle to files that no longer have one.
+        disk.inodes
+            .retain(|_, inode| inode.durable.is_some() && inode.links > 0);
+        let HostDisk { paths, inodes, .. } = disk;
+        paths.retain(|_, id| inodes.contains_key(id));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for SimFs {
+    fn default() -> Self {
+        SimFs::new(0)
+    }
+}
+
+impl Inner {
+    fn disk(&mut self, host: &str) -> &mut HostDisk {
+        self.hosts.entry(host.to_string()).or_default()
+    }
+
+    /// Fails with `EIO` when the host's fault roll says so.
+    fn check_eio(&mut self, host: &str) -> io::Result<()> {
+        let percent = self.disk(host).faults.eio_percent;
+        if roll(&mut self.rng, percent) {
+            Err(io::Error::from_raw_os_error(EIO))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Only draws from the RNG when the fault is enabled, so fault-free hosts do
+/// not perturb the random stream.
+fn roll(rng: &mut StdRng, percent: u8) -> bool {
+    percent > 0 && rng.random_range(0..100) < percent
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        ErrorKind::NotFound,
+        format!("{}: no such file", path.display()),
+    )
+}
+
+impl HostDisk {
+    fn lookup(&self, path: &Path) -> io::Result<u64> {
+        self.paths.get(path).copied().ok_or_else(|| not_found(path))
+    }
+
+    fn inode(&mut self, id: u64) -> io::Result<&mut Inode> {
+        self.inodes
+            .get_mut(&id)
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "stale file handle"))
+    }
+
+    fn used_bytes(&self) -> u64 {
+        self.inodes
+            .values()
+            .map(|inode| inode.data.len() as u64)
+            .sum()
+    }
+
+    /// Creates or truncates the file at `path`.
+    fn create(&mut self, path: &Path) -> io::Result<u64> {
+        if let Some(&id) = self.paths.get(path) {
+            self.set_len(id, 0)?;
+            return Ok(id);
+        }
+        let id = self.next_inode;
+        self.next_inode += 1;
+        self.inodes.insert(
+            id,
+            Inode {
+                links: 1,
+                ..Inode::default()
+            },
+        );
+        self.paths.insert(path.to_path_buf(), id);
+        Ok(id)
+    }
+
+    /// Removes one path to `id`, freeing the inode once nothing refers to it.
+    fn unlink(&mut self, id: u64) {
+        if let Some(inode) = self.inodes.get_mut(&id) {
+            inode.links -= 1;
+            self.free_if_unused(id);
+        }
+    }
+
+    /// Closes one handle to `id`, freeing the inode once nothing refers to it.
+    fn release(&mut self, id: u64) {
+        if let Some(inode) = self.inodes.get_mut(&id) {
+            inode.handles -= 1;
+            self.free_if_unused(id);
+        }
+    }
+
+    fn free_if_unused(&mut self, id: u64) {
+        if self
+            .inodes
+            .get(&id)
+            .is_some_and(|inode| inode.links == 0 && inode.handles == 0)
+        {
+            self.inodes.remove(&id);
+        }
+    }
+
+    fn write(&mut self, id: u64, offset: usize, bytes: &[u8]) -> io::Result<()> {
+        let current = self.inode(id)?.data.len();
+        let grows_by = (offset + bytes.len()).saturating_sub(current) as u64;
+        if let Some(capacity) = self.faults.capacity_bytes {
+            if self.used_bytes() + grows_by > capacity {
+                return Err(io::Error::from_raw_os_error(ENOSPC));
+            }
+        }
+        let inode = self.inode(id)?;
+        let change = Change::Write {
+            offset,
+            bytes: bytes.to_vec(),
+        };
+        change.apply(&mut inode.data);
+        inode.unsynced.push(change);
+        Ok(())
+    }
+
+    fn set_len(&mut self, id: u64, len: usize) -> io::Result<()> {
+        let current = self.inode(id)?.data.len();
+        if let Some(capacity) = self.faults.capacity_bytes {
+            let grows_by = len.saturating_sub(current) as u64;
+            if self.used_bytes() + grows_by > capacity {
+                return Err(io::Error::from_raw_os_error(ENOSPC));
+            }
+        }
+        let inode = self.inode(id)?;
+        let change = Change::SetLen(len);
+        change.apply(&mut inode.data);
+        inode.unsynced.push(change);
+        Ok(())
+    }
+}
+
+/// One host's view of a [`SimFs`], mirroring the `tokio::fs` functions.
+#[derive(Debug, Clone)]
+pub struct HostFs {
+    fs: SimFs,
+    host: String,
+}
+
+impl HostFs {
+    /// The host this filesystem belongs to.
+    pub fn name(&self) -> &str {
+        &self.host
+    }
+
+    /// Reads the whole file at `path`.
+    pub async fn read(&self, path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+        let mut inner = self.fs.lock();
+        inner.check_eio(&self.host)?;
+        let disk = inner.disk(&self.host);
+        let id = disk.lookup(path.as_ref())?;
+        Ok(disk.inode(id)?.data.clone())
+    }
+
+    /// Creates or truncates the file at `path` and writes `contents` to it,
+    /// without syncing.
+    pub async fn write(
+        &self,
+        path: impl AsRef<Path>,
+        contents: impl AsRef<[u8]>,
+    ) -> io::Result<()> {
+        let mut inner = self.fs.lock();
+        inner.check_eio(&self.host)?;
+        let disk = inner.disk(&self.host);
+        let id = disk.create(path.as_ref())?;
+        disk.write(id, 0, contents.as_ref())
+    }
+
+    /// Removes the file at `path`. Open handles keep working until they are
+    /// dropped or the host crashes.
+    pub async fn remove_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut inner = self.fs.lock();
+        let disk = inner.disk(&self.host);
+        let id = disk.paths.remove(path).ok_or_else(|| not_found(path))?;
+        disk.unlink(id);
+        Ok(())
+    }
+
+    /// Atomically renames `from` to `to`, replacing `to` if it exists.
+    pub async fn rename(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+        let from = from.as_ref();
+        let mut inner = self.fs.lock();
+        let disk = inner.disk(&self.host);
+        let id = disk.paths.remove(from).ok_or_else(|| not_found(from))?;
+        if let Some(replaced) = disk.paths.insert(to.as_ref().to_path_buf(), id) {
+            disk.unlink(replaced);
+        }
+        Ok(())
+    }
+
+    /// Returns whether a file exists at `path`.
+    pub async fn try_exists(&self, path: impl AsRef<Path>) -> io::Result<bool> {
+        let mut inner = self.fs.lock();
+        Ok(inner.disk(&self.host).paths.contains_key(path.as_ref()))
+    }
+
+    /// Opens the existing file at `path` for reading and writing.
+    pub async fn open(&self, path: impl AsRef<Path>) -> io::Result<SimFile> {
+        let mut inner = self.fs.lock();
+        let disk = inner.disk(&self.host);
+        let id = disk.lookup(path.as_ref())?;
+        disk.inode(id)?.handles += 1;
+        Ok(self.file(id))
+    }
+
+    /// Creates or truncates the file at `path` and opens it for reading and
+    /// writing.
+    pub async fn create(&self, path: impl AsRef<Path>) -> io::Result<SimFile> {
+        let mut inner = self.fs.lock();
+        inner.check_eio(&self.host)?;
+        let disk = inner.disk(&self.host);
+        let id = disk.create(path.as_ref())?;
+        disk.inode(id)?.handles += 1;
+        Ok(self.file(id))
+    }
+
+    /// Wraps an inode whose handle count the caller already raised.
+    fn file(&self, inode: u64) -> SimFile {
+        SimFile {
+            fs: self.clone(),
+            inode,
+            position: 0,
+        }
+    }
+}
+
+/// An open file on a [`HostFs`].
+///
+/// Reads and writes start at the file's cursor and advance it.
+#[derive(Debug)]
+pub struct SimFile {
+    fs: HostFs,
+    inode: u64,
+    position: u64,
+}
+
+impl SimFile {
+    /// Reads into `buf` from the cursor, returning the number of bytes read.
+    pub async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.with_disk(true, |disk, id| Ok(disk.inode(id)?.data.clone()))?;
+        let start = usize::try_from(self.position)
+            .unwrap_or(usize::MAX)
+            .min(data.len());
+        let n = buf.len().min(data.len() - start);
+        buf[..n].copy_from_slice(&data[start..start + n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    /// Appends the rest of the file, from the cursor, to `buf`.
+    pub async fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let data = self.with_disk(true, |disk, id| Ok(disk.inode(id)?.data.clone()))?;
+        let start = usize::try_from(self.position)
+            .unwrap_or(usize::MAX)
+            .min(data.len());
+        buf.extend_from_slice(&data[start..]);
+        self.position = data.len() as u64;
+        Ok(data.len() - start)
+    }
+
+    /// Writes all of `buf` at the cursor. The bytes are lost on crash until
+    /// the file is synced.
+    pub async fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let offset = self.offset()?;
+        self.with_disk(true, |disk, id| disk.write(id, offset, buf))?;
+        self.position += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Moves the cursor.
+    pub async fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let len = self.len().await?;
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(delta) => len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = target
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "seek before start of file"))?;
+        Ok(self.position)
+    }
+
+    /// Truncates or zero-extends the file.
+    pub async fn set_len(&mut self, len: u64) -> io::Result<()> {
+        let len = usize::try_from(len).map_err(|_| io::Error::from_raw_os_error(ENOSPC))?;
+        self.with_disk(true, |disk, id| disk.set_len(id, len))
+    }
+
+    /// Current length of the file.
+    pub async fn len(&self) -> io::Result<u64> {
+        self.with_disk(false, |disk, id| Ok(disk.inode(id)?.data.len() as u64))
+    }
+
+    /// Persists the file's contents, so a crash keeps them.
+    ///
+    /// With [`DiskFaults::fsync_loss_percent`] set, this may report success
+    /// without persisting anything.
+    pub async fn sync_all(&self) -> io::Result<()> {
+        let mut inner = self.fs.fs.lock();
+        inner.check_eio(&self.fs.host)?;
+        let percent = inner.disk(&self.fs.host).faults.fsync_loss_percent;
+        if roll(&mut inner.rng, percent) {
+            return Ok(());
+        }
+        let inode = inner.disk(&self.fs.host).inode(self.inode)?;
+        inode.durable = Some(inode.data.clone());
+        inode.unsynced.clear();
+        Ok(())
+    }
+
+    /// Same as [`SimFile::sync_all`]; the simulation keeps no metadata apart
+    /// from the contents.
+    pub async fn sync_data(&self) -> io::Result<()> {
+        self.sync_all().await
+    }
+
+    fn offset(&self) -> io::Result<usize> {
+        usize::try_from(self.position).map_err(|_| io::Error::from_raw_os_error(ENOSPC))
+    }
+
+    fn with_disk<T>(
+        &self,
+        may_fail: bool,
+        f: impl FnOnce(&mut HostDisk, u64) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut inner = self.fs.fs.lock();
+        if may_fail {
+            inner.check_eio(&self.fs.host)?;
+        }
+        f(inner.disk(&self.fs.host), self.inode)
+    }
+}
+
+impl Drop for SimFile {
+    fn drop(&mut self) {
+        self.fs.fs.lock().disk(&self.fs.host).release(self.inode);
+    }
+}
@@ -0,0 +1,134 @@
+//! Deterministic hashing.
+//!
+//! Standard Rust `HashMap`s use randomized hashing for security, which makes
+//! iteration order differ between runs. In DST we need every run with the same
+//! seed to behave identically, so these types use FNV-1a with a fixed offset
+//! basis instead, or keyed from the seed with [`SeededBuildHasher`].
+//!
+//! The collections built on these hashers live in
+//! [`collections`](crate::collections); [`DetHashMap`] and [`det_hash_map`]
+//! are re-exported here for existing imports.
+
+use std::hash::{BuildHasher, Hasher};
+
+pub use crate::collections::{det_hash_map, DetHashMap};
+
+/// FNV-1a 64-bit offset basis.
+const FNV_OFFSET_BASIS: u64 = 14695981039346656037;
+
+/// FNV-1a 64-bit prime.
+const FNV_PRIME: u64 = 1099511628211;
+
+/// A hasher that produces deterministic results (no randomization).
+///
+/// Implements FNV-1a. Prefer building it through [`DeterministicBuildHasher`],
+/// which starts from the FNV offset basis; `Default` starts from zero.
+#[derive(Debug, Clone, Default)]
+pub struct DeterministicHasher {
+    state: u64,
+}
+
+impl Hasher for DeterministicHasher {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= u64::from(*byte);
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// `BuildHasher` that creates [`DeterministicHasher`] instances.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeterministicBuildHasher;
+
+impl BuildHasher for DeterministicBuildHasher {
+    type Hasher = DeterministicHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        DeterministicHasher {
+            state: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+/// FNV-1a keyed from a seed, with a final avalanche step.
+///
+/// Built by [`SeededBuildHasher`]. The key perturbs both the starting state
+/// and the finalizer, so collision patterns and iteration order change with
+/// the seed while staying the same for any one seed. The finalizer spreads
+/// every input bit over the whole hash, which plain FNV-1a does not do for
+/// the low bits hash tables index with.
+#[derive(Debug, Clone)]
+pub struct SeededHasher {
+    key: u64,
+    state: u64,
+}
+
+impl Hasher for SeededHasher {
+    fn finish(&self) -> u64 {
+        fmix64(self.state ^ self.key)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= u64::from(*byte);
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// `BuildHasher` that creates [`SeededHasher`] instances keyed from a seed.
+///
+/// Derive the key from the simulation seed, e.g. with
+/// [`HostEntropy::seed`](crate::HostEntropy::seed), so adversarial key sets
+/// ("hash flooding") hit different collisions on every seed.
+///
+/// # Examples
+///
+/// ```
+/// use std::hash::BuildHasher;
+/// use dst::{Entropy, SeededBuildHasher};
+///
+/// let entropy = Entropy::new(42).host("node-a");
+/// let hasher = SeededBuildHasher::new(entropy.seed("peers"));
+/// assert_eq!(hasher.hash_one("node-b"), SeededBuildHasher::new(entropy.seed("peers")).hash_one("node-b"));
+/// assert_ne!(hasher.hash_one("node-b"), SeededBuildHasher::new(7).hash_one("node-b"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeededBuildHasher {
+    key: u64,
+}
+
+impl SeededBuildHasher {
+    /// Creates a builder whose hashers are keyed with `key`.
+    pub fn new(key: u64) -> Self {
+        SeededBuildHasher {
+            key: fmix64(key ^ FNV_PRIME),
+        }
+    }
+}
+
+impl BuildHasher for SeededBuildHasher {
+    type Hasher = SeededHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        SeededHasher {
+            key: self.key,
+            state: FNV_OFFSET_BASIS ^ self.key,
+        }
+    }
+}
+
+/// MurmurHash3's 64-bit finalizer: every input bit affects every output bit.
+fn fmix64(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^= h >> 33;
+    h
+}
@@ -0,0 +1,232 @@
+//! Invariants checked after every simulation step.
+//!
+//! Hosts publish what they believe into shared state; an
+//! [`InvariantMonitor`] evaluates named predicates over that state after
+//! each [`Sim::step`] and stops at the first step that breaks one, reporting
+//! the step number, simulated time and seed.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//! use std::time::Duration;
+//! use dst::InvariantMonitor;
+//!
+//! let seed = 7;
+//! let leaders = Arc::new(Mutex::new(Vec::<&str>::new()));
+//!
+//! let mut sim = dst::turmoil::Builder::new().rng_seed(seed).build();
+//! let state = leaders.clone();
+//! sim.client("node-a", async move {
+//!     tokio::time::sleep(Duration::from_millis(10)).await;
+//!     state.lock().unwrap().push("node-a");
+//!     Ok(())
+//! });
+//!
+//! let mut monitor = InvariantMonitor::new(seed, leaders).invariant(
+//!     "at most one leader",
+//!     |leaders| match leaders.len() {
+//!         0 | 1 => Ok(()),
+//!         _ => Err(format!("leaders: {:?}", leaders)),
+//!     },
+//! );
+//! monitor.run(&mut sim).unwrap();
+//! assert!(monitor.steps() > 0);
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use thiserror::Error;
+use turmoil::Sim;
+
+use crate::driver::FaultDriver;
+use crate::seed::TEST_SEED_ENV;
+
+type Check<S> = Box<dyn FnMut(&S) -> Result<(), String>>;
+
+/// Evaluates named invariants over shared state after every step.
+///
+/// Each invariant is a closure over `&S` that returns `Err(message)` when
+/// the state is wrong. Closures are `FnMut`, so an invariant can remember
+/// earlier states, e.g. to check that a counter never goes backwards.
+pub struct InvariantMonitor<S> {
+    seed: u64,
+    state: Arc<Mutex<S>>,
+    invariants: Vec<(String, Check<S>)>,
+    steps: u64,
+}
+
+impl<S: fmt::Debug> fmt::Debug for InvariantMonitor<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.invariants.iter().map(|(n, _)| n.as_str()).collect();
+        f.debug_struct("InvariantMonitor")
+            .field("seed", &self.seed)
+            .field("state", &self.state)
+            .field("invariants", &names)
+            .field("steps", &self.steps)
+            .finish()
+    }
+}
+
+impl<S> InvariantMonitor<S> {
+    /// Creates a monitor over `state` with no invariants.
+    ///
+    /// `seed` is only used in violation reports; pass the seed the
+    /// simulation was built from.
+    pub fn new(seed: u64, state: Arc<Mutex<S>>) -> Self {
+        InvariantMonitor {
+            seed,
+            state,
+            invariants: Vec::new(),
+            steps: 0,
+        }
+    }
+
+    /// Adds an invariant. Invariants are evaluated in the order they were
+    /// added, and the first failure is reported.
+    pub fn invariant<F>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: FnMut(&S) -> Result<(), String> + 'static,
+    {
+        self.invariants.push((name.into(), Box::new(check)));
+        self
+    }
+
+    /// Number of steps taken through this monitor.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Evaluates every invariant against the current state.
+    ///
+    /// The violation is attributed to the current step count and
+    /// `sim.elapsed()`. [`InvariantMonitor::step`] calls this after each
+    /// step; call it directly to check the initial state.
+    pub fn check(&mut self, sim: &Sim<'_>) -> Result<(), InvariantViolation> {
+        let state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (name, check) in &mut self.invariants {
+            if let Err(message) = check(&state) {
+                return Err(InvariantViolation {
+                    invariant: name.clone(),
+                    message,
+                    step: self.steps,
+                    elapsed: sim.elapsed(),
+                    seed: self.seed,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Steps the simulation once and checks every invariant.
+    ///
+    /// Returns whether all clients have completed, as [`Sim::step`] does.
+    pub fn step(&mut self, sim: &mut Sim<'_>) -> Result<bool, MonitorError> {
+        let is_finished = sim.step().map_err(MonitorError::Simulation)?;
+        self.steps += 1;
+        self.check(sim)?;
+        Ok(is_finished)
+    }
+
+    /// Applies due faults from `driver`, steps the simulation once and
+    /// checks every invariant.
+    pub fn step_with(
+        &mut self,
+        driver: &mut FaultDriver,
+        sim: &mut Sim<'_>,
+    ) -> Result<bool, MonitorError> {
+        let is_finished = driver.step(sim).map_err(MonitorError::Simulation)?;
+        self.steps += 1;
+        self.check(sim)?;
+        Ok(is_finished)
+    }
+
+    /// Steps the simulation until all clients have completed, checking
+    /// invariants after every step.
+    pub fn run(&mut self, sim: &mut Sim<'_>) -> Result<(), MonitorError> {
+        while !self.step(sim)? {}
+        Ok(())
+    }
+
+    /// Steps the simulation until `sim.elapsed()` reaches `until`, checking
+    /// invariants after every step.
+    ///
+    /// Returns whether all clients had completed at the last step.
+    pub fn run_until(&mut self, sim: &mut Sim<'_>, until: Duration) -> Result<bool, MonitorError> {
+        let mut is_finished = false;
+        while sim.elapsed() < until {
+            is_finished = self.step(sim)?;
+        }
+        Ok(is_finished)
+    }
+
+    /// Like [`FaultDriver::run`], checking invariants after every step.
+    pub fn run_with(
+        &mut self,
+        driver: &mut FaultDriver,
+        sim: &mut Sim<'_>,
+    ) -> Result<(), MonitorError> {
+        loop {
+            let is_finished = self.step_with(driver, sim)?;
+            if is_finished && driver.pending().next().is_none() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// An invariant that did not hold after a step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantViolation {
+    /// Name of the failed invariant.
+    pub invariant: String,
+    /// The message the invariant returned.
+    pub message: String,
+    /// Number of steps taken when the invariant failed; `0` means the
+    /// initial state.
+    pub step: u64,
+    /// Simulated time when the invariant failed.
+    pub elapsed: Duration,
+    /// Seed of the failing run.
+    pub seed: u64,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invariant `{}` violated at step {} ({:?} simulated, {}={}): {}",
+            self.invariant, self.step, self.elapsed, TEST_SEED_ENV, self.seed, self.message
+        )
+    }
+}
+
+impl StdError for InvariantViolation {}
+
+/// Why a monitored run stopped early.
+#[derive(Debug, Error)]
+pub enum MonitorError {
+    /// An invariant did not hold.
+    #[error(transparent)]
+    Violation(#[from] InvariantViolation),
+
+    /// The simulation itself failed, e.g. a host returned an error.
+    #[error("simulation failed: {0}")]
+    Simulation(Box<dyn StdError>),
+}
+
+impl MonitorError {
+    /// The violation, if an invariant failed.
+    pub fn violation(&self) -> Option<&InvariantViolation> {
+        match self {
+            MonitorError::Violation(violation) => Some(violation),
+            MonitorError::Simulation(_) => None,
+        }
+    }
+}
@@ -0,0 +1,390 @@
+//! One import path for I/O that works in production and in simulation.
+//!
+//! Application code imports networking, time, files and randomness from
+//! here instead of from `tokio`, `std` or `rand`. Without the `simulation`
+//! feature every item is the production tokio/OS implementation; with it,
+//! the same names resolve to turmoil's network, `SimFs`
+//! files and seeded [`Entropy`](crate::Entropy) streams.
+//!
+//! | Module | Production | `simulation` |
+//! |--------|------------|--------------|
+//! | [`net`] | `tokio::net` | `turmoil::net` |
+//! | [`time`] | `tokio::time`, system clock | `tokio::time`, driven by turmoil's clock; [`HostClock`](crate::HostClock) |
+//! | [`fs`] | `tokio::fs` | `HostFs` / `SimFile` |
+//! | [`random`] | OS-seeded `StdRng` | [`HostEntropy`](crate::HostEntropy) |
+//!
+//! Wall clocks, files and randomness need per-host state in simulation, so
+//! they are values ([`time::Clock`], [`fs::Fs`], [`random::Random`]) that
+//! the application receives at startup rather than free functions. Under
+//! both features they are built the same way, through [`Env`]:
+//!
+//! ```
+//! let io = dst::io::Env::new(7).host("db");
+//! let (clock, fs, random) = (io.clock, io.fs, io.random);
+//! # let _ = (clock, fs, random);
+//! ```
+//!
+//! # Enforcing the facade
+//!
+//! The compiler does not stop code from bypassing the facade; the checks
+//! are lint-time only and hold only where CI runs them, as this workspace's
+//! does:
+//!
+//! - this crate's `clippy.toml`, copied into a crate, makes
+//!   `cargo clippy -- -D warnings` reject `std::time::Instant`,
+//!   `std::time::SystemTime` and `std::thread::sleep`;
+//! - `dst-lint` also flags `tokio::net` and `std::net` sockets, ambient
+//!   randomness, thread spawns and default-hashed maps. Clippy cannot catch
+//!   `tokio::net`: without `simulation`, [`net`] re-exports those very
+//!   items and clippy sees through re-exports, while `dst-lint` checks
+//!   paths as written.
+
+/// Hands out each host's [`time::Clock`], [`fs::Fs`] and
+/// [`random::Random`], with the same constructor under both features.
+///
+/// Under `simulation` it owns the `SimClocks`, `SimFs` and
+/// [`Entropy`](crate::Entropy) behind those values, so faults can be
+/// injected through `Env::clocks` and `Env::fs`. In production the seed is unused: clocks and files are the
+/// system's and randomness is OS-seeded.
+#[derive(Debug, Clone)]
+pub struct Env {
+    #[cfg(feature = "simulation")]
+    clocks: crate::SimClocks,
+    #[cfg(feature = "simulation")]
+    fs: crate::SimFs,
+    #[cfg(feature = "simulation")]
+    entropy: crate::Entropy,
+}
+
+/// One host's clock, files and randomness. Created by [`Env::host`].
+#[derive(Debug, Clone)]
+pub struct HostIo {
+    /// The host's clock.
+    pub clock: time::Clock,
+    /// The host's files.
+    pub fs: fs::Fs,
+    /// The host's random streams.
+    pub random: random::Random,
+}
+
+impl Env {
+    /// Creates the environment for a run with `seed`.
+    #[cfg(feature = "simulation")]
+    pub fn new(seed: u64) -> Self {
+        Env {
+            clocks: crate::SimClocks::new(),
+            fs: crate::SimFs::new(seed),
+            entropy: crate::Entropy::new(seed),
+        }
+    }
+
+    /// Creates the environment for a run with `seed`.
+    #[cfg(not(feature = "simulation"))]
+    pub fn new(seed: u64) -> Self {
+        let _ = seed;
+        Env {}
+    }
+
+    /// Returns `host`'s clock, files and randomness.
+    #[cfg(feature = "simulation")]
+    pub fn host(&self, host: impl Into<String>) -> HostIo {
+        let host = host.into();
+        HostIo {
+            clock: self.clocks.host(host.clone()),
+            fs: self.fs.host(host.clone()),
+            random: self.entropy.host(host),
+        }
+    }
+
+    /// Returns `host`'s clock, files and randomness.
+    #[cfg(not(feature = "simulation"))]
+    pub fn host(&self, host: impl Into<String>) -> HostIo {
+        let _ = host.into();
+        HostIo {
+            clock: time::Clock::new(),
+            fs: fs::Fs::new(),
+            random: random::Random::new(),
+        }
+    }
+
+    /// The simulated clocks, for
+    /// [`FaultDriver::with_clocks`](crate::FaultDriver::with_clocks).
+    #[cfg(feature = "simulation")]
+    pub fn clocks(&self) -> &crate::SimClocks {
+        &self.clocks
+    }
+
+    /// The simulated disks, for
+    /// [`FaultDriver::with_fs`](crate::FaultDriver::with_fs).
+    #[cfg(feature = "simulation")]
+    pub fn fs(&self) -> &crate::SimFs {
+        &self.fs
+    }
+
+    /// The seeded random streams.
+    #[cfg(feature = "simulation")]
+    pub fn entropy(&self) -> crate::Entropy {
+        self.entropy
+    }
+}
+
+/// TCP, UDP and DNS lookup.
+pub mod net {
+    #[cfg(not(feature = "simulation"))]
+    pub use tokio::net::{lookup_host, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+
+    #[cfg(feature = "simulation")]
+    pub use turmoil::net::{lookup_host, TcpListener, TcpStream, UdpSocket};
+    #[cfg(feature = "simulation")]
+    pub use turmoil::ToSocketAddrs;
+}
+
+/// Sleeps, timeouts and monotonic time.
+///
+/// Turmoil runs every host on a tokio runtime whose clock it controls, so
+/// `tokio::time` is already simulated; this module exists so applications
+/// never reach for `std::time::Instant` or `std::thread::sleep`.
+///
+/// That clock is perfect and shared by every host. Code whose correctness
+/// depends on clocks agreeing, such as leases and heartbeat timeouts, reads
+/// a [`Clock`](time::Clock) instead: the system clock in production, and in simulation a
+/// [`HostClock`](crate::HostClock) that clock faults skew, drift and step.
+pub mod time {
+    pub use std::time::Duration;
+    pub use tokio::time::error::Elapsed;
+    pub use tokio::time::{interval, sleep, sleep_until, timeout, Instant, Interval};
+
+    #[cfg(feature = "simulation")]
+    pub use crate::clock::HostClock as Clock;
+
+    #[cfg(not(feature = "simulation"))]
+    pub use self::system::Clock;
+
+    #[cfg(not(feature = "simulation"))]
+    mod system {
+        use std::future::Future;
+        use std::time::Duration;
+
+        use tokio::time::error::Elapsed;
+        use tokio::time::Instant;
+
+        /// The system clocks, with the same methods as
+        /// [`HostClock`](crate::HostClock).
+        #[derive(Debug, Clone, Copy)]
+        pub struct Clock {
+            start: Instant,
+        }
+
+        impl Clock {
+            /// Returns the system clocks, measuring
+            /// [`elapsed`](Self::elapsed) from now.
+            pub(crate) fn new() -> Self {
+                Clock {
+                    start: Instant::now(),
+                }
+            }
+
+            /// The system wall-clock time since the Unix epoch.
+            #[dst_macros::allow(time)]
+            #[allow(clippy::disallowed_types)]
+            pub fn wall_time(&self) -> Duration {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+            }
+
+            /// Monotonic time since the clock was created.
+            pub fn elapsed(&self) -> Duration {
+                self.start.elapsed()
+            }
+
+            /// Sleeps for `duration`.
+            pub async fn sleep(&self, duration: Duration) {
+                tokio::time::sleep(duration).await;
+            }
+
+            /// Runs `future` for at most `duration`.
+            pub async fn timeout<F: Future>(
+                &self,
+                duration: Duration,
+                future: F,
+            ) -> Result<F::Output, Elapsed> {
+                tokio::time::timeout(duration, future).await
+            }
+        }
+    }
+}
+
+/// Files.
+pub mod fs {
+    #[cfg(feature = "simulation")]
+    pub use crate::fs::{HostFs as Fs, SimFile as File};
+
+    #[cfg(not(feature = "simulation"))]
+    pub use self::tokio_fs::{File, Fs};
+
+    #[cfg(not(feature = "simulation"))]
+    mod tokio_fs {
+        use std::io::{self, SeekFrom};
+        use std::path::Path;
+
+        use tokio::fs::OpenOptions;
+        use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+        /// The local filesystem, with the same methods as
+        /// `HostFs`.
+        #[derive(Debug, Clone, Copy)]
+        pub struct Fs(());
+
+        impl Fs {
+            /// Returns the local filesystem.
+            pub(crate) fn new() -> Self {
+                Fs(())
+            }
+
+            /// Reads the whole file at `path`.
+            pub async fn read(&self, path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+                tokio::fs::read(path).await
+            }
+
+            /// Creates or truncates the file at `path` and writes
+            /// `contents` to it, without syncing.
+            pub async fn write(
+                &self,
+                path: impl AsRef<Path>,
+                contents: impl AsRef<[u8]>,
+            ) -> io::Result<()> {
+                tokio::fs::write(path, contents).await
+            }
+
+            /// Removes the file at `path`.
+            pub async fn remove_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+                tokio::fs::remove_file(path).await
+            }
+
+            /// Atomically renames `from` to `to`, replacing `to` if it
+            /// exists.
+            pub async fn rename(
+                &self,
+                from: impl AsRef<Path>,
+                to: impl AsRef<Path>,
+            ) -> io::Result<()> {
+                tokio::fs::rename(from, to).await
+            }
+
+            /// Returns whether a file exists at `path`.
+            pub async fn try_exists(&self, path: impl AsRef<Path>) -> io::Result<bool> {
+                tokio::fs::try_exists(path).await
+            }
+
+            /// Opens the existing file at `path` for reading and writing.
+            pub async fn open(&self, path: impl AsRef<Path>) -> io::Result<File> {
+                let file = OpenOptions::new().read(true).write(true).open(path).await?;
+                Ok(File { inner: file })
+            }
+
+            /// Creates or truncates the file at `path` and opens it for
+            /// reading and writing.
+            pub async fn create(&self, path: impl AsRef<Path>) -> io::Result<File> {
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(path)
+                    .await?;
+                Ok(File { inner: file })
+            }
+        }
+
+        /// An open file, with the same methods as
+        /// `SimFile`.
+        #[derive(Debug)]
+        pub struct File {
+            inner: tokio::fs::File,
+        }
+
+        impl File {
+            /// Reads into `buf` from the cursor, returning the number of
+            /// bytes read.
+            pub async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.inner.read(buf).await
+            }
+
+            /// Appends the rest of the file, from the cursor, to `buf`.
+            pub async fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+                self.inner.read_to_end(buf).await
+            }
+
+            /// Writes all of `buf` at the cursor.
+            pub async fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+                self.inner.write_all(buf).await
+            }
+
+            /// Moves the cursor.
+            pub async fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.inner.seek(pos).await
+            }
+
+            /// Truncates or zero-extends the file.
+            pub async fn set_len(&mut self, len: u64) -> io::Result<()> {
+                self.inner.set_len(len).await
+            }
+
+            /// Current length of the file.
+            pub async fn len(&self) -> io::Result<u64> {
+                Ok(self.inner.metadata().await?.len())
+            }
+
+            /// Flushes contents and metadata to disk.
+            pub async fn sync_all(&self) -> io::Result<()> {
+                self.inner.sync_all().await
+            }
+
+            /// Flushes contents to disk.
+            pub async fn sync_data(&self) -> io::Result<()> {
+                self.inner.sync_data().await
+            }
+        }
+    }
+}
+
+/// Random number streams.
+pub mod random {
+    pub use rand::rngs::StdRng;
+
+    #[cfg(feature = "simulation")]
+    pub use crate::entropy::HostEntropy as Random;
+
+    #[cfg(not(feature = "simulation"))]
+    pub use self::os::Random;
+
+    #[cfg(not(feature = "simulation"))]
+    mod os {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        /// OS-seeded random streams, with the same methods as
+        /// [`HostEntropy`](crate::HostEntropy).
+        #[derive(Debug, Clone, Copy)]
+        pub struct Random(());
+
+        #[dst_macros::allow(rand)]
+        impl Random {
+            /// Returns the OS-seeded provider.
+            pub(crate) fn new() -> Self {
+                Random(())
+            }
+
+            /// A fresh seed for `component`.
+            pub fn seed(&self, _component: &str) -> u64 {
+                rand::random()
+            }
+
+            /// A fresh random stream for `component`.
+            pub fn rng(&self, _component: &str) -> StdRng {
+                StdRng::from_os_rng()
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Deterministic simulation testing (DST) helpers for tokio services.
+//!
+//! This crate is the versioned home of the helpers that used to be copied
+//! out of `skills/dst-tokio-rust/examples/basic-dst-setup.rs`.
+//!
+//! # Features
+//!
+//! - **default**: deterministic hashing ([`DeterministicHasher`],
+//!   [`SeededBuildHasher`]) and [`collections`] built on it ([`DetHashMap`],
+//!   [`DetHashSet`], the insertion-ordered [`DetIndexMap`]), safe to use in
+//!   production code paths whose behaviour must not depend on `HashMap`
+//!   iteration order; per-host random streams
+//!   derived from one seed ([`Entropy`]); the [`Fault`] model
+//!   with its [`FaultScenarioGenerator`] and JSON persistence
+//!   ([`save_scenario`], [`load_scenario`]), since scenarios are plain data;
+//!   the network [`Topology`] of zones and per-link latency distributions,
+//!   loss and bandwidth caps that compiles into such a scenario;
+//!   the [`TraceRecorder`] whose JSON Lines [`Trace`]s can be diffed
+//!   for the first divergent event and exported as a [`Timeline`] (Chrome
+//!   Trace Event JSON or an HTML sequence diagram); the [`linearizability`] checker for
+//!   recorded operation [`History`]s; the phi-accrual [`FailureDetector`]
+//!   that heartbeats peers over the [`io`] facade; and the [`ChaosProxy`]
+//!   that injects the same faults into real localhost TCP connections.
+//! - **[`io`] facade**: networking, time, files and randomness that resolve
+//!   to tokio and the OS by default and to turmoil, [`SimFs`] and
+//!   [`Entropy`] under `simulation`, with [`io::Env`] building each host's
+//!   values the same way under both.
+//! - **`simulation`**: seed management and the simulation test runners
+//!   ([`get_test_seed`], [`run_simulation_test`], [`run_scenario_test`],
+//!   the parallel [`SeedSweep`]) with a checked-in [`RegressionCorpus`] of
+//!   failing seeds that is replayed before new ones, the [`DeterminismCheck`] that reruns a simulation and
+//!   compares traces, the [`InvariantMonitor`] that checks named
+//!   invariants after every step, the [`ScenarioShrinker`] that minimizes failing fault
+//!   scenarios, the [`Scheduler`] that explores seeded or exhaustive task
+//!   interleavings within one host, the [`FaultDriver`] that
+//!   applies fault scenarios to a live simulation, the per-host
+//!   [`SimClocks`] its clock faults skew, drift and step, the [`Runner`] that
+//!   executes declarative TOML or YAML [`ScenarioSpec`]s against registered
+//!   host factories (the engine of a `dst-run` binary), the [`FaultyUdpSocket`]
+//!   that observes faults turmoil cannot inject itself, the in-memory
+//!   [`SimFs`] with crash-consistency faults, the seeded [`Buggify`]
+//!   decisions behind the [`buggify!`] fault-injection macros (which
+//!   compile to nothing without the feature), plus a re-export of
+//!   [`turmoil`] so downstream crates test against the same version.
+//! - **`proptest`**: [`strategy`], with `proptest` strategies and
+//!   `Arbitrary` implementations for [`Fault`]s and timed fault schedules,
+//!   so failing schedules shrink and persist like any property test.
+//!
+//! ```toml
+//! [features]
+//! default = []
+//! simulation = ["dst/simulation"]
+//!
+//! [dependencies]
+//! dst = { git = "https://github.com/ewe-studios/agentic-coding-starter" }
+//! ```
+//!
+//! # Running
+//!
+//! ```bash
+//! # With random seed
+//! cargo test --features simulation
+//!
+//! # With specific seed for reproduction
+//! TEST_SEED=12345 cargo test --features simulation
+//! ```
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+pub mod buggify;
+pub mod collections;
+pub mod detector;
+pub mod entropy;
+pub mod error;
+pub mod fault;
+pub mod hash;
+pub mod io;
+pub mod linearizability;
+pub mod proxy;
+pub mod timeline;
+pub mod topology;
+pub mod trace;
+
+#[cfg(feature = "proptest")]
+pub mod strategy;
+
+#[cfg(feature = "simulation")]
+pub mod clock;
+#[cfg(feature = "simulation")]
+pub mod determinism;
+#[cfg(feature = "simulation")]
+pub mod driver;
+#[cfg(feature = "simulation")]
+pub mod fs;
+#[cfg(feature = "simulation")]
+pub mod invariant;
+#[cfg(feature = "simulation")]
+pub mod network;
+#[cfg(feature = "simulation")]
+pub mod regression;
+#[cfg(feature = "simulation")]
+pub mod runner;
+#[cfg(feature = "simulation")]
+pub mod scheduler;
+#[cfg(feature = "simulation")]
+pub mod seed;
+#[cfg(feature = "simulation")]
+pub mod shrink;
+#[cfg(feature = "simulation")]
+pub mod sweep;
+
+pub use collections::{
+    det_hash_map, det_hash_set, DetHashMap, DetHashSet, DetIndexMap, DetIndexSet, SeededHashMap,
+    SeededHashSet,
+};
+pub use detector::{FailureDetector, MembershipEvent, PhiAccrual};
+pub use dst_macros::allow;
+pub use entropy::{Entropy, HostEntropy};
+pub use error::{Error, Result};
+pub use fault::{
+    load_scenario, save_scenario, scenario_from_json, scenario_to_json, Fault,
+    FaultScenarioGenerator, FaultWeights, Scenario,
+};
+pub use hash::{DeterministicBuildHasher, DeterministicHasher, SeededBuildHasher, SeededHasher};
+pub use linearizability::{History, HistoryRecorder, LinearizabilityViolation, Model};
+pub use proxy::ChaosProxy;
+pub use timeline::{Mark, Timeline};
+pub use topology::{LatencyDistribution, Link, Topology};
+pub use trace::{Divergence, HostTrace, Trace, TraceEvent, TraceRecorder};
+
+#[cfg(feature = "simulation")]
+pub use buggify::{Buggify, BuggifyEvent, BuggifyGuard, BUGGIFY_HOST};
+#[cfg(feature = "simulation")]
+pub use clock::{HostClock, SimClocks};
+#[cfg(feature = "simulation")]
+pub use determinism::{assert_deterministic, DeterminismCheck, DeterminismFailure};
+#[cfg(feature = "simulation")]
+pub use driver::{AppliedFault, FaultDriver, FAULT_DRIVER_HOST};
+#[cfg(feature = "simulation")]
+pub use fs::{DiskFaults, HostFs, SimFile, SimFs};
+#[cfg(feature = "simulation")]
+pub use invariant::{InvariantMonitor, InvariantViolation, MonitorError};
+#[cfg(feature = "simulation")]
+pub use network::{FaultyUdpSocket, NetworkFaults};
+#[cfg(feature = "simulation")]
+pub use regression::{Regression, RegressionCorpus, REGRESSIONS_DIR_ENV};
+#[cfg(feature = "simulation")]
+pub use runner::{HostContext, RunError, RunReport, Runner, ScenarioSpec};
+#[cfg(feature = "simulation")]
+pub use scheduler::{ExploreReport, FailureKind, Schedule, ScheduleFailure, Scheduler};
+#[cfg(feature = "simulation")]
+pub use seed::{get_test_seed, run_scenario_test, run_simulation_test, TEST_SEED_ENV};
+#[cfg(feature = "simulation")]
+pub use shrink::{ScenarioShrinker, ShrinkOutcome};
+#[cfg(feature = "simulation")]
+pub use sweep::{SeedFailure, SeedSweep, SweepReport};
+
+#[cfg(feature = "simulation")]
+pub use turmoil;
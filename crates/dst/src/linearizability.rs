@@ -0,0 +1,564 @@
+//! Linearizability checking for operation histories recorded in simulation.
+//!
+//! Hosts record each client operation with a [`HistoryRecorder`]: once when
+//! it is invoked and once when its response arrives. [`History::check`] then
+//! searches for a sequential order of the operations that respects real-time
+//! order and matches a sequential [`Model`], using the Wing & Gong search
+//! with memoization of `(linearized set, model state)` pairs.
+//!
+//! Operations without a response (the client crashed or timed out) are
+//! indeterminate: they may have taken effect at any point after their
+//! invocation, or not at all.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//! use dst::linearizability::{HistoryRecorder, Register, RegisterOp, RegisterRet};
+//!
+//! let recorder = HistoryRecorder::new();
+//! let ms = Duration::from_millis;
+//!
+//! let write = recorder.invoke_at(ms(0), "a", RegisterOp::Write(1));
+//! let read = recorder.invoke_at(ms(1), "b", RegisterOp::Read);
+//! recorder.complete_at(ms(2), read, RegisterRet::Read(1));
+//! recorder.complete_at(ms(3), write, RegisterRet::Write);
+//!
+//! // The read overlaps the write, so observing 1 is allowed.
+//! assert!(recorder.history().check(Register::new(0)).is_ok());
+//! ```
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::hash::DeterministicBuildHasher;
+
+/// Search states one minimization step may visit. A long-running operation
+/// keeps every relaxed operation it overlaps in play, so a step can grow
+/// exponentially; past the budget the operation stays in the witness.
+const MINIMIZE_BUDGET: usize = 10_000;
+
+/// A sequential specification that histories are checked against.
+///
+/// The model value is the state of the object; [`Model::apply`] performs one
+/// operation on it and returns the response a correct implementation would
+/// give.
+pub trait Model: Clone + Eq + Hash {
+    /// Operations clients invoke.
+    type Op: Clone + fmt::Debug;
+    /// Responses clients observe.
+    type Ret: Clone + fmt::Debug + PartialEq;
+
+    /// Applies `op` to the state and returns its response.
+    fn apply(&mut self, op: &Self::Op) -> Self::Ret;
+}
+
+/// Identifies an invoked operation so its response can be recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OpId(usize);
+
+/// One operation in a [`History`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation<Op, Ret> {
+    /// Client that invoked the operation.
+    pub client: String,
+    /// The operation.
+    pub op: Op,
+    /// The response, or `None` if the operation never completed.
+    pub ret: Option<Ret>,
+    /// Simulated time of the invocation.
+    pub invoked_at: Duration,
+    /// Simulated time of the response, if any.
+    pub returned_at: Option<Duration>,
+    /// Position of the invocation in the recorded event order.
+    invoke_seq: u64,
+    /// Position of the response in the recorded event order.
+    return_seq: Option<u64>,
+}
+
+impl<Op, Ret> Operation<Op, Ret> {
+    /// Returns `true` if a response was recorded.
+    pub fn is_complete(&self) -> bool {
+        self.return_seq.is_some()
+    }
+}
+
+#[derive(Debug)]
+struct Recorded<Op, Ret> {
+    operations: Vec<Operation<Op, Ret>>,
+    next_seq: u64,
+}
+
+impl<Op, Ret> Recorded<Op, Ret> {
+    fn next_seq(&mut self) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        seq
+    }
+}
+
+/// Shared sink that clients record operations into.
+///
+/// Real-time order is taken from the order of `invoke` and `complete` calls,
+/// not from the timestamps, so events within one simulation tick are still
+/// ordered correctly. Cheap to clone; every clone records into the same
+/// history.
+#[derive(Debug)]
+pub struct HistoryRecorder<Op, Ret> {
+    inner: Arc<Mutex<Recorded<Op, Ret>>>,
+}
+
+impl<Op, Ret> Clone for HistoryRecorder<Op, Ret> {
+    fn clone(&self) -> Self {
+        HistoryRecorder {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Op, Ret> Default for HistoryRecorder<Op, Ret> {
+    fn default() -> Self {
+        HistoryRecorder {
+            inner: Arc::new(Mutex::new(Recorded {
+                operations: Vec::new(),
+                next_seq: 0,
+            })),
+        }
+    }
+}
+
+impl<Op, Ret> HistoryRecorder<Op, Ret> {
+    /// Creates an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the invocation of `op` by `client` at simulated time `at`.
+    pub fn invoke_at(&self, at: Duration, client: impl Into<String>, op: Op) -> OpId {
+        let mut inner = self.lock();
+        let invoke_seq = inner.next_seq();
+        inner.operations.push(Operation {
+            client: client.into(),
+            op,
+            ret: None,
+            invoked_at: at,
+            returned_at: None,
+            invoke_seq,
+            return_seq: None,
+        });
+        OpId(inner.operations.len() - 1)
+    }
+
+    /// Records the response to operation `id` at simulated time `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` already has a response.
+    pub fn complete_at(&self, at: Duration, id: OpId, ret: Ret) {
+        let mut inner = self.lock();
+        let return_seq = inner.next_seq();
+        let operation = &mut inner.operations[id.0];
+        assert!(
+            operation.return_seq.is_none(),
+            "operation {:?} completed twice",
+            id
+        );
+        operation.ret = Some(ret);
+        operation.returned_at = Some(at);
+        operation.return_seq = Some(return_seq);
+    }
+
+    /// Records the invocation of `op` by `client` at the current simulated
+    /// time.
+    #[cfg(feature = "simulation")]
+    pub fn invoke(&self, client: impl Into<String>, op: Op) -> OpId {
+        self.invoke_at(turmoil::sim_elapsed().unwrap_or_default(), client, op)
+    }
+
+    /// Records the response to operation `id` at the current simulated time.
+    #[cfg(feature = "simulation")]
+    pub fn complete(&self, id: OpId, ret: Ret) {
+        self.complete_at(turmoil::sim_elapsed().unwrap_or_default(), id, ret);
+    }
+
+    /// Snapshot of the operations recorded so far.
+    pub fn history(&self) -> History<Op, Ret>
+    where
+        Op: Clone,
+        Ret: Clone,
+    {
+        History {
+            operations: self.lock().operations.clone(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Recorded<Op, Ret>> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Operations recorded by a [`HistoryRecorder`], in invocation order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History<Op, Ret> {
+    operations: Vec<Operation<Op, Ret>>,
+}
+
+impl<Op: Clone + fmt::Debug, Ret: Clone + fmt::Debug + PartialEq> History<Op, Ret> {
+    /// The recorded operations, in invocation order.
+    pub fn operations(&self) -> &[Operation<Op, Ret>] {
+        &self.operations
+    }
+
+    /// Checks the history against `model`, which holds the initial state.
+    ///
+    /// On failure, the violation holds a minimal set of completed operations
+    /// whose responses cannot all be explained, even when every other
+    /// operation is treated as indeterminate. An operation whose removal
+    /// cannot be decided within a bounded search is kept, so in very
+    /// concurrent histories the set may be larger than minimal.
+    pub fn check<M>(&self, model: M) -> Result<(), LinearizabilityViolation<Op, Ret>>
+    where
+        M: Model<Op = Op, Ret = Ret>,
+    {
+        let mut constrained: Vec<bool> = self.operations.iter().map(|o| o.is_complete()).collect();
+        if self.is_linearizable(&model, &constrained, usize::MAX) == Some(true) {
+            return Ok(());
+        }
+
+        // Relaxing an operation only removes constraints, so one pass that
+        // keeps each relaxation the history survives is already 1-minimal.
+        // Later operations go first so the witness favors early ones.
+        for index in (0..constrained.len()).rev() {
+            if !constrained[index] {
+                continue;
+            }
+            constrained[index] = false;
+            if self.is_linearizable(&model, &constrained, MINIMIZE_BUDGET) != Some(false) {
+                constrained[index] = true;
+            }
+        }
+
+        Err(LinearizabilityViolation {
+            operations: self
+                .operations
+                .iter()
+                .zip(&constrained)
+                .filter(|(_, keep)| **keep)
+                .map(|(operation, _)| operation.clone())
+                .collect(),
+            total: self.operations.len(),
+        })
+    }
+
+    /// Returns `None` if the search visits more than `budget` states.
+    fn is_linearizable<M>(&self, model: &M, constrained: &[bool], budget: usize) -> Option<bool>
+    where
+        M: Model<Op = Op, Ret = Ret>,
+    {
+        let mut search = Search {
+            operations: &self.operations,
+            constrained,
+            linearized: vec![0; self.operations.len().div_ceil(64)],
+            seen: HashSet::with_hasher(DeterministicBuildHasher),
+            budget,
+        };
+        let remaining = constrained.iter().filter(|c| **c).count();
+        search.run(model, remaining)
+    }
+}
+
+/// Depth-first search for a linearization.
+struct Search<'a, Op, Ret, M> {
+    operations: &'a [Operation<Op, Ret>],
+    constrained: &'a [bool],
+    /// Bit set of operations already placed in the linearization.
+    linearized: Vec<u64>,
+    /// `(linearized, state)` pairs already known to be dead ends.
+    seen: HashSet<(Vec<u64>, M), DeterministicBuildHasher>,
+    /// States left to visit before giving up.
+    budget: usize,
+}
+
+impl<Op, Ret, M> Search<'_, Op, Ret, M>
+where
+    Ret: PartialEq,
+    M: Model<Op = Op, Ret = Ret>,
+{
+    fn run(&mut self, model: &M, remaining: usize) -> Option<bool> {
+        if remaining == 0 {
+            return Some(true);
+        }
+        if !self.seen.insert((self.linearized.clone(), model.clone())) {
+            return Some(false);
+        }
+        self.budget = self.budget.checked_sub(1)?;
+
+        // An operation can go next only if it was invoked before every
+        // pending constrained operation returned.
+        let deadline = (0..self.operations.len())
+            .filter(|&i| self.constrained[i] && !self.is_linearized(i))
+            .filter_map(|i| self.operations[i].return_seq)
+            .min()
+            .unwrap_or(u64::MAX);
+
+        for index in 0..self.operations.len() {
+            let operation = &self.operations[index];
+            if operation.invoke_seq > deadline {
+                break;
+            }
+            if self.is_linearized(index) {
+                continue;
+            }
+
+            let mut next = model.clone();
+            let ret = next.apply(&operation.op);
+            let constrained = self.constrained[index];
+            if constrained && operation.ret.as_ref() != Some(&ret) {
+                continue;
+            }
+
+            self.set_linearized(index, true);
+            if self.run(&next, remaining - usize::from(constrained))? {
+                return Some(true);
+            }
+            self.set_linearized(index, false);
+        }
+        Some(false)
+    }
+
+    fn is_linearized(&self, index: usize) -> bool {
+        self.linearized[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn set_linearized(&mut self, index: usize, value: bool) {
+        if value {
+            self.linearized[index / 64] |= 1 << (index % 64);
+        } else {
+            self.linearized[index / 64] &= !(1 << (index % 64));
+        }
+    }
+}
+
+/// A history with no valid linearization.
+#[derive(Debug, Clone)]
+pub struct LinearizabilityViolation<Op, Ret> {
+    /// Minimal set of completed operations whose responses contradict the
+    /// model, in invocation order.
+    pub operations: Vec<Operation<Op, Ret>>,
+    /// Number of operations in the full history.
+    pub total: usize,
+}
+
+impl<Op: fmt::Debug, Ret: fmt::Debug> fmt::Display for LinearizabilityViolation<Op, Ret> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "history of {} operations is not linearizable; these {} cannot be ordered \
+             (other operations treated as indeterminate):",
+            self.total,
+            self.operations.len()
+        )?;
+        for operation in &self.operations {
+            writeln!(
+                f,
+                "  [{:?} .. {:?}] {}: {:?} -> {:?}",
+                operation.invoked_at,
+                operation.returned_at.unwrap_or_default(),
+                operation.client,
+                operation.op,
+                operation
+                    .ret
+                    .as_ref()
+                    .expect("witness operations are complete"),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<Op: fmt::Debug, Ret: fmt::Debug> std::error::Error for LinearizabilityViolation<Op, Ret> {}
+
+/// A single read/write register with compare-and-set.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Register<T> {
+    value: T,
+}
+
+impl<T> Register<T> {
+    /// Creates a register holding `initial`.
+    pub fn new(initial: T) -> Self {
+        Register { value: initial }
+    }
+}
+
+/// Operations on a [`Register`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RegisterOp<T> {
+    /// Reads the current value.
+    Read,
+    /// Replaces the value.
+    Write(T),
+    /// Replaces the value with `new` if it equals `expected`.
+    Cas {
+        /// Value the register must hold.
+        expected: T,
+        /// Value to store.
+        new: T,
+    },
+}
+
+/// Responses from a [`Register`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RegisterRet<T> {
+    /// The value read.
+    Read(T),
+    /// The write took effect.
+    Write,
+    /// Whether the compare-and-set succeeded.
+    Cas(bool),
+}
+
+impl<T: Clone + fmt::Debug + Eq + Hash> Model for Register<T> {
+    type Op = RegisterOp<T>;
+    type Ret = RegisterRet<T>;
+
+    fn apply(&mut self, op: &RegisterOp<T>) -> RegisterRet<T> {
+        match op {
+            RegisterOp::Read => RegisterRet::Read(self.value.clone()),
+            RegisterOp::Write(value) => {
+                self.value = value.clone();
+                RegisterRet::Write
+            }
+            RegisterOp::Cas { expected, new } => {
+                let swapped = self.value == *expected;
+                if swapped {
+                    self.value = new.clone();
+                }
+                RegisterRet::Cas(swapped)
+            }
+        }
+    }
+}
+
+/// A key-value map.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KvStore<K, V> {
+    entries: BTreeMap<K, V>,
+}
+
+impl<K, V> Default for KvStore<K, V> {
+    fn default() -> Self {
+        KvStore {
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K, V> KvStore<K, V> {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Operations on a [`KvStore`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KvOp<K, V> {
+    /// Reads a key.
+    Get(K),
+    /// Sets a key.
+    Put(K, V),
+    /// Removes a key.
+    Delete(K),
+}
+
+/// Responses from a [`KvStore`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KvRet<V> {
+    /// The value read, or `None` if the key was absent.
+    Value(Option<V>),
+    /// The put or delete took effect.
+    Ok,
+}
+
+impl<K, V> Model for KvStore<K, V>
+where
+    K: Clone + fmt::Debug + Ord + Hash,
+    V: Clone + fmt::Debug + Eq + Hash,
+{
+    type Op = KvOp<K, V>;
+    type Ret = KvRet<V>;
+
+    fn apply(&mut self, op: &KvOp<K, V>) -> KvRet<V> {
+        match op {
+            KvOp::Get(key) => KvRet::Value(self.entries.get(key).cloned()),
+            KvOp::Put(key, value) => {
+                self.entries.insert(key.clone(), value.clone());
+                KvRet::Ok
+            }
+            KvOp::Delete(key) => {
+                self.entries.remove(key);
+                KvRet::Ok
+            }
+        }
+    }
+}
+
+/// A FIFO queue.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Queue<T> {
+    items: VecDeque<T>,
+}
+
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Queue {
+            items: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> Queue<T> {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Operations on a [`Queue`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum QueueOp<T> {
+    /// Appends an item.
+    Enqueue(T),
+    /// Removes the oldest item.
+    Dequeue,
+}
+
+/// Responses from a [`Queue`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum QueueRet<T> {
+    /// The enqueue took effect.
+    Ok,
+    /// The dequeued item, or `None` if the queue was empty.
+    Value(Option<T>),
+}
+
+impl<T: Clone + fmt::Debug + Eq + Hash> Model for Queue<T> {
+    type Op = QueueOp<T>;
+    type Ret = QueueRet<T>;
+
+    fn apply(&mut self, op: &QueueOp<T>) -> QueueRet<T> {
+        match op {
+            QueueOp::Enqueue(item) => {
+                self.items.push_back(item.clone());
+                QueueRet::Ok
+            }
+            QueueOp::Dequeue => QueueRet::Value(self.items.pop_front()),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Network faults that turmoil cannot inject by itself.
+//!
+//! Turmoil can partition links, drop messages and vary latency, but it has
+//! no way to deliver a message twice or to cap a link's throughput.
+//! [`NetworkFaults`] holds the extra per-link fault state set by
+//! [`FaultDriver`](crate::FaultDriver), and [`FaultyUdpSocket`] applies it on
+//! the receiving side.
+//!
+//! TCP streams are left alone: like a real TCP stack, turmoil's TCP already
+//! discards duplicate segments, so duplication only matters for datagrams.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use turmoil::net::UdpSocket;
+use turmoil::ToSocketAddrs;
+
+/// Shared, seeded per-link fault state.
+///
+/// Cheap to clone; every clone refers to the same state. Hand a clone to each
+/// host that should observe the faults.
+#[derive(Debug, Clone)]
+pub struct NetworkFaults {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    rng: StdRng,
+    duplicate_percent: BTreeMap<(String, String), u8>,
+    bandwidth: BTreeMap<(String, String), Bandwidth>,
+}
+
+/// A throughput cap and the time the link finishes its current backlog.
+#[derive(Debug)]
+struct Bandwidth {
+    bytes_per_sec: u64,
+    busy_until: Duration,
+}
+
+impl NetworkFaults {
+    /// Creates an empty fault state whose random decisions derive from `seed`.
+    pub fn new(seed: u64) -> Self {
+        NetworkFaults {
+            inner: Arc::new(Mutex::new(Inner {
+                rng: StdRng::seed_from_u64(seed),
+                duplicate_percent: BTreeMap::new(),
+                bandwidth: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Sets the chance, in percent, that a datagram between hosts `a` and `b`
+    /// is delivered twice. Zero clears the fault.
+    pub fn set_duplicate(&self, a: &str, b: &str, percent: u8) {
+        let mut inner = self.lock();
+        if percent == 0 {
+            inner.duplicate_percent.remove(&link(a, b));
+        } else {
+            inner.duplicate_percent.insert(link(a, b), percent.min(100));
+        }
+    }
+
+    /// Rolls whether a datagram from host `src` to host `dst` should be
+    /// duplicated.
+    ///
+    /// Only draws from the RNG when the link has a duplicate fault, so
+    /// fault-free links do not perturb the random stream.
+    pub fn should_duplicate(&self, src: &str, dst: &str) -> bool {
+        let mut inner = self.lock();
+        match inner.duplicate_percent.get(&link(src, dst)).copied() {
+            Some(percent) => inner.rng.random_range(0..100) < percent,
+            None => false,
+        }
+    }
+
+    /// Caps the link between hosts `a` and `b` at `bytes_per_sec`. Zero
+    /// clears the cap.
+    pub fn set_bandwidth(&self, a: &str, b: &str, bytes_per_sec: u64) {
+        let mut inner = self.lock();
+        if bytes_per_sec == 0 {
+            inner.bandwidth.remove(&link(a, b));
+        } else {
+            inner
+                .bandwidth
+                .entry(link(a, b))
+                .and_modify(|cap| cap.bytes_per_sec = bytes_per_sec)
+                .or_insert(Bandwidth {
+                    bytes_per_sec,
+                    busy_until: Duration::ZERO,
+                });
+        }
+    }
+
+    /// Queues `len` bytes from host `src` arriving at host `dst` at simulated
+    /// time `now`, and returns how long they wait until the link has carried
+    /// them.
+    ///
+    /// Both directions share one queue. Links without a cap return zero.
+    pub fn transmit_delay(&self, src: &str, dst: &str, len: usize, now: Duration) -> Duration {
+        let mut inner = self.lock();
+        let Some(cap) = inner.bandwidth.get_mut(&link(src, dst)) else {
+            return Duration::ZERO;
+        };
+        let nanos = len as u128 * 1_000_000_000 / u128::from(cap.bytes_per_sec);
+        let transmit = Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX));
+        cap.busy_until = cap.busy_until.max(now) + transmit;
+        cap.busy_until - now
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // A panic while holding the lock cannot leave the maps half-updated.
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for NetworkFaults {
+    fn default() -> Self {
+        NetworkFaults::new(0)
+    }
+}
+
+fn link(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+/// A turmoil `UdpSocket` that honours [`NetworkFaults`].
+///
+/// When a received datagram is chosen for duplication, the next
+/// [`recv_from`](Self::recv_from) returns the same datagram again before
+/// reading from the network. On a link with a bandwidth cap, each datagram
+/// is held until the link has had time to carry it and everything queued
+/// before it.
+#[derive(Debug)]
+pub struct FaultyUdpSocket {
+    inner: UdpSocket,
+    host: String,
+    faults: NetworkFaults,
+    /// A datagram read from the network but not yet returned.
+    pending: Mutex<Option<Pending>>,
+}
+
+/// A datagram held by the socket until `due`.
+#[derive(Debug, Clone)]
+struct Pending {
+    data: Vec<u8>,
+    from: SocketAddr,
+    /// Simulated time at which the link has carried the datagram.
+    due: Duration,
+    /// The sending host, while the duplicate roll is still to be made.
+    sender: Option<String>,
+}
+
+impl FaultyUdpSocket {
+    /// Binds a new socket to `addr` on the simulated host named `host`,
+    /// subject to `faults`.
+    ///
+    /// The host name is needed because sockets bound to `0.0.0.0` cannot tell
+    /// which host they live on.
+    pub async fn bind<A: ToSocketAddrs>(
+        host: impl Into<String>,
+        addr: A,
+        faults: NetworkFaults,
+    ) -> io::Result<Self> {
+        Ok(FaultyUdpSocket {
+            inner: UdpSocket::bind(addr).await?,
+            host: host.into(),
+            faults,
+            pending: Mutex::new(None),
+        })
+    }
+
+    /// Sends `buf` to `target`.
+    pub async fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], target: A) -> io::Result<usize> {
+        self.inner.send_to(buf, target).await
+    }
+
+    /// Receives a datagram, possibly a duplicate of the previous one.
+    ///
+    /// As with a real socket, a datagram longer than `buf` is truncated.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe, so it can be used in `tokio::select!`. A
+    /// datagram still waiting for a capped link is kept in the socket and
+    /// returned by the next call.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        loop {
+            let due = self.lock_pending().as_ref().map(|pending| pending.due);
+            match due {
+                Some(due) => {
+                    let now = turmoil::elapsed();
+                    if due > now {
+                        tokio::time::sleep(due - now).await;
+                    }
+                    return Ok(self.deliver(buf));
+                }
+                None => {
+                    let (n, from) = self.inner.recv_from(buf).await?;
+                    let data = buf[..n].to_vec();
+                    let sender = turmoil::reverse_lookup(from.ip());
+                    let now = turmoil::elapsed();
+                    let wait = sender.as_deref().map_or(Duration::ZERO, |sender| {
+                        self.faults.transmit_delay(sender, &self.host, n, now)
+                    });
+                    *self.lock_pending() = Some(Pending {
+                        data,
+                        from,
+                        due: now + wait,
+                        sender,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns the pending datagram and rolls whether it arrives again.
+    fn deliver(&self, buf: &mut [u8]) -> (usize, SocketAddr) {
+        let mut slot = self.lock_pending();
+        let pending = slot.take().expect("a pending datagram");
+        let n = pending.data.len().min(buf.len());
+        buf[..n].copy_from_slice(&pending.data[..n]);
+        let duplicate = pending
+            .sender
+            .as_deref()
+            .is_some_and(|sender| self.faults.should_duplicate(sender, &self.host));
+        if duplicate {
+            *slot = Some(Pending {
+                sender: None,
+                ..pending.clone()
+            });
+        }
+        (n, pending.from)
+    }
+
+    fn lock_pending(&self) -> MutexGuard<'_, Option<Pending>> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the local address this socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Returns the underlying turmoil socket.
+    pub fn get_ref(&self) -> &UdpSocket {
+        &self.inner
+    }
+}
@@ -0,0 +1,450 @@
+//! A TCP chaos proxy for integration tests against real sockets.
+//!
+//! Not every service can run inside turmoil: third-party clients open their
+//! own sockets, and some tests need real processes. [`ChaosProxy`] listens
+//! on localhost between a client and its server, in the style of
+//! toxiproxy, and injects faults into the connections it forwards.
+//!
+//! It accepts the same [`Fault`]s as [`FaultDriver`](crate::FaultDriver),
+//! so a scenario written for a simulation can be replayed against real
+//! sockets with [`ChaosProxy::run_scenario`]. A proxy is one link, so the
+//! node names in faults are ignored:
+//!
+//! | Fault | Effect on the proxied connections |
+//! |-------|-----------------------------------|
+//! | `Partition` / `Repair` | Blackhole: bytes, including a close, are silently dropped in both directions / forwarded again |
+//! | `Latency`, `LinkLatency` | Delay every chunk, in each direction |
+//! | `Bandwidth` | Cap the throughput of each direction; zero clears |
+//! | `Crash` | Reset every connection and reset new ones as they arrive |
+//! | `Restart` | Reset every connection and accept new ones again |
+//!
+//! Datagram and clock faults return [`Error::UnsupportedFault`]. Resets
+//! and half-open connections, which have no `Fault`, are methods.
+//!
+//! The proxy uses tokio's sockets and clock directly, never turmoil's, and
+//! must be started inside a tokio runtime. Its items are exempt from the
+//! `dst-lint` `net` rule for that reason.
+
+use std::collections::VecDeque;
+use std::future::pending;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, Instant};
+
+use crate::error::{Error, Result};
+use crate::fault::Fault;
+
+/// Largest chunk read from either side at once.
+const CHUNK: usize = 16 * 1024;
+
+/// Bandwidth caps release bytes in slices of this fraction of a second.
+const SLICES_PER_SEC: u64 = 20;
+
+/// Faults currently in effect. Resets and half-opens are counters that
+/// each connection compares with the last value it saw.
+#[derive(Debug, Clone, Default)]
+struct Toxics {
+    latency: Duration,
+    bytes_per_sec: u64,
+    blackhole: bool,
+    refusing: bool,
+    resets: u64,
+    half_opens: u64,
+}
+
+/// A TCP proxy that injects faults between a client and a server.
+///
+/// Dropping the proxy stops it and resets every connection.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use dst::{ChaosProxy, Fault};
+///
+/// # async fn example() -> dst::Result<()> {
+/// let proxy = ChaosProxy::start("127.0.0.1:0", "127.0.0.1:5432".parse().unwrap()).await?;
+/// // Point the client under test at `proxy.local_addr()`, then:
+/// proxy.apply(&Fault::Latency { node: "db".into(), delay_ms: 200 })?;
+/// proxy.reset_connections();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ChaosProxy {
+    local_addr: SocketAddr,
+    upstream: SocketAddr,
+    toxics: watch::Sender<Toxics>,
+    accept: JoinHandle<()>,
+}
+
+impl ChaosProxy {
+    /// Listens on `listen` and forwards every connection to `upstream`.
+    #[dst_macros::allow(net)]
+    pub async fn start(listen: impl ToSocketAddrs, upstream: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(listen).await?;
+        let local_addr = listener.local_addr()?;
+        let (toxics, receiver) = watch::channel(Toxics::default());
+        let accept = tokio::spawn(accept(listener, upstream, receiver));
+        Ok(ChaosProxy {
+            local_addr,
+            upstream,
+            toxics,
+            accept,
+        })
+    }
+
+    /// The address clients connect to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The server connections are forwarded to.
+    pub fn upstream(&self) -> SocketAddr {
+        self.upstream
+    }
+
+    /// Delays every chunk by `latency` in each direction. Zero clears it.
+    pub fn set_latency(&self, latency: Duration) {
+        self.toxics.send_modify(|toxics| toxics.latency = latency);
+    }
+
+    /// Caps each direction at `bytes_per_sec`. Zero clears the cap.
+    pub fn set_bandwidth(&self, bytes_per_sec: u64) {
+        self.toxics
+            .send_modify(|toxics| toxics.bytes_per_sec = bytes_per_sec);
+    }
+
+    /// Silently drops everything sent in either direction while `on`.
+    ///
+    /// Connections stay open, as over a partitioned network, so only
+    /// timeouts notice. Bytes dropped during a blackhole are lost for good,
+    /// so connections that carried data through one should be discarded.
+    pub fn set_blackhole(&self, on: bool) {
+        self.toxics.send_modify(|toxics| toxics.blackhole = on);
+    }
+
+    /// Resets existing and new connections while `on`, as if the server
+    /// had crashed.
+    pub fn set_refusing(&self, on: bool) {
+        self.toxics.send_modify(|toxics| {
+            if on && !toxics.refusing {
+                toxics.resets += 1;
+            }
+            toxics.refusing = on;
+        });
+    }
+
+    /// Resets every open connection on both sides.
+    pub fn reset_connections(&self) {
+        self.toxics.send_modify(|toxics| toxics.resets += 1);
+    }
+
+    /// Resets the server side of every open connection while the client
+    /// side stays open: the client's writes still succeed, and it never
+    /// hears back or learns that the connection is gone.
+    pub fn half_open_connections(&self) {
+        self.toxics.send_modify(|toxics| toxics.half_opens += 1);
+    }
+
+    /// Clears latency, bandwidth caps, blackholes and refusal. Connections
+    /// already reset or half-opened stay that way.
+    pub fn heal(&self) {
+        self.toxics.send_modify(|toxics| {
+            toxics.latency = Duration::ZERO;
+            toxics.bytes_per_sec = 0;
+            toxics.blackhole = false;
+            toxics.refusing = false;
+        });
+    }
+
+    /// Whether [`apply`](Self::apply) can inject `fault`.
+    pub fn supports(fault: &Fault) -> bool {
+        matches!(
+            fault,
+            Fault::Partition { .. }
+                | Fault::Repair { .. }
+                | Fault::Latency { .. }
+                | Fault::LinkLatency { .. }
+                | Fault::Bandwidth { .. }
+                | Fault::Crash { .. }
+                | Fault::Restart { .. }
+        )
+    }
+
+    /// Injects `fault`, as described in the [module docs](self).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedFault`] for faults a TCP proxy cannot
+    /// inject.
+    pub fn apply(&self, fault: &Fault) -> Result<()> {
+        match fault {
+            Fault::Partition { .. } => self.set_blackhole(true),
+            Fault::Repair { .. } => self.set_blackhole(false),
+            Fault::Latency { delay_ms, .. } | Fault::LinkLatency { delay_ms, .. } => {
+                self.set_latency(Duration::from_millis(*delay_ms))
+            }
+            Fault::Bandwidth { bytes_per_sec, .. } => self.set_bandwidth(*bytes_per_sec),
+            Fault::Crash { .. } => self.set_refusing(true),
+            Fault::Restart { .. } => self.toxics.send_modify(|toxics| {
+                toxics.refusing = false;
+                toxics.resets += 1;
+            }),
+            other => return Err(Error::UnsupportedFault(other.clone())),
+        }
+        Ok(())
+    }
+
+    /// Applies each fault of `scenario` once its time, counted from now,
+    /// has passed, and returns after the last one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedFault`] before applying anything if the
+    /// scenario contains a fault the proxy cannot inject.
+    pub async fn run_scenario(&self, scenario: &[(Duration, Fault)]) -> Result<()> {
+        if let Some((_, fault)) = scenario.iter().find(|(_, fault)| !Self::supports(fault)) {
+            return Err(Error::UnsupportedFault(fault.clone()));
+        }
+        let mut scenario = scenario.to_vec();
+        scenario.sort_by_key(|(at, _)| *at);
+
+        let start = Instant::now();
+        for (at, fault) in &scenario {
+            sleep_until(start + *at).await;
+            self.apply(fault)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ChaosProxy {
+    fn drop(&mut self) {
+        // Connections notice the closed channel and reset themselves.
+        self.accept.abort();
+    }
+}
+
+#[dst_macros::allow(net)]
+async fn accept(listener: TcpListener, upstream: SocketAddr, toxics: watch::Receiver<Toxics>) {
+    loop {
+        let Ok((client, _)) = listener.accept().await else {
+            continue;
+        };
+        let toxics = toxics.clone();
+        tokio::spawn(async move {
+            if toxics.borrow().refusing {
+                return reset(&client);
+            }
+            match TcpStream::connect(upstream).await {
+                Ok(server) => Connection::new(client, server, toxics).run().await,
+                Err(_) => reset(&client),
+            }
+        });
+    }
+}
+
+/// Closes with a RST instead of a FIN once the stream is dropped.
+#[dst_macros::allow(net)]
+fn reset(stream: &TcpStream) {
+    let _ = stream.set_zero_linger();
+}
+
+/// One side of a proxied connection.
+#[dst_macros::allow(net)]
+struct Side {
+    read: OwnedReadHalf,
+    write: OwnedWriteHalf,
+    /// False once the side has closed its end.
+    reading: bool,
+}
+
+#[dst_macros::allow(net)]
+impl Side {
+    fn new(stream: TcpStream) -> Self {
+        let (read, write) = stream.into_split();
+        Side {
+            read,
+            write,
+            reading: true,
+        }
+    }
+
+    fn reset(self) {
+        reset(self.read.as_ref());
+        // Dropping the write half would send a FIN ahead of the RST.
+        self.write.forget();
+    }
+}
+
+enum Chunk {
+    Data(Vec<u8>),
+    Close,
+}
+
+/// Bytes in flight in one direction, each due at a time.
+#[derive(Default)]
+struct Pipe {
+    queue: VecDeque<(Instant, Chunk)>,
+    /// When the bandwidth cap lets the next bytes out.
+    free_at: Option<Instant>,
+}
+
+impl Pipe {
+    fn next_due(&self) -> Option<Instant> {
+        let (due, _) = self.queue.front()?;
+        Some(self.free_at.map_or(*due, |free_at| free_at.max(*due)))
+    }
+
+    /// Writes the next due bytes to `to`, or drops them if `to` is gone or
+    /// blackholed.
+    async fn flush(&mut self, to: Option<&mut Side>, toxics: &Toxics) -> io::Result<()> {
+        let Some((due, chunk)) = self.queue.pop_front() else {
+            return Ok(());
+        };
+        let Some(to) = to.filter(|_| !toxics.blackhole) else {
+            return Ok(());
+        };
+        match chunk {
+            Chunk::Close => to.write.shutdown().await,
+            Chunk::Data(mut data) => {
+                if toxics.bytes_per_sec > 0 {
+                    let slice = (toxics.bytes_per_sec / SLICES_PER_SEC).max(1) as usize;
+                    if data.len() > slice {
+                        let rest = data.split_off(slice);
+                        self.queue.push_front((due, Chunk::Data(rest)));
+                    }
+                    let secs = data.len() as f64 / toxics.bytes_per_sec as f64;
+                    self.free_at = Some(Instant::now() + Duration::from_secs_f64(secs));
+                } else {
+                    self.free_at = None;
+                }
+                to.write.write_all(&data).await
+            }
+        }
+    }
+}
+
+struct Connection {
+    client: Side,
+    /// `None` once the connection is half-open.
+    server: Option<Side>,
+    toxics: watch::Receiver<Toxics>,
+    seen: Toxics,
+    /// Client to server.
+    upstream: Pipe,
+    /// Server to client.
+    downstream: Pipe,
+}
+
+#[dst_macros::allow(net)]
+impl Connection {
+    fn new(client: TcpStream, server: TcpStream, mut toxics: watch::Receiver<Toxics>) -> Self {
+        let seen = toxics.borrow_and_update().clone();
+        Connection {
+            client: Side::new(client),
+            server: Some(Side::new(server)),
+            toxics,
+            seen,
+            upstream: Pipe::default(),
+            downstream: Pipe::default(),
+        }
+    }
+
+    async fn run(mut self) {
+        let mut client_buf = vec![0u8; CHUNK];
+        let mut server_buf = vec![0u8; CHUNK];
+
+        loop {
+            let toxics = self.toxics.borrow_and_update().clone();
+            if toxics.resets != self.seen.resets {
+                return self.reset();
+            }
+            if toxics.half_opens != self.seen.half_opens {
+                if let Some(server) = self.server.take() {
+                    server.reset();
+                }
+                self.upstream = Pipe::default();
+                self.downstream = Pipe::default();
+            }
+            self.seen = toxics.clone();
+
+            let server_reading = self.server.as_ref().is_some_and(|server| server.reading);
+            let in_flight = !self.upstream.queue.is_empty() || !self.downstream.queue.is_empty();
+            if !self.client.reading && !server_reading && !in_flight {
+                return;
+            }
+
+            let result = tokio::select! {
+                changed = self.toxics.changed() => match changed {
+                    Ok(()) => Ok(()),
+                    // The proxy was dropped.
+                    Err(_) => return self.reset(),
+                },
+                read = self.client.read.read(&mut client_buf), if self.client.reading => {
+                    read.map(|len| {
+                        let chunk = if len == 0 {
+                            self.client.reading = false;
+                            Chunk::Close
+                        } else {
+                            Chunk::Data(client_buf[..len].to_vec())
+                        };
+                        self.upstream.queue.push_back((Instant::now() + toxics.latency, chunk));
+                    })
+                }
+                read = read_side(self.server.as_mut(), &mut server_buf), if server_reading => {
+                    read.map(|len| {
+                        let chunk = if len == 0 {
+                            if let Some(server) = &mut self.server {
+                                server.reading = false;
+                            }
+                            Chunk::Close
+                        } else {
+                            Chunk::Data(server_buf[..len].to_vec())
+                        };
+                        self.downstream.queue.push_back((Instant::now() + toxics.latency, chunk));
+                    })
+                }
+                _ = sleep_until_due(self.upstream.next_due()) => {
+                    self.upstream.flush(self.server.as_mut(), &toxics).await
+                }
+                _ = sleep_until_due(self.downstream.next_due()) => {
+                    self.downstream.flush(Some(&mut self.client), &toxics).await
+                }
+            };
+            if result.is_err() {
+                // One side reset or failed: pass it on to the other.
+                return self.reset();
+            }
+        }
+    }
+
+    fn reset(self) {
+        self.client.reset();
+        if let Some(server) = self.server {
+            server.reset();
+        }
+    }
+}
+
+async fn read_side(side: Option<&mut Side>, buf: &mut [u8]) -> io::Result<usize> {
+    match side {
+        Some(side) => side.read.read(buf).await,
+        None => pending().await,
+    }
+}
+
+async fn sleep_until_due(due: Option<Instant>) {
+    match due {
+        Some(due) => sleep_until(due).await,
+        None => pending().await,
+    }
+}
@@ -0,0 +1,316 @@
+//! Regression corpus of failing seeds.
+//!
+//! A seed that failed once is worth rerunning on every later run, so a fixed
+//! bug stays fixed. [`RegressionCorpus`] keeps one JSON Lines file per test,
+//! much like `proptest-regressions/`: each line holds a failing seed, its
+//! panic message and, for scenario tests, the shrunk fault scenario. Lines
+//! starting with `#` are comments.
+//!
+//! ```text
+//! # Seeds that failed test_cluster_survives_faults, replayed before new seeds.
+//! # Check this file in.
+//! {"seed":42,"message":"two leaders in term 3","scenario":[{"at_ms":0,"kind":"crash","node":"b"}]}
+//! ```
+//!
+//! [`run_simulation_test`](crate::run_simulation_test) and
+//! [`run_scenario_test`](crate::run_scenario_test) use the file for their
+//! test name under `dst-regressions/`, relative to the package root that
+//! `cargo test` runs in. Set `DST_REGRESSIONS_DIR` to keep the files
+//! elsewhere.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::panic::{self, RefUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::fault::{Fault, Scenario, TimedFault};
+use crate::seed::{env_seed, get_test_seed, reproduction_command, TEST_SEED_ENV};
+use crate::shrink::{panics, ScenarioShrinker};
+use crate::sweep::panic_message;
+
+/// Environment variable that overrides the directory holding regression
+/// files.
+pub const REGRESSIONS_DIR_ENV: &str = "DST_REGRESSIONS_DIR";
+
+/// Directory used when `DST_REGRESSIONS_DIR` is unset.
+const DEFAULT_DIR: &str = "dst-regressions";
+
+/// A recorded failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regression {
+    /// The failing seed.
+    pub seed: u64,
+    /// The panic message of the failing run.
+    pub message: String,
+    /// The shrunk fault scenario, for tests run with
+    /// [`run_scenario_test`](crate::run_scenario_test).
+    pub scenario: Option<Scenario>,
+}
+
+/// One line of a regression file.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    seed: u64,
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scenario: Option<Vec<TimedFault>>,
+}
+
+/// The recorded failing seeds of one test, backed by a JSON Lines file.
+///
+/// # Examples
+///
+/// ```
+/// use dst::{Regression, RegressionCorpus};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("test_counter.jsonl");
+///
+/// let mut corpus = RegressionCorpus::open("test_counter", &path).unwrap();
+/// corpus.run(|seed| assert!(seed != 7));
+///
+/// corpus
+///     .record(Regression { seed: 7, message: "boom".into(), scenario: None })
+///     .unwrap();
+/// let reopened = RegressionCorpus::open("test_counter", &path).unwrap();
+/// assert_eq!(reopened.seeds(), vec![7]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegressionCorpus {
+    test_name: String,
+    path: PathBuf,
+    regressions: Vec<Regression>,
+}
+
+impl RegressionCorpus {
+    /// Opens the corpus of `test_name` in `DST_REGRESSIONS_DIR`, or in
+    /// `dst-regressions/` when unset.
+    pub fn for_test(test_name: &str) -> Result<Self> {
+        let dir = std::env::var_os(REGRESSIONS_DIR_ENV)
+            .map_or_else(|| PathBuf::from(DEFAULT_DIR), PathBuf::from);
+        Self::open(test_name, dir.join(file_name(test_name)))
+    }
+
+    /// Opens the corpus stored at `path`. A missing file is an empty corpus;
+    /// it is created on the first [`record`](Self::record).
+    pub fn open(test_name: impl Into<String>, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut regressions = Vec::new();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry: Entry = serde_json::from_str(line)?;
+            regressions.push(Regression {
+                seed: entry.seed,
+                message: entry.message,
+                scenario: entry.scenario.map(TimedFault::into_scenario),
+            });
+        }
+
+        Ok(RegressionCorpus {
+            test_name: test_name.into(),
+            path,
+            regressions,
+        })
+    }
+
+    /// The file backing this corpus.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Recorded failures, oldest first.
+    pub fn regressions(&self) -> &[Regression] {
+        &self.regressions
+    }
+
+    /// Recorded seeds, oldest first.
+    pub fn seeds(&self) -> Vec<u64> {
+        self.regressions.iter().map(|r| r.seed).collect()
+    }
+
+    /// The recorded failure for `seed`, if any.
+    pub fn get(&self, seed: u64) -> Option<&Regression> {
+        self.regressions.iter().find(|r| r.seed == seed)
+    }
+
+    /// Appends `regression` to the file, creating it if needed.
+    ///
+    /// Returns `false` without writing if the seed is already recorded.
+    pub fn record(&mut self, regression: Regression) -> Result<bool> {
+        if self.get(regression.seed).is_some() {
+            return Ok(false);
+        }
+
+        let mut line = serde_json::to_string(&Entry {
+            seed: regression.seed,
+            message: regression.message.clone(),
+            scenario: regression
+                .scenario
+                .as_deref()
+                .map(TimedFault::from_scenario),
+        })?;
+        line.push('\n');
+
+        if !self.path.exists() {
+            if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+            line.insert_str(
+                0,
+                &format!(
+                    "# Seeds that failed {}, replayed before new seeds.\n# Check this file in.\n",
+                    self.test_name
+                ),
+            );
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+
+        self.regressions.push(regression);
+        Ok(true)
+    }
+
+    /// Replays every recorded seed, then runs one new seed.
+    ///
+    /// The new seed comes from [`get_test_seed`]; when `TEST_SEED` is set,
+    /// only that seed runs. A new failing seed is recorded before the panic
+    /// is resumed.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the first panic raised by `test_fn`.
+    pub fn run<F>(&mut self, test_fn: F)
+    where
+        F: Fn(u64) + RefUnwindSafe,
+    {
+        self.run_cases(|seed, _| test_fn(seed), |_| None);
+    }
+
+    /// Like [`run`](Self::run) for tests driven by a fault scenario.
+    ///
+    /// New seeds run the scenario `generate` returns for them; recorded
+    /// seeds run their recorded scenario. When a new seed fails, its
+    /// scenario is shrunk with [`ScenarioShrinker`] and the result recorded.
+    pub fn run_scenarios<G, F>(&mut self, generate: G, test_fn: F)
+    where
+        G: Fn(u64) -> Scenario + RefUnwindSafe,
+        F: Fn(u64, &[(Duration, Fault)]) + RefUnwindSafe,
+    {
+        self.run_cases(
+            |seed, recorded| match recorded {
+                Some(scenario) => test_fn(seed, scenario),
+                None => test_fn(seed, &generate(seed)),
+            },
+            |seed| {
+                let scenario = generate(seed);
+                eprintln!("Shrinking {} faults...", scenario.len());
+                let outcome = ScenarioShrinker::new(panics(|candidate| test_fn(seed, candidate)))
+                    .shrink(scenario);
+                eprintln!(
+                    "Shrunk to {} faults in {} runs",
+                    outcome.scenario.len(),
+                    outcome.runs
+                );
+                Some(outcome.scenario)
+            },
+        );
+    }
+
+    fn run_cases<F, S>(&mut self, test_fn: F, shrink: S)
+    where
+        F: Fn(u64, Option<&Scenario>) + RefUnwindSafe,
+        S: FnOnce(u64) -> Option<Scenario>,
+    {
+        println!("=== {} ===", self.test_name);
+
+        if env_seed().is_none() && !self.regressions.is_empty() {
+            println!(
+                "Replaying {} recorded seeds from {}",
+                self.regressions.len(),
+                self.path.display()
+            );
+            for regression in &self.regressions {
+                println!("{}={} (recorded)", TEST_SEED_ENV, regression.seed);
+                let scenario = regression.scenario.as_ref();
+                if let Err(payload) = panic::catch_unwind(|| test_fn(regression.seed, scenario)) {
+                    self.report(regression.seed);
+                    panic::resume_unwind(payload);
+                }
+            }
+        }
+
+        let seed = get_test_seed();
+        println!("{}={}", TEST_SEED_ENV, seed);
+        let recorded = self.get(seed).and_then(|r| r.scenario.clone());
+        let result = panic::catch_unwind(|| test_fn(seed, recorded.as_ref()));
+
+        if let Err(payload) = result {
+            if self.get(seed).is_none() {
+                let regression = Regression {
+                    seed,
+                    message: panic_message(payload.as_ref()),
+                    scenario: shrink(seed),
+                };
+                match self.record(regression) {
+                    Ok(_) => eprintln!("Saved seed {} to {}", seed, self.path.display()),
+                    Err(error) => eprintln!(
+                        "Could not save seed {} to {}: {}",
+                        seed,
+                        self.path.display(),
+                        error
+                    ),
+                }
+            }
+            self.report(seed);
+            panic::resume_unwind(payload);
+        }
+    }
+
+    fn report(&self, seed: u64) {
+        eprintln!("\n=== TEST FAILED ===");
+        eprintln!("To reproduce:");
+        eprintln!("  {}", reproduction_command(seed, &self.test_name));
+    }
+}
+
+/// File name for `test_name`, with anything but ASCII alphanumerics, `-` and
+/// `_` replaced so module paths stay on one level.
+fn file_name(test_name: &str) -> String {
+    let stem: String = test_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.jsonl", stem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_name_flattens_module_paths() {
+        assert_eq!(file_name("test_echo"), "test_echo.jsonl");
+        assert_eq!(file_name("cluster::test_a b"), "cluster__test_a_b.jsonl");
+    }
+}
@@ -0,0 +1,810 @@
+//! Declarative simulation scenarios and the runner behind `dst-run`.
+//!
+//! A [`ScenarioSpec`] describes one simulation in TOML, YAML or JSON: the hosts
+//! and clients to start, how long to simulate, the message latency, a fault
+//! schedule or generator parameters, and the invariants to check. Behaviour
+//! stays in code: a [`Runner`] maps the factory names a spec refers to onto
+//! host closures and its invariant names onto checks, so new scenarios need
+//! no new test code.
+//!
+//! ```toml
+//! name = "leader isolated"
+//! duration_ms = 60_000
+//! invariants = ["one leader per term", "linearizable"]
+//!
+//! [latency]
+//! min_ms = 1
+//! max_ms = 10
+//!
+//! [[hosts]]
+//! name = "node-a"
+//! factory = "kv-server"
+//!
+//! [[clients]]
+//! name = "client-1"
+//! factory = "kv-client"
+//! args = { ops = 40 }
+//!
+//! [[faults]]
+//! at_ms = 500
+//! kind = "partition"
+//! node_a = "node-a"
+//! node_b = "node-b"
+//!
+//! [generate]
+//! faults = 6
+//! weights = { crash = 0, restart = 0, duplicate = 0 }
+//! ```
+//!
+//! A project's `dst-run` binary registers its factories and invariants and
+//! hands over to [`Runner::main`]:
+//!
+//! ```bash
+//! dst-run scenarios/*.toml --count 20
+//! dst-run scenarios/leader-isolated.toml --seed 12345
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use thiserror::Error;
+use turmoil::Builder;
+
+use crate::clock::HostClock;
+use crate::driver::{AppliedFault, FaultDriver};
+use crate::entropy::{Entropy, HostEntropy};
+use crate::error::Result;
+use crate::fault::{Fault, FaultScenarioGenerator, FaultWeights, Scenario, TimedFault};
+use crate::fs::{HostFs, SimFs};
+use crate::invariant::{InvariantMonitor, InvariantViolation, MonitorError};
+use crate::network::NetworkFaults;
+use crate::seed::{env_seed, get_test_seed, TEST_SEED_ENV};
+
+const USAGE: &str = "usage: dst-run <scenario.toml|yaml|json>... [--seed N] [--count N] [--list]";
+
+type HostFuture = Pin<Box<dyn Future<Output = turmoil::Result>>>;
+type Factory<S> = Rc<dyn Fn(&HostContext<S>) -> HostFuture>;
+type Check<S> = Rc<dyn Fn(&S) -> Result<(), String>>;
+
+/// A simulation described as data.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioSpec {
+    /// Name shown in reports.
+    pub name: String,
+    /// What the scenario exercises.
+    #[serde(default)]
+    pub description: String,
+    /// Seed to run when none is given on the command line or in
+    /// `TEST_SEED`.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Simulated run time. Host-only scenarios run for exactly this long;
+    /// scenarios with clients fail if the clients are still running at the
+    /// end.
+    pub duration_ms: u64,
+    /// Message latency between every pair of nodes.
+    #[serde(default)]
+    pub latency: Option<LatencySpec>,
+    /// Long-running hosts, which faults may crash and restart.
+    #[serde(default)]
+    pub hosts: Vec<HostSpec>,
+    /// Clients; the run ends once they have all returned.
+    #[serde(default)]
+    pub clients: Vec<HostSpec>,
+    /// Faults at fixed times, in the format of
+    /// [`save_scenario`](crate::save_scenario).
+    #[serde(default, deserialize_with = "TimedFault::deserialize_scenario")]
+    pub faults: Scenario,
+    /// Seeded faults, added to `faults`.
+    #[serde(default)]
+    pub generate: Option<GenerateSpec>,
+    /// Names of the invariants and final checks to evaluate.
+    #[serde(default)]
+    pub invariants: Vec<String>,
+}
+
+/// Uniform message latency range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LatencySpec {
+    /// Minimum latency in milliseconds.
+    pub min_ms: u64,
+    /// Maximum latency in milliseconds.
+    pub max_ms: u64,
+}
+
+/// A host or client of a scenario.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostSpec {
+    /// Host name, which is also its network address.
+    pub name: String,
+    /// Name of the factory registered with [`Runner::factory`].
+    pub factory: String,
+    /// Free-form arguments for the factory, read with [`HostContext::arg`].
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// Parameters for [`FaultScenarioGenerator`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenerateSpec {
+    /// Number of faults to generate.
+    pub faults: usize,
+    /// Nodes to target; empty means every host. Clients cannot be crashed,
+    /// so only list them when crashes and restarts are weighted zero.
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    /// Relative weights of the fault kinds; missing kinds weigh 1.
+    #[serde(default)]
+    pub weights: FaultWeights,
+}
+
+impl ScenarioSpec {
+    /// Parses a TOML scenario.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    /// Parses a YAML scenario.
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        Ok(serde_yaml_ng::from_str(yaml)?)
+    }
+
+    /// Parses a JSON scenario.
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Reads a scenario file: JSON if the extension is `.json`, YAML if it
+    /// is `.yaml` or `.yml`, TOML otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            Some("yaml" | "yml") => Self::from_yaml(&contents),
+            _ => Self::from_toml(&contents),
+        }
+    }
+
+    /// Simulated run time.
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+
+    /// Names of every host and client, hosts first.
+    pub fn nodes(&self) -> Vec<String> {
+        self.hosts
+            .iter()
+            .chain(&self.clients)
+            .map(|host| host.name.clone())
+            .collect()
+    }
+
+    /// The fault schedule for `seed`: the fixed faults merged with the
+    /// generated ones, in time order. Generated faults due after the run
+    /// ends are dropped.
+    pub fn schedule(&self, seed: u64) -> Scenario {
+        let mut schedule = self.faults.clone();
+        if let Some(generate) = &self.generate {
+            let nodes = if generate.nodes.is_empty() {
+                self.hosts.iter().map(|host| host.name.clone()).collect()
+            } else {
+                generate.nodes.clone()
+            };
+            let duration = self.duration();
+            schedule.extend(
+                FaultScenarioGenerator::new(seed, nodes)
+                    .with_weights(generate.weights)
+                    .generate_scenario(generate.faults)
+                    .into_iter()
+                    .filter(|(at, _)| *at < duration),
+            );
+        }
+        schedule.sort_by_key(|(at, _)| *at);
+        schedule
+    }
+
+    /// Checks the spec for mistakes that do not depend on registered
+    /// factories: duplicate or unknown node names, faults after the end of
+    /// the run, and crashes aimed at clients.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen = Vec::new();
+        for node in self.nodes() {
+            if seen.contains(&node) {
+                return Err(format!("node {} is declared twice", node));
+            }
+            seen.push(node);
+        }
+        if self.hosts.is_empty() && self.clients.is_empty() {
+            return Err("no hosts or clients".to_string());
+        }
+        if let Some(latency) = self.latency {
+            if latency.min_ms > latency.max_ms {
+                return Err(format!(
+                    "latency min_ms {} exceeds max_ms {}",
+                    latency.min_ms, latency.max_ms
+                ));
+            }
+        }
+
+        let is_host = |node: &str| self.hosts.iter().any(|host| host.name == node);
+        let check_fault = |fault: &Fault| match fault {
+            Fault::Crash { node } | Fault::Restart { node } if !is_host(node) => {
+                Err(format!("{:?} targets {}, which is not a host", fault, node))
+            }
+            _ => match fault
+                .nodes()
+                .into_iter()
+                .find(|node| !seen.iter().any(|s| s == node))
+            {
+                Some(node) => Err(format!("{:?} targets unknown node {}", fault, node)),
+                None => Ok(()),
+            },
+        };
+        for (at, fault) in &self.faults {
+            if *at >= self.duration() {
+                return Err(format!(
+                    "{:?} at {:?} is after the run ends at {:?}",
+                    fault,
+                    at,
+                    self.duration()
+                ));
+            }
+            check_fault(fault)?;
+        }
+
+        if let Some(generate) = &self.generate {
+            if generate.faults > 0 && generate.weights.total() == 0 {
+                return Err("every generated fault kind is weighted zero".to_string());
+            }
+            let crashes = generate.weights.crash + generate.weights.restart > 0;
+            for node in &generate.nodes {
+                if !seen.contains(node) {
+                    return Err(format!("generate targets unknown node {}", node));
+                }
+                if crashes && !is_host(node) {
+                    return Err(format!(
+                        "generate may crash {}, which is not a host; weight crash and restart 0",
+                        node
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a factory gets to build one host or client.
+///
+/// Host factories are called again on every restart with the same context,
+/// so the disk in `fs` outlives the process as a real disk would.
+pub struct HostContext<S> {
+    /// This node's name.
+    pub name: String,
+    /// Seed of the run.
+    pub seed: u64,
+    /// Names of every host in the scenario, e.g. a server's peers.
+    pub hosts: Vec<String>,
+    /// The `args` table from the scenario.
+    pub args: serde_json::Value,
+    /// This node's random streams.
+    pub entropy: HostEntropy,
+    /// This node's simulated disk.
+    pub fs: HostFs,
+    /// This node's clocks, skewed by clock faults.
+    pub clock: HostClock,
+    /// Faults for [`FaultyUdpSocket`](crate::FaultyUdpSocket)s.
+    pub network: NetworkFaults,
+    /// State shared with the invariants.
+    pub state: Arc<Mutex<S>>,
+}
+
+impl<S> HostContext<S> {
+    /// Reads `args.key`, or `None` when it is absent.
+    pub fn arg<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        match self.args.get(key) {
+            Some(value) => Ok(Some(T::deserialize(value)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<S> fmt::Debug for HostContext<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostContext")
+            .field("name", &self.name)
+            .field("seed", &self.seed)
+            .field("hosts", &self.hosts)
+            .field("args", &self.args)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A successful run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunReport {
+    /// Seed of the run.
+    pub seed: u64,
+    /// Simulation steps taken.
+    pub steps: u64,
+    /// Simulated time at the end of the run.
+    pub elapsed: Duration,
+    /// Faults applied, in the order they fired.
+    pub applied: Vec<AppliedFault>,
+}
+
+/// Why a scenario run failed.
+#[derive(Debug, Error)]
+pub enum RunError {
+    /// The spec is invalid or names something that is not registered.
+    #[error("invalid scenario: {0}")]
+    Spec(String),
+
+    /// An invariant did not hold after a step.
+    #[error(transparent)]
+    Violation(#[from] InvariantViolation),
+
+    /// A final check did not hold once the run ended.
+    #[error("check `{name}` failed ({env}={seed}): {message}", env = TEST_SEED_ENV)]
+    Check {
+        /// Name of the check.
+        name: String,
+        /// The message the check returned.
+        message: String,
+        /// Seed of the failing run.
+        seed: u64,
+    },
+
+    /// The simulation itself failed, e.g. a host returned an error.
+    #[error("simulation failed: {0}")]
+    Simulation(Box<dyn StdError>),
+}
+
+impl From<MonitorError> for RunError {
+    fn from(error: MonitorError) -> Self {
+        match error {
+            MonitorError::Violation(violation) => RunError::Violation(violation),
+            MonitorError::Simulation(error) => RunError::Simulation(error),
+        }
+    }
+}
+
+/// Runs [`ScenarioSpec`]s against registered factories and invariants.
+///
+/// `S` is the state hosts share with the invariants; each run starts from
+/// `S::default()`.
+///
+/// # Examples
+///
+/// ```
+/// use dst::runner::{Runner, ScenarioSpec};
+///
+/// let runner = Runner::<u32>::new()
+///     .factory("counter", |ctx| {
+///         let state = ctx.state.clone();
+///         let count: u32 = ctx.arg("count").unwrap().unwrap_or(1);
+///         async move {
+///             for _ in 0..count {
+///                 tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+///                 *state.lock().unwrap() += 1;
+///             }
+///             Ok(())
+///         }
+///     })
+///     .invariant("at most 3", |n| if *n <= 3 { Ok(()) } else { Err(format!("{}", n)) })
+///     .check("counted", |n| if *n == 3 { Ok(()) } else { Err(format!("{}", n)) });
+///
+/// let spec = ScenarioSpec::from_toml(r#"
+///     name = "count to three"
+///     duration_ms = 1_000
+///     invariants = ["at most 3", "counted"]
+///
+///     [[clients]]
+///     name = "client"
+///     factory = "counter"
+///     args = { count = 3 }
+/// "#).unwrap();
+/// let report = runner.run(&spec, 7).unwrap();
+/// assert!(report.steps > 0);
+/// ```
+pub struct Runner<S> {
+    factories: BTreeMap<String, Factory<S>>,
+    invariants: BTreeMap<String, Check<S>>,
+    checks: BTreeMap<String, Check<S>>,
+    /// Factories whose hosts receive through a `FaultyUdpSocket`.
+    network_faults: BTreeSet<String>,
+}
+
+impl<S> fmt::Debug for Runner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Runner")
+            .field("factories", &self.factories.keys().collect::<Vec<_>>())
+            .field("invariants", &self.invariants.keys().collect::<Vec<_>>())
+            .field("checks", &self.checks.keys().collect::<Vec<_>>())
+            .field("network_faults", &self.network_faults)
+            .finish()
+    }
+}
+
+impl<S: Default + 'static> Default for Runner<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Default + 'static> Runner<S> {
+    /// Creates a runner with nothing registered.
+    pub fn new() -> Self {
+        Runner {
+            factories: BTreeMap::new(),
+            invariants: BTreeMap::new(),
+            checks: BTreeMap::new(),
+            network_faults: BTreeSet::new(),
+        }
+    }
+
+    /// Registers a factory that scenarios refer to by `name` from both
+    /// `hosts` and `clients`.
+    pub fn factory<F, Fut>(mut self, name: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn(&HostContext<S>) -> Fut + 'static,
+        Fut: Future<Output = turmoil::Result> + 'static,
+    {
+        let factory: Factory<S> = Rc::new(move |ctx| Box::pin(factory(ctx)));
+        self.factories.insert(name.into(), factory);
+        self
+    }
+
+    /// Declares that nodes built by `factory` receive through a
+    /// [`FaultyUdpSocket`](crate::FaultyUdpSocket) made from
+    /// [`HostContext::network`].
+    ///
+    /// Turmoil cannot duplicate datagrams or cap bandwidth by itself, so
+    /// [`validate`](Self::validate) rejects [`Fault::Duplicate`] and
+    /// [`Fault::Bandwidth`] aimed at any other node instead of letting them
+    /// pass as no-ops.
+    pub fn observes_network_faults(mut self, factory: impl Into<String>) -> Self {
+        self.network_faults.insert(factory.into());
+        self
+    }
+
+    /// Registers an invariant checked after every simulation step.
+    pub fn invariant<F>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn(&S) -> Result<(), String> + 'static,
+    {
+        self.invariants.insert(name.into(), Rc::new(check));
+        self
+    }
+
+    /// Registers a check evaluated once, after the run ends, e.g. a
+    /// linearizability check over a recorded history.
+    pub fn check<F>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn(&S) -> Result<(), String> + 'static,
+    {
+        self.checks.insert(name.into(), Rc::new(check));
+        self
+    }
+
+    /// Checks that `spec` is valid and only names registered factories,
+    /// invariants and checks, and that duplication and bandwidth faults only
+    /// target nodes that [observe them](Self::observes_network_faults).
+    pub fn validate(&self, spec: &ScenarioSpec) -> Result<(), RunError> {
+        spec.validate().map_err(RunError::Spec)?;
+        for host in spec.hosts.iter().chain(&spec.clients) {
+            if !self.factories.contains_key(&host.factory) {
+                return Err(RunError::Spec(format!(
+                    "{} uses unknown factory {}",
+                    host.name, host.factory
+                )));
+            }
+        }
+
+        // The factory of a node that would miss datagram-level faults.
+        let blind = |node: &str| {
+            spec.hosts
+                .iter()
+                .chain(&spec.clients)
+                .find(|host| host.name == node)
+                .map(|host| host.factory.as_str())
+                .filter(|factory| !self.network_faults.contains(*factory))
+        };
+        for (_, fault) in &spec.faults {
+            if !matches!(fault, Fault::Duplicate { .. } | Fault::Bandwidth { .. }) {
+                continue;
+            }
+            for node in fault.nodes() {
+                if let Some(factory) = blind(node) {
+                    return Err(RunError::Spec(format!(
+                        "{:?} targets {}, whose factory {} does not receive through a \
+                         FaultyUdpSocket",
+                        fault, node, factory
+                    )));
+                }
+            }
+        }
+        if let Some(generate) = spec.generate.as_ref().filter(|g| g.weights.duplicate > 0) {
+            let targets = if generate.nodes.is_empty() {
+                spec.hosts.iter().map(|host| host.name.clone()).collect()
+            } else {
+                generate.nodes.clone()
+            };
+            for node in &targets {
+                if let Some(factory) = blind(node) {
+                    return Err(RunError::Spec(format!(
+                        "generate may duplicate datagrams to {}, whose factory {} does not \
+                         receive through a FaultyUdpSocket; weight duplicate 0",
+                        node, factory
+                    )));
+                }
+            }
+        }
+        for name in &spec.invariants {
+            if !self.invariants.contains_key(name) && !self.checks.contains_key(name) {
+                return Err(RunError::Spec(format!("unknown invariant {}", name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `spec` once with `seed`.
+    ///
+    /// Invariants are checked after every step; final checks once every
+    /// client has returned and every fault has fired.
+    pub fn run(&self, spec: &ScenarioSpec, seed: u64) -> Result<RunReport, RunError> {
+        self.validate(spec)?;
+
+        let mut builder = Builder::new();
+        builder.rng_seed(seed).simulation_duration(spec.duration());
+        if let Some(latency) = spec.latency {
+            builder
+                .min_message_latency(Duration::from_millis(latency.min_ms))
+                .max_message_latency(Duration::from_millis(latency.max_ms));
+        }
+        let mut sim = builder.build();
+
+        let fs = SimFs::new(seed);
+        let entropy = Entropy::new(seed);
+        let state = Arc::new(Mutex::new(S::default()));
+        let mut driver = FaultDriver::new(spec.nodes(), spec.schedule(seed))
+            .with_seed(seed)
+            .with_fs(fs.clone());
+        let hosts: Vec<String> = spec.hosts.iter().map(|host| host.name.clone()).collect();
+        let context = |host: &HostSpec| HostContext {
+            name: host.name.clone(),
+            seed,
+            hosts: hosts.clone(),
+            args: host.args.clone(),
+            entropy: entropy.host(host.name.as_str()),
+            fs: fs.host(host.name.as_str()),
+            clock: driver.clocks().host(host.name.as_str()),
+            network: driver.network().clone(),
+            state: state.clone(),
+        };
+
+        for host in &spec.hosts {
+            let factory = self.factories[&host.factory].clone();
+            let ctx = context(host);
+            sim.host(host.name.as_str(), move || factory(&ctx));
+        }
+        for client in &spec.clients {
+            let factory = &self.factories[&client.factory];
+            sim.client(client.name.as_str(), factory(&context(client)));
+        }
+
+        let mut monitor = InvariantMonitor::new(seed, state.clone());
+        for name in &spec.invariants {
+            if let Some(check) = self.invariants.get(name) {
+                let check = check.clone();
+                monitor = monitor.invariant(name.clone(), move |state: &S| check(state));
+            }
+        }
+        if spec.clients.is_empty() {
+            while sim.elapsed() < spec.duration() {
+                monitor.step_with(&mut driver, &mut sim)?;
+            }
+        } else {
+            monitor.run_with(&mut driver, &mut sim)?;
+        }
+
+        let state = state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for name in &spec.invariants {
+            if let Some(check) = self.checks.get(name) {
+                check(&state).map_err(|message| RunError::Check {
+                    name: name.clone(),
+                    message,
+                    seed,
+                })?;
+            }
+        }
+
+        Ok(RunReport {
+            seed,
+            steps: monitor.steps(),
+            elapsed: sim.elapsed(),
+            applied: driver.applied().to_vec(),
+        })
+    }
+
+    /// Command-line entry point for a `dst-run` binary.
+    ///
+    /// ```text
+    /// dst-run <scenario.toml|yaml|json>... [--seed N] [--count N] [--list]
+    /// ```
+    ///
+    /// Each scenario runs with `--seed`, else `TEST_SEED`, else the seed in
+    /// the file; without any of them it runs `--count` random seeds
+    /// (default 1). `--list` prints the registered names. Exits with 0 when
+    /// every run passes, 1 when any fails and 2 on usage errors or
+    /// unreadable scenarios.
+    pub fn main(&self) -> ExitCode {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let options = match parse_args(&args) {
+            Ok(options) => options,
+            Err(message) => {
+                eprintln!("{}\n{}", message, USAGE);
+                return ExitCode::from(2);
+            }
+        };
+
+        if options.list {
+            self.list();
+            if options.paths.is_empty() {
+                return ExitCode::SUCCESS;
+            }
+        }
+
+        let mut specs = Vec::new();
+        for path in &options.paths {
+            match ScenarioSpec::load(path) {
+                Ok(spec) => specs.push((path, spec)),
+                Err(error) => {
+                    eprintln!("{}: {}", path, error);
+                    return ExitCode::from(2);
+                }
+            }
+        }
+
+        let mut failed = 0;
+        let mut runs = 0;
+        for (path, spec) in &specs {
+            let seeds = match options.seed.or_else(env_seed).or(spec.seed) {
+                Some(seed) => vec![seed],
+                None => (0..options.count).map(|_| get_test_seed()).collect(),
+            };
+            for seed in seeds {
+                runs += 1;
+                match self.run(spec, seed) {
+                    Ok(report) => println!(
+                        "ok   {} seed {}: {} steps, {} faults, {:?} simulated",
+                        spec.name,
+                        seed,
+                        report.steps,
+                        report.applied.len(),
+                        report.elapsed
+                    ),
+                    Err(RunError::Spec(message)) => {
+                        eprintln!("{}: invalid scenario: {}", path, message);
+                        return ExitCode::from(2);
+                    }
+                    Err(error) => {
+                        failed += 1;
+                        println!("FAIL {} seed {}: {}", spec.name, seed, error);
+                        println!("  To reproduce: dst-run {} --seed {}", path, seed);
+                    }
+                }
+            }
+        }
+
+        println!("{} of {} runs passed", runs - failed, runs);
+        if failed == 0 {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::from(1)
+        }
+    }
+
+    fn list(&self) {
+        let print = |heading: &str, names: Vec<&String>| {
+            println!("{}:", heading);
+            for name in names {
+                println!("  {}", name);
+            }
+        };
+        print("factories", self.factories.keys().collect());
+        print("invariants", self.invariants.keys().collect());
+        print("checks", self.checks.keys().collect());
+    }
+}
+
+/// Parsed `dst-run` arguments.
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    paths: Vec<String>,
+    seed: Option<u64>,
+    count: usize,
+    list: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        paths: Vec::new(),
+        seed: None,
+        count: 1,
+        list: false,
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = iter.next().ok_or("--seed needs a value")?;
+                let seed = value
+                    .parse()
+                    .map_err(|_| format!("invalid --seed value: {}", value))?;
+                options.seed = Some(seed);
+            }
+            "--count" => {
+                let value = iter.next().ok_or("--count needs a value")?;
+                options.count = value
+                    .parse()
+                    .map_err(|_| format!("invalid --count value: {}", value))?;
+            }
+            "--list" => options.list = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path => options.paths.push(path.to_string()),
+        }
+    }
+    if options.paths.is_empty() && !options.list {
+        return Err("expected at least one scenario file".to_string());
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_args_reads_paths_and_flags() {
+        let options = parse_args(&args(&["a.toml", "--seed", "9", "b.toml", "--count", "3"]));
+        assert_eq!(
+            options,
+            Ok(Options {
+                paths: vec!["a.toml".into(), "b.toml".into()],
+                seed: Some(9),
+                count: 3,
+                list: false,
+            })
+        );
+        assert!(parse_args(&args(&["--list"])).unwrap().list);
+    }
+
+    #[test]
+    fn parse_args_rejects_bad_input() {
+        assert!(parse_args(&[]).is_err());
+        assert!(parse_args(&args(&["a.toml", "--seed"])).is_err());
+        assert!(parse_args(&args(&["a.toml", "--seed", "x"])).is_err());
+        assert!(parse_args(&args(&["a.toml", "--verbose"])).is_err());
+    }
+}
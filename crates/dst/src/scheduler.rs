@@ -0,0 +1,764 @@
+//! Seeded exploration of task interleavings on a single thread.
+//!
+//! Turmoil makes the network deterministic, but tasks spawned inside one host
+//! still interleave however tokio's scheduler happens to run them. A
+//! [`Scheduler`] runs an async test on its own single-threaded executor and
+//! decides which runnable task to poll next, shuttle-style:
+//!
+//! - [`Scheduler::random`] picks pseudo-randomly from a seed, so a failing
+//!   interleaving replays from that seed alone;
+//! - [`Scheduler::exhaustive`] enumerates every interleaving depth-first, up
+//!   to a number of schedules and optionally a number of preemptions;
+//! - [`Scheduler::replay`] follows a recorded [`Schedule`] step by step.
+//!
+//! Tasks switch only where a future returns `Pending`: a channel receive
+//! with nothing queued, a contended `tokio::sync::Mutex`, a [`JoinHandle`]
+//! of an unfinished task, or an explicit [`yield_now`]. Code under test
+//! spawns with [`spawn`] instead of `tokio::spawn` and must not use tokio
+//! timers or I/O, which need a tokio runtime. `tokio::sync` primitives work
+//! on any executor.
+//!
+//! A run fails when a task panics, when every unfinished task waits on
+//! something no other task will do (a deadlock), or when it takes more than
+//! [`Scheduler::max_steps`] polls (a livelock). The run ends as soon as the
+//! test future returns; tasks still running are dropped.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::cell::Cell;
+//! use std::rc::Rc;
+//!
+//! use dst::scheduler::{self, FailureKind, Scheduler};
+//!
+//! // Two tasks increment a counter with a yield between the load and the
+//! // store, so an interleaving loses an update.
+//! let failure = Scheduler::exhaustive()
+//!     .check(|| async {
+//!         let counter = Rc::new(Cell::new(0));
+//!         let handles: Vec<_> = (0..2)
+//!             .map(|_| {
+//!                 let counter = counter.clone();
+//!                 scheduler::spawn(async move {
+//!                     let value = counter.get();
+//!                     scheduler::yield_now().await;
+//!                     counter.set(value + 1);
+//!                 })
+//!             })
+//!             .collect();
+//!         for handle in handles {
+//!             handle.await;
+//!         }
+//!         assert_eq!(counter.get(), 2, "lost update");
+//!     })
+//!     .unwrap_err();
+//!
+//! assert!(matches!(failure.kind, FailureKind::Panic(ref message) if message.contains("lost update")));
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::future::Future;
+use std::num::ParseIntError;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::seed::env_seed;
+use crate::sweep::{install_quiet_panic_hook, run_quietly};
+
+/// Default limit on polls per schedule.
+const DEFAULT_MAX_STEPS: usize = 100_000;
+
+/// Default limit on schedules explored by [`Scheduler::exhaustive`].
+const DEFAULT_MAX_SCHEDULES: usize = 10_000;
+
+/// Id of the test future itself; spawned tasks count up from 1.
+const MAIN_TASK: usize = 0;
+
+type LocalFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Runs an async test under seeded or exhaustive task interleavings.
+///
+/// # Examples
+///
+/// ```
+/// use dst::scheduler::{self, Scheduler};
+///
+/// let report = Scheduler::random(7).iterations(50).assert(|| async {
+///     let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+///     for worker in 0..3 {
+///         let tx = tx.clone();
+///         scheduler::spawn(async move { tx.send(worker).await.unwrap() });
+///     }
+///     drop(tx);
+///     let mut sum = 0;
+///     while let Some(worker) = rx.recv().await {
+///         sum += worker;
+///     }
+///     assert_eq!(sum, 3);
+/// });
+/// assert_eq!(report.schedules, 50);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    mode: Mode,
+    preemption_bound: Option<usize>,
+    max_steps: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Mode {
+    Random { seed: u64, iterations: usize },
+    Exhaustive { max_schedules: usize },
+    Replay(Schedule),
+}
+
+impl Scheduler {
+    /// Runs one schedule whose choices are drawn from `seed`.
+    ///
+    /// If `TEST_SEED` is set it replaces `seed` and only that schedule runs,
+    /// so a failure reported with a seed reproduces from the environment.
+    pub fn random(seed: u64) -> Self {
+        Scheduler::with_mode(Mode::Random {
+            seed,
+            iterations: 1,
+        })
+    }
+
+    /// Enumerates every schedule depth-first, up to 10,000 of them.
+    ///
+    /// The test must be deterministic apart from scheduling, or the search
+    /// cannot replay the prefix it backtracks to.
+    pub fn exhaustive() -> Self {
+        Scheduler::with_mode(Mode::Exhaustive {
+            max_schedules: DEFAULT_MAX_SCHEDULES,
+        })
+    }
+
+    /// Follows `schedule`, usually taken from a [`ScheduleFailure`].
+    ///
+    /// The run fails with [`FailureKind::Diverged`] if the test reaches a
+    /// step where the recorded task is not runnable.
+    pub fn replay(schedule: Schedule) -> Self {
+        Scheduler::with_mode(Mode::Replay(schedule))
+    }
+
+    fn with_mode(mode: Mode) -> Self {
+        Scheduler {
+            mode,
+            preemption_bound: None,
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    /// Runs `iterations` random schedules with the seeds `seed`,
+    /// `seed + 1`, and so on. Has no effect on the other modes.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        if let Mode::Random {
+            iterations: current,
+            ..
+        } = &mut self.mode
+        {
+            *current = iterations.max(1);
+        }
+        self
+    }
+
+    /// Stops an exhaustive search after `max_schedules` schedules. Has no
+    /// effect on the other modes.
+    pub fn max_schedules(mut self, max_schedules: usize) -> Self {
+        if let Mode::Exhaustive {
+            max_schedules: current,
+        } = &mut self.mode
+        {
+            *current = max_schedules.max(1);
+        }
+        self
+    }
+
+    /// Switches away from a task that could keep running at most
+    /// `preemptions` times per schedule.
+    ///
+    /// Most concurrency bugs need only one or two preemptions, and bounding
+    /// them keeps an exhaustive search small. Switching after a task blocks
+    /// is not a preemption. Ignored by [`Scheduler::replay`].
+    pub fn preemption_bound(mut self, preemptions: usize) -> Self {
+        self.preemption_bound = Some(preemptions);
+        self
+    }
+
+    /// Fails a schedule that polls tasks more than `max_steps` times, which
+    /// catches tasks that yield to each other forever. Defaults to 100,000.
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// Runs `test` once per schedule and returns the first failure.
+    ///
+    /// `test` builds a fresh future for every schedule; it and every task
+    /// it spawns run on the calling thread, so none of them need be `Send`.
+    pub fn check<F, Fut>(&self, test: F) -> Result<ExploreReport, ScheduleFailure>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        install_quiet_panic_hook();
+        match &self.mode {
+            Mode::Random { seed, iterations } => {
+                let seeds: Vec<u64> = match env_seed() {
+                    Some(seed) => vec![seed],
+                    None => (0..*iterations as u64)
+                        .map(|i| seed.wrapping_add(i))
+                        .collect(),
+                };
+                for (index, &seed) in seeds.iter().enumerate() {
+                    let mut chooser = Chooser::Random(Box::new(StdRng::seed_from_u64(seed)));
+                    let (schedule, result) = self.run_once(&test, &mut chooser);
+                    if let Err(kind) = result {
+                        return Err(ScheduleFailure {
+                            kind,
+                            schedule,
+                            seed: Some(seed),
+                            schedules: index + 1,
+                        });
+                    }
+                }
+                Ok(ExploreReport {
+                    schedules: seeds.len(),
+                    complete: false,
+                })
+            }
+            Mode::Exhaustive { max_schedules } => {
+                let mut prefix = Vec::new();
+                for schedules in 1..=*max_schedules {
+                    let mut chooser = Chooser::Exhaustive {
+                        prefix: &prefix,
+                        trail: Vec::new(),
+                    };
+                    let (schedule, result) = self.run_once(&test, &mut chooser);
+                    if let Err(kind) = result {
+                        return Err(ScheduleFailure {
+                            kind,
+                            schedule,
+                            seed: None,
+                            schedules,
+                        });
+                    }
+                    let Chooser::Exhaustive { trail, .. } = chooser else {
+                        unreachable!("the chooser does not change kind");
+                    };
+                    match next_prefix(trail) {
+                        Some(next) => prefix = next,
+                        None => {
+                            return Ok(ExploreReport {
+                                schedules,
+                                complete: true,
+                            })
+                        }
+                    }
+                }
+                Ok(ExploreReport {
+                    schedules: *max_schedules,
+                    complete: false,
+                })
+            }
+            Mode::Replay(expected) => {
+                let mut chooser = Chooser::Replay(&expected.0);
+                let (schedule, result) = self.run_once(&test, &mut chooser);
+                match result {
+                    Ok(()) => Ok(ExploreReport {
+                        schedules: 1,
+                        complete: false,
+                    }),
+                    Err(kind) => Err(ScheduleFailure {
+                        kind,
+                        schedule,
+                        seed: None,
+                        schedules: 1,
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Like [`Scheduler::check`], but panics with the failure report.
+    pub fn assert<F, Fut>(&self, test: F) -> ExploreReport
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.check(test)
+            .unwrap_or_else(|failure| panic!("{}", failure))
+    }
+
+    /// Runs one schedule, returning the tasks it polled and how it ended.
+    fn run_once<F, Fut>(
+        &self,
+        test: &F,
+        chooser: &mut Chooser<'_>,
+    ) -> (Schedule, Result<(), FailureKind>)
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let mut schedule = Vec::new();
+        let mut result = Ok(());
+        let panicked = run_quietly(AssertUnwindSafe(|| {
+            result = self.execute(test, chooser, &mut schedule);
+        }));
+        if let Err(message) = panicked {
+            result = Err(FailureKind::Panic(message));
+        }
+        (Schedule(schedule), result)
+    }
+
+    fn execute<F, Fut>(
+        &self,
+        test: &F,
+        chooser: &mut Chooser<'_>,
+        schedule: &mut Vec<usize>,
+    ) -> Result<(), FailureKind>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let spawner = Rc::new(Spawner::default());
+        let _enter = Enter::new(spawner.clone());
+        let woken = Arc::new(Mutex::new(BTreeSet::from([MAIN_TASK])));
+        let mut tasks: BTreeMap<usize, LocalFuture> = BTreeMap::new();
+        tasks.insert(MAIN_TASK, Box::pin(test()));
+
+        let bound = match self.mode {
+            Mode::Replay(_) => None,
+            _ => self.preemption_bound,
+        };
+        let mut last = None;
+        let mut preemptions = 0;
+        for step in 0.. {
+            if !tasks.contains_key(&MAIN_TASK) {
+                return Ok(());
+            }
+            if step == self.max_steps {
+                return Err(FailureKind::StepLimit(self.max_steps));
+            }
+
+            let mut runnable: Vec<usize> = lock(&woken)
+                .iter()
+                .copied()
+                .filter(|id| tasks.contains_key(id))
+                .collect();
+            if runnable.is_empty() {
+                return Err(FailureKind::Deadlock {
+                    blocked: tasks.keys().copied().collect(),
+                });
+            }
+            let continuing = last.filter(|id| runnable.contains(id));
+            if let (Some(id), Some(bound)) = (continuing, bound) {
+                if preemptions >= bound {
+                    runnable = vec![id];
+                }
+            }
+            // Replays check every step, including those with one choice.
+            let id = match runnable[..] {
+                [only] if !matches!(chooser, Chooser::Replay(_)) => only,
+                _ => chooser
+                    .choose(step, &runnable)
+                    .ok_or(FailureKind::Diverged { step })?,
+            };
+            if continuing.is_some_and(|current| current != id) {
+                preemptions += 1;
+            }
+
+            schedule.push(id);
+            lock(&woken).remove(&id);
+            let waker = Waker::from(Arc::new(TaskWaker {
+                id,
+                woken: woken.clone(),
+            }));
+            let task = tasks.get_mut(&id).expect("runnable tasks exist");
+            if task
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_ready()
+            {
+                tasks.remove(&id);
+            }
+            for (spawned, future) in spawner.spawned.borrow_mut().drain(..) {
+                tasks.insert(spawned, future);
+                lock(&woken).insert(spawned);
+            }
+            last = Some(id);
+        }
+        unreachable!("the step loop only exits by returning")
+    }
+}
+
+/// Picks the next task at steps where more than one can run, and at every
+/// step of a replay.
+enum Chooser<'a> {
+    Random(Box<StdRng>),
+    /// Follows `prefix` (indices into the runnable tasks) and then always
+    /// picks the first task, recording `(index, runnable count)` per choice.
+    Exhaustive {
+        prefix: &'a [usize],
+        trail: Vec<(usize, usize)>,
+    },
+    Replay(&'a [usize]),
+}
+
+impl Chooser<'_> {
+    fn choose(&mut self, step: usize, runnable: &[usize]) -> Option<usize> {
+        match self {
+            Chooser::Random(rng) => Some(runnable[rng.random_range(0..runnable.len())]),
+            Chooser::Exhaustive { prefix, trail } => {
+                let index = prefix
+                    .get(trail.len())
+                    .copied()
+                    .unwrap_or(0)
+                    .min(runnable.len() - 1);
+                trail.push((index, runnable.len()));
+                Some(runnable[index])
+            }
+            Chooser::Replay(schedule) => schedule
+                .get(step)
+                .copied()
+                .filter(|id| runnable.contains(id)),
+        }
+    }
+}
+
+/// The choice prefix of the next schedule in depth-first order, or `None`
+/// once every choice has been tried.
+fn next_prefix(mut trail: Vec<(usize, usize)>) -> Option<Vec<usize>> {
+    while let Some((index, count)) = trail.pop() {
+        if index + 1 < count {
+            trail.push((index + 1, count));
+            return Some(trail.into_iter().map(|(index, _)| index).collect());
+        }
+    }
+    None
+}
+
+fn lock(woken: &Mutex<BTreeSet<usize>>) -> std::sync::MutexGuard<'_, BTreeSet<usize>> {
+    woken
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Marks its task runnable. Wakers must be `Send`, so the runnable set is
+/// behind a mutex even though only one thread ever touches it.
+struct TaskWaker {
+    id: usize,
+    woken: Arc<Mutex<BTreeSet<usize>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        lock(&self.woken).insert(self.id);
+    }
+}
+
+/// Tasks spawned during a poll, collected by the executor afterwards.
+struct Spawner {
+    next_id: Cell<usize>,
+    spawned: RefCell<Vec<(usize, LocalFuture)>>,
+}
+
+impl Default for Spawner {
+    fn default() -> Self {
+        Spawner {
+            next_id: Cell::new(MAIN_TASK + 1),
+            spawned: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<Spawner>>> = const { RefCell::new(None) };
+}
+
+/// Makes [`spawn`] target one run's executor until dropped.
+struct Enter;
+
+impl Enter {
+    fn new(spawner: Rc<Spawner>) -> Self {
+        CURRENT.with(|current| {
+            let previous = current.borrow_mut().replace(spawner);
+            assert!(previous.is_none(), "schedulers cannot be nested");
+        });
+        Enter
+    }
+}
+
+impl Drop for Enter {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.borrow_mut().take());
+    }
+}
+
+/// Spawns a task on the scheduler running the current test.
+///
+/// The task may switch with others wherever it awaits a pending future, and
+/// is dropped unfinished if the test returns first. Dropping the
+/// [`JoinHandle`] detaches the task.
+///
+/// # Panics
+///
+/// Panics if called outside a test run by a [`Scheduler`].
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+{
+    let state = Rc::new(RefCell::new(JoinState {
+        output: None,
+        waiter: None,
+    }));
+    let task_state = state.clone();
+    let task = async move {
+        let output = future.await;
+        let mut state = task_state.borrow_mut();
+        state.output = Some(output);
+        if let Some(waiter) = state.waiter.take() {
+            waiter.wake();
+        }
+    };
+
+    CURRENT.with(|current| {
+        let current = current.borrow();
+        let spawner = current
+            .as_ref()
+            .expect("dst::scheduler::spawn called outside a Scheduler run");
+        let id = spawner.next_id.get();
+        spawner.next_id.set(id + 1);
+        spawner.spawned.borrow_mut().push((id, Box::pin(task)));
+        JoinHandle { id, state }
+    })
+}
+
+/// Returns `Pending` once, letting the scheduler switch to another task.
+///
+/// Put it between steps that another task could interleave with, such as a
+/// read and the write that depends on it.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+/// Future returned by [`yield_now`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless awaited"]
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+struct JoinState<T> {
+    output: Option<T>,
+    waiter: Option<Waker>,
+}
+
+/// Resolves to the output of a task started with [`spawn`].
+pub struct JoinHandle<T> {
+    id: usize,
+    state: Rc<RefCell<JoinState<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// The task's id, as it appears in a [`Schedule`].
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinHandle").field("id", &self.id).finish()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.borrow_mut();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waiter = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The task polled at each step of a run, written as comma-separated ids.
+///
+/// Task 0 is the test future; spawned tasks are numbered from 1 in spawn
+/// order.
+///
+/// ```
+/// use dst::scheduler::Schedule;
+///
+/// let schedule: Schedule = "0,1,2,1,0".parse().unwrap();
+/// assert_eq!(schedule.steps(), [0, 1, 2, 1, 0]);
+/// assert_eq!(schedule.to_string(), "0,1,2,1,0");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Schedule(Vec<usize>);
+
+impl Schedule {
+    /// Task ids in the order they were polled.
+    pub fn steps(&self) -> &[usize] {
+        &self.0
+    }
+}
+
+impl From<Vec<usize>> for Schedule {
+    fn from(steps: Vec<usize>) -> Self {
+        Schedule(steps)
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, id) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", id)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Ok(Schedule::default());
+        }
+        s.split(',')
+            .map(|id| id.trim().parse())
+            .collect::<Result<_, _>>()
+            .map(Schedule)
+    }
+}
+
+/// Outcome of a [`Scheduler`] run in which every schedule passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExploreReport {
+    /// Number of schedules run.
+    pub schedules: usize,
+    /// `true` if an exhaustive search tried every schedule within the
+    /// preemption bound before reaching its limit.
+    pub complete: bool,
+}
+
+/// Why a schedule failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The test or a task panicked with this message.
+    Panic(String),
+    /// The test had not finished and no task was runnable.
+    Deadlock {
+        /// Ids of the unfinished tasks, including 0 for the test itself.
+        blocked: Vec<usize>,
+    },
+    /// The run took more polls than the limit.
+    StepLimit(usize),
+    /// A replayed schedule named a task that could not run at this step.
+    Diverged {
+        /// Index of the step in the schedule.
+        step: usize,
+    },
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureKind::Panic(message) => write!(f, "panicked: {}", message),
+            FailureKind::Deadlock { blocked } => {
+                write!(f, "deadlock: tasks {:?} are all waiting", blocked)
+            }
+            FailureKind::StepLimit(steps) => {
+                write!(f, "no progress: still running after {} steps", steps)
+            }
+            FailureKind::Diverged { step } => write!(
+                f,
+                "the replayed schedule diverged at step {}; the test is nondeterministic or changed",
+                step
+            ),
+        }
+    }
+}
+
+/// A schedule under which the test failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleFailure {
+    /// How the schedule failed.
+    pub kind: FailureKind,
+    /// The tasks polled up to the failure; replay with
+    /// [`Scheduler::replay`].
+    pub schedule: Schedule,
+    /// Seed of the failing schedule, for [`Scheduler::random`].
+    pub seed: Option<u64>,
+    /// Number of schedules run, including the failing one.
+    pub schedules: usize,
+}
+
+impl fmt::Display for ScheduleFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "schedule {} failed: {}", self.schedules, self.kind)?;
+        writeln!(f, "schedule: {}", self.schedule)?;
+        writeln!(f, "To reproduce:")?;
+        if let Some(seed) = self.seed {
+            writeln!(f, "  TEST_SEED={} (or Scheduler::random({}))", seed, seed)?;
+        }
+        write!(
+            f,
+            "  Scheduler::replay(\"{}\".parse().unwrap())",
+            self.schedule
+        )
+    }
+}
+
+impl std::error::Error for ScheduleFailure {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_prefix_backtracks_depth_first() {
+        assert_eq!(next_prefix(vec![(0, 2), (0, 3)]), Some(vec![0, 1]));
+        assert_eq!(next_prefix(vec![(0, 2), (2, 3)]), Some(vec![1]));
+        assert_eq!(next_prefix(vec![(1, 2), (2, 3)]), None);
+        assert_eq!(next_prefix(Vec::new()), None);
+    }
+}
@@ -0,0 +1,154 @@
+//! Test seed management.
+//!
+//! Every simulation run is driven by a single `u64` seed. The seed is read
+//! from the `TEST_SEED` environment variable when present so that a failing
+//! run can be reproduced exactly, and is otherwise drawn at random. Failing
+//! seeds are kept in a [`RegressionCorpus`] and replayed on later runs.
+
+use std::panic::RefUnwindSafe;
+use std::time::Duration;
+
+use crate::fault::{Fault, Scenario};
+use crate::regression::RegressionCorpus;
+
+/// Environment variable used to pin the seed of a simulation test.
+pub const TEST_SEED_ENV: &str = "TEST_SEED";
+
+/// Returns the test seed from `TEST_SEED`, or a random one if unset.
+///
+/// A `TEST_SEED` value that does not parse as a `u64` is ignored rather than
+/// treated as an error, matching the behaviour of the original example.
+// The master seed is drawn once, outside any simulation.
+#[dst_macros::allow(rand)]
+pub fn get_test_seed() -> u64 {
+    env_seed().unwrap_or_else(rand::random)
+}
+
+/// Runs `test_fn` with a managed seed and prints reproduction instructions on
+/// failure.
+///
+/// Seeds that failed before are replayed first from the test's
+/// [`RegressionCorpus`], then one new seed runs. Each seed is printed before
+/// it runs. If `test_fn` panics, a new seed is saved to the corpus, a
+/// `TEST_SEED=... cargo test ...` line is written to stderr and the panic is
+/// resumed so the surrounding `#[test]` still fails. With `TEST_SEED` set,
+/// only that seed runs.
+///
+/// `test_fn` runs once per recorded seed, so it is `Fn` rather than
+/// `FnOnce`: clone captured values instead of moving them out.
+///
+/// # Examples
+///
+/// ```
+/// dst::run_simulation_test("test_my_feature", |seed| {
+///     let mut sim = dst::turmoil::Builder::new().rng_seed(seed).build();
+///     sim.client("client", async { Ok(()) });
+///     sim.run().unwrap();
+/// });
+/// ```
+///
+/// # Panics
+///
+/// Panics if the test's regression file exists but cannot be read.
+pub fn run_simulation_test<F>(test_name: &str, test_fn: F)
+where
+    F: Fn(u64) + RefUnwindSafe,
+{
+    open_corpus(test_name).run(test_fn);
+}
+
+/// Runs a test driven by a seeded fault scenario, recording shrunk failures.
+///
+/// Works like [`run_simulation_test`], except that `test_fn` receives the
+/// scenario to apply: recorded seeds get their recorded scenario and new
+/// seeds get `generate(seed)`. When a new seed fails, its scenario is shrunk
+/// with [`ScenarioShrinker`](crate::ScenarioShrinker) and saved with the
+/// seed, so later runs replay only the faults that matter.
+///
+/// # Examples
+///
+/// ```
+/// use dst::{FaultDriver, FaultScenarioGenerator, FaultWeights};
+///
+/// let nodes = || vec!["a".to_string(), "b".to_string()];
+/// dst::run_scenario_test(
+///     "test_my_cluster",
+///     |seed| {
+///         FaultScenarioGenerator::new(seed, nodes())
+///             .with_weights(FaultWeights::NETWORK)
+///             .generate_scenario(3)
+///     },
+///     |seed, scenario| {
+///         let mut sim = dst::turmoil::Builder::new().rng_seed(seed).build();
+///         for node in nodes() {
+///             sim.client(node, async { Ok(()) });
+///         }
+///         FaultDriver::new(nodes(), scenario.to_vec()).run(&mut sim).unwrap();
+///     },
+/// );
+/// ```
+///
+/// # Panics
+///
+/// Panics if the test's regression file exists but cannot be read.
+pub fn run_scenario_test<G, F>(test_name: &str, generate: G, test_fn: F)
+where
+    G: Fn(u64) -> Scenario + RefUnwindSafe,
+    F: Fn(u64, &[(Duration, Fault)]) + RefUnwindSafe,
+{
+    open_corpus(test_name).run_scenarios(generate, test_fn);
+}
+
+fn open_corpus(test_name: &str) -> RegressionCorpus {
+    RegressionCorpus::for_test(test_name).unwrap_or_else(|error| {
+        panic!(
+            "cannot read the regression corpus of {}: {}",
+            test_name, error
+        )
+    })
+}
+
+/// The seed pinned by `TEST_SEED`, if it is set and parses.
+pub(crate) fn env_seed() -> Option<u64> {
+    std::env::var(TEST_SEED_ENV)
+        .ok()
+        .and_then(|value| parse_seed(&value))
+}
+
+/// Formats the shell command that reruns `test_name` with `seed`.
+pub(crate) fn reproduction_command(seed: u64, test_name: &str) -> String {
+    format!(
+        "{}={} cargo test {} --features simulation",
+        TEST_SEED_ENV, seed, test_name
+    )
+}
+
+fn parse_seed(value: &str) -> Option<u64> {
+    value.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_seed_accepts_decimal_with_whitespace() {
+        assert_eq!(parse_seed("12345"), Some(12345));
+        assert_eq!(parse_seed(" 42\n"), Some(42));
+    }
+
+    #[test]
+    fn parse_seed_rejects_garbage() {
+        assert_eq!(parse_seed(""), None);
+        assert_eq!(parse_seed("abc"), None);
+        assert_eq!(parse_seed("-1"), None);
+    }
+
+    #[test]
+    fn reproduction_command_names_seed_and_test() {
+        assert_eq!(
+            reproduction_command(7, "test_echo"),
+            "TEST_SEED=7 cargo test test_echo --features simulation"
+        );
+    }
+}
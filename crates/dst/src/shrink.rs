@@ -0,0 +1,413 @@
+//! Shrinking of failing fault scenarios.
+//!
+//! A generated scenario that breaks an invariant usually contains many
+//! faults that have nothing to do with the failure. [`ScenarioShrinker`]
+//! minimizes it with delta debugging while the failure still reproduces:
+//!
+//! 1. drop nodes one at a time together with every fault that touches them
+//!    (see [`ScenarioShrinker::with_nodes`]);
+//! 2. remove faults with `ddmin`;
+//! 3. move each fault earlier, shortening the delays between faults;
+//! 4. shrink fault parameters such as latency and loss percentages.
+//!
+//! The result can be persisted with [`save_scenario`](crate::save_scenario)
+//! and replayed with [`load_scenario`](crate::load_scenario).
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use crate::fault::{Fault, Scenario};
+use crate::sweep::{install_quiet_panic_hook, run_quietly};
+
+/// Default cap on how many times the failure predicate is evaluated.
+const DEFAULT_MAX_RUNS: usize = 1_000;
+
+/// Minimizes a failing fault scenario.
+///
+/// The predicate receives a candidate scenario and returns `true` if the
+/// failure still reproduces. It must be deterministic, so run the simulation
+/// with a fixed seed inside it.
+///
+/// A shrinker built with [`new`](ScenarioShrinker::new) only sees the
+/// scenario, so it cannot take hosts out of the simulation: its first pass
+/// drops every fault of one node at a time and the node stays. Build it with
+/// [`with_nodes`](ScenarioShrinker::with_nodes) instead to let the predicate
+/// rebuild the simulation from a smaller node set.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use dst::{Fault, ScenarioShrinker};
+///
+/// let crash = |node: &str| Fault::Crash { node: node.into() };
+/// let scenario = vec![
+///     (Duration::from_secs(5), crash("a")),
+///     (Duration::from_secs(9), crash("b")),
+///     (Duration::from_secs(12), crash("c")),
+/// ];
+///
+/// // Fails whenever "b" crashes.
+/// let outcome = ScenarioShrinker::new(|s: &[(Duration, Fault)]| {
+///     s.iter().any(|(_, f)| *f == crash("b"))
+/// })
+/// .shrink(scenario);
+///
+/// assert_eq!(outcome.scenario, vec![(Duration::ZERO, crash("b"))]);
+/// ```
+pub struct ScenarioShrinker<F> {
+    fails: F,
+    nodes: Vec<String>,
+    max_runs: usize,
+    runs: usize,
+}
+
+/// Result of [`ScenarioShrinker::shrink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShrinkOutcome {
+    /// The smallest scenario found that still fails.
+    pub scenario: Scenario,
+    /// The nodes the failure still needs. Empty unless the shrinker was
+    /// built with [`ScenarioShrinker::with_nodes`].
+    pub nodes: Vec<String>,
+    /// How many times the predicate ran.
+    pub runs: usize,
+    /// `false` if shrinking stopped because it hit the run budget.
+    pub complete: bool,
+}
+
+impl ScenarioShrinker<()> {
+    /// Creates a shrinker around a "still fails" predicate over scenarios.
+    #[allow(clippy::type_complexity)]
+    pub fn new<G>(
+        mut fails: G,
+    ) -> ScenarioShrinker<impl FnMut(&[String], &[(Duration, Fault)]) -> bool>
+    where
+        G: FnMut(&[(Duration, Fault)]) -> bool,
+    {
+        ScenarioShrinker::with_nodes(
+            Vec::<String>::new(),
+            move |_: &[String], scenario: &[(Duration, Fault)]| fails(scenario),
+        )
+    }
+}
+
+impl<F> ScenarioShrinker<F>
+where
+    F: FnMut(&[String], &[(Duration, Fault)]) -> bool,
+{
+    /// Creates a shrinker that also removes nodes.
+    ///
+    /// The predicate receives the candidate node set along with the
+    /// scenario and should build the simulation with only those hosts.
+    /// Dropping a node drops every fault that names it, and the surviving
+    /// nodes are returned in [`ShrinkOutcome::nodes`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use dst::{Fault, ScenarioShrinker};
+    ///
+    /// let crash = |node: &str| Fault::Crash { node: node.into() };
+    ///
+    /// // Fails whenever "b" crashes while "a" is still around.
+    /// let outcome = ScenarioShrinker::with_nodes(
+    ///     ["a", "b", "c"],
+    ///     |nodes: &[String], s: &[(Duration, Fault)]| {
+    ///         nodes.iter().any(|n| n == "a") && s.iter().any(|(_, f)| *f == crash("b"))
+    ///     },
+    /// )
+    /// .shrink(vec![
+    ///     (Duration::from_secs(5), crash("c")),
+    ///     (Duration::from_secs(9), crash("b")),
+    /// ]);
+    ///
+    /// assert_eq!(outcome.nodes, ["a", "b"]);
+    /// assert_eq!(outcome.scenario, vec![(Duration::ZERO, crash("b"))]);
+    /// ```
+    pub fn with_nodes<I>(nodes: I, fails: F) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        ScenarioShrinker {
+            fails,
+            nodes: nodes.into_iter().map(Into::into).collect(),
+            max_runs: DEFAULT_MAX_RUNS,
+            runs: 0,
+        }
+    }
+
+    /// Caps the number of predicate evaluations. Defaults to 1000.
+    pub fn max_runs(mut self, max_runs: usize) -> Self {
+        self.max_runs = max_runs;
+        self
+    }
+
+    /// Shrinks `scenario`, which must fail to begin with.
+    ///
+    /// If the original scenario does not fail, it is returned unchanged.
+    pub fn shrink(mut self, scenario: Scenario) -> ShrinkOutcome {
+        let mut scenario = scenario;
+        scenario.sort_by_key(|(at, _)| *at);
+
+        if self.check(&scenario) != Some(true) {
+            return self.finish(scenario);
+        }
+
+        // Each pass can enable another (a shorter scenario may allow an
+        // earlier fault time), so repeat until nothing changes.
+        loop {
+            let before = (self.nodes.clone(), scenario.clone());
+            scenario = self.drop_nodes(scenario);
+            scenario = self.remove_faults(scenario);
+            scenario = self.shorten_delays(scenario);
+            scenario = self.shrink_parameters(scenario);
+            if (self.nodes == before.0 && scenario == before.1) || self.exhausted() {
+                return self.finish(scenario);
+            }
+        }
+    }
+
+    fn finish(self, scenario: Scenario) -> ShrinkOutcome {
+        ShrinkOutcome {
+            scenario,
+            nodes: self.nodes,
+            runs: self.runs,
+            complete: self.runs < self.max_runs,
+        }
+    }
+
+    fn exhausted(&self) -> bool {
+        self.runs >= self.max_runs
+    }
+
+    /// Runs the predicate, or returns `None` once the budget is spent.
+    fn check(&mut self, candidate: &[(Duration, Fault)]) -> Option<bool> {
+        let nodes = std::mem::take(&mut self.nodes);
+        let fails = self.check_nodes(&nodes, candidate);
+        self.nodes = nodes;
+        fails
+    }
+
+    /// Like [`check`](Self::check) with a candidate node set.
+    fn check_nodes(&mut self, nodes: &[String], candidate: &[(Duration, Fault)]) -> Option<bool> {
+        if self.exhausted() {
+            return None;
+        }
+        self.runs += 1;
+        Some((self.fails)(nodes, candidate))
+    }
+
+    /// Removes each node in turn along with the faults that name it.
+    ///
+    /// Without a node set, only the faults go and the nodes remain.
+    fn drop_nodes(&mut self, mut scenario: Scenario) -> Scenario {
+        let candidates: Vec<String> = if self.nodes.is_empty() {
+            scenario
+                .iter()
+                .flat_map(|(_, fault)| fault.nodes())
+                .map(str::to_string)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        } else {
+            self.nodes.clone()
+        };
+
+        for node in candidates {
+            let nodes: Vec<String> = self.nodes.iter().filter(|n| **n != node).cloned().collect();
+            let candidate: Scenario = scenario
+                .iter()
+                .filter(|(_, fault)| !fault.nodes().contains(&node.as_str()))
+                .cloned()
+                .collect();
+            let smaller = nodes.len() < self.nodes.len() || candidate.len() < scenario.len();
+            if smaller && self.check_nodes(&nodes, &candidate) == Some(true) {
+                self.nodes = nodes;
+                scenario = candidate;
+            }
+        }
+        scenario
+    }
+
+    /// Classic `ddmin`: try removing ever smaller chunks of faults.
+    fn remove_faults(&mut self, mut scenario: Scenario) -> Scenario {
+        if scenario.is_empty() {
+            return scenario;
+        }
+        if self.check(&[]) == Some(true) {
+            return Vec::new();
+        }
+
+        let mut granularity = 2;
+        while scenario.len() >= 2 {
+            let chunk = scenario.len().div_ceil(granularity);
+            let mut reduced = false;
+
+            for start in (0..scenario.len()).step_by(chunk) {
+                let end = (start + chunk).min(scenario.len());
+                let complement: Scenario = scenario[..start]
+                    .iter()
+                    .chain(&scenario[end..])
+                    .cloned()
+                    .collect();
+                match self.check(&complement) {
+                    Some(true) => {
+                        scenario = complement;
+                        granularity = (granularity - 1).max(2);
+                        reduced = true;
+                        break;
+                    }
+                    Some(false) => {}
+                    None => return scenario,
+                }
+            }
+
+            if !reduced {
+                if granularity >= scenario.len() {
+                    break;
+                }
+                granularity = (granularity * 2).min(scenario.len());
+            }
+        }
+        scenario
+    }
+
+    /// Moves each fault as close to its predecessor as the failure allows.
+    fn shorten_delays(&mut self, mut scenario: Scenario) -> Scenario {
+        for index in 0..scenario.len() {
+            let floor = if index == 0 {
+                Duration::ZERO
+            } else {
+                scenario[index - 1].0
+            };
+
+            // Binary search for the earliest failing time in [floor, at].
+            let mut low = floor;
+            let mut high = scenario[index].0;
+            while high > low {
+                let mid = low + (high - low) / 2;
+                let mut candidate = scenario.clone();
+                candidate[index].0 = mid;
+                match self.check(&candidate) {
+                    Some(true) => {
+                        scenario = candidate;
+                        high = mid;
+                    }
+                    Some(false) => low = mid + Duration::from_millis(1),
+                    None => return scenario,
+                }
+            }
+        }
+        scenario
+    }
+
+    /// Halves numeric fault parameters while the failure reproduces.
+    fn shrink_parameters(&mut self, mut scenario: Scenario) -> Scenario {
+        for index in 0..scenario.len() {
+            while let Some(smaller) = halve_parameter(&scenario[index].1) {
+                let mut candidate = scenario.clone();
+                candidate[index].1 = smaller;
+                match self.check(&candidate) {
+                    Some(true) => scenario = candidate,
+                    Some(false) | None => break,
+                }
+            }
+        }
+        scenario
+    }
+}
+
+/// Returns `fault` with its main numeric parameter halved, or `None` if it
+/// has none left to shrink.
+fn halve_parameter(fault: &Fault) -> Option<Fault> {
+    let mut fault = fault.clone();
+    let value: &mut u64 = match &mut fault {
+        Fault::Latency { delay_ms, .. } | Fault::LinkLatency { delay_ms, .. } => delay_ms,
+        Fault::Reorder { max_delay_ms, .. } => max_delay_ms,
+        Fault::MessageLoss { percent, .. } | Fault::Duplicate { percent, .. } => {
+            if *percent <= 1 {
+                return None;
+            }
+            *percent /= 2;
+            return Some(fault);
+        }
+        // Clock errors shrink towards zero from either side.
+        Fault::ClockOffset { offset_ms: ms, .. } | Fault::ClockJump { delta_ms: ms, .. } => {
+            if ms.unsigned_abs() <= 1 {
+                return None;
+            }
+            *ms /= 2;
+            return Some(fault);
+        }
+        Fault::ClockDrift { ppm, .. } => {
+            if ppm.unsigned_abs() <= 1 {
+                return None;
+            }
+            *ppm /= 2;
+            return Some(fault);
+        }
+        Fault::Partition { .. }
+        | Fault::Repair { .. }
+        | Fault::Crash { .. }
+        | Fault::Restart { .. }
+        // A lower cap is a harsher fault, not a smaller one.
+        | Fault::Bandwidth { .. } => return None,
+    };
+    if *value <= 1 {
+        return None;
+    }
+    *value /= 2;
+    Some(fault)
+}
+
+/// Adapts a test that panics on failure into a shrinker predicate.
+///
+/// The panic is caught and its default output suppressed, so shrinking a
+/// simulation test does not flood stderr with one backtrace per candidate.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use dst::{shrink::panics, Fault, ScenarioShrinker};
+///
+/// let outcome = ScenarioShrinker::new(panics(|scenario: &[(Duration, Fault)]| {
+///     assert!(scenario.len() < 2, "too many faults");
+/// }))
+/// .shrink(vec![
+///     (Duration::from_secs(1), Fault::Crash { node: "a".into() }),
+///     (Duration::from_secs(2), Fault::Crash { node: "b".into() }),
+///     (Duration::from_secs(3), Fault::Crash { node: "c".into() }),
+/// ]);
+///
+/// assert_eq!(outcome.scenario.len(), 2);
+/// ```
+pub fn panics<T>(test: T) -> impl FnMut(&[(Duration, Fault)]) -> bool
+where
+    T: Fn(&[(Duration, Fault)]) + std::panic::RefUnwindSafe,
+{
+    install_quiet_panic_hook();
+    move |scenario| {
+        let scenario: Scenario = scenario.to_vec();
+        let test = &test;
+        run_quietly(move || test(&scenario)).is_err()
+    }
+}
+
+/// Like [`panics`], for tests that build their simulation from a node set;
+/// pair it with [`ScenarioShrinker::with_nodes`].
+pub fn panics_with_nodes<T>(test: T) -> impl FnMut(&[String], &[(Duration, Fault)]) -> bool
+where
+    T: Fn(&[String], &[(Duration, Fault)]) + std::panic::RefUnwindSafe,
+{
+    install_quiet_panic_hook();
+    move |nodes, scenario| {
+        let nodes = nodes.to_vec();
+        let scenario: Scenario = scenario.to_vec();
+        let test = &test;
+        run_quietly(move || test(&nodes, &scenario)).is_err()
+    }
+}
@@ -0,0 +1,254 @@
+//! `proptest` strategies for faults and fault schedules.
+//!
+//! [`FaultScenarioGenerator`](crate::FaultScenarioGenerator) draws scenarios
+//! from its own RNG and cannot shrink them. The strategies here generate the
+//! same kinds of faults through `proptest`, so a failing schedule shrinks to
+//! the fewest, earliest and mildest faults that still fail, and the failure
+//! is persisted in `proptest-regressions/` like any other property test.
+//!
+//! ```
+//! use dst::strategy::{FaultSchedule, ScenarioParams};
+//! use dst::FaultWeights;
+//! use proptest::prelude::*;
+//!
+//! let params = ScenarioParams::new(["a", "b", "c"])
+//!     .with_weights(FaultWeights::NETWORK)
+//!     .with_horizon(std::time::Duration::from_secs(30));
+//!
+//! proptest!(|(seed in any::<u64>(), schedule in any_with::<FaultSchedule>(params.clone()))| {
+//!     // Build the simulation from `seed` and drive it with `schedule`.
+//!     prop_assert!(schedule.iter().all(|(at, _)| at.as_secs() < 30));
+//! });
+//! ```
+//!
+//! Requires the `proptest` feature.
+
+use std::ops::Deref;
+use std::time::Duration;
+
+use proptest::collection::{self, SizeRange};
+use proptest::prelude::*;
+use proptest::strategy::{BoxedStrategy, Union};
+
+use crate::fault::{Fault, FaultWeights, Scenario};
+
+/// The nodes, fault kinds, time horizon and length of generated schedules.
+///
+/// Used as the [`Arbitrary`] parameters of [`Fault`] and [`FaultSchedule`].
+/// The default targets `node-0` to `node-2` with every kind weighted
+/// equally, over 60 seconds and up to 10 faults.
+#[derive(Debug, Clone)]
+pub struct ScenarioParams {
+    nodes: Vec<String>,
+    weights: FaultWeights,
+    horizon: Duration,
+    len: SizeRange,
+}
+
+impl Default for ScenarioParams {
+    fn default() -> Self {
+        Self::new(["node-0", "node-1", "node-2"])
+    }
+}
+
+impl ScenarioParams {
+    /// Targets `nodes`, with the default weights, horizon and length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nodes` is empty.
+    pub fn new<I, S>(nodes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let nodes: Vec<String> = nodes.into_iter().map(Into::into).collect();
+        assert!(!nodes.is_empty(), "fault strategies need at least one node");
+        ScenarioParams {
+            nodes,
+            weights: FaultWeights::default(),
+            horizon: Duration::from_secs(60),
+            len: (0..=10).into(),
+        }
+    }
+
+    /// Replaces the weights used to pick fault kinds. Kinds weighted zero
+    /// are never generated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every weight is zero.
+    pub fn with_weights(mut self, weights: FaultWeights) -> Self {
+        assert!(
+            weights.total() > 0,
+            "at least one fault weight must be non-zero"
+        );
+        self.weights = weights;
+        self
+    }
+
+    /// Faults fire before `horizon`, at whole milliseconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `horizon` is shorter than a millisecond.
+    pub fn with_horizon(mut self, horizon: Duration) -> Self {
+        assert!(
+            horizon >= Duration::from_millis(1),
+            "the horizon must be at least 1ms"
+        );
+        self.horizon = horizon;
+        self
+    }
+
+    /// Number of faults per schedule, e.g. `1..=5`.
+    pub fn with_len(mut self, len: impl Into<SizeRange>) -> Self {
+        self.len = len.into();
+        self
+    }
+
+    /// The targeted nodes.
+    pub fn nodes(&self) -> &[String] {
+        &self.nodes
+    }
+
+    /// A single fault. Kinds shrink towards partitions, nodes towards the
+    /// first one and magnitudes towards their minimum.
+    pub fn fault(&self) -> BoxedStrategy<Fault> {
+        let node = self.node();
+        let link = self.link();
+        let w = self.weights;
+        let kinds: Vec<(u32, BoxedStrategy<Fault>)> = vec![
+            (
+                w.partition,
+                link.clone()
+                    .prop_map(|(node_a, node_b)| Fault::Partition { node_a, node_b })
+                    .boxed(),
+            ),
+            (
+                w.repair,
+                link.clone()
+                    .prop_map(|(node_a, node_b)| Fault::Repair { node_a, node_b })
+                    .boxed(),
+            ),
+            (
+                w.latency,
+                (node.clone(), 100..1000u64)
+                    .prop_map(|(node, delay_ms)| Fault::Latency { node, delay_ms })
+                    .boxed(),
+            ),
+            (
+                w.crash,
+                node.clone().prop_map(|node| Fault::Crash { node }).boxed(),
+            ),
+            (
+                w.restart,
+                node.prop_map(|node| Fault::Restart { node }).boxed(),
+            ),
+            (
+                w.message_loss,
+                (link.clone(), 1..=50u8)
+                    .prop_map(|((node_a, node_b), percent)| Fault::MessageLoss {
+                        node_a,
+                        node_b,
+                        percent,
+                    })
+                    .boxed(),
+            ),
+            (
+                w.duplicate,
+                (link.clone(), 1..=50u8)
+                    .prop_map(|((node_a, node_b), percent)| Fault::Duplicate {
+                        node_a,
+                        node_b,
+                        percent,
+                    })
+                    .boxed(),
+            ),
+            (
+                w.reorder,
+                (link, 10..500u64)
+                    .prop_map(|((node_a, node_b), max_delay_ms)| Fault::Reorder {
+                        node_a,
+                        node_b,
+                        max_delay_ms,
+                    })
+                    .boxed(),
+            ),
+        ];
+        Union::new_weighted(
+            kinds
+                .into_iter()
+                .filter(|(weight, _)| *weight > 0)
+                .collect(),
+        )
+        .boxed()
+    }
+
+    /// A timed schedule, sorted by time. Shrinking drops faults, moves the
+    /// rest earlier and simplifies each one.
+    pub fn scenario(&self) -> BoxedStrategy<Scenario> {
+        let horizon_ms = u64::try_from(self.horizon.as_millis()).unwrap_or(u64::MAX);
+        let timed = (0..horizon_ms, self.fault())
+            .prop_map(|(at_ms, fault)| (Duration::from_millis(at_ms), fault));
+        collection::vec(timed, self.len.clone())
+            .prop_map(|mut scenario| {
+                scenario.sort_by_key(|(at, _)| *at);
+                scenario
+            })
+            .boxed()
+    }
+
+    fn node(&self) -> BoxedStrategy<String> {
+        let nodes = self.nodes.clone();
+        (0..nodes.len()).prop_map(move |i| nodes[i].clone()).boxed()
+    }
+
+    /// Two distinct nodes, unless there is only one.
+    fn link(&self) -> BoxedStrategy<(String, String)> {
+        let nodes = self.nodes.clone();
+        let n = nodes.len();
+        (0..n, 1..n.max(2))
+            .prop_map(move |(i, offset)| (nodes[i].clone(), nodes[(i + offset) % n].clone()))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Fault {
+    type Parameters = ScenarioParams;
+    type Strategy = BoxedStrategy<Fault>;
+
+    fn arbitrary_with(params: ScenarioParams) -> Self::Strategy {
+        params.fault()
+    }
+}
+
+/// A timed fault schedule with an [`Arbitrary`] implementation.
+///
+/// [`Scenario`] is a plain `Vec`, which cannot implement `Arbitrary`
+/// outside `proptest`; this wrapper can, and dereferences to the schedule.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FaultSchedule(pub Scenario);
+
+impl Deref for FaultSchedule {
+    type Target = Scenario;
+
+    fn deref(&self) -> &Scenario {
+        &self.0
+    }
+}
+
+impl From<FaultSchedule> for Scenario {
+    fn from(schedule: FaultSchedule) -> Self {
+        schedule.0
+    }
+}
+
+impl Arbitrary for FaultSchedule {
+    type Parameters = ScenarioParams;
+    type Strategy = BoxedStrategy<FaultSchedule>;
+
+    fn arbitrary_with(params: ScenarioParams) -> Self::Strategy {
+        params.scenario().prop_map(FaultSchedule).boxed()
+    }
+}
@@ -0,0 +1,322 @@
+//! Parallel seed sweeps.
+//!
+//! [`run_simulation_test`](crate::run_simulation_test) runs one seed per
+//! invocation. [`SeedSweep`] runs the same simulation closure over many seeds
+//! on a pool of OS threads, collects every failing seed with its panic
+//! message, and prints `TEST_SEED=...` reproduction lines for each of them.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt;
+use std::ops::Range;
+use std::panic::{self, RefUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+use std::thread;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::seed::{env_seed, reproduction_command};
+
+/// Default number of seeds when neither [`SeedSweep::seeds`] nor
+/// [`SeedSweep::count`] is given.
+const DEFAULT_SEED_COUNT: usize = 100;
+
+/// Runs a simulation closure across many seeds in parallel.
+///
+/// If `TEST_SEED` is set, the sweep runs only that seed so a failure reported
+/// by an earlier sweep reproduces with the printed command.
+///
+/// # Examples
+///
+/// ```
+/// use dst::SeedSweep;
+///
+/// let report = SeedSweep::new("test_counter")
+///     .seed_range(0..64)
+///     .threads(4)
+///     .run(|seed| assert!(seed < 1_000));
+///
+/// assert_eq!(report.passed(), 64);
+/// report.assert_passed();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SeedSweep {
+    test_name: String,
+    seeds: Seeds,
+    threads: usize,
+    max_failures: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Seeds {
+    Explicit(Vec<u64>),
+    Random(usize),
+}
+
+impl SeedSweep {
+    /// Creates a sweep for `test_name` over 100 random seeds, using one
+    /// thread per available CPU.
+    pub fn new(test_name: impl Into<String>) -> Self {
+        SeedSweep {
+            test_name: test_name.into(),
+            seeds: Seeds::Random(DEFAULT_SEED_COUNT),
+            threads: thread::available_parallelism().map_or(1, usize::from),
+            max_failures: None,
+        }
+    }
+
+    /// Runs exactly the given seeds, in order.
+    pub fn seeds(mut self, seeds: impl IntoIterator<Item = u64>) -> Self {
+        self.seeds = Seeds::Explicit(seeds.into_iter().collect());
+        self
+    }
+
+    /// Runs every seed in `range`.
+    pub fn seed_range(self, range: Range<u64>) -> Self {
+        self.seeds(range)
+    }
+
+    /// Runs `count` seeds drawn at random.
+    pub fn count(mut self, count: usize) -> Self {
+        self.seeds = Seeds::Random(count);
+        self
+    }
+
+    /// Sets the number of worker threads. Values below one are treated as one.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Stops handing out new seeds once `failures` seeds have failed.
+    ///
+    /// Seeds already running on other threads still finish, so a parallel
+    /// sweep may report slightly more failures than the limit.
+    pub fn stop_after(mut self, failures: usize) -> Self {
+        self.max_failures = Some(failures.max(1));
+        self
+    }
+
+    /// Runs `test_fn` once per seed and returns the collected results.
+    ///
+    /// Panics inside `test_fn` are caught and recorded; their default
+    /// `panicked at` output is suppressed so a sweep with many failures stays
+    /// readable. A summary is printed to stderr when any seed fails.
+    #[dst_macros::allow(thread_spawn)]
+    pub fn run<F>(self, test_fn: F) -> SweepReport
+    where
+        F: Fn(u64) + Sync + RefUnwindSafe,
+    {
+        let seeds = self.resolve_seeds(env_seed());
+        install_quiet_panic_hook();
+
+        let next = AtomicUsize::new(0);
+        let failed = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let results = Mutex::new(Vec::with_capacity(seeds.len()));
+        let threads = self.threads.min(seeds.len()).max(1);
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(&seed) = seeds.get(index) else {
+                        break;
+                    };
+
+                    let outcome = run_quietly(|| test_fn(seed));
+                    if outcome.is_err() {
+                        let count = failed.fetch_add(1, Ordering::SeqCst) + 1;
+                        if self.max_failures.is_some_and(|max| count >= max) {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                    }
+
+                    results
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .push((index, seed, outcome));
+                });
+            }
+        });
+
+        let mut results = results
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        results.sort_by_key(|(index, _, _)| *index);
+
+        let mut passed = 0;
+        let mut failures = Vec::new();
+        for (_, seed, outcome) in results {
+            match outcome {
+                Ok(()) => passed += 1,
+                Err(message) => failures.push(SeedFailure { seed, message }),
+            }
+        }
+
+        let report = SweepReport {
+            test_name: self.test_name,
+            total: seeds.len(),
+            passed,
+            failures,
+        };
+        if !report.is_success() {
+            eprintln!("{}", report);
+        }
+        report
+    }
+
+    #[dst_macros::allow(rand)]
+    fn resolve_seeds(&self, env_seed: Option<u64>) -> Vec<u64> {
+        if let Some(seed) = env_seed {
+            return vec![seed];
+        }
+        match &self.seeds {
+            Seeds::Explicit(seeds) => seeds.clone(),
+            Seeds::Random(count) => {
+                let mut rng = StdRng::seed_from_u64(rand::random());
+                (0..*count).map(|_| rng.random()).collect()
+            }
+        }
+    }
+}
+
+/// A seed whose run panicked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedFailure {
+    /// The failing seed.
+    pub seed: u64,
+    /// The panic message, or a placeholder if the payload was not a string.
+    pub message: String,
+}
+
+/// Outcome of a [`SeedSweep`].
+#[derive(Debug, Clone)]
+pub struct SweepReport {
+    test_name: String,
+    total: usize,
+    passed: usize,
+    failures: Vec<SeedFailure>,
+}
+
+impl SweepReport {
+    /// Number of seeds that ran to completion without panicking.
+    pub fn passed(&self) -> usize {
+        self.passed
+    }
+
+    /// Failing seeds in the order they were scheduled.
+    pub fn failures(&self) -> &[SeedFailure] {
+        &self.failures
+    }
+
+    /// Number of seeds never run because the sweep stopped early.
+    pub fn skipped(&self) -> usize {
+        self.total - self.passed - self.failures.len()
+    }
+
+    /// Returns `true` if no seed failed.
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Panics with the summary if any seed failed.
+    pub fn assert_passed(&self) {
+        assert!(self.is_success(), "{}", self);
+    }
+}
+
+impl fmt::Display for SweepReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "=== {}: {} passed, {} failed, {} skipped ===",
+            self.test_name,
+            self.passed,
+            self.failures.len(),
+            self.skipped()
+        )?;
+        for failure in &self.failures {
+            writeln!(f, "seed {}: {}", failure.seed, failure.message)?;
+        }
+        if !self.failures.is_empty() {
+            writeln!(f, "To reproduce:")?;
+            for failure in &self.failures {
+                writeln!(
+                    f,
+                    "  {}",
+                    reproduction_command(failure.seed, &self.test_name)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Wraps the current panic hook so panics on sweep threads are not printed.
+pub(crate) fn install_quiet_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !QUIET.with(Cell::get) {
+                previous(info);
+            }
+        }));
+    });
+}
+
+pub(crate) fn run_quietly(f: impl FnOnce() + panic::UnwindSafe) -> Result<(), String> {
+    QUIET.with(|quiet| quiet.set(true));
+    let result = panic::catch_unwind(f);
+    QUIET.with(|quiet| quiet.set(false));
+    result.map_err(|payload| panic_message(payload.as_ref()))
+}
+
+/// Extracts the message from a panic payload.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_seed_overrides_configured_seeds() {
+        let sweep = SeedSweep::new("t").seed_range(0..10);
+        assert_eq!(sweep.resolve_seeds(Some(99)), vec![99]);
+        assert_eq!(sweep.resolve_seeds(None), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn random_seeds_have_requested_count() {
+        let sweep = SeedSweep::new("t").count(17);
+        assert_eq!(sweep.resolve_seeds(None).len(), 17);
+    }
+
+    #[test]
+    fn panic_message_handles_str_and_string() {
+        let owned: Box<dyn Any + Send> = Box::new(String::from("owned"));
+        let borrowed: Box<dyn Any + Send> = Box::new("borrowed");
+        let other: Box<dyn Any + Send> = Box::new(42u8);
+        assert_eq!(panic_message(owned.as_ref()), "owned");
+        assert_eq!(panic_message(borrowed.as_ref()), "borrowed");
+        assert_eq!(panic_message(other.as_ref()), "<non-string panic payload>");
+    }
+}
@@ -0,0 +1,661 @@
+//! Timeline export for recorded traces.
+//!
+//! A [`Timeline`] lays a [`Trace`] out as one lane per host, pairs message
+//! sends with their receives, and turns an applied fault schedule into
+//! intervals: a partition lasts until its repair, a crash until the restart,
+//! a latency or loss fault until it is set back to zero. It can then be
+//! written as:
+//!
+//! - Chrome Trace Event JSON ([`Timeline::write_chrome_trace`]), for
+//!   `chrome://tracing` or [Perfetto](https://ui.perfetto.dev): hosts are
+//!   threads, messages are flow arrows and faults are async slices.
+//! - A self-contained HTML page with an SVG sequence diagram
+//!   ([`Timeline::write_html`]): hosts are columns, messages are arrows and
+//!   faults are shaded intervals behind the columns they affect.
+//!
+//! The caller decides how each event is drawn by mapping it to a [`Mark`].
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//! use dst::timeline::{Mark, Timeline};
+//! use dst::{Fault, TraceRecorder};
+//!
+//! #[derive(Debug, Clone)]
+//! enum Event {
+//!     Sent(u64),
+//!     Received(u64),
+//! }
+//!
+//! let recorder = TraceRecorder::new();
+//! let ms = Duration::from_millis;
+//! recorder.record_at(ms(1), "a", Event::Sent(1));
+//! recorder.record_at(ms(4), "b", Event::Received(1));
+//! recorder.record_at(ms(9), "a", Event::Sent(2));
+//!
+//! let timeline = Timeline::new(&recorder.trace(), |event| match event {
+//!     Event::Sent(seq) => Some(Mark::send(seq, format!("ping {}", seq))),
+//!     Event::Received(seq) => Some(Mark::receive(seq, format!("got {}", seq))),
+//! })
+//! .with_faults([(
+//!     ms(5),
+//!     Fault::Partition { node_a: "a".into(), node_b: "b".into() },
+//! )]);
+//!
+//! assert_eq!(timeline.lanes(), ["a", "b"]);
+//! assert!(timeline.to_html("ping").contains("<svg"));
+//! let chrome = timeline.to_chrome_trace();
+//! assert!(chrome["traceEvents"].is_array());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::error::Result;
+use crate::fault::Fault;
+use crate::trace::Trace;
+
+/// Chrome trace process id every lane belongs to.
+const PID: u64 = 1;
+
+// HTML layout, in pixels.
+const LANE_WIDTH: usize = 180;
+const ROW_HEIGHT: usize = 22;
+const LEFT: usize = 100;
+const TOP: usize = 40;
+/// Labels longer than this are cut in the diagram; tooltips show them whole.
+const MAX_LABEL: usize = 24;
+
+/// How one trace event is drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mark {
+    /// A point on the recording host's lane.
+    Event {
+        /// Text shown next to the point.
+        label: String,
+    },
+    /// The host sent message `id`. Drawn as the tail of an arrow to every
+    /// receive with the same `id`, or as a lost message if there is none.
+    Send {
+        /// Identifies the message; must be unique within the trace.
+        id: String,
+        /// Text shown on the arrow.
+        label: String,
+    },
+    /// The host received message `id`. A duplicated delivery gets its own
+    /// arrow.
+    Receive {
+        /// Identifies the message sent.
+        id: String,
+        /// Text shown next to the arrow head.
+        label: String,
+    },
+}
+
+impl Mark {
+    /// A point labelled `label`.
+    pub fn event(label: impl Into<String>) -> Self {
+        Mark::Event {
+            label: label.into(),
+        }
+    }
+
+    /// The send of message `id`.
+    pub fn send(id: impl ToString, label: impl Into<String>) -> Self {
+        Mark::Send {
+            id: id.to_string(),
+            label: label.into(),
+        }
+    }
+
+    /// A receive of message `id`.
+    pub fn receive(id: impl ToString, label: impl Into<String>) -> Self {
+        Mark::Receive {
+            id: id.to_string(),
+            label: label.into(),
+        }
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            Mark::Event { label } | Mark::Send { label, .. } | Mark::Receive { label, .. } => label,
+        }
+    }
+
+    fn category(&self) -> &'static str {
+        match self {
+            Mark::Event { .. } => "event",
+            Mark::Send { .. } => "send",
+            Mark::Receive { .. } => "receive",
+        }
+    }
+}
+
+/// A marked event placed on a lane.
+#[derive(Debug, Clone)]
+struct Point {
+    at: Duration,
+    lane: usize,
+    mark: Mark,
+    /// The event's `Debug` output, shown in tooltips and Chrome args.
+    detail: String,
+}
+
+/// A message arrow between two points. `to` is `None` for lost messages.
+#[derive(Debug, Clone, Copy)]
+struct Arrow {
+    from: usize,
+    to: Option<usize>,
+}
+
+/// A fault interval on one or more lanes.
+#[derive(Debug, Clone)]
+struct Span {
+    kind: &'static str,
+    label: String,
+    lanes: Vec<usize>,
+    start: Duration,
+    /// `None` while the fault is still in effect at the end of the run.
+    end: Option<Duration>,
+}
+
+/// What a fault does to the interval of its kind on its nodes.
+enum Effect {
+    Open(String),
+    Close,
+    /// A momentary interval, such as a restart.
+    Instant(String),
+}
+
+/// Hosts, messages and fault intervals of one run, ready to export.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    lanes: Vec<String>,
+    points: Vec<Point>,
+    arrows: Vec<Arrow>,
+    spans: Vec<Span>,
+}
+
+impl Timeline {
+    /// Lays out `trace`, drawing each event as `mark` returns. Events for
+    /// which it returns `None` are left out, for example faults already
+    /// passed to [`Timeline::with_faults`].
+    pub fn new<E, F>(trace: &Trace<E>, mut mark: F) -> Self
+    where
+        E: fmt::Debug,
+        F: FnMut(&E) -> Option<Mark>,
+    {
+        let mut timeline = Timeline {
+            lanes: Vec::new(),
+            points: Vec::new(),
+            arrows: Vec::new(),
+            spans: Vec::new(),
+        };
+        for event in trace.events() {
+            let Some(mark) = mark(&event.event) else {
+                continue;
+            };
+            let lane = timeline.lane(&event.host);
+            timeline.points.push(Point {
+                at: event.at(),
+                lane,
+                mark,
+                detail: format!("{:?}", event.event),
+            });
+        }
+        // Stable, so events recorded at the same instant keep trace order.
+        timeline.points.sort_by_key(|point| point.at);
+
+        let mut sends: BTreeMap<&str, (usize, bool)> = BTreeMap::new();
+        for (index, point) in timeline.points.iter().enumerate() {
+            match &point.mark {
+                Mark::Send { id, .. } => {
+                    sends.insert(id, (index, false));
+                }
+                Mark::Receive { id, .. } => {
+                    if let Some((from, received)) = sends.get_mut(id.as_str()) {
+                        *received = true;
+                        timeline.arrows.push(Arrow {
+                            from: *from,
+                            to: Some(index),
+                        });
+                    }
+                }
+                Mark::Event { .. } => {}
+            }
+        }
+        timeline
+            .arrows
+            .extend(
+                sends
+                    .values()
+                    .filter(|(_, received)| !received)
+                    .map(|(from, _)| Arrow {
+                        from: *from,
+                        to: None,
+                    }),
+            );
+        timeline
+    }
+
+    /// Adds applied faults as intervals, e.g. from
+    /// `driver.applied().iter().map(|a| (a.applied_at, a.fault.clone()))`.
+    ///
+    /// Nodes that recorded no events get a lane of their own.
+    pub fn with_faults(mut self, faults: impl IntoIterator<Item = (Duration, Fault)>) -> Self {
+        let mut faults: Vec<_> = faults.into_iter().collect();
+        faults.sort_by_key(|(at, _)| *at);
+
+        let mut open: BTreeMap<(&'static str, Vec<String>), usize> = BTreeMap::new();
+        for (at, fault) in faults {
+            let (kind, effect) = effect(&fault);
+            let mut nodes: Vec<String> = fault.nodes().into_iter().map(String::from).collect();
+            nodes.sort();
+            let lanes = nodes.iter().map(|node| self.lane(node)).collect();
+            let key = (kind, nodes);
+
+            // A new setting on the same nodes replaces the old one.
+            if let Some(index) = open.remove(&key) {
+                self.spans[index].end = Some(at);
+            }
+            match effect {
+                Effect::Open(label) => {
+                    open.insert(key, self.spans.len());
+                    self.spans.push(Span {
+                        kind,
+                        label,
+                        lanes,
+                        start: at,
+                        end: None,
+                    });
+                }
+                Effect::Instant(label) => self.spans.push(Span {
+                    kind,
+                    label,
+                    lanes,
+                    start: at,
+                    end: Some(at),
+                }),
+                Effect::Close => {}
+            }
+        }
+        self
+    }
+
+    /// Host names, in lane order: the order hosts first recorded an event,
+    /// then nodes only named by faults.
+    pub fn lanes(&self) -> &[String] {
+        &self.lanes
+    }
+
+    /// Simulated time of the last event or fault.
+    pub fn end(&self) -> Duration {
+        let points = self.points.iter().map(|p| p.at);
+        let spans = self
+            .spans
+            .iter()
+            .flat_map(|s| [Some(s.start), s.end])
+            .flatten();
+        points.chain(spans).max().unwrap_or_default()
+    }
+
+    /// The timeline in the Chrome Trace Event format.
+    pub fn to_chrome_trace(&self) -> Value {
+        let mut events = vec![json!({
+            "ph": "M", "name": "process_name", "pid": PID, "tid": 0,
+            "args": { "name": "simulation" },
+        })];
+        for (tid, lane) in self.lanes.iter().enumerate() {
+            events.push(json!({
+                "ph": "M", "name": "thread_name", "pid": PID, "tid": tid,
+                "args": { "name": lane },
+            }));
+            events.push(json!({
+                "ph": "M", "name": "thread_sort_index", "pid": PID, "tid": tid,
+                "args": { "sort_index": tid },
+            }));
+        }
+
+        // Flow arrows bind to the slice enclosing them, so every point is a
+        // one-microsecond slice.
+        for point in &self.points {
+            events.push(json!({
+                "ph": "X", "name": point.mark.label(), "cat": point.mark.category(),
+                "pid": PID, "tid": point.lane, "ts": micros(point.at), "dur": 1,
+                "args": { "event": point.detail },
+            }));
+        }
+        for (id, arrow) in self.arrows.iter().enumerate() {
+            let from = &self.points[arrow.from];
+            let Some(to) = arrow.to.map(|to| &self.points[to]) else {
+                continue;
+            };
+            events.push(json!({
+                "ph": "s", "name": "message", "cat": "message", "id": id,
+                "pid": PID, "tid": from.lane, "ts": micros(from.at),
+            }));
+            events.push(json!({
+                "ph": "f", "bp": "e", "name": "message", "cat": "message", "id": id,
+                "pid": PID, "tid": to.lane, "ts": micros(to.at),
+            }));
+        }
+
+        let end = self.end();
+        let mut id = 0;
+        for span in &self.spans {
+            for lane in &span.lanes {
+                let cat = format!("fault,{}", span.kind);
+                if span.end == Some(span.start) {
+                    events.push(json!({
+                        "ph": "i", "s": "t", "name": span.label, "cat": cat,
+                        "pid": PID, "tid": lane, "ts": micros(span.start),
+                    }));
+                    continue;
+                }
+                id += 1;
+                for (ph, at) in [("b", span.start), ("e", span.end.unwrap_or(end))] {
+                    events.push(json!({
+                        "ph": ph, "name": span.label, "cat": cat, "id": id,
+                        "pid": PID, "tid": lane, "ts": micros(at),
+                    }));
+                }
+            }
+        }
+
+        json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+    }
+
+    /// Writes [`Timeline::to_chrome_trace`] to `path`.
+    pub fn write_chrome_trace(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_vec(&self.to_chrome_trace())?)?;
+        Ok(())
+    }
+
+    /// The timeline as a self-contained HTML page with an SVG sequence
+    /// diagram.
+    ///
+    /// Events run top to bottom in time order, one row each. Hover over an
+    /// event, arrow or fault for its full description.
+    pub fn to_html(&self, title: &str) -> String {
+        let width = LEFT + self.lanes.len() * LANE_WIDTH + 20;
+        let height = TOP + (self.points.len() + 1) * ROW_HEIGHT + 20;
+        let mut svg = String::new();
+        // Writing to a String cannot fail.
+        let _ = self.write_svg(&mut svg, width, height);
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>{style}</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n<p>{events} events on {lanes} hosts over {end:?}</p>\n\
+             {svg}</body>\n</html>\n",
+            title = escape(title),
+            style = STYLE,
+            events = self.points.len(),
+            lanes = self.lanes.len(),
+            end = self.end(),
+            svg = svg,
+        )
+    }
+
+    /// Writes [`Timeline::to_html`] to `path`.
+    pub fn write_html(&self, path: impl AsRef<Path>, title: &str) -> Result<()> {
+        fs::write(path, self.to_html(title))?;
+        Ok(())
+    }
+
+    fn lane(&mut self, host: &str) -> usize {
+        match self.lanes.iter().position(|lane| lane == host) {
+            Some(index) => index,
+            None => {
+                self.lanes.push(host.to_string());
+                self.lanes.len() - 1
+            }
+        }
+    }
+
+    fn write_svg(&self, out: &mut String, width: usize, height: usize) -> fmt::Result {
+        let lane_x = |lane: usize| LEFT + lane * LANE_WIDTH + LANE_WIDTH / 2;
+        let row_y = |row: usize| TOP + (row + 1) * ROW_HEIGHT;
+        // Fault boundaries sit just above the first event at or after them.
+        let time_y = |at: Duration| {
+            let row = self.points.partition_point(|point| point.at < at);
+            row_y(row) - ROW_HEIGHT / 2
+        };
+
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\">",
+            w = width,
+            h = height
+        )?;
+        writeln!(
+            out,
+            "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+             markerWidth=\"7\" markerHeight=\"7\" orient=\"auto-start-reverse\">\
+             <path d=\"M 0 0 L 10 5 L 0 10 z\"/></marker></defs>"
+        )?;
+
+        for span in &self.spans {
+            let top = time_y(span.start);
+            let bottom = span.end.map_or(height - 20, time_y).max(top + 3);
+            let until = span
+                .end
+                .map_or("end of run".to_string(), |end| format!("{:?}", end));
+            for lane in &span.lanes {
+                let x = lane_x(*lane) - LANE_WIDTH / 2 + 6;
+                writeln!(
+                    out,
+                    "<g class=\"fault {kind}\"><title>{label} ({start:?} to {until})</title>\
+                     <rect x=\"{x}\" y=\"{y}\" width=\"{w}\" height=\"{h}\"/>\
+                     <text x=\"{tx}\" y=\"{ty}\">{short}</text></g>",
+                    kind = span.kind,
+                    label = escape(&span.label),
+                    start = span.start,
+                    until = until,
+                    x = x,
+                    y = top,
+                    w = LANE_WIDTH - 12,
+                    h = bottom - top,
+                    tx = x + 4,
+                    ty = top + 12,
+                    short = escape(&truncate(&span.label)),
+                )?;
+            }
+        }
+
+        for (lane, name) in self.lanes.iter().enumerate() {
+            let x = lane_x(lane);
+            writeln!(
+                out,
+                "<text class=\"host\" x=\"{x}\" y=\"{y}\">{name}</text>\
+                 <line class=\"lifeline\" x1=\"{x}\" y1=\"{top}\" x2=\"{x}\" y2=\"{bottom}\"/>",
+                x = x,
+                y = TOP - 12,
+                name = escape(name),
+                top = TOP,
+                bottom = height - 20,
+            )?;
+        }
+
+        let mut previous = None;
+        for (row, point) in self.points.iter().enumerate() {
+            if previous != Some(point.at) {
+                writeln!(
+                    out,
+                    "<text class=\"time\" x=\"{x}\" y=\"{y}\">{at:?}</text>",
+                    x = LEFT - 10,
+                    y = row_y(row) + 4,
+                    at = point.at,
+                )?;
+                previous = Some(point.at);
+            }
+        }
+
+        for arrow in &self.arrows {
+            let from = &self.points[arrow.from];
+            let (x1, y1) = (lane_x(from.lane), row_y(arrow.from));
+            let label = escape(from.mark.label());
+            match arrow.to {
+                Some(to) => {
+                    let (x2, y2) = (lane_x(self.points[to].lane), row_y(to));
+                    writeln!(
+                        out,
+                        "<g class=\"message\"><title>{label}</title>\
+                         <line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" \
+                         marker-end=\"url(#arrow)\"/></g>",
+                        label = label,
+                        x1 = x1,
+                        y1 = y1,
+                        x2 = x2,
+                        y2 = y2,
+                    )?;
+                }
+                None => {
+                    writeln!(
+                        out,
+                        "<g class=\"message lost\"><title>{label} (never received)</title>\
+                         <line x1=\"{x1}\" y1=\"{y}\" x2=\"{x2}\" y2=\"{y}\"/>\
+                         <text x=\"{tx}\" y=\"{ty}\">&#x2717;</text></g>",
+                        label = label,
+                        x1 = x1,
+                        x2 = x1 + 40,
+                        y = y1,
+                        tx = x1 + 42,
+                        ty = y1 + 4,
+                    )?;
+                }
+            }
+        }
+
+        for (row, point) in self.points.iter().enumerate() {
+            let (x, y) = (lane_x(point.lane), row_y(row));
+            writeln!(
+                out,
+                "<g class=\"point {category}\"><title>{detail}</title>\
+                 <circle cx=\"{x}\" cy=\"{y}\" r=\"3\"/>\
+                 <text x=\"{tx}\" y=\"{ty}\">{label}</text></g>",
+                category = point.mark.category(),
+                detail = escape(&point.detail),
+                x = x,
+                y = y,
+                tx = x + 6,
+                ty = y - 4,
+                label = escape(&truncate(point.mark.label())),
+            )?;
+        }
+
+        writeln!(out, "</svg>")
+    }
+}
+
+/// Classifies a fault into the interval kind it opens or closes.
+fn effect(fault: &Fault) -> (&'static str, Effect) {
+    let level = |value: u64, label: String| {
+        if value == 0 {
+            Effect::Close
+        } else {
+            Effect::Open(label)
+        }
+    };
+    match fault {
+        Fault::Partition { node_a, node_b } => (
+            "partition",
+            Effect::Open(format!("partition {} | {}", node_a, node_b)),
+        ),
+        Fault::Repair { .. } => ("partition", Effect::Close),
+        Fault::Crash { .. } => ("crash", Effect::Open("crashed".to_string())),
+        // Ends any crash interval, and marks the restart itself.
+        Fault::Restart { .. } => ("crash", Effect::Instant("restart".to_string())),
+        Fault::Latency { delay_ms, .. } | Fault::LinkLatency { delay_ms, .. } => (
+            "latency",
+            level(*delay_ms, format!("latency {}ms", delay_ms)),
+        ),
+        Fault::MessageLoss { percent, .. } => (
+            "loss",
+            level(u64::from(*percent), format!("loss {}%", percent)),
+        ),
+        Fault::Duplicate { percent, .. } => (
+            "duplicate",
+            level(u64::from(*percent), format!("duplicate {}%", percent)),
+        ),
+        Fault::Reorder { max_delay_ms, .. } => (
+            "reorder",
+            level(*max_delay_ms, format!("reorder 0-{}ms", max_delay_ms)),
+        ),
+        Fault::Bandwidth { bytes_per_sec, .. } => (
+            "bandwidth",
+            level(*bytes_per_sec, format!("bandwidth {}B/s", bytes_per_sec)),
+        ),
+        Fault::ClockOffset { offset_ms, .. } => (
+            "clock",
+            level(offset_ms.unsigned_abs(), format!("clock {:+}ms", offset_ms)),
+        ),
+        Fault::ClockDrift { ppm, .. } => (
+            "drift",
+            level(u64::from(ppm.unsigned_abs()), format!("drift {:+}ppm", ppm)),
+        ),
+        Fault::ClockJump { delta_ms, .. } => (
+            "jump",
+            Effect::Instant(format!("clock jump {:+}ms", delta_ms)),
+        ),
+    }
+}
+
+fn micros(at: Duration) -> u64 {
+    u64::try_from(at.as_micros()).unwrap_or(u64::MAX)
+}
+
+fn truncate(label: &str) -> String {
+    match label.char_indices().nth(MAX_LABEL) {
+        Some((cut, _)) => format!("{}…", &label[..cut]),
+        None => label.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 16px; }
+svg { font-size: 11px; }
+.host { font-weight: bold; text-anchor: middle; font-size: 13px; }
+.lifeline { stroke: #888; stroke-dasharray: 4 3; }
+.time { fill: #666; text-anchor: end; }
+.point circle { fill: #333; }
+.point.send circle { fill: #1f5fbf; }
+.point.receive circle { fill: #2e8b57; }
+.message line { stroke: #1f5fbf; }
+.message.lost line { stroke: #c0392b; stroke-dasharray: 3 2; }
+.message.lost text { fill: #c0392b; }
+.fault rect { fill-opacity: 0.35; }
+.fault text { fill: #444; font-size: 10px; }
+.fault.partition rect { fill: #e74c3c; }
+.fault.crash rect { fill: #555; }
+.fault.latency rect { fill: #f1c40f; }
+.fault.loss rect { fill: #e67e22; }
+.fault.duplicate rect { fill: #27ae60; }
+.fault.reorder rect { fill: #3498db; }
+.fault.bandwidth rect { fill: #8e44ad; }
+.fault.clock rect { fill: #16a085; }
+.fault.drift rect { fill: #95a5a6; }
+.fault.jump rect { fill: #d35400; }
+";
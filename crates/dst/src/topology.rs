@@ -0,0 +1,398 @@
+//! Network topology model.
+//!
+//! `Builder::min_message_latency` and `max_message_latency` give every link
+//! in a simulation the same latency range. A [`Topology`] instead places
+//! nodes in named zones, such as regions or racks, and describes each link
+//! between two zones with a [`Link`]: a [`LatencyDistribution`], a loss rate
+//! and a bandwidth cap.
+//!
+//! [`Topology::schedule`] compiles the description into a fault scenario
+//! that [`FaultDriver`](crate::FaultDriver) applies like any other:
+//!
+//! - one [`Fault::LinkLatency`] per link and sample, drawn again from the
+//!   link's distribution every [`Topology::resample_every`];
+//! - a [`Fault::MessageLoss`] at time zero for lossy links;
+//! - a [`Fault::Bandwidth`] at time zero for capped links, observed by hosts
+//!   receiving through a [`FaultyUdpSocket`](crate::FaultyUdpSocket).
+//!
+//! Link latency is step-sampled: every message sent on a link within one
+//! resample window, 100ms by default, takes the same latency. The
+//! distribution shows across windows, not between back-to-back messages.
+//! Shorten the window for finer-grained jitter at the cost of more faults.
+//!
+//! Bandwidth caps are not enforced by turmoil. Only datagrams received
+//! through a `FaultyUdpSocket` wait for the link; TCP streams and plain
+//! `UdpSocket`s on a capped link run at full speed.
+//!
+//! Hosts outside every zone, such as test clients, use the default link
+//! through turmoil's global latency range, which
+//! [`Topology::configure`] sets.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//! use dst::{Fault, LatencyDistribution, Link, Topology};
+//!
+//! let ms = Duration::from_millis;
+//! let topology = Topology::new(Link::new(LatencyDistribution::constant(ms(1))))
+//!     .zone("us-east", ["a1", "a2"])
+//!     .zone("eu-west", ["b1"])
+//!     .link(
+//!         "us-east",
+//!         "eu-west",
+//!         Link::new(LatencyDistribution::LongTail { median: ms(40), p99: ms(150) })
+//!             .loss_percent(1),
+//!     );
+//!
+//! let schedule = topology.schedule(7, Duration::from_secs(10));
+//! assert!(schedule.contains(&(
+//!     Duration::ZERO,
+//!     Fault::MessageLoss { node_a: "a1".into(), node_b: "b1".into(), percent: 1 },
+//! )));
+//! assert_eq!(schedule, topology.schedule(7, Duration::from_secs(10)));
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::entropy::Entropy;
+use crate::fault::{Fault, Scenario};
+
+/// Host name under which link latencies draw from [`Entropy`].
+const TOPOLOGY_HOST: &str = "topology";
+
+/// Default interval between latency samples.
+const DEFAULT_RESAMPLE: Duration = Duration::from_millis(100);
+
+/// Standard normal quantile of the 99th percentile.
+const Z_99: f64 = 2.326_347_874;
+
+/// How the one-way latency of a link is distributed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyDistribution {
+    /// Every latency between `min` and `max` is equally likely.
+    Uniform {
+        /// Smallest latency.
+        min: Duration,
+        /// Largest latency.
+        max: Duration,
+    },
+    /// Normally distributed around `mean`, clamped at zero.
+    Normal {
+        /// Mean latency.
+        mean: Duration,
+        /// Standard deviation.
+        std_dev: Duration,
+    },
+    /// Log-normal: most samples sit near `median`, with a long tail that
+    /// reaches `p99` once in a hundred samples. Typical of WAN links.
+    LongTail {
+        /// Median latency.
+        median: Duration,
+        /// 99th percentile latency.
+        p99: Duration,
+    },
+}
+
+impl LatencyDistribution {
+    /// A fixed latency.
+    pub fn constant(latency: Duration) -> Self {
+        LatencyDistribution::Uniform {
+            min: latency,
+            max: latency,
+        }
+    }
+
+    /// Draws one latency.
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        match *self {
+            LatencyDistribution::Uniform { min, max } => {
+                if max <= min {
+                    return min;
+                }
+                let nanos = rng.random_range(min.as_nanos()..=max.as_nanos());
+                Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+            }
+            LatencyDistribution::Normal { mean, std_dev } => {
+                let latency = mean.as_secs_f64() + std_dev.as_secs_f64() * standard_normal(rng);
+                Duration::from_secs_f64(latency.max(0.0))
+            }
+            LatencyDistribution::LongTail { median, p99 } => {
+                if median.is_zero() {
+                    return median;
+                }
+                let sigma = (p99.as_secs_f64() / median.as_secs_f64()).ln().max(0.0) / Z_99;
+                Duration::from_secs_f64(median.as_secs_f64() * (sigma * standard_normal(rng)).exp())
+            }
+        }
+    }
+
+    /// Returns `true` if every sample is the same.
+    pub fn is_constant(&self) -> bool {
+        match *self {
+            LatencyDistribution::Uniform { min, max } => max <= min,
+            LatencyDistribution::Normal { std_dev, .. } => std_dev.is_zero(),
+            LatencyDistribution::LongTail { median, p99 } => p99 <= median,
+        }
+    }
+
+    /// The range most samples fall in, for turmoil's global latency range:
+    /// the bounds of a uniform distribution, two standard deviations around
+    /// a normal mean, and the median to the 99th percentile of a long tail.
+    pub fn typical_range(&self) -> (Duration, Duration) {
+        match *self {
+            LatencyDistribution::Uniform { min, max } => (min, max.max(min)),
+            LatencyDistribution::Normal { mean, std_dev } => {
+                (mean.saturating_sub(std_dev * 2), mean + std_dev * 2)
+            }
+            LatencyDistribution::LongTail { median, p99 } => (median, p99.max(median)),
+        }
+    }
+}
+
+/// Box-Muller transform; `rand` itself ships no normal distribution.
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    // `1 - u` keeps the logarithm's argument in (0, 1].
+    let u1: f64 = 1.0 - rng.random::<f64>();
+    let u2: f64 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+/// Properties of the links between two zones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Link {
+    /// One-way latency distribution.
+    pub latency: LatencyDistribution,
+    /// Chance, in percent, that a message is lost.
+    pub loss_percent: u8,
+    /// Throughput cap in bytes per second, if any. Only enforced on
+    /// datagrams received through a [`FaultyUdpSocket`](crate::FaultyUdpSocket).
+    pub bandwidth: Option<u64>,
+}
+
+impl Link {
+    /// A lossless, uncapped link with the given latency.
+    pub fn new(latency: LatencyDistribution) -> Self {
+        Link {
+            latency,
+            loss_percent: 0,
+            bandwidth: None,
+        }
+    }
+
+    /// Loses `percent` of messages. Values above 100 are treated as 100.
+    pub fn loss_percent(mut self, percent: u8) -> Self {
+        self.loss_percent = percent.min(100);
+        self
+    }
+
+    /// Caps throughput at `bytes_per_sec`. Zero removes the cap.
+    ///
+    /// The cap only delays datagrams that the receiving host reads through a
+    /// [`FaultyUdpSocket`](crate::FaultyUdpSocket); other traffic on the link
+    /// is unaffected.
+    pub fn bandwidth(mut self, bytes_per_sec: u64) -> Self {
+        self.bandwidth = (bytes_per_sec > 0).then_some(bytes_per_sec);
+        self
+    }
+}
+
+/// Nodes grouped into zones, and the links between them.
+///
+/// Latencies are sampled once per [`resample_every`](Self::resample_every)
+/// window and bandwidth caps only reach
+/// [`FaultyUdpSocket`](crate::FaultyUdpSocket) receivers; see the
+/// [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Topology {
+    zones: Vec<(String, Vec<String>)>,
+    links: BTreeMap<(String, String), Link>,
+    default_link: Link,
+    resample_every: Duration,
+}
+
+impl Topology {
+    /// Creates an empty topology whose links default to `default_link`.
+    pub fn new(default_link: Link) -> Self {
+        Topology {
+            zones: Vec::new(),
+            links: BTreeMap::new(),
+            default_link,
+            resample_every: DEFAULT_RESAMPLE,
+        }
+    }
+
+    /// Adds a zone, such as a region or a rack, holding `nodes`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the zone or one of the nodes was already added.
+    pub fn zone<I, S>(mut self, name: impl Into<String>, nodes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let name = name.into();
+        assert!(
+            !self.zones.iter().any(|(zone, _)| *zone == name),
+            "zone {} added twice",
+            name
+        );
+        let nodes: Vec<String> = nodes.into_iter().map(Into::into).collect();
+        for node in &nodes {
+            assert!(
+                self.zone_of(node).is_none(),
+                "node {} is already in zone {}",
+                node,
+                self.zone_of(node).unwrap_or_default()
+            );
+        }
+        self.zones.push((name, nodes));
+        self
+    }
+
+    /// Sets the links between nodes of `zone_a` and nodes of `zone_b`. Pass
+    /// the same zone twice for the links inside it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either zone has not been added.
+    pub fn link(mut self, zone_a: &str, zone_b: &str, link: Link) -> Self {
+        for zone in [zone_a, zone_b] {
+            assert!(
+                self.zones.iter().any(|(name, _)| name == zone),
+                "unknown zone {}",
+                zone
+            );
+        }
+        self.links.insert(zone_pair(zone_a, zone_b), link);
+        self
+    }
+
+    /// Sets how often link latencies are drawn again. Defaults to 100ms;
+    /// zero draws each link's latency once.
+    ///
+    /// Messages sent between two samples share one latency, so a shorter
+    /// interval gives finer jitter and a longer schedule.
+    pub fn resample_every(mut self, interval: Duration) -> Self {
+        self.resample_every = interval;
+        self
+    }
+
+    /// Every node, zone by zone, in the order they were added.
+    pub fn nodes(&self) -> Vec<String> {
+        self.zones
+            .iter()
+            .flat_map(|(_, nodes)| nodes.iter().cloned())
+            .collect()
+    }
+
+    /// The zone holding `node`.
+    pub fn zone_of(&self, node: &str) -> Option<&str> {
+        self.zones
+            .iter()
+            .find(|(_, nodes)| nodes.iter().any(|n| n == node))
+            .map(|(zone, _)| zone.as_str())
+    }
+
+    /// The link between two nodes: the one set for their zones, or the
+    /// default.
+    pub fn link_between(&self, node_a: &str, node_b: &str) -> &Link {
+        match (self.zone_of(node_a), self.zone_of(node_b)) {
+            (Some(a), Some(b)) => self
+                .links
+                .get(&zone_pair(a, b))
+                .unwrap_or(&self.default_link),
+            _ => &self.default_link,
+        }
+    }
+
+    /// Compiles the topology into a fault scenario covering `duration`.
+    ///
+    /// Each link draws from its own [`Entropy`] stream, so the same seed
+    /// always yields the same schedule and adding a node does not change the
+    /// samples of existing links. A latency fault is only emitted when the
+    /// sampled value, in whole milliseconds, changes.
+    pub fn schedule(&self, seed: u64, duration: Duration) -> Scenario {
+        let entropy = Entropy::new(seed);
+        let nodes = self.nodes();
+        let mut scenario = Vec::new();
+
+        for (index, node_a) in nodes.iter().enumerate() {
+            for node_b in &nodes[index + 1..] {
+                let link = self.link_between(node_a, node_b);
+                let (a, b) = (node_a.clone(), node_b.clone());
+                if link.loss_percent > 0 {
+                    scenario.push((
+                        Duration::ZERO,
+                        Fault::MessageLoss {
+                            node_a: a.clone(),
+                            node_b: b.clone(),
+                            percent: link.loss_percent,
+                        },
+                    ));
+                }
+                if let Some(bytes_per_sec) = link.bandwidth {
+                    scenario.push((
+                        Duration::ZERO,
+                        Fault::Bandwidth {
+                            node_a: a.clone(),
+                            node_b: b.clone(),
+                            bytes_per_sec,
+                        },
+                    ));
+                }
+
+                let mut rng = entropy.rng(TOPOLOGY_HOST, &format!("link {} {}", a, b));
+                let mut last = None;
+                let mut at = Duration::ZERO;
+                loop {
+                    let delay_ms = millis(link.latency.sample(&mut rng));
+                    if last != Some(delay_ms) {
+                        scenario.push((
+                            at,
+                            Fault::LinkLatency {
+                                node_a: a.clone(),
+                                node_b: b.clone(),
+                                delay_ms,
+                            },
+                        ));
+                        last = Some(delay_ms);
+                    }
+                    at += self.resample_every;
+                    if link.latency.is_constant() || self.resample_every.is_zero() || at >= duration
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+
+        scenario.sort_by_key(|(at, _)| *at);
+        scenario
+    }
+
+    /// Sets turmoil's global latency range to the default link's
+    /// [`typical_range`](LatencyDistribution::typical_range), for hosts
+    /// outside every zone.
+    #[cfg(feature = "simulation")]
+    pub fn configure<'b>(&self, builder: &'b mut turmoil::Builder) -> &'b mut turmoil::Builder {
+        let (min, max) = self.default_link.latency.typical_range();
+        builder.min_message_latency(min).max_message_latency(max)
+    }
+}
+
+fn zone_pair(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+fn millis(latency: Duration) -> u64 {
+    u64::try_from(latency.as_millis()).unwrap_or(u64::MAX)
+}
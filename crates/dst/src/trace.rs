@@ -0,0 +1,315 @@
+//! Trace recording and first-divergence diffing.
+//!
+//! A [`TraceRecorder`] collects typed events from any number of simulated
+//! hosts. The resulting [`Trace`] can be written to disk as JSON Lines and
+//! compared against another run of the same seed with [`Trace::diff`],
+//! which reports the first event where the runs disagree.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//! use dst::TraceRecorder;
+//!
+//! let recorder = TraceRecorder::new();
+//! let server = recorder.host("server");
+//! server.record_at(Duration::from_millis(5), "accepted");
+//!
+//! let run1 = recorder.trace();
+//! let run2 = recorder.trace();
+//! assert!(run1.diff(&run2, 3).is_none());
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// One recorded event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEvent<E> {
+    /// Position of the event in the trace, starting at zero.
+    pub seq: u64,
+    /// Simulated time of the event, in microseconds.
+    pub at_us: u64,
+    /// Host that recorded the event.
+    pub host: String,
+    /// The event payload.
+    pub event: E,
+}
+
+impl<E> TraceEvent<E> {
+    /// Simulated time of the event.
+    pub fn at(&self) -> Duration {
+        Duration::from_micros(self.at_us)
+    }
+
+    /// Compares everything except `seq`, which is equal by construction when
+    /// two traces are walked in lockstep.
+    fn same_as(&self, other: &Self) -> bool
+    where
+        E: PartialEq,
+    {
+        self.at_us == other.at_us && self.host == other.host && self.event == other.event
+    }
+}
+
+/// Shared sink that hosts write events to.
+///
+/// Cheap to clone; every clone appends to the same trace.
+#[derive(Debug)]
+pub struct TraceRecorder<E> {
+    events: Arc<Mutex<Vec<TraceEvent<E>>>>,
+}
+
+impl<E> Clone for TraceRecorder<E> {
+    fn clone(&self) -> Self {
+        TraceRecorder {
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl<E> Default for TraceRecorder<E> {
+    fn default() -> Self {
+        TraceRecorder {
+            events: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl<E> TraceRecorder<E> {
+    /// Creates an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a handle that records events on behalf of `host`.
+    pub fn host(&self, host: impl Into<String>) -> HostTrace<E> {
+        HostTrace {
+            recorder: self.clone(),
+            host: host.into(),
+        }
+    }
+
+    /// Appends an event for `host` at simulated time `at`.
+    pub fn record_at(&self, at: Duration, host: impl Into<String>, event: E) {
+        let mut events = self.lock();
+        let seq = events.len() as u64;
+        events.push(TraceEvent {
+            seq,
+            at_us: u64::try_from(at.as_micros()).unwrap_or(u64::MAX),
+            host: host.into(),
+            event,
+        });
+    }
+
+    /// Appends an event for `host` at the current simulated time.
+    ///
+    /// Outside a running simulation the time is recorded as zero.
+    #[cfg(feature = "simulation")]
+    pub fn record(&self, host: impl Into<String>, event: E) {
+        let at = turmoil::sim_elapsed().unwrap_or_default();
+        self.record_at(at, host, event);
+    }
+
+    /// Number of events recorded so far.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Snapshot of the events recorded so far.
+    pub fn trace(&self) -> Trace<E>
+    where
+        E: Clone,
+    {
+        Trace {
+            events: self.lock().clone(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<TraceEvent<E>>> {
+        self.events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A [`TraceRecorder`] bound to one host name.
+#[derive(Debug)]
+pub struct HostTrace<E> {
+    recorder: TraceRecorder<E>,
+    host: String,
+}
+
+impl<E> Clone for HostTrace<E> {
+    fn clone(&self) -> Self {
+        HostTrace {
+            recorder: self.recorder.clone(),
+            host: self.host.clone(),
+        }
+    }
+}
+
+impl<E> HostTrace<E> {
+    /// The host this handle records for.
+    pub fn name(&self) -> &str {
+        &self.host
+    }
+
+    /// Appends an event at simulated time `at`.
+    pub fn record_at(&self, at: Duration, event: E) {
+        self.recorder.record_at(at, self.host.clone(), event);
+    }
+
+    /// Appends an event at the current simulated time.
+    #[cfg(feature = "simulation")]
+    pub fn record(&self, event: E) {
+        self.recorder.record(self.host.clone(), event);
+    }
+}
+
+/// An ordered list of recorded events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace<E> {
+    events: Vec<TraceEvent<E>>,
+}
+
+impl<E> Trace<E> {
+    /// The recorded events, in order.
+    pub fn events(&self) -> &[TraceEvent<E>] {
+        &self.events
+    }
+
+    /// Returns the first event where `self` and `other` disagree, with up to
+    /// `context` events before and after it, or `None` if they are identical.
+    ///
+    /// A trace that is a strict prefix of the other diverges at the first
+    /// missing event.
+    pub fn diff<'a>(&'a self, other: &'a Trace<E>, context: usize) -> Option<Divergence<'a, E>>
+    where
+        E: PartialEq,
+    {
+        let index = self
+            .events
+            .iter()
+            .zip(&other.events)
+            .position(|(left, right)| !left.same_as(right))
+            .or_else(|| {
+                (self.events.len() != other.events.len())
+                    .then(|| self.events.len().min(other.events.len()))
+            })?;
+
+        let after = |events: &'a [TraceEvent<E>]| {
+            let end = (index + context + 1).min(events.len());
+            &events[index.min(events.len())..end]
+        };
+
+        Some(Divergence {
+            index,
+            context: &self.events[index.saturating_sub(context)..index],
+            left: after(&self.events),
+            right: after(&other.events),
+        })
+    }
+
+    /// Writes the trace as JSON Lines, one event per line.
+    pub fn write_jsonl(&self, path: impl AsRef<Path>) -> Result<()>
+    where
+        E: Serialize,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for event in &self.events {
+            serde_json::to_writer(&mut writer, event)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads a trace written by [`Trace::write_jsonl`]. Blank lines are
+    /// skipped.
+    pub fn read_jsonl(path: impl AsRef<Path>) -> Result<Self>
+    where
+        E: DeserializeOwned,
+    {
+        let reader = BufReader::new(File::open(path)?);
+        let mut events = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            events.push(serde_json::from_str(&line)?);
+        }
+        Ok(Trace { events })
+    }
+}
+
+impl<E> From<Vec<TraceEvent<E>>> for Trace<E> {
+    fn from(events: Vec<TraceEvent<E>>) -> Self {
+        Trace { events }
+    }
+}
+
+/// The first point where two traces disagree.
+#[derive(Debug)]
+pub struct Divergence<'a, E> {
+    /// Index of the first differing event.
+    pub index: usize,
+    /// Matching events immediately before the divergence.
+    pub context: &'a [TraceEvent<E>],
+    /// The left trace from the divergence on. Empty if it ended early.
+    pub left: &'a [TraceEvent<E>],
+    /// The right trace from the divergence on. Empty if it ended early.
+    pub right: &'a [TraceEvent<E>],
+}
+
+impl<E: fmt::Debug> fmt::Display for Divergence<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "traces diverge at event #{}", self.index)?;
+        writeln!(f, "  common context:")?;
+        for event in self.context {
+            write_event(f, "    ", event)?;
+        }
+        for (label, side) in [("left", self.left), ("right", self.right)] {
+            writeln!(f, "  {}:", label)?;
+            if side.is_empty() {
+                writeln!(f, "  > <end of trace>")?;
+            }
+            for (offset, event) in side.iter().enumerate() {
+                let marker = if offset == 0 { "  > " } else { "    " };
+                write_event(f, marker, event)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_event<E: fmt::Debug>(
+    f: &mut fmt::Formatter<'_>,
+    prefix: &str,
+    event: &TraceEvent<E>,
+) -> fmt::Result {
+    writeln!(
+        f,
+        "{}#{} [{:?}] {}: {:?}",
+        prefix,
+        event.seq,
+        event.at(),
+        event.host,
+        event.event
+    )
+}
@@ -2,6 +2,9 @@
 //!
 //! This example demonstrates a complete DST test setup using turmoil.
 //!
+//! The hashing and seed helpers below are also available as the `dst` crate
+//! (`crates/dst`); prefer depending on it over copying them.
+//!
 //! ## Running
 //! ```bash
 //! # With random seed
@@ -174,6 +177,9 @@ fn test_echo_server() {
 // ============================================================================
 
 /// Shared state for tracking node status (simulates monitoring)
+///
+/// Counting heartbeats shows progress, not failure; `dst::FailureDetector`
+/// turns heartbeats into phi-accrual suspicion (skill.md, Pattern 8).
 #[derive(Debug, Clone, Default)]
 struct ClusterState {
     heartbeats: DetHashMap<String, u64>,
@@ -0,0 +1,290 @@
+//! Scenario tests for the replicated key-value store.
+//!
+//! Every scenario runs a three-node cluster and three clients over several
+//! seeds, checks after every step that no term has two leaders, and checks
+//! the clients' history against a sequential key-value store.
+//!
+//! Linearizability is compositional, so each key's history is checked on
+//! its own; that keeps the search small even when a bug makes it fail.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dst::linearizability::{HistoryRecorder, KvOp, KvRet, KvStore};
+use dst::turmoil::{Builder, Sim};
+use dst::{Entropy, Fault, FaultDriver, InvariantMonitor, SeedSweep, SimFs};
+use dst_kv::{Client, Command, Config, Error, Response, Role, Server};
+use rand::Rng;
+
+const NODES: [&str; 3] = ["node-a", "node-b", "node-c"];
+const PORT: u16 = 7000;
+const CLIENTS: [&str; 3] = ["client-1", "client-2", "client-3"];
+const KEYS: [&str; 2] = ["x", "y"];
+const OPS_PER_CLIENT: usize = 40;
+
+type Leaders = BTreeMap<u64, BTreeSet<String>>;
+type Recorder = HistoryRecorder<KvOp<String, String>, KvRet<String>>;
+
+/// Outcome of one scenario run.
+struct Run {
+    completed: usize,
+    timed_out: usize,
+}
+
+/// Runs the cluster and workload under `schedule` and checks safety.
+fn run_scenario(seed: u64, schedule: Vec<(Duration, Fault)>) -> Run {
+    let mut sim = Builder::new()
+        .rng_seed(seed)
+        .simulation_duration(Duration::from_secs(120))
+        .min_message_latency(Duration::from_millis(1))
+        .max_message_latency(Duration::from_millis(10))
+        .build();
+    let fs = SimFs::new(seed);
+    let entropy = Entropy::new(seed);
+    let leaders = Arc::new(Mutex::new(Leaders::new()));
+    let histories: Vec<Recorder> = KEYS.iter().map(|_| Recorder::new()).collect();
+
+    for node in NODES {
+        let (fs, leaders) = (fs.clone(), leaders.clone());
+        sim.host(node, move || {
+            let leaders = leaders.clone();
+            let server = Server::new(
+                Config::new(node, NODES, PORT),
+                fs.host(node),
+                entropy.host(node),
+            )
+            .observe(move |status| {
+                if status.role == Role::Leader {
+                    let mut leaders = leaders.lock().unwrap();
+                    leaders
+                        .entry(status.term)
+                        .or_default()
+                        .insert(status.id.clone());
+                }
+            });
+            async move { Ok(server.run().await?) }
+        });
+    }
+    for name in CLIENTS {
+        workload(&mut sim, name, entropy, histories.clone());
+    }
+
+    let mut driver = FaultDriver::new(NODES, schedule)
+        .with_seed(seed)
+        .with_fs(fs);
+    let mut monitor =
+        InvariantMonitor::new(seed, leaders).invariant("one leader per term", |leaders| {
+            match leaders.iter().find(|(_, ids)| ids.len() > 1) {
+                Some((term, ids)) => Err(format!("term {} has leaders {:?}", term, ids)),
+                None => Ok(()),
+            }
+        });
+    monitor
+        .run_with(&mut driver, &mut sim)
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    let mut run = Run {
+        completed: 0,
+        timed_out: 0,
+    };
+    for (key, recorder) in KEYS.iter().zip(&histories) {
+        let history = recorder.history();
+        if let Err(violation) = history.check(KvStore::new()) {
+            panic!("seed {}, key {}: {}", seed, key, violation);
+        }
+        let completed = history
+            .operations()
+            .iter()
+            .filter(|op| op.is_complete())
+            .count();
+        run.completed += completed;
+        run.timed_out += history.operations().len() - completed;
+    }
+    run
+}
+
+/// A client issuing random gets, puts and deletes on two keys.
+fn workload(sim: &mut Sim<'_>, name: &'static str, entropy: Entropy, histories: Vec<Recorder>) {
+    sim.client(name, async move {
+        let mut rng = entropy.rng(name, "workload");
+        let mut client = Client::connect(name, NODES, PORT).await?;
+
+        for i in 0..OPS_PER_CLIENT {
+            let k = rng.random_range(0..KEYS.len());
+            let (key, history) = (KEYS[k].to_string(), &histories[k]);
+            let (command, op) = match rng.random_range(0..3) {
+                0 => (Command::Get { key: key.clone() }, KvOp::Get(key)),
+                1 => {
+                    let value = format!("{}-{}", name, i);
+                    let command = Command::Put {
+                        key: key.clone(),
+                        value: value.clone(),
+                    };
+                    (command, KvOp::Put(key, value))
+                }
+                _ => (Command::Delete { key: key.clone() }, KvOp::Delete(key)),
+            };
+
+            let id = history.invoke(name, op);
+            match client.execute(command).await {
+                Ok(Response::Value(value)) => history.complete(id, KvRet::Value(value)),
+                Ok(_) => history.complete(id, KvRet::Ok),
+                // Indeterminate: the command may still commit later.
+                Err(Error::Timeout(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+            tokio::time::sleep(Duration::from_millis(rng.random_range(0..300))).await;
+        }
+        Ok(())
+    });
+}
+
+fn sweep(test_name: &str, scenario: fn(u64) -> Vec<(Duration, Fault)>) {
+    SeedSweep::new(test_name)
+        .seed_range(0..8)
+        .run(|seed| {
+            let run = run_scenario(seed, scenario(seed));
+            assert!(
+                run.completed >= run.timed_out * 4,
+                "seed {}: only {} of {} operations completed",
+                seed,
+                run.completed,
+                run.completed + run.timed_out
+            );
+        })
+        .assert_passed();
+}
+
+fn at(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+/// Cuts `node` off from the other two nodes.
+fn isolate(node: &str) -> Vec<Fault> {
+    NODES
+        .iter()
+        .filter(|other| **other != node)
+        .map(|other| Fault::Partition {
+            node_a: node.into(),
+            node_b: (*other).into(),
+        })
+        .collect()
+}
+
+fn heal(node: &str) -> Vec<Fault> {
+    NODES
+        .iter()
+        .filter(|other| **other != node)
+        .map(|other| Fault::Repair {
+            node_a: node.into(),
+            node_b: (*other).into(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_fault_free_cluster_completes_every_operation() {
+    for seed in 0..3 {
+        let run = run_scenario(seed, Vec::new());
+        assert_eq!(
+            run.completed,
+            CLIENTS.len() * OPS_PER_CLIENT,
+            "seed {}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn test_partitions_isolating_each_node_in_turn() {
+    // Whichever node leads is isolated at some point, forcing a new
+    // election while clients keep writing.
+    sweep("test_partitions_isolating_each_node_in_turn", |_| {
+        let mut schedule = Vec::new();
+        for (i, node) in NODES.iter().enumerate() {
+            let start = 500 + 1_500 * i as u64;
+            schedule.extend(isolate(node).into_iter().map(|f| (at(start), f)));
+            schedule.extend(heal(node).into_iter().map(|f| (at(start + 1_000), f)));
+        }
+        schedule
+    });
+}
+
+#[test]
+fn test_crashes_and_restarts_keep_committed_writes() {
+    // Restarts discard unsynced disk writes, so only what the servers
+    // fsynced survives.
+    sweep("test_crashes_and_restarts_keep_committed_writes", |seed| {
+        let victim = NODES[seed as usize % NODES.len()];
+        vec![
+            (
+                at(700),
+                Fault::Restart {
+                    node: "node-a".into(),
+                },
+            ),
+            (
+                at(1_500),
+                Fault::Restart {
+                    node: "node-b".into(),
+                },
+            ),
+            (
+                at(2_300),
+                Fault::Restart {
+                    node: "node-c".into(),
+                },
+            ),
+            (
+                at(3_000),
+                Fault::Crash {
+                    node: victim.into(),
+                },
+            ),
+            (
+                at(4_000),
+                Fault::Restart {
+                    node: victim.into(),
+                },
+            ),
+        ]
+    });
+}
+
+#[test]
+fn test_slow_and_lossy_links() {
+    sweep("test_slow_and_lossy_links", |seed| {
+        let slow = NODES[seed as usize % NODES.len()];
+        let mut schedule = vec![
+            (
+                at(500),
+                Fault::Latency {
+                    node: slow.into(),
+                    delay_ms: 120,
+                },
+            ),
+            (
+                at(3_000),
+                Fault::Latency {
+                    node: slow.into(),
+                    delay_ms: 0,
+                },
+            ),
+        ];
+        for (a, b) in [
+            ("node-a", "node-b"),
+            ("node-b", "node-c"),
+            ("node-a", "node-c"),
+        ] {
+            let loss = |percent| Fault::MessageLoss {
+                node_a: a.into(),
+                node_b: b.into(),
+                percent,
+            };
+            schedule.push((at(1_000), loss(20)));
+            schedule.push((at(2_500), loss(0)));
+        }
+        schedule
+    });
+}
@@ -0,0 +1,48 @@
+//! Runs every scenario file in `crates/dst-kv/scenarios/` over a seed sweep,
+//! as `dst-run` would.
+
+use std::path::{Path, PathBuf};
+
+use dst::runner::ScenarioSpec;
+use dst::SeedSweep;
+use dst_kv::scenarios::runner;
+
+fn scenario_files() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_scenario_files_are_valid() {
+    let files = scenario_files();
+    assert!(files.len() >= 4, "{:?}", files);
+    let runner = runner();
+    for path in files {
+        let spec =
+            ScenarioSpec::load(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        runner
+            .validate(&spec)
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    }
+}
+
+#[test]
+fn test_scenario_files_pass() {
+    for path in scenario_files() {
+        let spec = ScenarioSpec::load(&path).unwrap();
+        SeedSweep::new(format!("scenarios/{}", spec.name))
+            .seed_range(0..4)
+            .run(|seed| {
+                if let Err(error) = runner().run(&spec, seed) {
+                    panic!("{}: {}", path.display(), error);
+                }
+            })
+            .assert_passed();
+    }
+}
@@ -0,0 +1,291 @@
+//! Integration tests for the dst-lint rules.
+
+use std::path::Path;
+
+use dst_lint::{lint_path, lint_source, Diagnostic, Error, Rule};
+
+fn lint(source: &str) -> Vec<Diagnostic> {
+    lint_source(Path::new("lib.rs"), source).unwrap()
+}
+
+/// `(line, rule)` for each diagnostic.
+fn findings(source: &str) -> Vec<(usize, Rule)> {
+    lint(source).iter().map(|d| (d.line, d.rule)).collect()
+}
+
+#[test]
+fn test_time_rule_follows_imports() {
+    let source = r#"
+use std::time::{Instant, SystemTime as Wall};
+use tokio::time::Instant as SimInstant;
+
+fn a() { let _ = Instant::now(); }
+fn b() -> Wall { Wall::now() }
+fn c() { let _ = SimInstant::now(); }
+fn d() { let _ = std::time::Duration::from_secs(1); }
+fn e() { let _ = ::std::time::Instant::now(); }
+"#;
+    assert_eq!(
+        findings(source),
+        [(5, Rule::Time), (6, Rule::Time), (9, Rule::Time)]
+    );
+}
+
+#[test]
+fn test_hash_map_rule_only_flags_default_hasher() {
+    let source = r#"
+use std::collections::{HashMap, HashSet};
+use std::collections::*;
+
+struct Peers { by_id: HashMap<u64, String>, det: HashMap<u64, String, dst::DeterministicBuildHasher> }
+fn a() { let _ = HashMap::<u64, u64>::with_hasher(dst::DeterministicBuildHasher); }
+fn b() { let _ = HashSet::from([1, 2]); }
+fn c() { let _: dst::DetHashMap<u64, u64> = dst::det_hash_map(); }
+fn d() { let _: Vec<u8> = Vec::new(); let _ = BTreeMap::<u8, u8>::new(); }
+fn e() -> std::collections::hash_map::HashMap<u8, u8> { Default::default() }
+"#;
+    assert_eq!(
+        findings(source),
+        [(5, Rule::HashMap), (7, Rule::HashMap), (10, Rule::HashMap)]
+    );
+    assert!(lint(source)[0].message.contains("dst::DetHashMap"));
+}
+
+#[test]
+fn test_rand_and_thread_rules() {
+    let source = r#"
+use rand::thread_rng;
+use std::thread;
+use rand::{rngs::StdRng, SeedableRng};
+
+fn a() -> u64 { rand::random() }
+fn b() { let _ = thread_rng(); }
+fn c() { let _ = rand::rng(); }
+fn d() { let _ = StdRng::seed_from_u64(7); }
+fn e() { thread::spawn(|| {}); }
+fn f() { std::thread::scope(|_| {}); }
+fn g() { tokio::spawn(async {}); }
+"#;
+    assert_eq!(
+        findings(source),
+        [
+            (6, Rule::Rand),
+            (7, Rule::Rand),
+            (8, Rule::Rand),
+            (10, Rule::ThreadSpawn),
+            (11, Rule::ThreadSpawn),
+        ]
+    );
+}
+
+#[test]
+fn test_net_rule_allows_the_facade() {
+    let source = r#"
+use tokio::net::TcpListener;
+use dst::io::net::{TcpStream, UdpSocket};
+use std::net::SocketAddr;
+
+async fn a() { let _ = TcpListener::bind("0.0.0.0:80").await; }
+async fn b(addr: SocketAddr) { let _ = TcpStream::connect(addr).await; }
+async fn c() { let _ = tokio::net::UdpSocket::bind("0.0.0.0:0").await; }
+fn d(socket: &std::net::UdpSocket) {}
+async fn e() { let _ = UdpSocket::bind("0.0.0.0:0").await; }
+fn f(half: tokio::net::tcp::OwnedReadHalf) {}
+
+#[dst::allow(net)]
+async fn proxy() { let _ = TcpListener::bind("127.0.0.1:0").await; }
+"#;
+    assert_eq!(
+        findings(source),
+        [
+            (6, Rule::Net),
+            (8, Rule::Net),
+            (9, Rule::Net),
+            (11, Rule::Net)
+        ]
+    );
+    assert!(lint(source)[0].message.contains("dst::io::net"));
+}
+
+#[test]
+fn test_unordered_iteration() {
+    let source = r#"
+use std::collections::{BTreeMap, HashMap};
+
+struct Node { peers: HashMap<String, u64>, log: BTreeMap<u64, String> }
+
+impl Node {
+    fn broadcast(&self) {
+        for peer in self.peers.keys() {}
+        for entry in self.log.values() {}
+    }
+}
+
+#[dst::allow(hash_map)]
+fn tally(votes: &HashMap<String, u64>) -> u64 {
+    let mut seen = HashMap::new();
+    seen.insert(1, 2);
+    for (_, count) in votes {}
+    let _ = seen.get(&1);
+    seen.drain().count() as u64
+}
+"#;
+    assert_eq!(
+        findings(source),
+        [
+            (4, Rule::HashMap),
+            (8, Rule::UnorderedIter),
+            (17, Rule::UnorderedIter),
+            (19, Rule::UnorderedIter),
+        ]
+    );
+}
+
+#[test]
+fn test_unordered_iteration_is_scoped() {
+    let source = r#"
+use std::collections::HashMap;
+
+#[dst::allow(hash_map)]
+fn tally(peers: HashMap<String, u64>) -> usize { peers.len() }
+
+fn broadcast(peers: Vec<String>) {
+    for peer in peers.iter() {}
+    let votes = vec![1, 2];
+    for vote in &votes {}
+}
+
+#[dst::allow(hash_map)]
+fn shadowed() {
+    let votes = HashMap::<u8, u8>::new();
+    let votes: Vec<u8> = votes.into_keys().collect();
+    for vote in votes.iter() {}
+    { let votes = 1; }
+}
+
+struct Cluster { peers: Vec<String> }
+#[dst::allow(hash_map)]
+struct Index { peers: HashMap<String, u64> }
+
+impl Cluster {
+    fn send(&self) { for peer in self.peers.iter() {} }
+}
+"#;
+    assert_eq!(findings(source), [(16, Rule::UnorderedIter)]);
+}
+
+#[test]
+fn test_unordered_iteration_forms() {
+    let source = r#"
+use std::collections::{HashMap, HashSet};
+
+#[dst::allow(hash_map)]
+mod forms {
+    fn default_map() { let m = HashMap::<u8, u8>::default(); for _ in m.keys() {} }
+    fn collected(v: Vec<(u8, u8)>) { let m = v.into_iter().collect::<HashMap<_, _>>(); for _ in &m {} }
+    fn generic<K, V>(m: &HashMap<K, V>) { for _ in m.values() {} }
+    fn closure() { let f = |s: HashSet<u8>| { for _ in s {} }; }
+    fn annotated() { let m: dst::DetHashMap<u8, u8> = HashMap::default(); for _ in m.keys() {} }
+}
+"#;
+    assert_eq!(
+        findings(source),
+        [
+            (6, Rule::UnorderedIter),
+            (7, Rule::UnorderedIter),
+            (8, Rule::UnorderedIter),
+            (9, Rule::UnorderedIter),
+        ]
+    );
+}
+
+#[test]
+fn test_macro_arguments_are_checked() {
+    let source = r#"
+fn a() { println!("{:?}", std::time::Instant::now()); }
+fn b() { let _ = vec![rand::random::<u8>()]; }
+"#;
+    assert_eq!(findings(source), [(2, Rule::Time), (3, Rule::Rand)]);
+}
+
+#[test]
+fn test_allow_attribute_scopes() {
+    let source = r#"
+#[dst::allow(thread_spawn)]
+fn exporter() {
+    std::thread::spawn(|| {});
+    let _ = std::time::SystemTime::now();
+}
+
+#[dst::allow(all)]
+mod tooling {
+    fn now() -> std::time::Instant { std::time::Instant::now() }
+}
+
+struct Sweep;
+
+impl Sweep {
+    #[dst::allow(thread_spawn, rand)]
+    fn run(&self) { std::thread::scope(|_| {}); let _ = rand::random::<u8>(); }
+
+    fn seed(&self) -> u64 { rand::random() }
+}
+"#;
+    assert_eq!(findings(source), [(5, Rule::Time), (19, Rule::Rand)]);
+}
+
+#[test]
+fn test_invalid_input_is_an_error() {
+    let err = lint_source(Path::new("x.rs"), "#[dst::allow(clock)] fn a() {}").unwrap_err();
+    assert!(matches!(err, Error::Parse { line: 1, .. }), "{}", err);
+    assert!(err.to_string().contains("unknown dst-lint rule `clock`"));
+
+    let err = lint_source(Path::new("x.rs"), "fn a( {").unwrap_err();
+    assert!(matches!(err, Error::Parse { .. }));
+}
+
+#[test]
+fn test_lint_path_walks_directories_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |rel: &str, source: &str| {
+        let path = dir.path().join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    };
+    write("src/b.rs", "fn b() { rand::random::<u8>(); }");
+    write("src/a.rs", "fn a() { std::thread::spawn(|| {}); }");
+    write("src/notes.txt", "std::time::Instant::now()");
+    write("target/debug/build.rs", "fn t() { rand::random::<u8>(); }");
+
+    let diagnostics = lint_path(dir.path()).unwrap();
+    let report: Vec<String> = diagnostics
+        .iter()
+        .map(|d| {
+            let rel = d.path.strip_prefix(dir.path()).unwrap();
+            format!("{}:{}:{} {}", rel.display(), d.line, d.column, d.rule)
+        })
+        .collect();
+    assert_eq!(report, ["src/a.rs:1:10 thread_spawn", "src/b.rs:1:10 rand"]);
+}
+
+/// Compiles only because `#[dst::allow]` is a real attribute.
+#[dst::allow(thread_spawn)]
+fn spawn_and_join() -> u32 {
+    std::thread::spawn(|| 7).join().unwrap()
+}
+
+#[dst::allow(all)]
+mod inline_module {
+    pub fn wall_clock_is_set() -> bool {
+        std::time::SystemTime::now() > std::time::UNIX_EPOCH
+    }
+}
+
+#[test]
+fn test_allow_attribute_compiles_and_silences_this_file() {
+    assert_eq!(spawn_and_join(), 7);
+    assert!(inline_module::wall_clock_is_set());
+
+    let this_file = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/dst-lint/rules.rs");
+    assert_eq!(lint_path(Path::new(this_file)).unwrap(), []);
+}
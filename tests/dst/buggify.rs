@@ -0,0 +1,180 @@
+//! Integration tests for buggify fault-injection sites.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use dst::buggify::Site;
+use dst::turmoil::Builder;
+use dst::{Buggify, BuggifyEvent, TraceRecorder, BUGGIFY_HOST};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Event {
+    Buggify(BuggifyEvent),
+    Handled(u32),
+}
+
+impl From<BuggifyEvent> for Event {
+    fn from(event: BuggifyEvent) -> Self {
+        Event::Buggify(event)
+    }
+}
+
+fn first() -> bool {
+    dst::buggify!()
+}
+
+fn second() -> bool {
+    dst::buggify!()
+}
+
+fn never() -> bool {
+    dst::buggify!(0)
+}
+
+/// Passes each of eight distinct sites `passes` times and returns what fired.
+fn exercise(buggify: &Buggify, passes: usize) -> BTreeMap<Site, u64> {
+    let _enabled = buggify.enable();
+    for _ in 0..passes {
+        let _ = [
+            dst::buggify!(),
+            dst::buggify!(),
+            dst::buggify!(),
+            dst::buggify!(),
+            dst::buggify!(),
+            dst::buggify!(),
+            dst::buggify!(),
+            dst::buggify!(),
+        ];
+    }
+    buggify.fired()
+}
+
+#[test]
+fn test_sites_do_nothing_until_enabled() {
+    assert!((0..100).all(|_| !first()));
+
+    let buggify = Buggify::new(1).activation_percent(100).fire_percent(100);
+    {
+        let _enabled = buggify.enable();
+        assert!(first());
+    }
+    assert!(!first());
+    assert_eq!(buggify.fired().values().sum::<u64>(), 1);
+}
+
+#[test]
+fn test_active_sites_fire_and_overrides_apply() {
+    let buggify = Buggify::new(1).activation_percent(100).fire_percent(100);
+    let _enabled = buggify.enable();
+
+    assert!(first() && second());
+    assert!(!never());
+    for len in [1, 2, 64] {
+        let shrunk = dst::buggify_shrink!(len);
+        assert!((1..=len).contains(&shrunk), "{} shrunk to {}", len, shrunk);
+    }
+    assert_eq!(dst::buggify_shrink!(0), 0);
+
+    let sites = buggify.sites();
+    assert!(sites.values().all(|active| *active));
+    assert!(sites.keys().all(|site| site.file.ends_with("buggify.rs")));
+    assert_eq!(buggify.fired().len(), sites.len() - 1);
+}
+
+#[test]
+fn test_inactive_sites_never_fire() {
+    let buggify = Buggify::new(1).activation_percent(0).fire_percent(100);
+    let fired = exercise(&buggify, 100);
+    assert!(fired.is_empty());
+    assert_eq!(buggify.sites().len(), 8);
+}
+
+#[test]
+fn test_decisions_are_reproducible_per_seed() {
+    let runs: Vec<_> = (0..16)
+        .map(|seed| exercise(&Buggify::new(seed), 40))
+        .collect();
+    for (seed, fired) in runs.iter().enumerate() {
+        assert_eq!(
+            *fired,
+            exercise(&Buggify::new(seed as u64), 40),
+            "seed {}",
+            seed
+        );
+    }
+
+    // A quarter of the sites are active by default: some seeds enable a few
+    // sites, and different seeds pick different ones.
+    assert!(runs.iter().any(|fired| !fired.is_empty()));
+    assert!(runs.iter().all(|fired| fired.len() < 8));
+    assert!(runs.windows(2).any(|pair| pair[0] != pair[1]));
+}
+
+#[test]
+fn test_enable_restores_the_previous_decisions() {
+    let outer = Buggify::new(1).activation_percent(100).fire_percent(100);
+    let inner = Buggify::new(1).activation_percent(0);
+    let _outer = outer.enable();
+    {
+        let _inner = inner.enable();
+        assert!(!first());
+    }
+    assert!(first());
+}
+
+/// A server that fails, stalls or shortens replies at buggify sites.
+fn run_server(seed: u64, recorder: &TraceRecorder<Event>) -> Buggify {
+    let buggify = Buggify::new(seed)
+        .activation_percent(100)
+        .fire_percent(30)
+        .with_trace(recorder.clone());
+    let _enabled = buggify.enable();
+
+    let mut sim = Builder::new().rng_seed(seed).build();
+    let trace = recorder.host("server");
+    sim.client("server", async move {
+        for request in 0..20u32 {
+            if dst::buggify!() {
+                continue;
+            }
+            dst::buggify_sleep!(Duration::from_millis(100));
+            let len = dst::buggify_shrink!(8);
+            trace.record(Event::Handled(request * 10 + len as u32));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        Ok(())
+    });
+    sim.run().unwrap();
+    buggify
+}
+
+#[test]
+fn test_firings_are_recorded_in_the_trace() {
+    let recorder = TraceRecorder::new();
+    let buggify = run_server(3, &recorder);
+    let trace = recorder.trace();
+
+    let recorded: Vec<_> = trace
+        .events()
+        .iter()
+        .filter_map(|e| match &e.event {
+            Event::Buggify(event) => {
+                assert_eq!(e.host, BUGGIFY_HOST);
+                Some(event.site.clone())
+            }
+            Event::Handled(_) => None,
+        })
+        .collect();
+    let fired = buggify.fired();
+    assert_eq!(recorded.len() as u64, fired.values().sum::<u64>());
+    assert!(!recorded.is_empty());
+    for site in fired.keys() {
+        assert!(recorded.contains(&site.to_string()), "{} not traced", site);
+    }
+
+    // The same seed replays the same firings at the same simulated times.
+    let again = TraceRecorder::new();
+    run_server(3, &again);
+    assert!(trace.diff(&again.trace(), 3).is_none());
+}
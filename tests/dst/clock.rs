@@ -0,0 +1,225 @@
+//! Integration tests for per-host clocks and clock faults.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dst::clock::DEFAULT_EPOCH;
+use dst::io::time::Clock;
+use dst::turmoil::Builder;
+use dst::{scenario_from_json, scenario_to_json, Fault, FaultDriver, ScenarioShrinker, SimClocks};
+
+fn epoch() -> Duration {
+    DEFAULT_EPOCH
+}
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn test_unfaulted_clocks_read_true_time() {
+    let clocks = SimClocks::new();
+    assert_eq!(clocks.wall_time("a", secs(7)), epoch() + secs(7));
+    assert_eq!(clocks.monotonic("a", secs(7)), secs(7));
+    assert_eq!(clocks.true_duration("a", secs(3)), secs(3));
+
+    let clocks = SimClocks::new().with_epoch(Duration::ZERO);
+    assert_eq!(clocks.wall_time("a", secs(7)), secs(7));
+    // Before the epoch, wall clocks read zero.
+    clocks.set_offset("a", -10_000, Duration::ZERO);
+    assert_eq!(clocks.wall_time("a", secs(7)), Duration::ZERO);
+}
+
+#[test]
+fn test_offsets_drift_and_jumps_compose() {
+    let clocks = SimClocks::new();
+
+    // An offset moves only the wall clock, either way.
+    clocks.set_offset("a", 1_500, secs(1));
+    assert_eq!(
+        clocks.wall_time("a", secs(2)),
+        epoch() + secs(2) + ms(1_500)
+    );
+    assert_eq!(clocks.monotonic("a", secs(2)), secs(2));
+    clocks.set_offset("b", -3_000, Duration::ZERO);
+    assert_eq!(clocks.wall_time("b", secs(1)), epoch() - secs(2));
+
+    // 1000ppm fast from 10s: 10ms gained by 20s, on both clocks.
+    clocks.set_drift("a", 1_000, secs(10));
+    assert_eq!(clocks.monotonic("a", secs(20)), secs(20) + ms(10));
+    assert_eq!(
+        clocks.wall_time("a", secs(20)),
+        epoch() + secs(20) + ms(1_510)
+    );
+
+    // Clearing the drift keeps what was gained.
+    clocks.set_drift("a", 0, secs(20));
+    assert_eq!(clocks.monotonic("a", secs(100)), secs(100) + ms(10));
+
+    // A jump steps the wall clock only.
+    clocks.jump("a", -1_510);
+    assert_eq!(clocks.wall_time("a", secs(100)), epoch() + secs(100));
+    assert_eq!(clocks.monotonic("a", secs(100)), secs(100) + ms(10));
+
+    // A new offset replaces all accumulated wall-clock error.
+    clocks.set_offset("a", 0, secs(100));
+    assert_eq!(clocks.wall_time("a", secs(100)), epoch() + secs(100));
+
+    // Other hosts are unaffected.
+    assert_eq!(clocks.wall_time("c", secs(100)), epoch() + secs(100));
+}
+
+#[test]
+fn test_drift_stretches_local_durations() {
+    let clocks = SimClocks::new();
+    clocks.set_drift("slow", -100_000, Duration::ZERO);
+    clocks.set_drift("fast", 250_000, Duration::ZERO);
+
+    // A 10% slow clock needs 10s / 0.9 of true time to count 10s.
+    let slow = clocks.true_duration("slow", secs(10));
+    assert!(slow > ms(11_111) && slow < ms(11_112), "{:?}", slow);
+    // Rounded up, so the local clock has always reached 10s.
+    let local = clocks.monotonic("slow", slow);
+    assert!(local >= secs(10) && local - secs(10) < Duration::from_micros(1));
+    assert_eq!(clocks.true_duration("fast", secs(10)), secs(8));
+
+    // Drift that would stop the clock is clamped.
+    clocks.set_drift("stopped", -2_000_000, Duration::ZERO);
+    assert!(clocks.monotonic("stopped", secs(1_000_000)) > Duration::ZERO);
+}
+
+#[test]
+fn test_clock_faults_round_trip_through_json() {
+    let scenario = vec![
+        (
+            secs(1),
+            Fault::ClockOffset {
+                node: "a".into(),
+                offset_ms: -250,
+            },
+        ),
+        (
+            secs(2),
+            Fault::ClockDrift {
+                node: "a".into(),
+                ppm: 200,
+            },
+        ),
+        (
+            secs(3),
+            Fault::ClockJump {
+                node: "a".into(),
+                delta_ms: 1_000,
+            },
+        ),
+    ];
+    let json = scenario_to_json(&scenario).unwrap();
+    assert!(json.contains("\"kind\": \"clock_offset\""), "{}", json);
+    assert_eq!(scenario_from_json(&json).unwrap(), scenario);
+    assert_eq!(scenario[2].1.nodes(), ["a"]);
+}
+
+#[test]
+fn test_shrinking_moves_clock_errors_towards_zero() {
+    let scenario = vec![(
+        secs(4),
+        Fault::ClockOffset {
+            node: "a".into(),
+            offset_ms: -10_000,
+        },
+    )];
+    // Fails while the clock is more than 100ms behind.
+    let outcome = ScenarioShrinker::new(|s: &[(Duration, Fault)]| {
+        s.iter()
+            .any(|(_, f)| matches!(f, Fault::ClockOffset { offset_ms, .. } if *offset_ms < -100))
+    })
+    .shrink(scenario);
+
+    match &outcome.scenario[..] {
+        [(at, Fault::ClockOffset { offset_ms, .. })] => {
+            assert_eq!(*at, Duration::ZERO);
+            assert!((-200..-100).contains(offset_ms), "{}", offset_ms);
+        }
+        other => panic!("unexpected scenario {:?}", other),
+    }
+}
+
+#[test]
+fn test_hosts_observe_clock_faults() {
+    let readings = Arc::new(Mutex::new(Vec::new()));
+
+    let mut sim = Builder::new().simulation_duration(secs(60)).build();
+    let schedule = vec![
+        (
+            secs(1),
+            Fault::ClockOffset {
+                node: "skewed".into(),
+                offset_ms: 5_000,
+            },
+        ),
+        (
+            secs(1),
+            Fault::ClockDrift {
+                node: "skewed".into(),
+                ppm: -100_000,
+            },
+        ),
+        (
+            secs(3),
+            Fault::ClockJump {
+                node: "skewed".into(),
+                delta_ms: -2_000,
+            },
+        ),
+    ];
+    let mut driver = FaultDriver::new(["skewed", "honest"], schedule);
+
+    for node in ["skewed", "honest"] {
+        let clock: Clock = driver.clocks().host(node);
+        let readings = readings.clone();
+        sim.client(node, async move {
+            // Both sleep 4s on their own clock: the slow one takes longer.
+            tokio::time::sleep(ms(1_500)).await;
+            let before = clock.wall_time();
+            clock.sleep(secs(4)).await;
+            let slept = dst::turmoil::elapsed() - ms(1_500);
+            readings.lock().unwrap().push((
+                node,
+                before,
+                clock.wall_time(),
+                slept,
+                clock.elapsed(),
+            ));
+            Ok(())
+        });
+    }
+    driver.run(&mut sim).unwrap();
+
+    let readings = readings.lock().unwrap();
+    let reading = |node| readings.iter().find(|r| r.0 == node).unwrap();
+
+    let (_, before, after, slept, elapsed) = *reading("honest");
+    assert_eq!(before, epoch() + ms(1_500));
+    assert_eq!(slept, secs(4));
+    assert_eq!(after, epoch() + ms(5_500));
+    assert_eq!(elapsed, ms(5_500));
+
+    let (_, before, after, slept, elapsed) = *reading("skewed");
+    // 5s ahead, minus 50ms lost in the 0.5s since the drift started.
+    assert_eq!(before, epoch() + ms(1_500) + ms(5_000) - ms(50));
+    // 4s at 0.9 speed is 4.44s of true time.
+    assert!(slept > ms(4_444) && slept < ms(4_446), "{:?}", slept);
+    // The monotonic clock counted exactly the local 4s; the wall clock also
+    // jumped back 2s.
+    assert!(elapsed >= ms(5_450) && elapsed < ms(5_451), "{:?}", elapsed);
+    let wall_advance = after - before;
+    assert!(
+        wall_advance >= ms(2_000) && wall_advance < ms(2_001),
+        "{:?}",
+        wall_advance
+    );
+}
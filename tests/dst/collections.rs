@@ -0,0 +1,106 @@
+//! Integration tests for the deterministic collections.
+
+use std::collections::BTreeSet;
+use std::hash::{BuildHasher, Hash};
+
+use dst::collections::{det_index_map, det_index_set, seeded_hash_map, seeded_hash_set};
+use dst::{det_hash_set, DeterministicBuildHasher, Entropy, SeededBuildHasher};
+
+/// Distinct values of the low `bits` of each key's hash, as a hash table
+/// with `2^bits` buckets would index them.
+fn buckets<K: Hash>(hasher: impl BuildHasher, keys: &[K], bits: u32) -> usize {
+    keys.iter()
+        .map(|key| hasher.hash_one(key) & ((1 << bits) - 1))
+        .collect::<BTreeSet<_>>()
+        .len()
+}
+
+#[test]
+fn test_det_hash_set_iteration_order_is_reproducible() {
+    let build = || {
+        let mut set = det_hash_set();
+        for i in 0..256u64 {
+            set.insert(format!("node-{}", i));
+        }
+        set.into_iter().collect::<Vec<_>>()
+    };
+    assert_eq!(build(), build());
+}
+
+#[test]
+fn test_index_collections_iterate_in_insertion_order() {
+    let mut map = det_index_map();
+    for key in ["node-c", "node-a", "node-b"] {
+        map.insert(key, key.len());
+    }
+    map.insert("node-a", 0);
+    assert_eq!(
+        map.keys().copied().collect::<Vec<_>>(),
+        ["node-c", "node-a", "node-b"]
+    );
+    assert_eq!(map["node-a"], 0);
+
+    // Removing shifts later entries up instead of swapping the last one in.
+    map.shift_remove("node-c");
+    assert_eq!(
+        map.keys().copied().collect::<Vec<_>>(),
+        ["node-a", "node-b"]
+    );
+
+    let mut set = det_index_set();
+    for i in (0..100u32).rev() {
+        set.insert(i);
+    }
+    assert!(set.iter().copied().eq((0..100).rev()));
+}
+
+#[test]
+fn test_seeded_order_is_reproducible_per_seed_and_varies_across_seeds() {
+    let order = |seed: u64| {
+        let mut map = seeded_hash_map(seed);
+        for i in 0..64u64 {
+            map.insert(format!("key-{}", i), i);
+        }
+        map.into_values().collect::<Vec<_>>()
+    };
+    assert_eq!(order(1), order(1));
+    assert_ne!(order(1), order(2));
+
+    let mut set = seeded_hash_set(Entropy::new(9).derive_seed("node-a", "peers"));
+    assert!(set.insert("node-b"));
+    assert!(!set.insert("node-b"));
+}
+
+#[test]
+fn test_seeded_hashes_depend_on_the_key() {
+    let a = SeededBuildHasher::new(1);
+    let b = SeededBuildHasher::new(2);
+    assert_eq!(
+        a.hash_one("node-a"),
+        SeededBuildHasher::new(1).hash_one("node-a")
+    );
+    assert_ne!(a.hash_one("node-a"), b.hash_one("node-a"));
+    assert_ne!(
+        a.hash_one("node-a"),
+        DeterministicBuildHasher.hash_one("node-a")
+    );
+}
+
+#[test]
+fn test_seeded_hasher_spreads_keys_fnv_collapses() {
+    // Keys that differ only in the top bit of each byte: the low 7 bits of
+    // each FNV-1a step depend only on the low 7 bits of each byte.
+    let keys: Vec<u128> = (0..4096u64)
+        .map(|i| {
+            (0..12).fold(0, |key, byte| {
+                key | (u128::from((i >> byte) & 1) << (byte * 8 + 7))
+            })
+        })
+        .collect();
+
+    assert_eq!(buckets(DeterministicBuildHasher, &keys, 7), 1);
+    for seed in 0..8 {
+        assert_eq!(buckets(SeededBuildHasher::new(seed), &keys, 7), 128);
+        assert!(buckets(SeededBuildHasher::new(seed), &keys, 10) > 600);
+    }
+}
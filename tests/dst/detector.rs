@@ -0,0 +1,357 @@
+//! Integration tests for the phi-accrual failure detector.
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dst::turmoil::Builder;
+use dst::{
+    FailureDetector, Fault, FaultDriver, FaultScenarioGenerator, FaultWeights, MembershipEvent,
+    PhiAccrual,
+};
+
+const PORT: u16 = 7946;
+const NODES: [&str; 3] = ["node-a", "node-b", "node-c"];
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+/// Membership events each node observed, with the simulated time.
+type Events = Arc<Mutex<Vec<(Duration, String, MembershipEvent)>>>;
+
+/// Runs a detector on every node under `scenario` for `duration`.
+fn simulate(
+    scenario: Vec<(Duration, Fault)>,
+    phi: PhiAccrual,
+    duration: Duration,
+) -> (Vec<(Duration, String, MembershipEvent)>, FaultDriver) {
+    let events: Events = Arc::default();
+    let mut sim = Builder::new()
+        .simulation_duration(duration + Duration::from_secs(1))
+        .min_message_latency(ms(1))
+        .max_message_latency(ms(20))
+        .build();
+    let mut driver = FaultDriver::new(NODES, scenario);
+
+    for node in NODES {
+        let clock = driver.clocks().host(node);
+        let events = events.clone();
+        let phi = phi.clone();
+        sim.host(node, move || {
+            let events = events.clone();
+            let detector = FailureDetector::new(node, NODES, PORT, clock.clone())
+                .phi_accrual(phi.clone())
+                .observe(move |event| {
+                    let at = dst::turmoil::elapsed();
+                    events
+                        .lock()
+                        .unwrap()
+                        .push((at, node.to_string(), event.clone()));
+                });
+            async move {
+                detector.run().await?;
+                Ok(())
+            }
+        });
+    }
+    driver.run_until(&mut sim, duration).unwrap();
+
+    let events = events.lock().unwrap().clone();
+    (events, driver)
+}
+
+fn link(a: &str, b: &str) -> (String, String) {
+    if a < b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+/// Detection latency and false positives of one run, judged against the
+/// partitions the driver actually applied.
+#[derive(Debug, Default)]
+struct Stats {
+    /// Delay from each partition to its detection, per observing side.
+    latencies: Vec<Duration>,
+    /// Partition sides that were never suspected.
+    missed: usize,
+    /// Suspicions of peers that were reachable.
+    false_positives: usize,
+}
+
+impl Stats {
+    fn mean_latency(&self) -> Duration {
+        self.latencies.iter().sum::<Duration>() / self.latencies.len().max(1) as u32
+    }
+
+    fn max_latency(&self) -> Duration {
+        self.latencies.iter().copied().max().unwrap_or_default()
+    }
+
+    /// Share of suspicions that were wrong.
+    fn false_positive_rate(&self) -> f64 {
+        let suspicions = self.false_positives + self.latencies.len();
+        self.false_positives as f64 / suspicions.max(1) as f64
+    }
+}
+
+fn measure(events: &[(Duration, String, MembershipEvent)], driver: &FaultDriver) -> Stats {
+    // Partitioned intervals per link, from the faults as applied.
+    let mut partitioned: Vec<((String, String), Duration, Duration)> = Vec::new();
+    let mut open = std::collections::BTreeMap::new();
+    for applied in driver.applied() {
+        match &applied.fault {
+            Fault::Partition { node_a, node_b } => {
+                open.entry(link(node_a, node_b))
+                    .or_insert(applied.applied_at);
+            }
+            Fault::Repair { node_a, node_b } => {
+                if let Some(start) = open.remove(&link(node_a, node_b)) {
+                    partitioned.push((link(node_a, node_b), start, applied.applied_at));
+                }
+            }
+            _ => {}
+        }
+    }
+    for (link, start) in open {
+        partitioned.push((link, start, Duration::MAX));
+    }
+    let cut = |a: &str, b: &str, at: Duration| {
+        partitioned
+            .iter()
+            .any(|(l, start, end)| *l == link(a, b) && *start <= at && at < *end)
+    };
+
+    let mut stats = Stats::default();
+    for (at, observer, event) in events {
+        if let MembershipEvent::Suspected { peer, .. } = event {
+            if !cut(observer, peer, *at) {
+                stats.false_positives += 1;
+            }
+        }
+    }
+    for (l, start, end) in &partitioned {
+        // Too short to be detected reliably.
+        if end.saturating_sub(*start) < Duration::from_secs(2) {
+            continue;
+        }
+        for (observer, peer) in [(&l.0, &l.1), (&l.1, &l.0)] {
+            // Suspected when the partition started, or first during it.
+            let mut suspected_at = None;
+            for (at, _, event) in events
+                .iter()
+                .filter(|(_, o, e)| o == observer && e.peer() == peer)
+                .take_while(|(at, _, _)| at < end)
+            {
+                match event {
+                    MembershipEvent::Suspected { .. } => {
+                        suspected_at.get_or_insert((*at).max(*start));
+                    }
+                    MembershipEvent::Recovered { .. } if at <= start => suspected_at = None,
+                    MembershipEvent::Recovered { .. } => {}
+                }
+                if at >= start && suspected_at.is_some() {
+                    break;
+                }
+            }
+            match suspected_at {
+                Some(at) => stats.latencies.push(at - *start),
+                None => stats.missed += 1,
+            }
+        }
+    }
+    stats
+}
+
+#[test]
+fn test_phi_grows_with_silence_and_resets_on_heartbeat() {
+    let mut detector = PhiAccrual::new();
+    assert_eq!(detector.phi("node-b", ms(0)), 0.0);
+
+    for beat in 0..50 {
+        detector.heartbeat("node-b", ms(100 * beat));
+    }
+    let last = ms(4_900);
+    let levels: Vec<f64> = (0..10)
+        .map(|step| detector.phi("node-b", last + ms(50 * step)))
+        .collect();
+    assert!(levels.windows(2).all(|w| w[0] <= w[1]), "{:?}", levels);
+    assert!(levels[2] < 1.0, "{:?}", levels);
+    // Mean 100ms with the 50ms floor on the deviation: suspected a few
+    // deviations past the mean.
+    assert!(detector.is_available("node-b", last + ms(300)));
+    assert!(!detector.is_available("node-b", last + ms(400)));
+
+    detector.heartbeat("node-b", last + ms(150));
+    assert!(detector.phi("node-b", last + ms(150)) < 1.0);
+
+    detector.remove("node-b");
+    assert_eq!(detector.phi("node-b", last + ms(10_000)), 0.0);
+}
+
+#[test]
+fn test_threshold_jitter_and_pause_delay_suspicion() {
+    let first_suspicion = |mut detector: PhiAccrual, jitter: u64| {
+        let mut at = Duration::ZERO;
+        for beat in 0..100 {
+            at += ms(100 - jitter + 2 * jitter * (beat % 2));
+            detector.heartbeat("node-b", at);
+        }
+        (1..)
+            .map(|step| at + ms(10 * step))
+            .find(|now| !detector.is_available("node-b", *now))
+            .unwrap()
+            - at
+    };
+
+    let default = first_suspicion(PhiAccrual::new(), 0);
+    let lenient = first_suspicion(PhiAccrual::new().threshold(16.0), 0);
+    let eager = first_suspicion(PhiAccrual::new().threshold(1.0), 0);
+    assert!(
+        eager < default && default < lenient,
+        "{:?} {:?} {:?}",
+        eager,
+        default,
+        lenient
+    );
+
+    // Irregular heartbeats widen the distribution.
+    assert!(first_suspicion(PhiAccrual::new(), 90) > default);
+
+    let paused = first_suspicion(PhiAccrual::new().acceptable_pause(ms(1_000)), 0);
+    assert_eq!(paused, default + ms(1_000));
+}
+
+#[test]
+fn test_suspected_intervals_stay_out_of_the_history() {
+    let mut detector = PhiAccrual::new();
+    for beat in 0..20 {
+        detector.heartbeat("node-b", ms(100 * beat));
+    }
+    let before = detector.clone();
+
+    // A 30s partition, then regular heartbeats again.
+    detector.heartbeat("node-b", ms(31_900));
+    let at = ms(31_900 + 400);
+    assert_eq!(
+        detector.phi("node-b", at),
+        before.phi("node-b", ms(1_900 + 400))
+    );
+    assert!(!detector.is_available("node-b", at));
+}
+
+#[test]
+fn test_partition_is_detected_and_healed() {
+    let isolate = |fault: fn(String, String) -> Fault| {
+        ["node-a", "node-b"].map(|other| fault("node-c".into(), other.into()))
+    };
+    let mut scenario = Vec::new();
+    for fault in isolate(|node_a, node_b| Fault::Partition { node_a, node_b }) {
+        scenario.push((Duration::from_secs(5), fault));
+    }
+    for fault in isolate(|node_a, node_b| Fault::Repair { node_a, node_b }) {
+        scenario.push((Duration::from_secs(10), fault));
+    }
+
+    let (events, driver) = simulate(scenario, PhiAccrual::new(), Duration::from_secs(15));
+
+    // Each side suspects the other, within half a second.
+    let suspicions: BTreeSet<_> = events
+        .iter()
+        .filter(|(_, _, event)| matches!(event, MembershipEvent::Suspected { .. }))
+        .map(|(at, observer, event)| {
+            assert!(
+                *at >= Duration::from_secs(5) && *at < Duration::from_secs(5) + ms(500),
+                "{:?} {} {:?}",
+                at,
+                observer,
+                event
+            );
+            (observer.as_str(), event.peer())
+        })
+        .collect();
+    assert_eq!(
+        suspicions,
+        BTreeSet::from([
+            ("node-a", "node-c"),
+            ("node-b", "node-c"),
+            ("node-c", "node-a"),
+            ("node-c", "node-b"),
+        ])
+    );
+
+    // Every suspicion is lifted soon after the repair.
+    let recoveries: Vec<_> = events
+        .iter()
+        .filter(|(_, _, event)| matches!(event, MembershipEvent::Recovered { .. }))
+        .collect();
+    assert_eq!(recoveries.len(), 4, "{:?}", events);
+    assert!(recoveries.iter().all(
+        |(at, _, _)| *at >= Duration::from_secs(10) && *at < Duration::from_secs(10) + ms(500)
+    ));
+
+    let stats = measure(&events, &driver);
+    assert_eq!((stats.missed, stats.false_positives), (0, 0));
+}
+
+/// Runs a strict and a lenient detector over `seeds` generated scenarios of
+/// `faults` network faults each and checks the trade-off between them.
+fn check_detection_tradeoff(seeds: Range<u64>, faults: usize, horizon: Duration) {
+    let nodes: Vec<String> = NODES.iter().map(|node| node.to_string()).collect();
+    let sweep = |phi: PhiAccrual| {
+        let mut total = Stats::default();
+        for seed in seeds.clone() {
+            let scenario = FaultScenarioGenerator::new(seed, nodes.clone())
+                .with_weights(FaultWeights::NETWORK)
+                .generate_scenario(faults);
+            let (events, driver) = simulate(scenario, phi.clone(), horizon);
+            let stats = measure(&events, &driver);
+            total.latencies.extend(stats.latencies);
+            total.missed += stats.missed;
+            total.false_positives += stats.false_positives;
+        }
+        eprintln!(
+            "{:?}: {} partitions detected, mean latency {:?}, max {:?}, {} missed, \
+             false-positive rate {:.2}",
+            phi,
+            total.latencies.len(),
+            total.mean_latency(),
+            total.max_latency(),
+            total.missed,
+            total.false_positive_rate()
+        );
+        total
+    };
+
+    // Every partition is noticed within a few heartbeat intervals, but the
+    // jump in delay when a latency fault lands looks like a failure too.
+    let strict = sweep(PhiAccrual::new());
+    assert!(!strict.latencies.is_empty());
+    assert_eq!(strict.missed, 0);
+    assert!(strict.max_latency() <= ms(500), "{:?}", strict);
+    assert!(strict.false_positive_rate() > 0.0);
+
+    // Tolerating a pause as long as the largest latency change removes the
+    // false positives, at the cost of a second of detection latency.
+    let lenient = sweep(PhiAccrual::new().acceptable_pause(ms(1_000)));
+    assert_eq!(lenient.latencies.len(), strict.latencies.len());
+    assert_eq!(lenient.missed, 0);
+    assert_eq!(lenient.false_positive_rate(), 0.0);
+    assert!(lenient.max_latency() <= ms(1_500), "{:?}", lenient);
+    assert!(lenient.mean_latency() > strict.mean_latency());
+}
+
+#[test]
+fn test_detection_latency_and_false_positives_under_generated_faults() {
+    check_detection_tradeoff(0..2, 4, Duration::from_secs(40));
+}
+
+/// More seeds over longer runs; about 45s in debug.
+#[test]
+#[ignore = "slow; run with --ignored"]
+fn test_detection_tradeoff_full_sweep() {
+    check_detection_tradeoff(0..6, 8, Duration::from_secs(80));
+}
@@ -0,0 +1,99 @@
+//! Integration tests for the determinism checker.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use dst::turmoil::{net::UdpSocket, Builder};
+use dst::{assert_deterministic, DeterminismCheck, TraceRecorder};
+
+/// Ping-pong between a client and a server with seeded link jitter, so the
+/// trace depends on the seed.
+fn ping_pong(seed: u64, recorder: &TraceRecorder<String>) {
+    let mut sim = Builder::new()
+        .rng_seed(seed)
+        .min_message_latency(Duration::from_millis(1))
+        .max_message_latency(Duration::from_millis(50))
+        .build();
+
+    let server = recorder.host("server");
+    sim.host("server", move || {
+        let server = server.clone();
+        async move {
+            let socket = UdpSocket::bind("0.0.0.0:9000").await?;
+            let mut buf = [0u8; 8];
+            loop {
+                let (n, from) = socket.recv_from(&mut buf).await?;
+                server.record(format!("ping {}", buf[0]));
+                socket.send_to(&buf[..n], from).await?;
+            }
+        }
+    });
+
+    let client = recorder.host("client");
+    sim.client("client", async move {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        let mut buf = [0u8; 8];
+        for i in 0..10u8 {
+            socket.send_to(&[i], "server:9000").await?;
+            socket.recv_from(&mut buf).await?;
+            client.record(format!("pong {}", buf[0]));
+        }
+        Ok(())
+    });
+
+    sim.run().unwrap();
+}
+
+#[test]
+fn test_seeded_simulation_is_deterministic() {
+    let trace = assert_deterministic(42, ping_pong);
+    assert_eq!(trace.events().len(), 20);
+
+    DeterminismCheck::new(7).runs(4).assert(ping_pong);
+}
+
+#[test]
+fn test_leaked_state_is_reported_as_divergence() {
+    let calls = AtomicU64::new(0);
+    let failure = DeterminismCheck::new(3)
+        .runs(3)
+        .context(1)
+        .check(|seed, recorder: &TraceRecorder<u64>| {
+            recorder.record_at(Duration::ZERO, "node", seed);
+            // Leaks state between runs, like a process-wide counter would.
+            let call = calls.fetch_add(1, Ordering::Relaxed);
+            recorder.record_at(Duration::from_millis(1), "node", call);
+        })
+        .unwrap_err();
+
+    assert_eq!(failure.seed, 3);
+    assert_eq!(failure.run, 1);
+
+    let divergence = failure.divergence();
+    assert_eq!(divergence.index, 1);
+    assert_eq!(divergence.left[0].event, 0);
+    assert_eq!(divergence.right[0].event, 1);
+
+    let report = failure.to_string();
+    assert!(report.contains("seed 3 is not deterministic"), "{}", report);
+    assert!(report.contains("diverge at event #1"), "{}", report);
+}
+
+#[test]
+#[should_panic(expected = "is not deterministic")]
+fn test_assert_deterministic_panics_on_divergence() {
+    let calls = AtomicU64::new(0);
+    assert_deterministic(1, |_, recorder: &TraceRecorder<u64>| {
+        recorder.record_at(
+            Duration::ZERO,
+            "node",
+            calls.fetch_add(1, Ordering::Relaxed),
+        );
+    });
+}
+
+#[test]
+#[should_panic(expected = "at least two runs")]
+fn test_single_run_is_rejected() {
+    let _ = DeterminismCheck::new(0).runs(1);
+}
@@ -0,0 +1,398 @@
+//! Integration tests for fault scenario generation and the fault driver.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dst::turmoil::Builder;
+use dst::{
+    run_simulation_test, DetHashMap, Fault, FaultDriver, FaultScenarioGenerator, FaultWeights,
+    FaultyUdpSocket,
+};
+
+fn nodes() -> Vec<String> {
+    vec!["alpha".into(), "beta".into(), "gamma".into()]
+}
+
+#[test]
+fn test_generated_fault_scenarios_are_deterministic() {
+    run_simulation_test("test_generated_fault_scenarios_are_deterministic", |seed| {
+        let scenario = FaultScenarioGenerator::new(seed, nodes()).generate_scenario(5);
+        let scenario2 = FaultScenarioGenerator::new(seed, nodes()).generate_scenario(5);
+
+        assert_eq!(scenario.len(), 5);
+        assert_eq!(scenario, scenario2);
+        assert!(scenario.windows(2).all(|w| w[0].0 < w[1].0));
+    });
+}
+
+#[test]
+fn test_generator_respects_weights() {
+    let only_crashes = FaultWeights {
+        partition: 0,
+        repair: 0,
+        latency: 0,
+        crash: 1,
+        ..FaultWeights::NETWORK
+    };
+
+    let scenario = FaultScenarioGenerator::new(3, nodes())
+        .with_weights(only_crashes)
+        .generate_scenario(20);
+    assert!(scenario
+        .iter()
+        .all(|(_, fault)| matches!(fault, Fault::Crash { .. })));
+
+    let network = FaultScenarioGenerator::new(3, nodes())
+        .with_weights(FaultWeights::NETWORK)
+        .generate_scenario(50);
+    assert!(network.iter().all(|(_, fault)| matches!(
+        fault,
+        Fault::Partition { .. } | Fault::Repair { .. } | Fault::Latency { .. }
+    )));
+}
+
+#[test]
+fn test_default_weights_produce_every_kind() {
+    let scenario = FaultScenarioGenerator::new(11, nodes()).generate_scenario(200);
+    let mut kinds: Vec<_> = scenario
+        .iter()
+        .map(|(_, fault)| std::mem::discriminant(fault))
+        .collect();
+    kinds.sort_by_key(|kind| format!("{:?}", kind));
+    kinds.dedup();
+    assert_eq!(kinds.len(), 8);
+}
+
+#[test]
+#[should_panic(expected = "at least one fault weight")]
+fn test_generator_rejects_all_zero_weights() {
+    let none = FaultWeights {
+        partition: 0,
+        repair: 0,
+        latency: 0,
+        ..FaultWeights::NETWORK
+    };
+    let _ = FaultScenarioGenerator::new(1, nodes()).with_weights(none);
+}
+
+#[test]
+fn test_single_node_gets_no_link_faults() {
+    let scenario = FaultScenarioGenerator::new(5, vec!["solo".into()]).generate_scenario(30);
+    assert!(scenario.iter().all(|(_, fault)| matches!(
+        fault,
+        Fault::Latency { .. } | Fault::Crash { .. } | Fault::Restart { .. }
+    )));
+
+    // The driver applies all of them without touching a self-link.
+    let end = scenario.last().unwrap().0 + Duration::from_secs(1);
+    let mut sim = Builder::new().simulation_duration(end * 2).build();
+    sim.host("solo", || async {
+        loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+    let mut driver = FaultDriver::new(["solo"], scenario);
+    driver.run_until(&mut sim, end).unwrap();
+    assert_eq!(driver.applied().len(), 30);
+}
+
+#[test]
+#[should_panic(expected = "a single node has no links")]
+fn test_single_node_with_only_link_weights_panics() {
+    let links_only = FaultWeights {
+        latency: 0,
+        ..FaultWeights::NETWORK
+    };
+    FaultScenarioGenerator::new(5, vec!["solo".into()])
+        .with_weights(links_only)
+        .generate_fault();
+}
+
+#[test]
+fn test_driver_fires_faults_at_scheduled_time() {
+    let mut sim = Builder::new()
+        .simulation_duration(Duration::from_secs(60))
+        .build();
+    for node in ["alpha", "beta"] {
+        sim.host(node, || async { std::future::pending().await });
+    }
+
+    let partition = Fault::Partition {
+        node_a: "alpha".into(),
+        node_b: "beta".into(),
+    };
+    let repair = Fault::Repair {
+        node_a: "alpha".into(),
+        node_b: "beta".into(),
+    };
+    // Deliberately out of order: the driver sorts by time.
+    let schedule = vec![
+        (Duration::from_secs(3), repair.clone()),
+        (Duration::from_secs(1), partition.clone()),
+    ];
+
+    let mut driver = FaultDriver::new(["alpha", "beta"], schedule);
+    driver
+        .run_until(&mut sim, Duration::from_millis(500))
+        .unwrap();
+    assert!(driver.applied().is_empty());
+
+    driver.run(&mut sim).unwrap();
+
+    let applied = driver.applied();
+    assert_eq!(applied.len(), 2);
+    assert_eq!(applied[0].fault, partition);
+    assert_eq!(applied[0].scheduled_at, Duration::from_secs(1));
+    assert!(applied[0].applied_at >= Duration::from_secs(1));
+    assert_eq!(applied[1].fault, repair);
+    assert!(applied[1].applied_at >= Duration::from_secs(3));
+    assert_eq!(driver.pending().count(), 0);
+}
+
+#[test]
+fn test_driver_latency_fault_slows_link() {
+    let received_at = Arc::new(Mutex::new(None));
+
+    let mut sim = Builder::new()
+        .simulation_duration(Duration::from_secs(60))
+        .min_message_latency(Duration::from_millis(1))
+        .max_message_latency(Duration::from_millis(1))
+        .build();
+
+    sim.host("server", || async {
+        let sock = dst::turmoil::net::UdpSocket::bind("0.0.0.0:9000").await?;
+        let mut buf = [0u8; 16];
+        loop {
+            let (n, from) = sock.recv_from(&mut buf).await?;
+            sock.send_to(&buf[..n], from).await?;
+        }
+    });
+
+    let observed = received_at.clone();
+    sim.client("client", async move {
+        let sock = dst::turmoil::net::UdpSocket::bind("0.0.0.0:9001").await?;
+        // Wait until after the latency fault has fired.
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        let start = tokio::time::Instant::now();
+        sock.send_to(b"ping", "server:9000").await?;
+        let mut buf = [0u8; 16];
+        sock.recv_from(&mut buf).await?;
+        *observed.lock().unwrap() = Some(start.elapsed());
+        Ok(())
+    });
+
+    let schedule = vec![(
+        Duration::from_secs(1),
+        Fault::Latency {
+            node: "server".into(),
+            delay_ms: 500,
+        },
+    )];
+    let mut driver = FaultDriver::new(["server", "client"], schedule);
+    driver.run(&mut sim).unwrap();
+
+    let round_trip = received_at.lock().unwrap().expect("client finished");
+    assert!(
+        round_trip >= Duration::from_millis(1000),
+        "expected both legs to take 500ms, got {:?}",
+        round_trip
+    );
+}
+
+#[test]
+fn test_survives_generated_partitions() {
+    run_simulation_test("test_survives_generated_partitions", |seed| {
+        let heartbeats = Arc::new(Mutex::new(DetHashMap::<String, u64>::default()));
+
+        let mut sim = Builder::new()
+            .simulation_duration(Duration::from_secs(120))
+            .min_message_latency(Duration::from_millis(1))
+            .max_message_latency(Duration::from_millis(50))
+            .rng_seed(seed)
+            .build();
+
+        for name in nodes() {
+            let heartbeats = heartbeats.clone();
+            sim.host(name.clone(), move || {
+                let name = name.clone();
+                let heartbeats = heartbeats.clone();
+                async move {
+                    for tick in 1..=500u64 {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        heartbeats.lock().unwrap().insert(name.clone(), tick);
+                    }
+                    std::future::pending().await
+                }
+            });
+        }
+
+        let schedule = FaultScenarioGenerator::new(seed, nodes())
+            .with_weights(FaultWeights::NETWORK)
+            .generate_scenario(5);
+        let mut driver = FaultDriver::new(nodes(), schedule);
+        driver.run_until(&mut sim, Duration::from_secs(60)).unwrap();
+
+        assert_eq!(driver.applied().len(), 5);
+        let heartbeats = heartbeats.lock().unwrap();
+        for node in nodes() {
+            let count = heartbeats.get(&node).copied().unwrap_or(0);
+            assert!(
+                count > 100,
+                "{} should have made progress (got {})",
+                node,
+                count
+            );
+        }
+    });
+}
+
+/// Runs a UDP sender that sends `count` numbered datagrams to a receiver and
+/// returns the sequence numbers the receiver observed.
+fn run_udp_stream(
+    schedule: Vec<(Duration, Fault)>,
+    count: u8,
+    interval: Duration,
+) -> (Vec<u8>, Vec<dst::AppliedFault>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    let mut sim = Builder::new()
+        .simulation_duration(Duration::from_secs(60))
+        .min_message_latency(Duration::from_millis(1))
+        .max_message_latency(Duration::from_millis(1))
+        .rng_seed(7)
+        .build();
+
+    let mut driver = FaultDriver::new(["sender", "receiver"], schedule).with_seed(7);
+    let network = driver.network().clone();
+    let observed = received.clone();
+    sim.host("receiver", move || {
+        let network = network.clone();
+        let observed = observed.clone();
+        async move {
+            let sock = FaultyUdpSocket::bind("receiver", "0.0.0.0:9000", network).await?;
+            let mut buf = [0u8; 1];
+            loop {
+                sock.recv_from(&mut buf).await?;
+                observed.lock().unwrap().push(buf[0]);
+            }
+        }
+    });
+
+    sim.client("sender", async move {
+        let sock = dst::turmoil::net::UdpSocket::bind("0.0.0.0:9001").await?;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        for seq in 0..count {
+            sock.send_to(&[seq], "receiver:9000").await?;
+            tokio::time::sleep(interval).await;
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        Ok(())
+    });
+
+    driver.run(&mut sim).unwrap();
+    let applied = driver.applied().to_vec();
+    let received = received.lock().unwrap().clone();
+    (received, applied)
+}
+
+fn link_fault(make: impl Fn(String, String) -> Fault) -> Vec<(Duration, Fault)> {
+    vec![(
+        Duration::from_millis(500),
+        make("sender".into(), "receiver".into()),
+    )]
+}
+
+#[test]
+fn test_duplicate_fault_delivers_datagrams_twice() {
+    let schedule = link_fault(|node_a, node_b| Fault::Duplicate {
+        node_a,
+        node_b,
+        percent: 100,
+    });
+    let (received, _) = run_udp_stream(schedule, 10, Duration::from_millis(50));
+
+    let expected: Vec<u8> = (0..10).flat_map(|seq| [seq, seq]).collect();
+    assert_eq!(received, expected);
+}
+
+#[test]
+fn test_message_loss_fault_drops_datagrams() {
+    let schedule = link_fault(|node_a, node_b| Fault::MessageLoss {
+        node_a,
+        node_b,
+        percent: 100,
+    });
+    let (received, _) = run_udp_stream(schedule, 20, Duration::from_millis(50));
+
+    assert!(
+        received.len() < 20,
+        "expected losses, received all {} datagrams",
+        received.len()
+    );
+}
+
+#[test]
+fn test_reorder_fault_lets_datagrams_overtake() {
+    let schedule = link_fault(|node_a, node_b| Fault::Reorder {
+        node_a,
+        node_b,
+        max_delay_ms: 500,
+    });
+    let (received, _) = run_udp_stream(schedule, 50, Duration::from_millis(5));
+
+    assert_eq!(received.len(), 50);
+    assert!(
+        received.windows(2).any(|pair| pair[0] > pair[1]),
+        "expected at least one out-of-order delivery: {:?}",
+        received
+    );
+}
+
+#[test]
+fn test_crash_and_restart_faults() {
+    let ticks = Arc::new(Mutex::new(Vec::new()));
+
+    let mut sim = Builder::new()
+        .simulation_duration(Duration::from_secs(60))
+        .build();
+
+    let observed = ticks.clone();
+    sim.host("worker", move || {
+        let observed = observed.clone();
+        async move {
+            let mut tick = 0u64;
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                tick += 1;
+                observed
+                    .lock()
+                    .unwrap()
+                    .push((dst::turmoil::elapsed().as_secs(), tick));
+            }
+        }
+    });
+
+    let schedule = vec![
+        (
+            Duration::from_millis(3500),
+            Fault::Crash {
+                node: "worker".into(),
+            },
+        ),
+        (
+            Duration::from_millis(6500),
+            Fault::Restart {
+                node: "worker".into(),
+            },
+        ),
+    ];
+    let mut driver = FaultDriver::new(["worker"], schedule);
+    driver.run_until(&mut sim, Duration::from_secs(9)).unwrap();
+
+    let ticks = ticks.lock().unwrap();
+    // Three ticks before the crash, nothing while down, then a fresh count.
+    let seconds: Vec<u64> = ticks.iter().map(|(at, _)| *at).collect();
+    assert_eq!(seconds, vec![1, 2, 3, 7, 8]);
+    let counts: Vec<u64> = ticks.iter().map(|(_, tick)| *tick).collect();
+    assert_eq!(counts, vec![1, 2, 3, 1, 2]);
+}
@@ -0,0 +1,238 @@
+//! Integration tests for the simulated filesystem.
+
+use std::io::{ErrorKind, SeekFrom};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dst::turmoil::Builder;
+use dst::{DiskFaults, Fault, FaultDriver, SimFs};
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn test_basic_file_operations() {
+    block_on(async {
+        let disk = SimFs::new(1).host("node");
+
+        disk.write("a.txt", b"hello").await.unwrap();
+        assert_eq!(disk.read("a.txt").await.unwrap(), b"hello");
+
+        let mut file = disk.open("a.txt").await.unwrap();
+        file.seek(SeekFrom::End(0)).await.unwrap();
+        file.write_all(b", world").await.unwrap();
+        file.seek(SeekFrom::Start(7)).await.unwrap();
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"world");
+
+        disk.rename("a.txt", "b.txt").await.unwrap();
+        assert!(!disk.try_exists("a.txt").await.unwrap());
+        assert_eq!(disk.read("b.txt").await.unwrap(), b"hello, world");
+
+        disk.remove_file("b.txt").await.unwrap();
+        let err = disk.read("b.txt").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        // Hosts do not share files.
+        let other = SimFs::new(1).host("other");
+        assert!(!other.try_exists("a.txt").await.unwrap());
+    });
+}
+
+#[test]
+fn test_crash_discards_unsynced_data() {
+    block_on(async {
+        let fs = SimFs::new(2);
+        let disk = fs.host("node");
+
+        let mut synced = disk.create("synced").await.unwrap();
+        synced.write_all(b"durable").await.unwrap();
+        synced.sync_all().await.unwrap();
+        synced.write_all(b" lost").await.unwrap();
+
+        disk.write("never-synced", b"gone").await.unwrap();
+
+        fs.crash("node");
+        assert_eq!(disk.read("synced").await.unwrap(), b"durable");
+        assert!(!disk.try_exists("never-synced").await.unwrap());
+    });
+}
+
+#[test]
+fn test_fsync_loss_and_torn_writes() {
+    block_on(async {
+        let fs = SimFs::new(3);
+        let disk = fs.host("node");
+        fs.set_faults(
+            "node",
+            DiskFaults {
+                fsync_loss_percent: 100,
+                ..DiskFaults::default()
+            },
+        );
+        let mut file = disk.create("log").await.unwrap();
+        file.write_all(b"record").await.unwrap();
+        file.sync_all().await.unwrap();
+        fs.crash("node");
+        assert!(!disk.try_exists("log").await.unwrap());
+
+        fs.set_faults(
+            "node",
+            DiskFaults {
+                torn_write_percent: 100,
+                ..DiskFaults::default()
+            },
+        );
+        let full: Vec<u8> = (0..=255).collect();
+        let mut seen_lengths = Vec::new();
+        for _ in 0..20 {
+            let mut file = disk.create("log").await.unwrap();
+            file.write_all(b"head").await.unwrap();
+            file.sync_all().await.unwrap();
+            for chunk in full.chunks(64) {
+                file.write_all(chunk).await.unwrap();
+            }
+            fs.crash("node");
+
+            let recovered = disk.read("log").await.unwrap();
+            assert!(recovered.starts_with(b"head"));
+            assert!(full.starts_with(&recovered[4..]));
+            seen_lengths.push(recovered.len());
+        }
+        seen_lengths.dedup();
+        assert!(
+            seen_lengths.len() > 1,
+            "torn writes always cut at one place"
+        );
+    });
+}
+
+#[test]
+fn test_enospc_and_eio() {
+    block_on(async {
+        let fs = SimFs::new(4);
+        let disk = fs.host("node");
+        fs.set_faults(
+            "node",
+            DiskFaults {
+                capacity_bytes: Some(8),
+                ..DiskFaults::default()
+            },
+        );
+        disk.write("a", b"12345").await.unwrap();
+        let err = disk.write("b", b"6789").await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(28));
+        // Overwriting in place needs no new space.
+        disk.write("a", b"abcde").await.unwrap();
+
+        fs.set_faults(
+            "node",
+            DiskFaults {
+                eio_percent: 100,
+                ..DiskFaults::default()
+            },
+        );
+        let err = disk.read("a").await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(5));
+    });
+}
+
+#[test]
+fn test_deleted_files_free_their_space() {
+    block_on(async {
+        let fs = SimFs::new(5);
+        let disk = fs.host("node");
+        fs.set_faults(
+            "node",
+            DiskFaults {
+                capacity_bytes: Some(8),
+                ..DiskFaults::default()
+            },
+        );
+        let full = [7u8; 8];
+
+        disk.write("a", full).await.unwrap();
+        disk.remove_file("a").await.unwrap();
+        disk.write("b", full).await.unwrap();
+
+        // Renaming over a file frees the replaced one.
+        disk.write("b", b"1234").await.unwrap();
+        disk.write("tmp", b"5678").await.unwrap();
+        disk.rename("tmp", "b").await.unwrap();
+        disk.write("c", b"abcd").await.unwrap();
+        disk.remove_file("c").await.unwrap();
+
+        // An open handle keeps a removed file's bytes until it is dropped.
+        let file = disk.open("b").await.unwrap();
+        disk.remove_file("b").await.unwrap();
+        let err = disk.write("d", full).await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(28));
+        drop(file);
+        disk.write("d", full).await.unwrap();
+
+        // ... or until the host crashes.
+        let file = disk.open("d").await.unwrap();
+        file.sync_all().await.unwrap();
+        disk.remove_file("d").await.unwrap();
+        fs.crash("node");
+        disk.write("e", full).await.unwrap();
+        drop(file);
+        disk.write("e", full).await.unwrap();
+    });
+}
+
+/// A host appends one byte per 100ms to its log, syncing only if `sync`,
+/// and records how many bytes it finds each time it starts.
+fn recovered_after_restart(sync: bool) -> Vec<usize> {
+    let fs = SimFs::new(5);
+    let recovered = Arc::new(Mutex::new(Vec::new()));
+    let mut sim = Builder::new().rng_seed(5).build();
+
+    let disk = fs.host("db");
+    let starts = recovered.clone();
+    sim.host("db", move || {
+        let disk = disk.clone();
+        let starts = starts.clone();
+        async move {
+            let existing = disk.read("log").await.map(|d| d.len()).unwrap_or(0);
+            starts.lock().unwrap().push(existing);
+
+            let mut log = if existing > 0 {
+                let mut log = disk.open("log").await?;
+                log.seek(std::io::SeekFrom::End(0)).await?;
+                log
+            } else {
+                disk.create("log").await?
+            };
+            loop {
+                log.write_all(b"x").await?;
+                if sync {
+                    log.sync_data().await?;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    });
+
+    let schedule = vec![(Duration::from_secs(2), Fault::Restart { node: "db".into() })];
+    let mut driver = FaultDriver::new(["db"], schedule).with_fs(fs);
+    driver.run_until(&mut sim, Duration::from_secs(3)).unwrap();
+
+    let recovered = recovered.lock().unwrap().clone();
+    recovered
+}
+
+#[test]
+fn test_driver_crash_loses_unsynced_log() {
+    let synced = recovered_after_restart(true);
+    assert_eq!(synced.len(), 2);
+    assert_eq!(synced[0], 0);
+    assert!(synced[1] >= 19, "recovered {:?}", synced);
+
+    assert_eq!(recovered_after_restart(false), [0, 0]);
+}
@@ -0,0 +1,49 @@
+//! Integration tests for deterministic hashing.
+
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use dst::{det_hash_map, DetHashMap, DeterministicBuildHasher, DeterministicHasher};
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    DeterministicBuildHasher.hash_one(value)
+}
+
+#[test]
+fn test_fnv1a_known_vectors() {
+    // Reference values from the FNV-1a 64-bit specification.
+    let mut empty = DeterministicBuildHasher.build_hasher();
+    empty.write(b"");
+    assert_eq!(empty.finish(), 0xcbf29ce484222325);
+
+    let mut a = DeterministicBuildHasher.build_hasher();
+    a.write(b"a");
+    assert_eq!(a.finish(), 0xaf63dc4c8601ec8c);
+
+    let mut foobar = DeterministicBuildHasher.build_hasher();
+    foobar.write(b"foobar");
+    assert_eq!(foobar.finish(), 0x85944171f73967e8);
+}
+
+#[test]
+fn test_hash_is_stable_across_builders() {
+    assert_eq!(hash_of(&"node-a"), hash_of(&"node-a"));
+    assert_ne!(hash_of(&"node-a"), hash_of(&"node-b"));
+}
+
+#[test]
+fn test_default_hasher_starts_from_zero() {
+    assert_eq!(DeterministicHasher::default().finish(), 0);
+}
+
+#[test]
+fn test_det_hash_map_iteration_order_is_reproducible() {
+    let build = || {
+        let mut map: DetHashMap<String, u64> = det_hash_map();
+        for i in 0..256u64 {
+            map.insert(format!("key-{}", i), i);
+        }
+        map.into_iter().collect::<Vec<_>>()
+    };
+
+    assert_eq!(build(), build());
+}
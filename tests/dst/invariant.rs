@@ -0,0 +1,185 @@
+//! Integration tests for per-step invariant monitors.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dst::turmoil::Builder;
+use dst::{DetHashMap, Fault, FaultDriver, InvariantMonitor, MonitorError};
+
+/// What each node believes, published for the monitor.
+#[derive(Debug, Default)]
+struct ClusterState {
+    heartbeats: DetHashMap<String, u64>,
+    leaders: DetHashMap<u64, Vec<String>>,
+}
+
+/// At most one leader per term.
+fn single_leader(state: &ClusterState) -> Result<(), String> {
+    for (term, leaders) in &state.leaders {
+        if leaders.len() > 1 {
+            return Err(format!("term {} has leaders {:?}", term, leaders));
+        }
+    }
+    Ok(())
+}
+
+/// Heartbeat counters never go backwards.
+fn no_heartbeat_regression() -> impl FnMut(&ClusterState) -> Result<(), String> {
+    let mut last = DetHashMap::default();
+    move |state: &ClusterState| {
+        for (node, &beat) in &state.heartbeats {
+            let previous = last.insert(node.clone(), beat).unwrap_or(0);
+            if beat < previous {
+                return Err(format!("{} went from {} to {}", node, previous, beat));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Registers a node that heartbeats every 100ms and claims leadership of
+/// `term` at `claim_at`.
+fn node(
+    sim: &mut dst::turmoil::Sim<'_>,
+    name: &'static str,
+    state: &Arc<Mutex<ClusterState>>,
+    claim: Option<(Duration, u64)>,
+) {
+    let state = state.clone();
+    sim.host(name, move || {
+        let state = state.clone();
+        async move {
+            let mut tick = 0;
+            loop {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                tick += 1;
+                let now = dst::turmoil::sim_elapsed().unwrap_or_default();
+                let mut s = state.lock().unwrap();
+                s.heartbeats.insert(name.to_string(), tick);
+                if let Some((at, term)) = claim {
+                    if now >= at {
+                        let leaders = s.leaders.entry(term).or_default();
+                        if !leaders.iter().any(|l| l == name) {
+                            leaders.push(name.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[test]
+fn test_healthy_cluster_passes_every_step() {
+    let state = Arc::new(Mutex::new(ClusterState::default()));
+    let mut sim = Builder::new().rng_seed(1).build();
+    node(
+        &mut sim,
+        "node-a",
+        &state,
+        Some((Duration::from_millis(500), 1)),
+    );
+    node(&mut sim, "node-b", &state, None);
+
+    let mut monitor = InvariantMonitor::new(1, state.clone())
+        .invariant("single leader per term", single_leader)
+        .invariant("no heartbeat regression", no_heartbeat_regression());
+
+    monitor.check(&sim).unwrap();
+    monitor.run_until(&mut sim, Duration::from_secs(2)).unwrap();
+
+    assert_eq!(monitor.steps(), 2000);
+    assert_eq!(state.lock().unwrap().heartbeats["node-b"], 19);
+}
+
+#[test]
+fn test_violation_stops_at_the_offending_step() {
+    let state = Arc::new(Mutex::new(ClusterState::default()));
+    let mut sim = Builder::new().rng_seed(9).build();
+    node(
+        &mut sim,
+        "node-a",
+        &state,
+        Some((Duration::from_millis(300), 4)),
+    );
+    node(
+        &mut sim,
+        "node-b",
+        &state,
+        Some((Duration::from_millis(700), 4)),
+    );
+
+    let mut monitor = InvariantMonitor::new(9, state)
+        .invariant("no heartbeat regression", no_heartbeat_regression())
+        .invariant("single leader per term", single_leader);
+
+    let err = monitor
+        .run_until(&mut sim, Duration::from_secs(60))
+        .unwrap_err();
+    let violation = err.violation().expect("invariant violation");
+
+    assert_eq!(violation.invariant, "single leader per term");
+    assert_eq!(violation.seed, 9);
+    // node-b first claims on its 700ms heartbeat; turmoil advances the
+    // clock one tick after running hosts, so that is step 701 and nothing
+    // past it runs.
+    assert_eq!(violation.step, 701);
+    assert_eq!(violation.elapsed, Duration::from_millis(701));
+    assert_eq!(monitor.steps(), 701);
+    assert_eq!(sim.elapsed(), Duration::from_millis(701));
+
+    let report = err.to_string();
+    assert!(
+        report.starts_with("invariant `single leader per term` violated at step 701"),
+        "{}",
+        report
+    );
+    assert!(report.contains("TEST_SEED=9"), "{}", report);
+    assert!(report.contains("term 4 has leaders"), "{}", report);
+}
+
+#[test]
+fn test_stateful_invariant_catches_restart_that_loses_progress() {
+    let state = Arc::new(Mutex::new(ClusterState::default()));
+    let mut sim = Builder::new().rng_seed(3).build();
+    node(&mut sim, "node-a", &state, None);
+    node(&mut sim, "node-b", &state, None);
+    sim.client("observer", async {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        Ok(())
+    });
+
+    // Restarting node-a resets its in-memory tick counter to zero.
+    let schedule = vec![(
+        Duration::from_secs(1),
+        Fault::Restart {
+            node: "node-a".into(),
+        },
+    )];
+    let mut driver = FaultDriver::new(["node-a", "node-b"], schedule);
+    let mut monitor = InvariantMonitor::new(3, state)
+        .invariant("no heartbeat regression", no_heartbeat_regression());
+
+    let err = monitor.run_with(&mut driver, &mut sim).unwrap_err();
+    let violation = err.violation().expect("invariant violation");
+
+    assert_eq!(violation.invariant, "no heartbeat regression");
+    // The restart lands before node-a's tenth heartbeat.
+    assert_eq!(violation.message, "node-a went from 9 to 1");
+    assert_eq!(violation.elapsed, Duration::from_millis(1101));
+    assert_eq!(driver.applied().len(), 1);
+}
+
+#[test]
+fn test_simulation_errors_are_not_violations() {
+    let state = Arc::new(Mutex::new(()));
+    let mut sim = Builder::new().build();
+    sim.client("client", async { Err("boom".into()) });
+
+    let mut monitor = InvariantMonitor::new(0, state).invariant("never checked", |_| Ok(()));
+    let err = monitor.run(&mut sim).unwrap_err();
+
+    assert!(matches!(err, MonitorError::Simulation(_)));
+    assert!(err.violation().is_none());
+    assert_eq!(monitor.steps(), 0);
+}
@@ -0,0 +1,257 @@
+//! Integration tests for the linearizability checker.
+
+use std::time::Duration;
+
+use dst::linearizability::{
+    HistoryRecorder, KvOp, KvRet, KvStore, Queue, QueueOp, QueueRet, Register, RegisterOp,
+    RegisterRet,
+};
+use dst::turmoil::net::{TcpListener, TcpStream};
+use dst::turmoil::Builder;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+type RegisterHistory = HistoryRecorder<RegisterOp<u64>, RegisterRet<u64>>;
+
+/// Records a complete operation spanning `[start, end]` milliseconds.
+/// Operations must be added in invocation order; responses are recorded
+/// later by the caller when they overlap.
+fn op<Op, Ret>(rec: &HistoryRecorder<Op, Ret>, start: u64, end: u64, client: &str, o: Op, r: Ret) {
+    let id = rec.invoke_at(ms(start), client, o);
+    rec.complete_at(ms(end), id, r);
+}
+
+#[test]
+fn test_sequential_register_history_is_linearizable() {
+    let rec = RegisterHistory::new();
+    op(&rec, 0, 1, "a", RegisterOp::Write(1), RegisterRet::Write);
+    op(&rec, 2, 3, "b", RegisterOp::Read, RegisterRet::Read(1));
+    op(
+        &rec,
+        4,
+        5,
+        "a",
+        RegisterOp::Cas {
+            expected: 1,
+            new: 2,
+        },
+        RegisterRet::Cas(true),
+    );
+    op(&rec, 6, 7, "b", RegisterOp::Read, RegisterRet::Read(2));
+
+    assert!(rec.history().check(Register::new(0)).is_ok());
+}
+
+#[test]
+fn test_concurrent_reads_may_see_either_value() {
+    let rec = RegisterHistory::new();
+    let write = rec.invoke_at(ms(0), "a", RegisterOp::Write(1));
+    op(&rec, 1, 2, "b", RegisterOp::Read, RegisterRet::Read(1));
+    op(&rec, 3, 4, "c", RegisterOp::Read, RegisterRet::Read(1));
+    rec.complete_at(ms(5), write, RegisterRet::Write);
+
+    assert!(rec.history().check(Register::new(0)).is_ok());
+
+    // Once a read has observed the write, a later read may not go back.
+    let rec = RegisterHistory::new();
+    let write = rec.invoke_at(ms(0), "a", RegisterOp::Write(1));
+    op(&rec, 1, 2, "b", RegisterOp::Read, RegisterRet::Read(1));
+    op(&rec, 3, 4, "c", RegisterOp::Read, RegisterRet::Read(0));
+    rec.complete_at(ms(5), write, RegisterRet::Write);
+
+    assert!(rec.history().check(Register::new(0)).is_err());
+}
+
+#[test]
+fn test_violation_reports_minimal_sub_history() {
+    let rec = RegisterHistory::new();
+    op(&rec, 0, 1, "a", RegisterOp::Write(5), RegisterRet::Write);
+    op(&rec, 2, 3, "b", RegisterOp::Read, RegisterRet::Read(5));
+    op(&rec, 4, 5, "c", RegisterOp::Write(6), RegisterRet::Write);
+    op(&rec, 6, 7, "a", RegisterOp::Read, RegisterRet::Read(6));
+    op(&rec, 8, 9, "b", RegisterOp::Write(7), RegisterRet::Write);
+    op(&rec, 10, 11, "c", RegisterOp::Read, RegisterRet::Read(6));
+    op(&rec, 12, 13, "a", RegisterOp::Read, RegisterRet::Read(6));
+
+    let violation = rec.history().check(Register::new(0)).unwrap_err();
+    assert_eq!(violation.total, 7);
+
+    // Stale read of 6 after the write of 7 completed.
+    let ops: Vec<_> = violation
+        .operations
+        .iter()
+        .map(|o| (o.op.clone(), o.ret.clone().unwrap()))
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            (RegisterOp::Write(6), RegisterRet::Write),
+            (RegisterOp::Write(7), RegisterRet::Write),
+            (RegisterOp::Read, RegisterRet::Read(6)),
+        ]
+    );
+    assert!(violation.to_string().contains("not linearizable"));
+}
+
+#[test]
+fn test_incomplete_operations_may_or_may_not_take_effect() {
+    let rec = RegisterHistory::new();
+    let _lost = rec.invoke_at(ms(0), "a", RegisterOp::Write(1));
+    op(&rec, 1, 2, "b", RegisterOp::Read, RegisterRet::Read(0));
+    op(&rec, 3, 4, "b", RegisterOp::Read, RegisterRet::Read(1));
+    assert!(rec.history().check(Register::new(0)).is_ok());
+
+    let rec = RegisterHistory::new();
+    let _lost = rec.invoke_at(ms(0), "a", RegisterOp::Write(1));
+    op(&rec, 1, 2, "b", RegisterOp::Read, RegisterRet::Read(0));
+    assert!(rec.history().check(Register::new(0)).is_ok());
+}
+
+#[test]
+fn test_kv_and_queue_models() {
+    let kv = HistoryRecorder::new();
+    op(&kv, 0, 1, "a", KvOp::Put("x", 1), KvRet::Ok);
+    op(&kv, 2, 3, "b", KvOp::Get("y"), KvRet::Value(None));
+    op(&kv, 4, 5, "b", KvOp::Get("x"), KvRet::Value(Some(1)));
+    op(&kv, 6, 7, "a", KvOp::Delete("x"), KvRet::Ok);
+    assert!(kv.history().check(KvStore::new()).is_ok());
+    op(&kv, 8, 9, "b", KvOp::Get("x"), KvRet::Value(Some(1)));
+    assert!(kv.history().check(KvStore::new()).is_err());
+
+    let queue = HistoryRecorder::new();
+    op(&queue, 0, 1, "a", QueueOp::Enqueue(1), QueueRet::Ok);
+    op(&queue, 2, 3, "a", QueueOp::Enqueue(2), QueueRet::Ok);
+    op(
+        &queue,
+        4,
+        5,
+        "b",
+        QueueOp::Dequeue,
+        QueueRet::Value(Some(2)),
+    );
+    let violation = queue.history().check(Queue::new()).unwrap_err();
+    // Enqueue(2) may be treated as indeterminate: Enqueue(1) completing before
+    // a dequeue that returns anything else is already a contradiction.
+    let ops: Vec<_> = violation.operations.iter().map(|o| o.op.clone()).collect();
+    assert_eq!(ops, [QueueOp::Enqueue(1), QueueOp::Dequeue]);
+}
+
+#[test]
+fn test_minimizing_a_highly_concurrent_history_terminates() {
+    // The slow write overlaps every other operation, so each relaxed write
+    // stays in play and an exhaustive minimization would never finish.
+    let rec = RegisterHistory::new();
+    let slow = rec.invoke_at(ms(0), "a", RegisterOp::Write(1_000));
+    for i in 0..60 {
+        op(
+            &rec,
+            2 * i + 1,
+            2 * i + 2,
+            "b",
+            RegisterOp::Write(i),
+            RegisterRet::Write,
+        );
+    }
+    op(&rec, 200, 201, "c", RegisterOp::Read, RegisterRet::Read(7));
+    rec.complete_at(ms(300), slow, RegisterRet::Write);
+
+    let violation = rec.history().check(Register::new(0)).unwrap_err();
+    assert_eq!(violation.total, 62);
+    let last = violation.operations.last().unwrap();
+    assert_eq!(last.ret, Some(RegisterRet::Read(7)));
+}
+
+/// A single-server register over TCP. With `cache_reads`, clients answer
+/// reads from their first write instead of asking the server, a cache that
+/// is never invalidated.
+fn run_register_cluster(seed: u64, cache_reads: bool) -> RegisterHistory {
+    let history = RegisterHistory::new();
+    let mut sim = Builder::new().rng_seed(seed).build();
+
+    sim.host("server", || async {
+        let listener = TcpListener::bind("0.0.0.0:9000").await?;
+        let value = std::sync::Arc::new(std::sync::Mutex::new(0u64));
+        loop {
+            let (mut stream, _) = listener.accept().await?;
+            let value = value.clone();
+            tokio::spawn(async move {
+                let mut request = [0u8; 9];
+                while stream.read_exact(&mut request).await.is_ok() {
+                    let reply = {
+                        let mut value = value.lock().unwrap();
+                        if request[0] == b'w' {
+                            *value = u64::from_be_bytes(request[1..].try_into().unwrap());
+                        }
+                        *value
+                    };
+                    if stream.write_all(&reply.to_be_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    for client in 0..3u64 {
+        let name = format!("client-{}", client);
+        let history = history.clone();
+        sim.client(name.clone(), async move {
+            let mut stream = TcpStream::connect("server:9000").await?;
+            let mut first_write = None;
+            for i in 0..4u64 {
+                let write = i % 2 == 0;
+                let op = if write {
+                    RegisterOp::Write(client * 10 + i)
+                } else {
+                    RegisterOp::Read
+                };
+                let id = history.invoke(name.as_str(), op);
+
+                let value = client * 10 + i;
+                if let (false, true, Some(cached)) = (write, cache_reads, first_write) {
+                    history.complete(id, RegisterRet::Read(cached));
+                    continue;
+                }
+                let mut request = [0u8; 9];
+                request[0] = if write { b'w' } else { b'r' };
+                request[1..].copy_from_slice(&value.to_be_bytes());
+                stream.write_all(&request).await?;
+                let mut reply = [0u8; 8];
+                stream.read_exact(&mut reply).await?;
+                if write {
+                    first_write.get_or_insert(value);
+                    history.complete(id, RegisterRet::Write);
+                } else {
+                    history.complete(id, RegisterRet::Read(u64::from_be_bytes(reply)));
+                }
+            }
+            Ok(())
+        });
+    }
+
+    sim.run().unwrap();
+    history
+}
+
+#[test]
+fn test_simulated_register_is_linearizable() {
+    for seed in 0..5 {
+        let history = run_register_cluster(seed, false).history();
+        assert_eq!(history.operations().len(), 12);
+        if let Err(violation) = history.check(Register::new(0)) {
+            panic!("seed {}: {}", seed, violation);
+        }
+    }
+}
+
+#[test]
+fn test_client_read_cache_is_caught() {
+    let violation = run_register_cluster(1, true)
+        .history()
+        .check(Register::new(0))
+        .unwrap_err();
+    assert!(violation.operations.len() <= 3, "{}", violation);
+}
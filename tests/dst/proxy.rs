@@ -0,0 +1,257 @@
+//! Integration tests for the TCP chaos proxy, over real localhost sockets.
+
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use dst::{ChaosProxy, Error, Fault};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Instant};
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+/// An echo server, like the one in `test_echo_server`, that reports how
+/// each connection ended.
+async fn echo_server() -> (SocketAddr, mpsc::UnboundedReceiver<ErrorKind>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (ended, ends) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let ended = ended.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let end = loop {
+                    match socket.read(&mut buf).await {
+                        Ok(0) => break ErrorKind::UnexpectedEof,
+                        Ok(n) => {
+                            if let Err(e) = socket.write_all(&buf[..n]).await {
+                                break e.kind();
+                            }
+                        }
+                        Err(e) => break e.kind(),
+                    }
+                };
+                let _ = ended.send(end);
+            });
+        }
+    });
+    (addr, ends)
+}
+
+/// Sends `message` and reads back as many bytes, or fails after `limit`.
+async fn echo(socket: &mut TcpStream, message: &[u8], limit: Duration) -> std::io::Result<Vec<u8>> {
+    socket.write_all(message).await?;
+    let mut buf = vec![0u8; message.len()];
+    match timeout(limit, socket.read_exact(&mut buf)).await {
+        Ok(read) => read.map(|_| buf),
+        Err(_) => Err(ErrorKind::TimedOut.into()),
+    }
+}
+
+#[test]
+fn test_proxy_forwards_and_delays() {
+    block_on(async {
+        let (server, _) = echo_server().await;
+        let proxy = ChaosProxy::start("127.0.0.1:0", server).await.unwrap();
+        assert_eq!(proxy.upstream(), server);
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        assert_eq!(
+            echo(&mut socket, b"hello world", ms(1_000)).await.unwrap(),
+            b"hello world"
+        );
+
+        // 150ms each way.
+        proxy
+            .apply(&Fault::Latency {
+                node: "server".into(),
+                delay_ms: 150,
+            })
+            .unwrap();
+        let start = Instant::now();
+        echo(&mut socket, b"slow", ms(2_000)).await.unwrap();
+        assert!(start.elapsed() >= ms(300), "{:?}", start.elapsed());
+
+        proxy.heal();
+        let start = Instant::now();
+        echo(&mut socket, b"fast", ms(1_000)).await.unwrap();
+        assert!(start.elapsed() < ms(150), "{:?}", start.elapsed());
+    });
+}
+
+#[test]
+fn test_bandwidth_cap_limits_throughput() {
+    block_on(async {
+        let (server, _) = echo_server().await;
+        let proxy = ChaosProxy::start("127.0.0.1:0", server).await.unwrap();
+        proxy
+            .apply(&Fault::Bandwidth {
+                node_a: "client".into(),
+                node_b: "server".into(),
+                bytes_per_sec: 20_000,
+            })
+            .unwrap();
+
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        let start = Instant::now();
+        let message = vec![7u8; 10_000];
+        assert_eq!(
+            echo(&mut socket, &message, ms(5_000)).await.unwrap(),
+            message
+        );
+        // Half a second each way, pipelined.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= ms(450) && elapsed < ms(2_000), "{:?}", elapsed);
+    });
+}
+
+#[test]
+fn test_partition_blackholes_until_repaired() {
+    block_on(async {
+        let (server, _) = echo_server().await;
+        let proxy = ChaosProxy::start("127.0.0.1:0", server).await.unwrap();
+        let partition = Fault::Partition {
+            node_a: "client".into(),
+            node_b: "server".into(),
+        };
+
+        proxy.apply(&partition).unwrap();
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        // The write succeeds; the echo never comes.
+        let err = echo(&mut socket, b"lost", ms(300)).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        proxy
+            .apply(&Fault::Repair {
+                node_a: "client".into(),
+                node_b: "server".into(),
+            })
+            .unwrap();
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        assert_eq!(
+            echo(&mut socket, b"back", ms(1_000)).await.unwrap(),
+            b"back"
+        );
+    });
+}
+
+#[test]
+fn test_resets_crashes_and_restarts() {
+    block_on(async {
+        let (server, mut ends) = echo_server().await;
+        let proxy = ChaosProxy::start("127.0.0.1:0", server).await.unwrap();
+
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        echo(&mut socket, b"one", ms(1_000)).await.unwrap();
+        proxy.reset_connections();
+        let mut buf = [0u8; 8];
+        let err = socket.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+        assert_eq!(ends.recv().await, Some(ErrorKind::ConnectionReset));
+
+        // A crashed server resets connections as they arrive.
+        proxy
+            .apply(&Fault::Crash {
+                node: "server".into(),
+            })
+            .unwrap();
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        assert!(echo(&mut socket, b"two", ms(1_000)).await.is_err());
+
+        proxy
+            .apply(&Fault::Restart {
+                node: "server".into(),
+            })
+            .unwrap();
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        assert_eq!(
+            echo(&mut socket, b"three", ms(1_000)).await.unwrap(),
+            b"three"
+        );
+    });
+}
+
+#[test]
+fn test_half_open_connection_goes_silent_for_the_client() {
+    block_on(async {
+        let (server, mut ends) = echo_server().await;
+        let proxy = ChaosProxy::start("127.0.0.1:0", server).await.unwrap();
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        echo(&mut socket, b"hello", ms(1_000)).await.unwrap();
+
+        proxy.half_open_connections();
+        // The server's side is gone...
+        assert_eq!(ends.recv().await, Some(ErrorKind::ConnectionReset));
+        // ...while the client can still write and waits forever for a reply.
+        let err = echo(&mut socket, b"anyone?", ms(300)).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        // New connections are unaffected.
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        assert_eq!(
+            echo(&mut socket, b"fresh", ms(1_000)).await.unwrap(),
+            b"fresh"
+        );
+    });
+}
+
+#[test]
+fn test_scenarios_replay_supported_faults_only() {
+    block_on(async {
+        let (server, _) = echo_server().await;
+        let proxy = ChaosProxy::start("127.0.0.1:0", server).await.unwrap();
+
+        let duplicate = Fault::Duplicate {
+            node_a: "client".into(),
+            node_b: "server".into(),
+            percent: 10,
+        };
+        assert!(!ChaosProxy::supports(&duplicate));
+        let scenario = vec![
+            (
+                ms(0),
+                Fault::Latency {
+                    node: "server".into(),
+                    delay_ms: 500,
+                },
+            ),
+            (ms(10), duplicate.clone()),
+        ];
+        match proxy.run_scenario(&scenario).await {
+            Err(Error::UnsupportedFault(fault)) => assert_eq!(fault, duplicate),
+            other => panic!("expected an unsupported fault, got {:?}", other),
+        }
+        // Nothing was applied.
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        echo(&mut socket, b"quick", ms(200)).await.unwrap();
+
+        let partition = |at, fault: fn(String, String) -> Fault| {
+            (ms(at), fault("client".into(), "server".into()))
+        };
+        let scenario = vec![
+            partition(100, |node_a, node_b| Fault::Repair { node_a, node_b }),
+            partition(0, |node_a, node_b| Fault::Partition { node_a, node_b }),
+        ];
+        let start = Instant::now();
+        proxy.run_scenario(&scenario).await.unwrap();
+        assert!(start.elapsed() >= ms(100));
+        let mut socket = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        assert_eq!(
+            echo(&mut socket, b"healed", ms(1_000)).await.unwrap(),
+            b"healed"
+        );
+    });
+}
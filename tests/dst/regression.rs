@@ -0,0 +1,152 @@
+//! Integration tests for the regression corpus of failing seeds.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use dst::{Fault, Regression, RegressionCorpus, Scenario};
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+fn crash(node: &str) -> Fault {
+    Fault::Crash { node: node.into() }
+}
+
+fn regression(seed: u64, scenario: Option<Scenario>) -> Regression {
+    Regression {
+        seed,
+        message: format!("seed {} failed", seed),
+        scenario,
+    }
+}
+
+/// Runs `f`, which is expected to panic, and returns the panic message.
+fn expect_panic(f: impl FnOnce()) -> String {
+    let payload = panic::catch_unwind(AssertUnwindSafe(f)).expect_err("expected a panic");
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_regressions_round_trip_through_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested").join("test_round_trip.jsonl");
+
+    let mut corpus = RegressionCorpus::open("test_round_trip", &path).unwrap();
+    assert!(corpus.regressions().is_empty());
+    assert!(!path.exists());
+
+    let shrunk = vec![(secs(0), crash("beta")), (secs(2), crash("gamma"))];
+    assert!(corpus.record(regression(11, None)).unwrap());
+    assert!(corpus.record(regression(7, Some(shrunk.clone()))).unwrap());
+    assert!(!corpus.record(regression(11, None)).unwrap());
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.starts_with("# Seeds that failed test_round_trip"));
+    assert_eq!(contents.lines().filter(|l| !l.starts_with('#')).count(), 2);
+
+    let reopened = RegressionCorpus::open("test_round_trip", &path).unwrap();
+    assert_eq!(reopened.seeds(), vec![11, 7]);
+    assert_eq!(reopened.regressions(), corpus.regressions());
+    assert_eq!(reopened.get(7).unwrap().scenario, Some(shrunk));
+}
+
+#[test]
+fn test_malformed_files_are_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("broken.jsonl");
+    std::fs::write(&path, "# comment\n{\"seed\": \"twelve\"}\n").unwrap();
+
+    assert!(matches!(
+        RegressionCorpus::open("broken", &path),
+        Err(dst::Error::Json(_))
+    ));
+}
+
+#[test]
+fn test_recorded_seeds_replay_before_a_new_seed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test_replay.jsonl");
+    let mut corpus = RegressionCorpus::open("test_replay", &path).unwrap();
+    corpus.record(regression(3, None)).unwrap();
+    corpus.record(regression(5, None)).unwrap();
+
+    let seen = Mutex::new(Vec::new());
+    corpus.run(|seed| seen.lock().unwrap().push(seed));
+
+    let seen = seen.into_inner().unwrap();
+    assert_eq!(seen.len(), 3);
+    assert_eq!(seen[..2], [3, 5]);
+}
+
+#[test]
+fn test_new_failing_seeds_are_recorded_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test_failing.jsonl");
+
+    let mut corpus = RegressionCorpus::open("test_failing", &path).unwrap();
+    let message = expect_panic(|| corpus.run(|seed| panic!("seed {} failed", seed)));
+    let seeds = corpus.seeds();
+    assert_eq!(seeds.len(), 1);
+    assert_eq!(message, format!("seed {} failed", seeds[0]));
+
+    // The recorded seed fails first on the next run and is not saved again.
+    let mut corpus = RegressionCorpus::open("test_failing", &path).unwrap();
+    assert_eq!(corpus.regressions()[0].message, message);
+    let again = expect_panic(|| corpus.run(|seed| panic!("seed {} failed", seed)));
+    assert_eq!(again, message);
+    assert_eq!(corpus.seeds(), seeds);
+
+    // Once the bug is fixed the recorded seed passes, followed by a new one.
+    let seen = Mutex::new(Vec::new());
+    corpus.run(|seed| seen.lock().unwrap().push(seed));
+    assert_eq!(seen.into_inner().unwrap()[0], seeds[0]);
+}
+
+#[test]
+fn test_failing_scenarios_are_shrunk_before_recording() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test_scenarios.jsonl");
+    let generate = |_seed: u64| {
+        vec![
+            (secs(5), crash("alpha")),
+            (secs(9), crash("beta")),
+            (secs(12), crash("gamma")),
+        ]
+    };
+    // Fails whenever beta crashes.
+    let test = |_seed: u64, scenario: &[(Duration, Fault)]| {
+        assert!(
+            !scenario.iter().any(|(_, fault)| *fault == crash("beta")),
+            "beta crashed"
+        );
+    };
+
+    let mut corpus = RegressionCorpus::open("test_scenarios", &path).unwrap();
+    assert_eq!(
+        expect_panic(|| corpus.run_scenarios(generate, test)),
+        "beta crashed"
+    );
+
+    let mut corpus = RegressionCorpus::open("test_scenarios", &path).unwrap();
+    let recorded = corpus.regressions()[0].clone();
+    assert_eq!(recorded.message, "beta crashed");
+    assert_eq!(
+        recorded.scenario,
+        Some(vec![(Duration::ZERO, crash("beta"))])
+    );
+
+    // Replays get the shrunk scenario; the new seed gets a generated one.
+    let seen = Mutex::new(Vec::new());
+    corpus.run_scenarios(generate, |seed, scenario| {
+        seen.lock().unwrap().push((seed, scenario.len()));
+    });
+    let seen = seen.into_inner().unwrap();
+    assert_eq!(seen[0], (recorded.seed, 1));
+    assert_eq!(seen[1].1, 3);
+}
@@ -0,0 +1,387 @@
+//! Integration tests for declarative scenarios and the runner.
+
+use std::time::Duration;
+
+use dst::runner::{HostContext, RunError, Runner, ScenarioSpec};
+use dst::turmoil::net::UdpSocket;
+use dst::{Fault, FaultWeights};
+
+/// What the ping scenario's hosts and clients share with the invariants.
+#[derive(Debug, Default)]
+struct Pings {
+    starts: u32,
+    sent: u32,
+    answered: u32,
+}
+
+const PING: &str = r#"
+name = "ping"
+description = "One client pings two echo servers."
+duration_ms = 20_000
+invariants = ["answered <= sent", "all answered"]
+
+[latency]
+min_ms = 1
+max_ms = 5
+
+[[hosts]]
+name = "echo-a"
+factory = "echo"
+
+[[hosts]]
+name = "echo-b"
+factory = "echo"
+
+[[clients]]
+name = "pinger"
+factory = "pinger"
+args = { rounds = 20 }
+"#;
+
+fn echo(ctx: &HostContext<Pings>) -> impl std::future::Future<Output = dst::turmoil::Result> {
+    ctx.state.lock().unwrap().starts += 1;
+    async {
+        let socket = UdpSocket::bind("0.0.0.0:9000").await?;
+        let mut buf = [0u8; 4];
+        loop {
+            let (n, from) = socket.recv_from(&mut buf).await?;
+            socket.send_to(&buf[..n], from).await?;
+        }
+    }
+}
+
+fn pinger(ctx: &HostContext<Pings>) -> impl std::future::Future<Output = dst::turmoil::Result> {
+    let state = ctx.state.clone();
+    let targets = ctx.hosts.clone();
+    let rounds: Result<Option<u32>, _> = ctx.arg("rounds");
+    async move {
+        let socket = UdpSocket::bind("0.0.0.0:9000").await?;
+        let mut buf = [0u8; 4];
+        for round in 0..rounds?.unwrap_or(1) {
+            for target in &targets {
+                socket
+                    .send_to(&round.to_be_bytes(), (target.as_str(), 9000))
+                    .await?;
+                state.lock().unwrap().sent += 1;
+                let reply =
+                    tokio::time::timeout(Duration::from_millis(200), socket.recv_from(&mut buf))
+                        .await;
+                if reply.is_ok() {
+                    state.lock().unwrap().answered += 1;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Ok(())
+    }
+}
+
+fn runner() -> Runner<Pings> {
+    Runner::new()
+        .factory("echo", echo)
+        .factory("pinger", pinger)
+        .invariant("answered <= sent", |pings: &Pings| {
+            if pings.answered <= pings.sent {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} answers to {} pings",
+                    pings.answered, pings.sent
+                ))
+            }
+        })
+        .check("all answered", |pings: &Pings| {
+            if pings.answered == pings.sent {
+                Ok(())
+            } else {
+                Err(format!("{} of {} answered", pings.answered, pings.sent))
+            }
+        })
+}
+
+fn ping(extra: &str) -> ScenarioSpec {
+    ScenarioSpec::from_toml(&format!("{}{}", PING, extra)).unwrap()
+}
+
+fn spec_error(spec: &ScenarioSpec) -> String {
+    match runner().run(spec, 1) {
+        Err(RunError::Spec(message)) => message,
+        other => panic!("expected a spec error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_specs_parse_from_toml() {
+    let spec = ping(
+        r#"
+[[faults]]
+at_ms = 3_000
+kind = "crash"
+node = "echo-b"
+
+[[faults]]
+at_ms = 1_000
+kind = "partition"
+node_a = "echo-a"
+node_b = "pinger"
+
+[generate]
+faults = 4
+nodes = ["echo-a"]
+weights = { crash = 0, restart = 0, reorder = 3 }
+"#,
+    );
+    assert_eq!(spec.name, "ping");
+    assert_eq!(spec.duration(), Duration::from_secs(20));
+    assert_eq!(spec.nodes(), ["echo-a", "echo-b", "pinger"]);
+    assert_eq!(spec.clients[0].args["rounds"], 20);
+    assert_eq!(
+        spec.faults[1],
+        (
+            Duration::from_secs(1),
+            Fault::Partition {
+                node_a: "echo-a".into(),
+                node_b: "pinger".into(),
+            }
+        )
+    );
+
+    let generate = spec.generate.as_ref().unwrap();
+    assert_eq!(
+        generate.weights,
+        FaultWeights {
+            crash: 0,
+            restart: 0,
+            reorder: 3,
+            ..FaultWeights::default()
+        }
+    );
+
+    // Fixed and generated faults merge in time order, the same for a seed.
+    let schedule = spec.schedule(5);
+    assert!(schedule.len() > 2);
+    assert!(schedule.windows(2).all(|w| w[0].0 <= w[1].0));
+    assert!(schedule.iter().all(|(at, _)| *at < spec.duration()));
+    assert_eq!(schedule, spec.schedule(5));
+}
+
+#[test]
+fn test_specs_load_from_toml_yaml_and_json_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let toml = dir.path().join("ping.toml");
+    std::fs::write(&toml, PING).unwrap();
+    let json = dir.path().join("ping.json");
+    std::fs::write(
+        &json,
+        r#"{
+            "name": "ping",
+            "duration_ms": 1000,
+            "clients": [{"name": "pinger", "factory": "pinger"}],
+            "faults": [{"at_ms": 10, "kind": "latency", "node": "pinger", "delay_ms": 5}]
+        }"#,
+    )
+    .unwrap();
+    let yaml = dir.path().join("ping.yml");
+    std::fs::write(
+        &yaml,
+        r#"
+name: ping
+duration_ms: 1000
+clients:
+  - name: pinger
+    factory: pinger
+    args: { rounds: 3 }
+faults:
+  - { at_ms: 10, kind: partition, node_a: pinger, node_b: echo-a }
+generate:
+  faults: 2
+  weights: { crash: 0, restart: 0 }
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(ScenarioSpec::load(&toml).unwrap(), ping(""));
+    let from_json = ScenarioSpec::load(&json).unwrap();
+    assert_eq!(from_json.faults.len(), 1);
+    assert!(from_json.hosts.is_empty());
+    let from_yaml = ScenarioSpec::load(&yaml).unwrap();
+    assert_eq!(from_yaml.clients[0].args["rounds"], 3);
+    assert_eq!(
+        from_yaml.faults,
+        [(
+            Duration::from_millis(10),
+            Fault::Partition {
+                node_a: "pinger".into(),
+                node_b: "echo-a".into(),
+            }
+        )]
+    );
+    assert_eq!(from_yaml.generate.unwrap().weights.duplicate, 1);
+
+    assert!(matches!(
+        ScenarioSpec::from_toml("name = \"x\"\nduration_ms = 1\ntypo = 1"),
+        Err(dst::Error::Toml(_))
+    ));
+    assert!(matches!(
+        ScenarioSpec::from_yaml("name: x\nduration_ms: 1\ntypo: 1"),
+        Err(dst::Error::Yaml(_))
+    ));
+}
+
+#[test]
+fn test_invalid_specs_are_rejected_before_running() {
+    let message = spec_error(&ping("[[hosts]]\nname = \"echo-a\"\nfactory = \"echo\"\n"));
+    assert_eq!(message, "node echo-a is declared twice");
+
+    let message = spec_error(&ping(
+        "[[faults]]\nat_ms = 10\nkind = \"crash\"\nnode = \"pinger\"\n",
+    ));
+    assert!(
+        message.contains("pinger, which is not a host"),
+        "{}",
+        message
+    );
+
+    let message = spec_error(&ping(
+        "[[faults]]\nat_ms = 10\nkind = \"latency\"\nnode = \"echo-c\"\ndelay_ms = 5\n",
+    ));
+    assert!(message.contains("unknown node echo-c"), "{}", message);
+
+    let message = spec_error(&ping(
+        "[[faults]]\nat_ms = 20_000\nkind = \"crash\"\nnode = \"echo-a\"\n",
+    ));
+    assert!(message.contains("after the run ends"), "{}", message);
+
+    let message = spec_error(&ping("[generate]\nfaults = 2\nnodes = [\"pinger\"]\n"));
+    assert!(message.contains("may crash pinger"), "{}", message);
+
+    let mut spec = ping("");
+    spec.hosts[1].factory = "ehco".into();
+    assert_eq!(spec_error(&spec), "echo-b uses unknown factory ehco");
+
+    let mut spec = ping("");
+    spec.invariants.push("no duplicates".into());
+    assert_eq!(spec_error(&spec), "unknown invariant no duplicates");
+}
+
+#[test]
+fn test_datagram_faults_need_factories_that_observe_them() {
+    let duplicate = ping(
+        "[[faults]]\nat_ms = 10\nkind = \"duplicate\"\nnode_a = \"echo-a\"\nnode_b = \"pinger\"\npercent = 50\n",
+    );
+    let message = spec_error(&duplicate);
+    assert!(
+        message.contains("echo-a, whose factory echo does not receive through a FaultyUdpSocket"),
+        "{}",
+        message
+    );
+
+    let generated = ping("[generate]\nfaults = 2\nweights = { crash = 0, restart = 0 }\n");
+    let message = spec_error(&generated);
+    assert!(
+        message.contains("may duplicate datagrams to echo-a"),
+        "{}",
+        message
+    );
+    let mut quiet = generated.clone();
+    quiet.generate.as_mut().unwrap().weights.duplicate = 0;
+    runner().validate(&quiet).unwrap();
+
+    // Once both factories read through a FaultyUdpSocket, the faults apply.
+    let runner = runner()
+        .observes_network_faults("echo")
+        .observes_network_faults("pinger");
+    runner.validate(&duplicate).unwrap();
+    runner.validate(&generated).unwrap();
+}
+
+#[test]
+fn test_runs_check_invariants_and_final_checks() {
+    let runner = runner();
+    let report = runner.run(&ping(""), 3).unwrap();
+    assert_eq!(report.seed, 3);
+    assert!(report.steps > 0);
+    assert!(report.applied.is_empty());
+
+    // Partitioning a server from the client loses pings: the step
+    // invariant still holds, the final check does not.
+    let partitioned = ping(
+        "[[faults]]\nat_ms = 500\nkind = \"partition\"\nnode_a = \"echo-b\"\nnode_b = \"pinger\"\n",
+    );
+    match runner.run(&partitioned, 3) {
+        Err(RunError::Check {
+            name,
+            message,
+            seed,
+        }) => {
+            assert_eq!(name, "all answered");
+            assert!(message.ends_with("of 40 answered"), "{}", message);
+            assert_eq!(seed, 3);
+        }
+        other => panic!("expected a failed check, got {:?}", other),
+    }
+
+    // Without the final check the partitioned run passes.
+    let mut lenient = partitioned.clone();
+    lenient.invariants.retain(|name| name != "all answered");
+    assert_eq!(runner.run(&lenient, 3).unwrap().applied.len(), 1);
+}
+
+#[test]
+fn test_step_invariants_report_the_failing_step() {
+    let runner = runner().invariant("fewer than 5 pings", |pings: &Pings| {
+        if pings.sent < 5 {
+            Ok(())
+        } else {
+            Err(format!("{} pings sent", pings.sent))
+        }
+    });
+    let mut spec = ping("");
+    spec.invariants = vec!["fewer than 5 pings".into()];
+
+    match runner.run(&spec, 9) {
+        Err(RunError::Violation(violation)) => {
+            assert_eq!(violation.invariant, "fewer than 5 pings");
+            assert_eq!(violation.message, "5 pings sent");
+            assert_eq!(violation.seed, 9);
+            assert!(violation.step > 0);
+        }
+        other => panic!("expected a violation, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_host_only_runs_last_the_duration_and_restart_hosts() {
+    let spec = ScenarioSpec::from_toml(
+        r#"
+name = "restarts"
+duration_ms = 3_000
+
+[[hosts]]
+name = "echo-a"
+factory = "echo"
+
+[[faults]]
+at_ms = 1_000
+kind = "crash"
+node = "echo-a"
+
+[[faults]]
+at_ms = 2_000
+kind = "restart"
+node = "echo-a"
+"#,
+    )
+    .unwrap();
+
+    let runner = runner().check("started twice", |pings: &Pings| match pings.starts {
+        2 => Ok(()),
+        n => Err(format!("started {} times", n)),
+    });
+    let mut spec = spec;
+    spec.invariants.push("started twice".into());
+
+    let report = runner.run(&spec, 4).unwrap();
+    assert!(report.elapsed >= Duration::from_secs(3));
+    assert_eq!(report.applied.len(), 2);
+}
@@ -0,0 +1,268 @@
+//! Integration tests for the deterministic task scheduler.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use dst::scheduler::{self, FailureKind, Schedule, Scheduler};
+use tokio::sync::{mpsc, oneshot};
+
+/// Two tasks increment a shared counter with a yield between the load and
+/// the store.
+async fn racy_increments() {
+    let counter = Rc::new(Cell::new(0));
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let counter = counter.clone();
+            scheduler::spawn(async move {
+                let value = counter.get();
+                scheduler::yield_now().await;
+                counter.set(value + 1);
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await;
+    }
+    assert_eq!(counter.get(), 2, "lost update");
+}
+
+/// A coordinator that hands tasks to workers over a channel and expects the
+/// results back in submission order, which only holds when the workers
+/// happen to finish in order.
+async fn ordered_coordinator() {
+    let (task_tx, task_rx) = mpsc::channel::<u32>(8);
+    let (result_tx, mut result_rx) = mpsc::channel::<u32>(8);
+    let task_rx = Rc::new(tokio::sync::Mutex::new(task_rx));
+    for _ in 0..3 {
+        let task_rx = task_rx.clone();
+        let result_tx = result_tx.clone();
+        scheduler::spawn(async move {
+            loop {
+                let task = task_rx.lock().await.recv().await;
+                let Some(task) = task else { break };
+                scheduler::yield_now().await;
+                result_tx.send(task * 10).await.unwrap();
+            }
+        });
+    }
+    drop(result_tx);
+    for task in 0..3 {
+        task_tx.send(task).await.unwrap();
+    }
+    drop(task_tx);
+
+    let mut results = Vec::new();
+    while let Some(result) = result_rx.recv().await {
+        results.push(result);
+    }
+    assert_eq!(results, [0, 10, 20], "results out of order");
+}
+
+#[test]
+fn test_exhaustive_search_finds_lost_update_and_replays_it() {
+    let failure = Scheduler::exhaustive().check(racy_increments).unwrap_err();
+    match &failure.kind {
+        FailureKind::Panic(message) => assert!(message.contains("lost update"), "{}", message),
+        other => panic!("expected a panic, got {:?}", other),
+    }
+    assert!(failure.seed.is_none());
+    assert!(failure.to_string().contains(&format!(
+        "Scheduler::replay(\"{}\".parse().unwrap())",
+        failure.schedule
+    )));
+
+    let replayed = Scheduler::replay(failure.schedule.clone())
+        .check(racy_increments)
+        .unwrap_err();
+    assert_eq!(replayed.kind, failure.kind);
+    assert_eq!(replayed.schedule, failure.schedule);
+}
+
+#[test]
+fn test_exhaustive_search_completes_on_correct_code() {
+    let report = Scheduler::exhaustive().assert(|| async {
+        let counter = Rc::new(tokio::sync::Mutex::new(0));
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let counter = counter.clone();
+                scheduler::spawn(async move {
+                    let mut value = counter.lock().await;
+                    scheduler::yield_now().await;
+                    *value += 1;
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await;
+        }
+        assert_eq!(*counter.lock().await, 3);
+    });
+    assert!(report.complete);
+    assert!(report.schedules > 1);
+}
+
+#[test]
+fn test_preemption_bound_prunes_schedules() {
+    let count = |scheduler: Scheduler| {
+        scheduler
+            .assert(|| async {
+                let handles: Vec<_> = (0..3)
+                    .map(|_| {
+                        scheduler::spawn(async {
+                            for _ in 0..2 {
+                                scheduler::yield_now().await;
+                            }
+                        })
+                    })
+                    .collect();
+                for handle in handles {
+                    handle.await;
+                }
+            })
+            .schedules
+    };
+    let unbounded = count(Scheduler::exhaustive());
+    let one = count(Scheduler::exhaustive().preemption_bound(1));
+    let zero = count(Scheduler::exhaustive().preemption_bound(0));
+    assert!(
+        zero < one && one < unbounded,
+        "{} {} {}",
+        zero,
+        one,
+        unbounded
+    );
+
+    // The lost update needs one preemption between the load and the store.
+    assert!(Scheduler::exhaustive()
+        .preemption_bound(0)
+        .check(racy_increments)
+        .is_ok());
+    assert!(Scheduler::exhaustive()
+        .preemption_bound(1)
+        .check(racy_increments)
+        .is_err());
+}
+
+#[test]
+fn test_max_schedules_stops_an_incomplete_search() {
+    let report = Scheduler::exhaustive().max_schedules(3).assert(|| async {
+        let handle = scheduler::spawn(async {
+            for _ in 0..4 {
+                scheduler::yield_now().await;
+            }
+        });
+        for _ in 0..4 {
+            scheduler::yield_now().await;
+        }
+        handle.await;
+    });
+    assert_eq!(report.schedules, 3);
+    assert!(!report.complete);
+}
+
+#[test]
+fn test_random_schedules_replay_from_their_seed() {
+    let order = |seed: u64| {
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let recorded = order.clone();
+        Scheduler::random(seed).assert(move || {
+            let order = recorded.clone();
+            async move {
+                order.borrow_mut().clear();
+                let handles: Vec<_> = (0..4)
+                    .map(|task| {
+                        let order = order.clone();
+                        scheduler::spawn(async move {
+                            scheduler::yield_now().await;
+                            order.borrow_mut().push(task);
+                        })
+                    })
+                    .collect();
+                for handle in handles {
+                    handle.await;
+                }
+            }
+        });
+        order.take()
+    };
+    assert_eq!(order(3), order(3));
+    let distinct: std::collections::BTreeSet<_> = (0..20).map(order).collect();
+    assert!(distinct.len() > 1);
+}
+
+#[test]
+fn test_random_search_finds_coordinator_race() {
+    let failure = Scheduler::random(0)
+        .iterations(200)
+        .check(ordered_coordinator)
+        .unwrap_err();
+    let seed = failure.seed.unwrap();
+    assert!(
+        matches!(&failure.kind, FailureKind::Panic(message) if message.contains("out of order")),
+        "{}",
+        failure
+    );
+    assert!(failure.to_string().contains(&format!("TEST_SEED={}", seed)));
+
+    // The seed alone reproduces the same interleaving.
+    let again = Scheduler::random(seed)
+        .check(ordered_coordinator)
+        .unwrap_err();
+    assert_eq!(again.schedule, failure.schedule);
+}
+
+#[test]
+fn test_deadlocks_and_livelocks_are_reported() {
+    let failure = Scheduler::random(1)
+        .check(|| async {
+            let (tx, rx) = oneshot::channel::<()>();
+            let waiter = scheduler::spawn(async move {
+                rx.await.ok();
+            });
+            // Waits for the task, which waits for a sender held right here.
+            waiter.await;
+            drop(tx);
+        })
+        .unwrap_err();
+    assert_eq!(
+        failure.kind,
+        FailureKind::Deadlock {
+            blocked: vec![0, 1]
+        }
+    );
+
+    let failure = Scheduler::random(1)
+        .max_steps(500)
+        .check(|| async {
+            let flag = Rc::new(Cell::new(false));
+            let spinner = flag.clone();
+            scheduler::spawn(async move {
+                while !spinner.get() {
+                    scheduler::yield_now().await;
+                }
+            })
+            .await;
+        })
+        .unwrap_err();
+    assert_eq!(failure.kind, FailureKind::StepLimit(500));
+    assert_eq!(failure.schedule.steps().len(), 500);
+}
+
+#[test]
+fn test_replaying_a_different_test_diverges() {
+    let schedule: Schedule = "0,0,0".parse().unwrap();
+    let failure = Scheduler::replay(schedule)
+        .check(|| async {
+            scheduler::spawn(async {}).await;
+            scheduler::spawn(async {}).await;
+        })
+        .unwrap_err();
+    assert_eq!(failure.kind, FailureKind::Diverged { step: 1 });
+    assert!("0,x".parse::<Schedule>().is_err());
+}
+
+#[test]
+#[should_panic(expected = "outside a Scheduler run")]
+fn test_spawn_outside_a_run_panics() {
+    drop(scheduler::spawn(async {}));
+}
@@ -0,0 +1,95 @@
+//! Integration tests for seed management and the simulation test runner.
+
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use dst::turmoil::Builder;
+use dst::{get_test_seed, run_simulation_test, RegressionCorpus, TEST_SEED_ENV};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[test]
+fn test_get_test_seed_honours_env() {
+    // Setting `TEST_SEED` here would pin the seed of every test running in
+    // parallel, so check it in a child process running only this test.
+    if std::env::var(TEST_SEED_ENV).as_deref() == Ok("12345") {
+        assert_eq!(get_test_seed(), 12345);
+        return;
+    }
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["test_get_test_seed_honours_env", "--exact", "--quiet"])
+        .env(TEST_SEED_ENV, "12345")
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn test_runner_runs_closure_once() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+
+    run_simulation_test("test_runner_runs_closure_once", move |_seed| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_runner_resumes_panic_and_records_the_seed() {
+    // A corpus in a temporary directory, so the failure is not checked in.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test_runner_resumes_panic.jsonl");
+    let mut corpus = RegressionCorpus::open("test_runner_resumes_panic", &path).unwrap();
+
+    let payload = std::panic::catch_unwind(move || {
+        corpus.run(|_seed| panic!("invariant violated"));
+    })
+    .expect_err("the panic is resumed");
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"invariant violated"));
+
+    let recorded = RegressionCorpus::open("test_runner_resumes_panic", &path).unwrap();
+    assert_eq!(recorded.regressions().len(), 1);
+    assert_eq!(recorded.regressions()[0].message, "invariant violated");
+}
+
+#[test]
+fn test_echo_server() {
+    run_simulation_test("test_echo_server", |seed| {
+        let mut sim = Builder::new()
+            .simulation_duration(Duration::from_secs(60))
+            .rng_seed(seed)
+            .build();
+
+        sim.host("server", || async {
+            let listener = dst::turmoil::net::TcpListener::bind("0.0.0.0:8080").await?;
+            let (mut socket, _) = listener.accept().await?;
+
+            let mut buf = [0u8; 1024];
+            loop {
+                let n = socket.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                socket.write_all(&buf[..n]).await?;
+            }
+
+            Ok(())
+        });
+
+        sim.client("client", async {
+            let mut socket = dst::turmoil::net::TcpStream::connect("server:8080").await?;
+            socket.write_all(b"hello world").await?;
+
+            let mut buf = [0u8; 1024];
+            let n = socket.read(&mut buf).await?;
+            assert_eq!(&buf[..n], b"hello world", "Echo mismatch!");
+
+            Ok(())
+        });
+
+        sim.run().expect("Simulation failed");
+    });
+}
@@ -0,0 +1,214 @@
+//! Integration tests for scenario shrinking and persistence.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dst::shrink::{panics, panics_with_nodes};
+use dst::turmoil::Builder;
+use dst::{
+    load_scenario, save_scenario, scenario_from_json, Fault, FaultDriver, FaultScenarioGenerator,
+    Scenario, ScenarioShrinker,
+};
+
+fn nodes() -> Vec<String> {
+    vec!["alpha".into(), "beta".into(), "gamma".into()]
+}
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+fn partition(a: &str, b: &str) -> Fault {
+    Fault::Partition {
+        node_a: a.into(),
+        node_b: b.into(),
+    }
+}
+
+fn crash(node: &str) -> Fault {
+    Fault::Crash { node: node.into() }
+}
+
+#[test]
+fn test_shrinks_to_the_faults_that_matter() {
+    let scenario = vec![
+        (secs(5), crash("gamma")),
+        (secs(8), partition("alpha", "beta")),
+        (secs(11), crash("delta")),
+        (
+            secs(14),
+            Fault::Latency {
+                node: "beta".into(),
+                delay_ms: 800,
+            },
+        ),
+        (secs(20), crash("alpha")),
+        (secs(25), partition("beta", "gamma")),
+    ];
+
+    // Fails when alpha/beta are partitioned and alpha later crashes.
+    let fails = |s: &[(Duration, Fault)]| {
+        let split = s.iter().position(|(_, f)| *f == partition("alpha", "beta"));
+        let crashed = s.iter().position(|(_, f)| *f == crash("alpha"));
+        matches!((split, crashed), (Some(p), Some(c)) if p < c)
+    };
+
+    let outcome = ScenarioShrinker::new(fails).shrink(scenario);
+
+    assert!(outcome.complete);
+    assert_eq!(
+        outcome.scenario,
+        vec![
+            (Duration::ZERO, partition("alpha", "beta")),
+            (Duration::ZERO, crash("alpha")),
+        ]
+    );
+}
+
+#[test]
+fn test_shrinks_fault_parameters() {
+    let scenario = vec![(
+        secs(3),
+        Fault::Latency {
+            node: "alpha".into(),
+            delay_ms: 900,
+        },
+    )];
+
+    let outcome = ScenarioShrinker::new(|s: &[(Duration, Fault)]| {
+        s.iter()
+            .any(|(_, f)| matches!(f, Fault::Latency { delay_ms, .. } if *delay_ms >= 100))
+    })
+    .shrink(scenario);
+
+    assert_eq!(
+        outcome.scenario,
+        vec![(
+            Duration::ZERO,
+            Fault::Latency {
+                node: "alpha".into(),
+                delay_ms: 112,
+            }
+        )]
+    );
+}
+
+#[test]
+fn test_passing_scenario_is_returned_unchanged() {
+    let scenario = vec![(secs(1), crash("alpha"))];
+    let outcome = ScenarioShrinker::new(|_: &[(Duration, Fault)]| false).shrink(scenario.clone());
+
+    assert_eq!(outcome.scenario, scenario);
+    assert_eq!(outcome.runs, 1);
+}
+
+#[test]
+fn test_run_budget_is_respected() {
+    let scenario = FaultScenarioGenerator::new(1, nodes()).generate_scenario(30);
+    let outcome = ScenarioShrinker::new(|s: &[(Duration, Fault)]| !s.is_empty())
+        .max_runs(5)
+        .shrink(scenario);
+
+    assert_eq!(outcome.runs, 5);
+    assert!(!outcome.complete);
+}
+
+#[test]
+fn test_scenario_round_trips_through_disk() {
+    let scenario = FaultScenarioGenerator::new(42, nodes()).generate_scenario(12);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scenario.json");
+
+    save_scenario(&path, &scenario).unwrap();
+    assert_eq!(load_scenario(&path).unwrap(), scenario);
+}
+
+#[test]
+fn test_scenario_json_format_is_flat() {
+    let json = r#"[{"at_ms": 1500, "kind": "partition", "node_a": "a", "node_b": "b"}]"#;
+    assert_eq!(
+        scenario_from_json(json).unwrap(),
+        vec![(Duration::from_millis(1500), partition("a", "b"))]
+    );
+    assert!(scenario_from_json("[{\"kind\": \"meteor\"}]").is_err());
+}
+
+/// Runs a heartbeat cluster under `scenario` and panics if any node stops
+/// making progress for good.
+fn heartbeat_cluster(scenario: &[(Duration, Fault)]) {
+    heartbeat_cluster_on(&nodes(), scenario);
+}
+
+fn heartbeat_cluster_on(nodes: &[String], scenario: &[(Duration, Fault)]) {
+    let heartbeats = Arc::new(Mutex::new(dst::det_hash_map::<String, u64>()));
+
+    let mut sim = Builder::new()
+        .simulation_duration(Duration::from_secs(120))
+        .tick_duration(Duration::from_millis(10))
+        .rng_seed(7)
+        .build();
+
+    for name in nodes {
+        let name = name.clone();
+        let heartbeats = heartbeats.clone();
+        sim.host(name.clone(), move || {
+            let name = name.clone();
+            let heartbeats = heartbeats.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    *heartbeats.lock().unwrap().entry(name.clone()).or_default() += 1;
+                }
+            }
+        });
+    }
+
+    let mut driver = FaultDriver::new(nodes.to_vec(), scenario.to_vec());
+    driver.run_until(&mut sim, secs(60)).unwrap();
+
+    let heartbeats = heartbeats.lock().unwrap();
+    for node in nodes {
+        let count = heartbeats.get(node).copied().unwrap_or(0);
+        assert!(count > 400, "{} stalled at {} heartbeats", node, count);
+    }
+}
+
+#[test]
+fn test_shrinks_a_failing_simulation() {
+    let mut scenario: Scenario = FaultScenarioGenerator::new(3, nodes())
+        .with_weights(dst::FaultWeights::NETWORK)
+        .generate_scenario(6);
+    scenario.push((secs(40), crash("beta")));
+
+    let outcome = ScenarioShrinker::new(panics(heartbeat_cluster)).shrink(scenario);
+
+    assert_eq!(outcome.scenario.len(), 1);
+    assert!(matches!(outcome.scenario[0].1, Fault::Crash { ref node } if node == "beta"));
+    // Crashing beta any time before 40s leaves it short of 400 heartbeats.
+    assert!(outcome.scenario[0].0 < secs(40));
+}
+
+#[test]
+fn test_shrinks_away_uninvolved_nodes() {
+    let scenario = vec![
+        (secs(10), partition("alpha", "gamma")),
+        (secs(40), crash("beta")),
+    ];
+
+    let outcome = ScenarioShrinker::with_nodes(nodes(), panics_with_nodes(heartbeat_cluster_on))
+        .shrink(scenario);
+
+    assert!(outcome.complete);
+    assert_eq!(outcome.nodes, ["beta"]);
+    assert_eq!(outcome.scenario.len(), 1);
+    assert!(matches!(outcome.scenario[0].1, Fault::Crash { ref node } if node == "beta"));
+}
+
+#[test]
+fn test_new_keeps_every_node() {
+    let outcome = ScenarioShrinker::new(|s: &[(Duration, Fault)]| !s.is_empty())
+        .shrink(vec![(secs(1), crash("alpha"))]);
+
+    assert!(outcome.nodes.is_empty());
+    assert_eq!(outcome.scenario, vec![(Duration::ZERO, crash("alpha"))]);
+}
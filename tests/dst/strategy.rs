@@ -0,0 +1,132 @@
+//! Integration tests for the proptest fault strategies.
+
+use std::time::Duration;
+
+use dst::strategy::{FaultSchedule, ScenarioParams};
+use dst::turmoil::net::UdpSocket;
+use dst::turmoil::Builder;
+use dst::{Fault, FaultDriver, FaultWeights};
+use proptest::prelude::*;
+use proptest::test_runner::{Config, RngAlgorithm, TestError, TestRng, TestRunner};
+
+const NODES: [&str; 3] = ["alpha", "beta", "gamma"];
+
+fn params() -> ScenarioParams {
+    ScenarioParams::new(NODES)
+}
+
+fn crash(node: &str) -> Fault {
+    Fault::Crash { node: node.into() }
+}
+
+proptest! {
+    #[test]
+    fn test_faults_target_the_given_nodes(fault in any_with::<Fault>(params())) {
+        let nodes = fault.nodes();
+        prop_assert!(nodes.iter().all(|node| NODES.contains(node)), "{:?}", fault);
+        // Links always join two different nodes.
+        if let [a, b] = nodes[..] {
+            prop_assert_ne!(a, b);
+        }
+    }
+
+    #[test]
+    fn test_zero_weights_disable_fault_kinds(
+        fault in any_with::<Fault>(params().with_weights(FaultWeights::NETWORK)),
+    ) {
+        prop_assert!(
+            matches!(
+                fault,
+                Fault::Partition { .. } | Fault::Repair { .. } | Fault::Latency { .. }
+            ),
+            "{:?}",
+            fault
+        );
+    }
+
+    #[test]
+    fn test_schedules_are_sorted_within_the_horizon(
+        schedule in any_with::<FaultSchedule>(
+            params().with_horizon(Duration::from_secs(5)).with_len(2..=6),
+        ),
+    ) {
+        prop_assert!((2..=6).contains(&schedule.len()));
+        prop_assert!(schedule.windows(2).all(|w| w[0].0 <= w[1].0));
+        prop_assert!(schedule.iter().all(|(at, _)| *at < Duration::from_secs(5)));
+        prop_assert!(schedule.iter().all(|(at, _)| at.subsec_nanos() % 1_000_000 == 0));
+    }
+}
+
+#[test]
+fn test_single_node_links_loop_back() {
+    let mut runner = TestRunner::deterministic();
+    let strategy = ScenarioParams::new(["solo"]).fault();
+    for _ in 0..50 {
+        let fault = strategy.new_tree(&mut runner).unwrap().current();
+        assert!(fault.nodes().iter().all(|node| *node == "solo"));
+    }
+}
+
+#[test]
+fn test_failing_schedules_shrink_to_the_culprit() {
+    // The property fails whenever beta crashes, however many other faults
+    // the schedule holds.
+    let mut runner = TestRunner::new_with_rng(
+        Config {
+            failure_persistence: None,
+            ..Config::default()
+        },
+        TestRng::deterministic_rng(RngAlgorithm::ChaCha),
+    );
+    let result = runner.run(
+        &any_with::<FaultSchedule>(params().with_len(0..=20)),
+        |schedule| {
+            prop_assert!(!schedule.iter().any(|(_, fault)| *fault == crash("beta")));
+            Ok(())
+        },
+    );
+
+    match result {
+        Err(TestError::Fail(_, minimal)) => {
+            assert_eq!(
+                minimal,
+                FaultSchedule(vec![(Duration::ZERO, crash("beta"))])
+            );
+        }
+        other => panic!("expected a failure, got {:?}", other),
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig { cases: 16, failure_persistence: None, ..ProptestConfig::default() })]
+
+    #[test]
+    fn test_generated_schedules_drive_a_simulation(
+        seed in any::<u64>(),
+        schedule in any_with::<FaultSchedule>(
+            params()
+                .with_weights(FaultWeights { crash: 0, ..FaultWeights::default() })
+                .with_horizon(Duration::from_secs(10)),
+        ),
+    ) {
+        let mut sim = Builder::new()
+            .rng_seed(seed)
+            .simulation_duration(Duration::from_secs(60))
+            .build();
+        for node in NODES {
+            sim.host(node, || async {
+                let socket = UdpSocket::bind("0.0.0.0:9000").await?;
+                let mut buf = [0u8; 8];
+                loop {
+                    let (n, from) = socket.recv_from(&mut buf).await?;
+                    socket.send_to(&buf[..n], from).await?;
+                }
+            });
+        }
+
+        let mut driver = FaultDriver::new(NODES, schedule.to_vec());
+        driver.run(&mut sim).map_err(|e| TestCaseError::fail(e.to_string()))?;
+        prop_assert_eq!(driver.applied().len(), schedule.len());
+        prop_assert!(driver.applied().iter().all(|a| a.applied_at >= a.scheduled_at));
+    }
+}
@@ -0,0 +1,97 @@
+//! Integration tests for the parallel seed sweep runner.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use dst::turmoil::Builder;
+use dst::{FaultDriver, FaultScenarioGenerator, FaultWeights, SeedSweep};
+
+#[test]
+fn test_sweep_runs_every_seed() {
+    let runs = AtomicUsize::new(0);
+
+    let report = SeedSweep::new("test_sweep_runs_every_seed")
+        .seed_range(0..50)
+        .threads(4)
+        .run(|_seed| {
+            runs.fetch_add(1, Ordering::SeqCst);
+        });
+
+    assert_eq!(runs.load(Ordering::SeqCst), 50);
+    assert_eq!(report.passed(), 50);
+    assert_eq!(report.skipped(), 0);
+    report.assert_passed();
+}
+
+#[test]
+fn test_sweep_collects_failing_seeds_in_order() {
+    let report = SeedSweep::new("test_sweep_collects_failing_seeds_in_order")
+        .seed_range(0..30)
+        .threads(3)
+        .run(|seed| assert!(seed % 7 != 0, "seed {} is divisible by 7", seed));
+
+    let failing: Vec<u64> = report.failures().iter().map(|f| f.seed).collect();
+    assert_eq!(failing, vec![0, 7, 14, 21, 28]);
+    assert_eq!(report.failures()[1].message, "seed 7 is divisible by 7");
+    assert_eq!(report.passed(), 25);
+
+    let summary = report.to_string();
+    assert!(summary.contains("25 passed, 5 failed, 0 skipped"));
+    assert!(summary.contains(
+        "TEST_SEED=14 cargo test test_sweep_collects_failing_seeds_in_order --features simulation"
+    ));
+}
+
+#[test]
+fn test_sweep_stops_after_k_failures() {
+    let runs = AtomicUsize::new(0);
+
+    let report = SeedSweep::new("test_sweep_stops_after_k_failures")
+        .seeds([1, 2, 3, 4, 5, 6, 7, 8])
+        .threads(1)
+        .stop_after(2)
+        .run(|seed| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            assert!(seed % 2 == 1, "even seed");
+        });
+
+    // Seeds 1..=4 run; the second failure (seed 4) stops the sweep.
+    assert_eq!(runs.load(Ordering::SeqCst), 4);
+    assert_eq!(report.failures().len(), 2);
+    assert_eq!(report.skipped(), 4);
+}
+
+#[test]
+#[should_panic(expected = "1 failed")]
+fn test_assert_passed_panics_with_summary() {
+    SeedSweep::new("test_assert_passed_panics_with_summary")
+        .seeds([1, 2])
+        .run(|seed| assert_ne!(seed, 2))
+        .assert_passed();
+}
+
+#[test]
+fn test_sweep_runs_turmoil_simulations() {
+    let nodes = || vec!["a".to_string(), "b".to_string()];
+
+    SeedSweep::new("test_sweep_runs_turmoil_simulations")
+        .count(16)
+        .run(|seed| {
+            let mut sim = Builder::new()
+                .simulation_duration(Duration::from_secs(60))
+                .tick_duration(Duration::from_millis(10))
+                .rng_seed(seed)
+                .build();
+            for node in nodes() {
+                sim.host(node, || async { std::future::pending().await });
+            }
+
+            let schedule = FaultScenarioGenerator::new(seed, nodes())
+                .with_weights(FaultWeights::NETWORK)
+                .generate_scenario(3);
+            let mut driver = FaultDriver::new(nodes(), schedule);
+            driver.run(&mut sim).unwrap();
+            assert_eq!(driver.applied().len(), 3);
+        })
+        .assert_passed();
+}
@@ -0,0 +1,292 @@
+//! Integration tests for timeline export.
+
+use std::time::Duration;
+
+use dst::timeline::{Mark, Timeline};
+use dst::turmoil::{net::UdpSocket, Builder};
+use dst::{Fault, FaultDriver, Trace, TraceRecorder, FAULT_DRIVER_HOST};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    Fault(Fault),
+    Started,
+    Sent(u32),
+    Received(u32),
+}
+
+impl From<Fault> for Event {
+    fn from(fault: Fault) -> Self {
+        Event::Fault(fault)
+    }
+}
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+fn mark(event: &Event) -> Option<Mark> {
+    match event {
+        Event::Fault(_) => None,
+        Event::Started => Some(Mark::event("started")),
+        Event::Sent(i) => Some(Mark::send(i, format!("ping {}", i))),
+        Event::Received(i) => Some(Mark::receive(i, format!("ping {}", i))),
+    }
+}
+
+/// Chrome trace events with phase `ph`.
+fn phase<'a>(chrome: &'a Value, ph: &str) -> Vec<&'a Value> {
+    chrome["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|event| event["ph"] == ph)
+        .collect()
+}
+
+/// Pings from `client` to `server` every 100ms with a partition in the
+/// middle, recording sends, receives and the applied faults.
+fn record_run(seed: u64) -> Trace<Event> {
+    let recorder = TraceRecorder::<Event>::new();
+    let mut sim = Builder::new()
+        .rng_seed(seed)
+        .simulation_duration(Duration::from_secs(30))
+        .build();
+
+    let server = recorder.host("server");
+    sim.host("server", move || {
+        let server = server.clone();
+        async move {
+            server.record(Event::Started);
+            let socket = UdpSocket::bind("0.0.0.0:9000").await?;
+            let mut buf = [0u8; 4];
+            loop {
+                socket.recv_from(&mut buf).await?;
+                server.record(Event::Received(u32::from_be_bytes(buf)));
+            }
+        }
+    });
+
+    let client = recorder.host("client");
+    sim.client("client", async move {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        for i in 0..10u32 {
+            socket.send_to(&i.to_be_bytes(), "server:9000").await?;
+            client.record(Event::Sent(i));
+            tokio::time::sleep(ms(100)).await;
+        }
+        Ok(())
+    });
+
+    let partition = |heal: bool| {
+        let (node_a, node_b) = ("client".into(), "server".into());
+        if heal {
+            Fault::Repair { node_a, node_b }
+        } else {
+            Fault::Partition { node_a, node_b }
+        }
+    };
+    let schedule = vec![(ms(250), partition(false)), (ms(550), partition(true))];
+    let mut driver = FaultDriver::new(["client", "server"], schedule).with_trace(recorder.clone());
+    driver.run(&mut sim).unwrap();
+    recorder.trace()
+}
+
+/// The faults a run recorded under `FAULT_DRIVER_HOST`.
+fn faults(trace: &Trace<Event>) -> Vec<(Duration, Fault)> {
+    trace
+        .events()
+        .iter()
+        .filter(|e| e.host == FAULT_DRIVER_HOST)
+        .filter_map(|e| match &e.event {
+            Event::Fault(fault) => Some((e.at(), fault.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_simulated_run_exports_lanes_arrows_and_faults() {
+    let trace = record_run(1);
+    let timeline = Timeline::new(&trace, mark).with_faults(faults(&trace));
+    assert_eq!(timeline.lanes(), ["server", "client"]);
+
+    let chrome = timeline.to_chrome_trace();
+    let received = trace
+        .events()
+        .iter()
+        .filter(|e| matches!(e.event, Event::Received(_)))
+        .count();
+    // Pings sent during the partition are lost and get no arrow.
+    assert!(received > 0 && received < 10, "{} received", received);
+    assert_eq!(phase(&chrome, "s").len(), received);
+    assert_eq!(phase(&chrome, "f").len(), received);
+    for (start, finish) in phase(&chrome, "s").iter().zip(phase(&chrome, "f")) {
+        assert_eq!(start["id"], finish["id"]);
+        assert!(start["ts"].as_u64() <= finish["ts"].as_u64());
+    }
+
+    // The partition is an interval on both lanes, from 250ms to 550ms.
+    let begins = phase(&chrome, "b");
+    let ends = phase(&chrome, "e");
+    assert_eq!(begins.len(), 2);
+    assert_eq!(ends.len(), 2);
+    assert!(begins.iter().all(|b| b["ts"] == 250_000));
+    assert!(ends.iter().all(|e| e["ts"] == 550_000));
+    assert_eq!(begins[0]["name"], "partition client | server");
+
+    let lanes: Vec<_> = phase(&chrome, "M")
+        .into_iter()
+        .filter(|m| m["name"] == "thread_name")
+        .map(|m| m["args"]["name"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(lanes, ["server", "client"]);
+
+    let html = timeline.to_html("seed 1");
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert_eq!(html.matches("class=\"fault partition\"").count(), 2);
+    assert_eq!(html.matches("class=\"message\"").count(), received);
+    assert_eq!(
+        html.matches("class=\"message lost\"").count(),
+        10 - received
+    );
+}
+
+#[test]
+fn test_timelines_are_written_to_disk() {
+    let trace = record_run(2);
+    let timeline = Timeline::new(&trace, mark).with_faults(faults(&trace));
+
+    let dir = tempfile::tempdir().unwrap();
+    let (json, html) = (dir.path().join("run.json"), dir.path().join("run.html"));
+    timeline.write_chrome_trace(&json).unwrap();
+    timeline.write_html(&html, "seed 2").unwrap();
+
+    let loaded: Value = serde_json::from_slice(&std::fs::read(&json).unwrap()).unwrap();
+    assert_eq!(loaded, timeline.to_chrome_trace());
+    assert_eq!(
+        std::fs::read_to_string(&html).unwrap(),
+        timeline.to_html("seed 2")
+    );
+}
+
+#[test]
+fn test_fault_intervals_pair_up_by_kind_and_nodes() {
+    let recorder = TraceRecorder::new();
+    recorder.record_at(ms(0), "a", Event::Started);
+    recorder.record_at(ms(900), "b", Event::Started);
+
+    let latency = |delay_ms| Fault::Latency {
+        node: "a".into(),
+        delay_ms,
+    };
+    let timeline = Timeline::new(&recorder.trace(), mark).with_faults([
+        (ms(100), Fault::Crash { node: "b".into() }),
+        (ms(200), latency(50)),
+        (ms(300), latency(80)),
+        (ms(400), Fault::Restart { node: "b".into() }),
+        (ms(500), latency(0)),
+        (ms(600), Fault::Restart { node: "a".into() }),
+        (
+            ms(700),
+            Fault::MessageLoss {
+                node_a: "c".into(),
+                node_b: "a".into(),
+                percent: 30,
+            },
+        ),
+    ]);
+    assert_eq!(timeline.lanes(), ["a", "b", "c"]);
+    assert_eq!(timeline.end(), ms(900));
+
+    let chrome = timeline.to_chrome_trace();
+    let spans: Vec<_> = phase(&chrome, "b")
+        .iter()
+        .zip(phase(&chrome, "e"))
+        .map(|(b, e)| {
+            let name = b["name"].as_str().unwrap().to_string();
+            (
+                name,
+                b["tid"].as_u64().unwrap(),
+                b["ts"].as_u64().unwrap() / 1_000,
+                e["ts"].as_u64().unwrap() / 1_000,
+            )
+        })
+        .collect();
+    let span = |name: &str, tid: u64, start: u64, end: u64| (name.to_string(), tid, start, end);
+    assert_eq!(
+        spans,
+        [
+            span("crashed", 1, 100, 400),
+            // A new latency replaces the old one; zero ends it.
+            span("latency 50ms", 0, 200, 300),
+            span("latency 80ms", 0, 300, 500),
+            // Still lossy when the run ends.
+            span("loss 30%", 0, 700, 900),
+            span("loss 30%", 2, 700, 900),
+        ]
+    );
+
+    // Restarts are instants, whether or not the node had crashed.
+    let restarts: Vec<_> = phase(&chrome, "i")
+        .iter()
+        .map(|i| {
+            (
+                i["tid"].as_u64().unwrap(),
+                i["ts"].as_u64().unwrap() / 1_000,
+            )
+        })
+        .collect();
+    assert_eq!(restarts, [(1, 400), (0, 600)]);
+}
+
+#[test]
+fn test_clock_faults_become_intervals_and_instants() {
+    let recorder = TraceRecorder::new();
+    recorder.record_at(ms(900), "a", Event::Started);
+    let offset = |offset_ms| Fault::ClockOffset {
+        node: "a".into(),
+        offset_ms,
+    };
+    let timeline = Timeline::new(&recorder.trace(), mark).with_faults([
+        (ms(100), offset(-250)),
+        (
+            ms(200),
+            Fault::ClockDrift {
+                node: "a".into(),
+                ppm: 50,
+            },
+        ),
+        (
+            ms(300),
+            Fault::ClockJump {
+                node: "a".into(),
+                delta_ms: 250,
+            },
+        ),
+        (ms(400), offset(0)),
+    ]);
+
+    let chrome = timeline.to_chrome_trace();
+    let names = |ph: &str| -> Vec<String> {
+        phase(&chrome, ph)
+            .iter()
+            .map(|event| event["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(names("b"), ["clock -250ms", "drift +50ppm"]);
+    assert_eq!(names("i"), ["clock jump +250ms"]);
+}
+
+#[test]
+fn test_html_escapes_labels() {
+    let recorder = TraceRecorder::new();
+    recorder.record_at(ms(1), "<host>", "a & b");
+    let timeline = Timeline::new(&recorder.trace(), |label: &&str| Some(Mark::event(*label)));
+
+    let html = timeline.to_html("\"quoted\"");
+    assert!(html.contains("&lt;host&gt;"));
+    assert!(html.contains("a &amp; b"));
+    assert!(html.contains("<title>&quot;quoted&quot;</title>"));
+    assert!(!html.contains("<host>"));
+}
@@ -0,0 +1,320 @@
+//! Integration tests for the network topology model.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dst::turmoil::net::UdpSocket;
+use dst::turmoil::Builder;
+use dst::{Fault, FaultDriver, FaultyUdpSocket, LatencyDistribution, Link, Topology};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+fn constant(n: u64) -> Link {
+    Link::new(LatencyDistribution::constant(ms(n)))
+}
+
+fn wan() -> Link {
+    Link::new(LatencyDistribution::LongTail {
+        median: ms(40),
+        p99: ms(150),
+    })
+    .loss_percent(2)
+}
+
+/// Two regions, the second split over two racks.
+fn regions() -> Topology {
+    Topology::new(constant(5))
+        .zone("us-east", ["a1", "a2"])
+        .zone("eu-west/rack-1", ["b1"])
+        .zone("eu-west/rack-2", ["b2"])
+        .link("us-east", "us-east", constant(1))
+        .link("us-east", "eu-west/rack-1", wan())
+        .link("eu-west/rack-2", "us-east", wan())
+}
+
+fn link_latencies(schedule: &[(Duration, Fault)], a: &str, b: &str) -> Vec<(Duration, u64)> {
+    schedule
+        .iter()
+        .filter_map(|(at, fault)| match fault {
+            Fault::LinkLatency {
+                node_a,
+                node_b,
+                delay_ms,
+            } if node_a == a && node_b == b => Some((*at, *delay_ms)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_links_resolve_through_zones() {
+    let topology = regions();
+    assert_eq!(topology.nodes(), ["a1", "a2", "b1", "b2"]);
+    assert_eq!(topology.zone_of("b2"), Some("eu-west/rack-2"));
+    assert_eq!(topology.zone_of("client"), None);
+
+    assert_eq!(*topology.link_between("a1", "a2"), constant(1));
+    assert_eq!(*topology.link_between("b1", "a2"), wan());
+    assert_eq!(*topology.link_between("a1", "b2"), wan());
+    // Neither racks nor outsiders have links of their own.
+    assert_eq!(*topology.link_between("b1", "b2"), constant(5));
+    assert_eq!(*topology.link_between("a1", "client"), constant(5));
+}
+
+#[test]
+#[should_panic(expected = "node a1 is already in zone us-east")]
+fn test_nodes_belong_to_one_zone() {
+    let _ = regions().zone("us-west", ["a1"]);
+}
+
+#[test]
+fn test_schedule_compiles_every_link() {
+    let topology = regions().resample_every(ms(250));
+    let schedule = topology.schedule(3, Duration::from_secs(5));
+    assert!(schedule.windows(2).all(|w| w[0].0 <= w[1].0));
+
+    // Constant links are set once, at the start.
+    assert_eq!(link_latencies(&schedule, "a1", "a2"), [(Duration::ZERO, 1)]);
+    assert_eq!(link_latencies(&schedule, "b1", "b2"), [(Duration::ZERO, 5)]);
+
+    // WAN links are resampled, within the run, and lose messages.
+    let samples = link_latencies(&schedule, "a1", "b1");
+    assert!(samples.len() > 5, "{:?}", samples);
+    assert_eq!(samples[0].0, Duration::ZERO);
+    assert!(samples.iter().all(|(at, _)| at.as_millis() % 250 == 0));
+    assert!(samples.iter().all(|(at, _)| *at < Duration::from_secs(5)));
+    assert!(samples.windows(2).all(|w| w[0].1 != w[1].1));
+    assert!(schedule.contains(&(
+        Duration::ZERO,
+        Fault::MessageLoss {
+            node_a: "a2".into(),
+            node_b: "b2".into(),
+            percent: 2,
+        }
+    )));
+
+    assert_eq!(schedule, topology.schedule(3, Duration::from_secs(5)));
+    assert_ne!(schedule, topology.schedule(4, Duration::from_secs(5)));
+
+    // A new zone adds links without disturbing the existing ones.
+    let grown = topology
+        .zone("ap-south", ["c1"])
+        .schedule(3, Duration::from_secs(5));
+    assert_eq!(link_latencies(&grown, "a1", "b1"), samples);
+}
+
+#[test]
+fn test_distributions_match_their_parameters() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut draw = |distribution: LatencyDistribution| {
+        let mut samples: Vec<f64> = (0..20_000)
+            .map(|_| distribution.sample(&mut rng).as_secs_f64() * 1_000.0)
+            .collect();
+        samples.sort_by(f64::total_cmp);
+        samples
+    };
+    let mean = |samples: &[f64]| samples.iter().sum::<f64>() / samples.len() as f64;
+    let percentile = |samples: &[f64], p: usize| samples[samples.len() * p / 100];
+
+    let uniform = draw(LatencyDistribution::Uniform {
+        min: ms(10),
+        max: ms(30),
+    });
+    assert!(uniform[0] >= 10.0 && uniform[uniform.len() - 1] <= 30.0);
+    assert!((mean(&uniform) - 20.0).abs() < 0.5);
+
+    let normal = draw(LatencyDistribution::Normal {
+        mean: ms(50),
+        std_dev: ms(5),
+    });
+    assert!((mean(&normal) - 50.0).abs() < 0.5);
+    // Two standard deviations either side hold about 95% of samples.
+    assert!((percentile(&normal, 2) - 40.0).abs() < 1.0);
+    assert!((percentile(&normal, 97) - 60.0).abs() < 1.0);
+
+    let long_tail = draw(LatencyDistribution::LongTail {
+        median: ms(40),
+        p99: ms(200),
+    });
+    assert!((percentile(&long_tail, 50) - 40.0).abs() < 2.0);
+    assert!((percentile(&long_tail, 99) - 200.0).abs() < 20.0);
+    assert!(mean(&long_tail) > 40.0);
+
+    let fixed = LatencyDistribution::constant(ms(7));
+    assert!(fixed.is_constant());
+    assert_eq!(fixed.sample(&mut rng), ms(7));
+}
+
+/// Pings `targets` from `a1` and returns each target's round trip times.
+fn ping_rtts(topology: &Topology, targets: &[&str]) -> Vec<Vec<Duration>> {
+    let rtts = Arc::new(Mutex::new(vec![Vec::new(); targets.len()]));
+    let mut builder = Builder::new();
+    builder
+        .simulation_duration(Duration::from_secs(60))
+        .rng_seed(5);
+    let mut sim = topology.configure(&mut builder).build();
+
+    for target in targets {
+        sim.host(*target, || async {
+            let socket = UdpSocket::bind("0.0.0.0:9000").await?;
+            let mut buf = [0u8; 1];
+            loop {
+                let (n, from) = socket.recv_from(&mut buf).await?;
+                socket.send_to(&buf[..n], from).await?;
+            }
+        });
+    }
+
+    let observed = rtts.clone();
+    let targets: Vec<String> = targets.iter().map(|t| t.to_string()).collect();
+    sim.client("a1", async move {
+        let socket = UdpSocket::bind("0.0.0.0:9000").await?;
+        let mut buf = [0u8; 1];
+        for round in 0..40u8 {
+            for (index, target) in targets.iter().enumerate() {
+                let sent = dst::turmoil::elapsed();
+                socket.send_to(&[round], (target.as_str(), 9000)).await?;
+                let reply =
+                    tokio::time::timeout(Duration::from_secs(2), socket.recv_from(&mut buf)).await;
+                if reply.is_ok() {
+                    observed.lock().unwrap()[index].push(dst::turmoil::elapsed() - sent);
+                }
+            }
+            tokio::time::sleep(ms(100)).await;
+        }
+        Ok(())
+    });
+
+    let schedule = topology.schedule(5, Duration::from_secs(60));
+    FaultDriver::new(topology.nodes(), schedule)
+        .run(&mut sim)
+        .unwrap();
+    let rtts = rtts.lock().unwrap().clone();
+    rtts
+}
+
+#[test]
+fn test_simulated_latency_follows_the_topology() {
+    let topology = Topology::new(constant(5))
+        .zone("us-east", ["a1", "a2"])
+        .zone("eu-west", ["b1"])
+        .link("us-east", "us-east", constant(1))
+        .link(
+            "us-east",
+            "eu-west",
+            Link::new(LatencyDistribution::Normal {
+                mean: ms(40),
+                std_dev: ms(5),
+            }),
+        );
+    let rtts = ping_rtts(&topology, &["a2", "b1"]);
+    let (local, remote) = (&rtts[0], &rtts[1]);
+
+    assert_eq!(local.len(), 40);
+    assert_eq!(remote.len(), 40);
+    assert!(local.iter().all(|rtt| *rtt <= ms(4)), "{:?}", local);
+    let remote_mean = remote.iter().sum::<Duration>() / remote.len() as u32;
+    assert!(
+        remote_mean > ms(70) && remote_mean < ms(95),
+        "{:?}",
+        remote_mean
+    );
+    assert!(remote.iter().any(|rtt| *rtt != remote[0]));
+}
+
+#[test]
+fn test_bandwidth_caps_queue_datagrams() {
+    // 1000 bytes per second: each 100 byte datagram takes 100ms to carry.
+    let topology = Topology::new(constant(1))
+        .zone("edge", ["sender"])
+        .zone("core", ["receiver"])
+        .link("edge", "core", constant(10).bandwidth(1_000));
+    let arrivals = Arc::new(Mutex::new(Vec::new()));
+
+    let mut sim = Builder::new()
+        .simulation_duration(Duration::from_secs(30))
+        .build();
+    let mut driver = FaultDriver::new(topology.nodes(), topology.schedule(1, Duration::ZERO));
+    let network = driver.network().clone();
+    let observed = arrivals.clone();
+    sim.host("receiver", move || {
+        let network = network.clone();
+        let observed = observed.clone();
+        async move {
+            let socket = FaultyUdpSocket::bind("receiver", "0.0.0.0:9000", network).await?;
+            let mut buf = [0u8; 100];
+            loop {
+                socket.recv_from(&mut buf).await?;
+                observed.lock().unwrap().push(dst::turmoil::elapsed());
+            }
+        }
+    });
+    sim.client("sender", async {
+        let socket = UdpSocket::bind("0.0.0.0:9001").await?;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        for _ in 0..10 {
+            socket.send_to(&[0u8; 100], "receiver:9000").await?;
+        }
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        Ok(())
+    });
+    driver.run(&mut sim).unwrap();
+
+    let arrivals = arrivals.lock().unwrap();
+    assert_eq!(arrivals.len(), 10);
+    for pair in arrivals.windows(2) {
+        let gap = pair[1] - pair[0];
+        assert!(gap >= ms(99) && gap <= ms(101), "{:?}", gap);
+    }
+}
+
+#[test]
+fn test_capped_datagrams_survive_a_cancelled_receive() {
+    let topology = Topology::new(constant(1))
+        .zone("edge", ["sender"])
+        .zone("core", ["receiver"])
+        .link("edge", "core", constant(10).bandwidth(1_000));
+    let arrivals = Arc::new(Mutex::new(Vec::new()));
+
+    let mut sim = Builder::new()
+        .simulation_duration(Duration::from_secs(30))
+        .build();
+    let mut driver = FaultDriver::new(topology.nodes(), topology.schedule(1, Duration::ZERO));
+    let network = driver.network().clone();
+    let observed = arrivals.clone();
+    sim.host("receiver", move || {
+        let network = network.clone();
+        let observed = observed.clone();
+        async move {
+            let socket = FaultyUdpSocket::bind("receiver", "0.0.0.0:9000", network).await?;
+            let mut buf = [0u8; 100];
+            loop {
+                // The tick drops each receive well before the link has
+                // carried its 100ms datagram.
+                tokio::select! {
+                    received = socket.recv_from(&mut buf) => {
+                        received?;
+                        observed.lock().unwrap().push(buf[0]);
+                    }
+                    _ = tokio::time::sleep(ms(30)) => {}
+                }
+            }
+        }
+    });
+    sim.client("sender", async {
+        let socket = UdpSocket::bind("0.0.0.0:9001").await?;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        for i in 0..5u8 {
+            socket.send_to(&[i; 100], "receiver:9000").await?;
+        }
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        Ok(())
+    });
+    driver.run(&mut sim).unwrap();
+
+    assert_eq!(*arrivals.lock().unwrap(), [0, 1, 2, 3, 4]);
+}
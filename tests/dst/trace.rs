@@ -0,0 +1,180 @@
+//! Integration tests for trace recording and divergence diffing.
+
+use std::time::Duration;
+
+use dst::turmoil::{net::UdpSocket, Builder};
+use dst::{Fault, FaultDriver, Trace, TraceRecorder, FAULT_DRIVER_HOST};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Event {
+    Fault(Fault),
+    Sent(u32),
+    Received(u32),
+}
+
+impl From<Fault> for Event {
+    fn from(fault: Fault) -> Self {
+        Event::Fault(fault)
+    }
+}
+
+fn trace_of(events: &[(u64, &str, Event)]) -> Trace<Event> {
+    let recorder = TraceRecorder::new();
+    for (ms, host, event) in events {
+        recorder.record_at(Duration::from_millis(*ms), *host, event.clone());
+    }
+    recorder.trace()
+}
+
+#[test]
+fn test_trace_round_trips_through_jsonl() {
+    let trace = trace_of(&[
+        (0, "a", Event::Sent(1)),
+        (3, "b", Event::Received(1)),
+        (
+            5,
+            FAULT_DRIVER_HOST,
+            Event::Fault(Fault::Crash { node: "b".into() }),
+        ),
+    ]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("run.jsonl");
+    trace.write_jsonl(&path).unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(text.lines().count(), 3);
+    assert!(text.lines().next().unwrap().contains("\"host\":\"a\""));
+
+    let loaded = Trace::<Event>::read_jsonl(&path).unwrap();
+    assert_eq!(loaded, trace);
+    assert_eq!(loaded.events()[1].at(), Duration::from_millis(3));
+}
+
+#[test]
+fn test_diff_reports_first_divergence_with_context() {
+    let left = trace_of(&[
+        (0, "a", Event::Sent(1)),
+        (1, "b", Event::Received(1)),
+        (2, "a", Event::Sent(2)),
+        (3, "b", Event::Received(2)),
+        (4, "a", Event::Sent(3)),
+    ]);
+    let right = trace_of(&[
+        (0, "a", Event::Sent(1)),
+        (1, "b", Event::Received(1)),
+        (2, "a", Event::Sent(2)),
+        (3, "b", Event::Received(3)),
+        (4, "a", Event::Sent(3)),
+    ]);
+
+    assert!(left.diff(&left.clone(), 2).is_none());
+
+    let divergence = left.diff(&right, 2).unwrap();
+    assert_eq!(divergence.index, 3);
+    assert_eq!(divergence.context.len(), 2);
+    assert_eq!(divergence.left[0].event, Event::Received(2));
+    assert_eq!(divergence.right[0].event, Event::Received(3));
+    assert_eq!(divergence.left.len(), 2);
+
+    let report = divergence.to_string();
+    assert!(report.contains("diverge at event #3"), "{}", report);
+    assert!(report.contains("Received(2)") && report.contains("Received(3)"));
+}
+
+#[test]
+fn test_diff_treats_truncated_trace_as_divergent() {
+    let long = trace_of(&[(0, "a", Event::Sent(1)), (1, "a", Event::Sent(2))]);
+    let short = trace_of(&[(0, "a", Event::Sent(1))]);
+
+    let divergence = short.diff(&long, 5).unwrap();
+    assert_eq!(divergence.index, 1);
+    assert!(divergence.left.is_empty());
+    assert_eq!(divergence.right.len(), 1);
+    assert!(divergence.to_string().contains("<end of trace>"));
+}
+
+/// Runs a ping stream from `client` to `server` with a partition in the
+/// middle, recording every send, receive and fault.
+fn record_run(seed: u64) -> Trace<Event> {
+    let recorder = TraceRecorder::<Event>::new();
+    let mut sim = Builder::new()
+        .rng_seed(seed)
+        .tick_duration(Duration::from_millis(10))
+        .simulation_duration(Duration::from_secs(30))
+        .build();
+
+    let server = recorder.host("server");
+    sim.host("server", move || {
+        let server = server.clone();
+        async move {
+            let socket = UdpSocket::bind("0.0.0.0:9000").await?;
+            let mut buf = [0u8; 4];
+            loop {
+                socket.recv_from(&mut buf).await?;
+                server.record(Event::Received(u32::from_be_bytes(buf)));
+            }
+        }
+    });
+
+    let client = recorder.host("client");
+    sim.client("client", async move {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        for i in 0..20u32 {
+            socket.send_to(&i.to_be_bytes(), "server:9000").await?;
+            client.record(Event::Sent(i));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Ok(())
+    });
+
+    let schedule = vec![
+        (
+            Duration::from_millis(500),
+            Fault::Partition {
+                node_a: "client".into(),
+                node_b: "server".into(),
+            },
+        ),
+        (
+            Duration::from_millis(1200),
+            Fault::Repair {
+                node_a: "client".into(),
+                node_b: "server".into(),
+            },
+        ),
+    ];
+    let mut driver = FaultDriver::new(["client", "server"], schedule).with_trace(recorder.clone());
+    driver.run(&mut sim).unwrap();
+
+    recorder.trace()
+}
+
+#[test]
+fn test_same_seed_records_identical_traces() {
+    let first = record_run(7);
+    let second = record_run(7);
+
+    if let Some(divergence) = first.diff(&second, 5) {
+        panic!("{}", divergence);
+    }
+
+    let events = first.events();
+    let faults: Vec<_> = events
+        .iter()
+        .filter(|e| e.host == FAULT_DRIVER_HOST)
+        .map(|e| e.at())
+        .collect();
+    assert_eq!(
+        faults,
+        [Duration::from_millis(500), Duration::from_millis(1200)]
+    );
+
+    let received = events
+        .iter()
+        .filter(|e| matches!(e.event, Event::Received(_)))
+        .count();
+    assert!(received > 0 && received < 20, "received {}", received);
+    assert!(events.windows(2).all(|w| w[0].at() <= w[1].at()));
+}